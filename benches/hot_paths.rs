@@ -0,0 +1,95 @@
+//! Benchmarks for the request hot paths: token validation, post listing
+//! serialisation, and password hashing. Run with `cargo bench`.
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2
+};
+use chrono::Utc;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+use posted_server::auth::jwt::{Claims, JwtKeys};
+use posted_server::config::config::TokenConfig;
+use posted_server::models::{CommentMode, MySqlBool, Post, PostKind, PostVisibility, TextFormat};
+use posted_server::text::summarise;
+
+const LISTING_SIZE: usize = 100;
+
+fn sample_posts(count: usize) -> Vec<Post> {
+    let body = "Lorem ipsum dolor sit amet, **consectetur** adipiscing elit. ".repeat(40);
+    (0..count as u64).map(|id| Post {
+        id,
+        poster_id: id % 7 + 1,
+        kind: PostKind::Text,
+        title: format!("Post number {}", id),
+        url: None,
+        alt_text: None,
+        body: body.clone(),
+        tldr: None,
+        body_format: TextFormat::Markdown,
+        comment_mode: CommentMode::Open,
+        visibility: PostVisibility::Public,
+        likes: id * 3,
+        time_stamp: Utc::now(),
+        updated_at: Utc::now(),
+        expires_at: None,
+        body_edited: MySqlBool(id % 2 == 0),
+        title_edited: MySqlBool(false)
+    }).collect()
+}
+
+fn token_validation(c: &mut Criterion) {
+    let lifetime = TokenConfig::default();
+    let now = Utc::now().timestamp();
+    let keys = JwtKeys::hs256(b"bench");
+    let claims = Claims { sub: "10000".to_string(), name: "bench".to_string(), iat: now, exp: lifetime.expires_at(now), gen: 0 };
+    let token = keys.sign(&claims).unwrap();
+
+    c.bench_function("jwt_token_generate", |b| {
+        b.iter(|| keys.sign(black_box(&claims)))
+    });
+    c.bench_function("jwt_token_validate", |b| {
+        b.iter(|| keys.verify(black_box(&token)))
+    });
+}
+
+fn post_listing(c: &mut Criterion) {
+    let posts = sample_posts(LISTING_SIZE);
+
+    c.bench_function("post_listing_json", |b| {
+        b.iter(|| serde_json::to_vec(black_box(&posts)).unwrap())
+    });
+    c.bench_function("post_listing_msgpack", |b| {
+        b.iter(|| rmp_serde::to_vec_named(black_box(&posts)).unwrap())
+    });
+    c.bench_function("post_listing_summarise", |b| {
+        b.iter(|| posts.iter().map(|post| summarise(black_box(&post.body), 280)).collect::<Vec<_>>())
+    });
+}
+
+fn password_hashing(c: &mut Criterion) {
+    let argon2 = Argon2::default();
+    let password = b"correct horse battery staple";
+    let salt = SaltString::generate(&mut OsRng);
+    let stored = argon2.hash_password(password, &salt).unwrap().to_string();
+
+    let mut group = c.benchmark_group("argon2");
+    group.sample_size(10);
+    group.bench_function("hash", |b| {
+        b.iter_batched(
+            || SaltString::generate(&mut OsRng),
+            |salt| argon2.hash_password(black_box(password), &salt).unwrap().to_string(),
+            BatchSize::SmallInput
+        )
+    });
+    group.bench_function("verify", |b| {
+        b.iter(|| {
+            let hash = PasswordHash::new(&stored).unwrap();
+            argon2.verify_password(black_box(password), &hash).is_ok()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, token_validation, post_listing, password_hashing);
+criterion_main!(benches);
\ No newline at end of file
@@ -0,0 +1,25 @@
+//! Deserialises arbitrary bytes as each JSON request body and query, as the
+//! handlers' extractors do. Malformed input must be rejected, never panic.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use posted_server::models::*;
+
+macro_rules! deserialise {
+    ($data:expr, $($model:ty),+) => {
+        $(let _ = serde_json::from_slice::<$model>($data);)+
+    };
+}
+
+fuzz_target!(|data: &[u8]| {
+    // Opaque ids decode every id field through the codec, numeric ids do not
+    posted_server::ids::init(Some("fuzz"));
+    deserialise!(data,
+        Account, AccountPasswordUpdate, SudoRequest, RefreshRequest, TermsAcceptance, AccountSettingsUpdate,
+        NewPost, PostUpdate, CommentModeUpdate, NewComment, PostCommentUpdate,
+        PostLike, CommentLike, ReactionRequest, AwardRequest,
+        ModerationDecision, NewAppeal, AppealDecision, NewLegalHold, NewAnnouncement, NewApiKey, ApiKeyQuota,
+        PostsQuery, PageQuery, DiscoverQuery, SyncQuery, ViewerQuery, ExperimentQuery, AnalyticsQuery, AuditQuery,
+        LikesQuery, CommentSearchQuery, ThreadQuery, ContextQuery, TranslateQuery
+    );
+});
\ No newline at end of file
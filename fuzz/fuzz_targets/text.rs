@@ -0,0 +1,25 @@
+//! Runs arbitrary bodies through the markdown, HTML and excerpt utilities that
+//! every post and comment passes through.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use posted_server::{format, links, text};
+use posted_server::models::TextFormat;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&max_chars, body)) = data.split_first() else {
+        return
+    };
+    let Ok(body) = std::str::from_utf8(body) else {
+        return
+    };
+
+    let _ = text::summarise(body, max_chars as usize);
+    let _ = text::close_markdown(body);
+    for body_format in [TextFormat::Markdown, TextFormat::Plain, TextFormat::Html] {
+        let prepared = format::prepare(body, body_format);
+        let _ = format::to_html(&prepared, body_format);
+        let _ = format::to_plain(&prepared, body_format);
+    }
+    let _ = links::canonicalise(body);
+});
\ No newline at end of file
@@ -1,13 +1,5 @@
-use posted_mysql;
-
 -- (Dev)Test ID/PK range: 0..=100.
 
-DROP TABLE IF EXISTS PostLike;
-DROP TABLE IF EXISTS CommentLike;
-DROP TABLE IF EXISTS Comment;
-DROP TABLE IF EXISTS Post;
-DROP TABLE IF EXISTS Account;
-
 CREATE TABLE Account (
     id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
     username VARCHAR(127) NOT NULL,
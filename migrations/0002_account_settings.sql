@@ -0,0 +1,9 @@
+CREATE TABLE AccountSettings (
+    account_id BIGINT UNSIGNED NOT NULL,
+    show_likes BOOLEAN NOT NULL DEFAULT true,
+    show_posts BOOLEAN NOT NULL DEFAULT true,
+    allow_mentions BOOLEAN NOT NULL DEFAULT true,
+    allow_direct_messages BOOLEAN NOT NULL DEFAULT true,
+    PRIMARY KEY (account_id),
+    FOREIGN KEY (account_id) REFERENCES Account(id)
+);
\ No newline at end of file
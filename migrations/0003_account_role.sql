@@ -0,0 +1,2 @@
+ALTER TABLE Account
+    ADD COLUMN role ENUM('user', 'moderator', 'admin') NOT NULL DEFAULT 'user' AFTER password_hash;
\ No newline at end of file
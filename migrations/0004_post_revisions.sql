@@ -0,0 +1,15 @@
+ALTER TABLE Post
+    RENAME COLUMN edited TO body_edited;
+
+ALTER TABLE Post
+    ADD COLUMN title_edited BOOLEAN NOT NULL DEFAULT false AFTER body_edited;
+
+CREATE TABLE PostRevision (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    post_id BIGINT UNSIGNED NOT NULL,
+    title VARCHAR(127) NOT NULL,
+    body VARCHAR(1024) NOT NULL,
+    time_stamp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(), -- when the revision was replaced
+    PRIMARY KEY (id),
+    FOREIGN KEY (post_id) REFERENCES Post(id) ON DELETE CASCADE
+);
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- Existing accounts receive the time of the migration
+ALTER TABLE Account
+    ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP() AFTER role;
\ No newline at end of file
@@ -0,0 +1,12 @@
+ALTER TABLE Post
+    ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP() ON UPDATE CURRENT_TIMESTAMP() AFTER time_stamp;
+
+ALTER TABLE Comment
+    ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP() ON UPDATE CURRENT_TIMESTAMP() AFTER time_stamp;
+
+-- Existing rows were last changed no later than they were created, as far as we know
+UPDATE Post SET updated_at = time_stamp;
+UPDATE Comment SET updated_at = time_stamp;
+
+CREATE INDEX post_updated_at ON Post (updated_at);
+CREATE INDEX comment_updated_at ON Comment (updated_at);
\ No newline at end of file
@@ -0,0 +1,4 @@
+ALTER TABLE Account
+    ADD COLUMN email VARCHAR(255) AFTER username,
+    ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT false AFTER email,
+    ADD UNIQUE (email);
\ No newline at end of file
@@ -0,0 +1,14 @@
+ALTER TABLE Post
+    ADD COLUMN like_count BIGINT UNSIGNED NOT NULL DEFAULT 0;
+
+ALTER TABLE Comment
+    ADD COLUMN like_count BIGINT UNSIGNED NOT NULL DEFAULT 0;
+
+-- updated_at is kept as is, as a like is not an edit
+UPDATE Post p
+SET p.like_count = (SELECT count(*) FROM PostLike pl WHERE pl.post_id = p.id),
+    p.updated_at = p.updated_at;
+
+UPDATE Comment c
+SET c.like_count = (SELECT count(*) FROM CommentLike cl WHERE cl.comment_id = c.id),
+    c.updated_at = c.updated_at;
\ No newline at end of file
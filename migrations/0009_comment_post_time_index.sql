@@ -0,0 +1,2 @@
+-- Comments are loaded per post, oldest first
+CREATE INDEX comment_post_time ON Comment (post_id, time_stamp);
\ No newline at end of file
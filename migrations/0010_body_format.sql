@@ -0,0 +1,6 @@
+-- Existing posts and comments were all written as markdown
+ALTER TABLE Post
+    ADD COLUMN body_format ENUM('markdown', 'plain', 'html') NOT NULL DEFAULT 'markdown' AFTER body;
+
+ALTER TABLE Comment
+    ADD COLUMN body_format ENUM('markdown', 'plain', 'html') NOT NULL DEFAULT 'markdown' AFTER body;
\ No newline at end of file
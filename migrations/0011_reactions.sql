@@ -0,0 +1,16 @@
+-- Emoji reactions, alongside likes. A reaction is on either a post or a comment
+CREATE TABLE Reaction (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    post_id BIGINT UNSIGNED,
+    comment_id BIGINT UNSIGNED,
+    account_id BIGINT UNSIGNED NOT NULL,
+    emoji VARCHAR(32) NOT NULL,
+    time_stamp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    PRIMARY KEY (id),
+    UNIQUE KEY reaction_post (post_id, account_id, emoji),
+    UNIQUE KEY reaction_comment (comment_id, account_id, emoji),
+    FOREIGN KEY (post_id) REFERENCES Post(id) ON DELETE CASCADE,
+    FOREIGN KEY (comment_id) REFERENCES Comment(id) ON DELETE CASCADE,
+    FOREIGN KEY (account_id) REFERENCES Account(id) ON DELETE CASCADE,
+    CHECK ((post_id IS NULL) <> (comment_id IS NULL))
+) DEFAULT CHARSET = utf8mb4;
\ No newline at end of file
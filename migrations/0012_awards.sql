@@ -0,0 +1,29 @@
+-- Catalog of awards that can be given to posts
+CREATE TABLE Award (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    name VARCHAR(32) NOT NULL UNIQUE,
+    description VARCHAR(255) NOT NULL,
+    PRIMARY KEY (id)
+);
+
+INSERT INTO Award (name, description) VALUES
+    ('Silver', 'Shows appreciation for a post'),
+    ('Gold', 'For a post that stood out'),
+    ('Platinum', 'For an exceptional post');
+
+-- Ledger of awards given. The receiver is the poster at the time of giving
+CREATE TABLE AwardGiven (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    award_id BIGINT UNSIGNED NOT NULL,
+    giver_id BIGINT UNSIGNED NOT NULL,
+    receiver_id BIGINT UNSIGNED NOT NULL,
+    post_id BIGINT UNSIGNED NOT NULL,
+    time_stamp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    PRIMARY KEY (id),
+    INDEX award_given_receiver (receiver_id, id),
+    INDEX award_given_post (post_id),
+    FOREIGN KEY (award_id) REFERENCES Award(id),
+    FOREIGN KEY (giver_id) REFERENCES Account(id) ON DELETE CASCADE,
+    FOREIGN KEY (receiver_id) REFERENCES Account(id) ON DELETE CASCADE,
+    FOREIGN KEY (post_id) REFERENCES Post(id) ON DELETE CASCADE
+);
\ No newline at end of file
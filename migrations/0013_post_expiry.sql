@@ -0,0 +1,6 @@
+-- Posts can expire, after which the expiry job soft-deletes them
+ALTER TABLE Post
+    ADD COLUMN expires_at TIMESTAMP NULL DEFAULT NULL AFTER updated_at,
+    ADD COLUMN deleted_at TIMESTAMP NULL DEFAULT NULL AFTER expires_at;
+
+CREATE INDEX post_expires_at ON Post (expires_at);
\ No newline at end of file
@@ -0,0 +1,11 @@
+-- Automatic moderation scores, and whether content is held for review until a moderator approves it
+ALTER TABLE Post
+    ADD COLUMN moderation_score DOUBLE NULL DEFAULT NULL,
+    ADD COLUMN held BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE Comment
+    ADD COLUMN moderation_score DOUBLE NULL DEFAULT NULL,
+    ADD COLUMN held BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX post_held ON Post (held);
+CREATE INDEX comment_held ON Comment (held);
\ No newline at end of file
@@ -0,0 +1,33 @@
+-- Removals of posts and comments by moderators, with the reason shown to the author
+CREATE TABLE ModerationAction (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    account_id BIGINT UNSIGNED NOT NULL,
+    moderator_id BIGINT UNSIGNED NOT NULL,
+    post_id BIGINT UNSIGNED,
+    comment_id BIGINT UNSIGNED,
+    reason VARCHAR(1000) NOT NULL,
+    time_stamp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    PRIMARY KEY (id),
+    INDEX moderation_action_account (account_id),
+    FOREIGN KEY (account_id) REFERENCES Account(id) ON DELETE CASCADE,
+    FOREIGN KEY (moderator_id) REFERENCES Account(id) ON DELETE CASCADE,
+    FOREIGN KEY (post_id) REFERENCES Post(id) ON DELETE CASCADE,
+    FOREIGN KEY (comment_id) REFERENCES Comment(id) ON DELETE CASCADE,
+    CHECK ((post_id IS NULL) <> (comment_id IS NULL))
+);
+
+-- An author contesting a removal. At most one appeal per action
+CREATE TABLE Appeal (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    action_id BIGINT UNSIGNED NOT NULL,
+    message VARCHAR(2000) NOT NULL,
+    status ENUM('open', 'upheld', 'overturned') NOT NULL DEFAULT 'open',
+    time_stamp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    resolver_id BIGINT UNSIGNED,
+    resolved_at TIMESTAMP NULL DEFAULT NULL,
+    PRIMARY KEY (id),
+    UNIQUE KEY appeal_action (action_id),
+    INDEX appeal_status (status),
+    FOREIGN KEY (action_id) REFERENCES ModerationAction(id) ON DELETE CASCADE,
+    FOREIGN KEY (resolver_id) REFERENCES Account(id) ON DELETE SET NULL
+);
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- The comment pinned to the top of each post's thread, if any
+ALTER TABLE Post
+    ADD COLUMN pinned_comment_id BIGINT UNSIGNED NULL DEFAULT NULL,
+    ADD FOREIGN KEY (pinned_comment_id) REFERENCES Comment(id) ON DELETE SET NULL;
\ No newline at end of file
@@ -0,0 +1,11 @@
+-- Accounts following other accounts
+CREATE TABLE Follow (
+    follower_id BIGINT UNSIGNED NOT NULL,
+    followee_id BIGINT UNSIGNED NOT NULL,
+    time_stamp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    PRIMARY KEY (follower_id, followee_id),
+    INDEX follow_followee (followee_id),
+    FOREIGN KEY (follower_id) REFERENCES Account(id) ON DELETE CASCADE,
+    FOREIGN KEY (followee_id) REFERENCES Account(id) ON DELETE CASCADE,
+    CHECK (follower_id <> followee_id)
+);
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- Who may comment on each post
+ALTER TABLE Post
+    ADD COLUMN comment_mode ENUM('open', 'followers', 'disabled') NOT NULL DEFAULT 'open' AFTER body_format;
\ No newline at end of file
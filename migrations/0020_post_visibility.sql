@@ -0,0 +1,3 @@
+-- Who can see each post. Unlisted posts are left out of listings, followers-only posts are only shown to followers of the author
+ALTER TABLE Post
+    ADD COLUMN visibility ENUM('public', 'unlisted', 'followers') NOT NULL DEFAULT 'public' AFTER comment_mode;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- What a post is: text, or a link or image at url
+ALTER TABLE Post
+    ADD COLUMN kind ENUM('text', 'link', 'image') NOT NULL DEFAULT 'text' AFTER poster_id,
+    ADD COLUMN url VARCHAR(2048) NULL DEFAULT NULL AFTER title;
\ No newline at end of file
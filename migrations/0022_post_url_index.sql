@@ -0,0 +1,2 @@
+-- Link posts are looked up by their canonical url to find earlier submissions of it
+CREATE INDEX post_url ON Post (url(255));
\ No newline at end of file
@@ -0,0 +1,13 @@
+-- Domain events, written in the same transaction as the change they describe and
+-- relayed by the outbox job until delivered
+CREATE TABLE Outbox (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    event_type VARCHAR(64) NOT NULL,
+    payload TEXT NOT NULL,
+    time_stamp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    dispatched_at TIMESTAMP NULL DEFAULT NULL,
+    attempts INT UNSIGNED NOT NULL DEFAULT 0,
+    last_error VARCHAR(1000) NULL DEFAULT NULL,
+    PRIMARY KEY (id),
+    INDEX outbox_dispatched_at (dispatched_at, id)
+);
\ No newline at end of file
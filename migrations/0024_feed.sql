@@ -0,0 +1,32 @@
+-- Home feeds, denormalised from posts and follows by the feed projection job.
+-- score orders a feed, newest first: the unix time the post was created
+CREATE TABLE Feed (
+    user_id BIGINT UNSIGNED NOT NULL,
+    post_id BIGINT UNSIGNED NOT NULL,
+    score BIGINT NOT NULL,
+    reason ENUM('own', 'followed') NOT NULL,
+    PRIMARY KEY (user_id, post_id),
+    INDEX feed_user_score (user_id, score, post_id),
+    FOREIGN KEY (user_id) REFERENCES Account(id) ON DELETE CASCADE,
+    FOREIGN KEY (post_id) REFERENCES Post(id) ON DELETE CASCADE
+);
+
+-- The id of the last outbox event each projection has applied
+CREATE TABLE ProjectionCursor (
+    name VARCHAR(64) NOT NULL,
+    position BIGINT UNSIGNED NOT NULL DEFAULT 0,
+    PRIMARY KEY (name)
+);
+
+-- Feeds of the existing posts and follows, continuing from the current end of the outbox
+INSERT INTO Feed (user_id, post_id, score, reason)
+SELECT p.poster_id, p.id, UNIX_TIMESTAMP(p.time_stamp), 'own' FROM Post p;
+
+INSERT IGNORE INTO Feed (user_id, post_id, score, reason)
+SELECT f.follower_id, p.id, UNIX_TIMESTAMP(p.time_stamp), 'followed'
+FROM Follow f
+INNER JOIN Post p ON p.poster_id = f.followee_id
+WHERE p.visibility <> 'unlisted';
+
+INSERT INTO ProjectionCursor (name, position)
+SELECT 'feed', COALESCE(MAX(id), 0) FROM Outbox;
\ No newline at end of file
@@ -0,0 +1,16 @@
+-- When each like was made, for the daily stats. Likes from before this migration
+-- count as made when it ran
+ALTER TABLE PostLike ADD COLUMN time_stamp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP();
+ALTER TABLE CommentLike ADD COLUMN time_stamp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP();
+
+-- Activity per UTC day, aggregated by the analytics job once the day is over
+CREATE TABLE DailyStats (
+    day DATE NOT NULL,
+    active_users BIGINT UNSIGNED NOT NULL,
+    new_accounts BIGINT UNSIGNED NOT NULL,
+    posts BIGINT UNSIGNED NOT NULL,
+    comments BIGINT UNSIGNED NOT NULL,
+    votes BIGINT UNSIGNED NOT NULL,
+    computed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP() ON UPDATE CURRENT_TIMESTAMP(),
+    PRIMARY KEY (day)
+);
\ No newline at end of file
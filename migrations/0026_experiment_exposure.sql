@@ -0,0 +1,10 @@
+-- The first time each account or anonymous client was served the variant of an experiment
+CREATE TABLE ExperimentExposure (
+    experiment VARCHAR(64) NOT NULL,
+    subject VARCHAR(80) NOT NULL,
+    variant VARCHAR(64) NOT NULL,
+    account_id BIGINT UNSIGNED NULL DEFAULT NULL,
+    time_stamp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    PRIMARY KEY (experiment, subject),
+    FOREIGN KEY (account_id) REFERENCES Account(id) ON DELETE CASCADE
+);
\ No newline at end of file
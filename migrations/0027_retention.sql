@@ -0,0 +1,27 @@
+-- Legal holds exempt posts and comments from the retention job's purges. Released
+-- holds are kept, and every purge is logged, as an audit trail of both
+CREATE TABLE LegalHold (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    post_id BIGINT UNSIGNED NULL DEFAULT NULL,
+    comment_id BIGINT UNSIGNED NULL DEFAULT NULL,
+    reason VARCHAR(1000) NOT NULL,
+    placed_by BIGINT UNSIGNED NOT NULL,
+    placed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    released_by BIGINT UNSIGNED NULL DEFAULT NULL,
+    released_at TIMESTAMP NULL DEFAULT NULL,
+    PRIMARY KEY (id),
+    INDEX legal_hold_post (post_id),
+    INDEX legal_hold_comment (comment_id),
+    FOREIGN KEY (placed_by) REFERENCES Account(id),
+    FOREIGN KEY (released_by) REFERENCES Account(id)
+);
+
+CREATE TABLE RetentionPurge (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    post_id BIGINT UNSIGNED NOT NULL,
+    poster_id BIGINT UNSIGNED NOT NULL,
+    comment_count INT UNSIGNED NOT NULL,
+    deleted_at TIMESTAMP NOT NULL,
+    purged_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    PRIMARY KEY (id)
+);
\ No newline at end of file
@@ -0,0 +1,8 @@
+-- Each version of the terms of service an account has accepted
+CREATE TABLE TermsAcceptance (
+    account_id BIGINT UNSIGNED NOT NULL,
+    version VARCHAR(64) NOT NULL,
+    time_stamp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    PRIMARY KEY (account_id, version),
+    FOREIGN KEY (account_id) REFERENCES Account(id) ON DELETE CASCADE
+);
\ No newline at end of file
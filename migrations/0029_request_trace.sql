@@ -0,0 +1,17 @@
+-- Sampled requests and their responses, recorded with credentials stripped to debug
+-- client reports by their request id. Rows are deleted after request_log.keep_hours
+CREATE TABLE RequestTrace (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    request_id VARCHAR(64) NOT NULL,
+    method VARCHAR(16) NOT NULL,
+    uri VARCHAR(2048) NOT NULL,
+    request_headers TEXT NOT NULL,
+    request_body MEDIUMTEXT NULL DEFAULT NULL,
+    status SMALLINT UNSIGNED NOT NULL,
+    response_body MEDIUMTEXT NULL DEFAULT NULL,
+    duration_ms INT UNSIGNED NOT NULL,
+    recorded_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    PRIMARY KEY (id),
+    INDEX request_trace_request_id (request_id),
+    INDEX request_trace_recorded_at (recorded_at)
+);
\ No newline at end of file
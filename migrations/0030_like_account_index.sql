@@ -0,0 +1,3 @@
+-- Likes are listed by the account that gave them, see `GET /api/users/me/likes`
+CREATE INDEX post_like_account ON PostLike (account_id, post_id);
+CREATE INDEX comment_like_account ON CommentLike (account_id, comment_id);
\ No newline at end of file
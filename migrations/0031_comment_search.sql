@@ -0,0 +1,2 @@
+-- Comment bodies are searched through `GET /api/search/comments`
+CREATE FULLTEXT INDEX comment_body_search ON Comment (body);
\ No newline at end of file
@@ -0,0 +1,12 @@
+-- Salted hashes of the address and user agent of logins, correlated by the duplicates
+-- job. Rows are deleted login_signals.keep_days after they were last seen
+CREATE TABLE LoginSignal (
+    account_id BIGINT UNSIGNED NOT NULL,
+    kind ENUM('address', 'device') NOT NULL,
+    signal_hash CHAR(64) NOT NULL,
+    last_seen TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    PRIMARY KEY (account_id, kind, signal_hash),
+    INDEX login_signal_hash (signal_hash),
+    INDEX login_signal_last_seen (last_seen),
+    FOREIGN KEY (account_id) REFERENCES Account(id) ON DELETE CASCADE
+);
\ No newline at end of file
@@ -0,0 +1,13 @@
+-- Banners shown to clients between starts_at and ends_at. An announcement with a role
+-- is shown to accounts of at least that role, one without to everyone
+CREATE TABLE Announcement (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    body VARCHAR(1000) NOT NULL,
+    role ENUM('user', 'moderator', 'admin') NULL,
+    starts_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    ends_at TIMESTAMP NULL,
+    created_by BIGINT UNSIGNED NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    INDEX announcement_ends_at (ends_at),
+    FOREIGN KEY (created_by) REFERENCES Account(id) ON DELETE CASCADE
+);
\ No newline at end of file
@@ -0,0 +1,9 @@
+-- When an account first completed each onboarding step, so that a step stays complete
+-- even if what completed it (e.g. the first post) is later deleted
+CREATE TABLE OnboardingStep (
+    account_id BIGINT UNSIGNED NOT NULL,
+    step ENUM('verified_email', 'first_post') NOT NULL,
+    completed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    PRIMARY KEY (account_id, step),
+    FOREIGN KEY (account_id) REFERENCES Account(id) ON DELETE CASCADE
+);
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Alt text of image posts, and an optional TL;DR of any post, for accessible clients
+ALTER TABLE Post
+    ADD COLUMN alt_text VARCHAR(1000) NULL DEFAULT NULL AFTER url,
+    ADD COLUMN tldr VARCHAR(280) NULL DEFAULT NULL AFTER body;
\ No newline at end of file
@@ -0,0 +1,13 @@
+-- Sensitive actions, for admins to review. actor_id has no foreign key, so that the
+-- entries of an account outlive it
+CREATE TABLE AuditLog (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    actor_id BIGINT UNSIGNED NOT NULL,
+    action ENUM('register', 'password_change', 'post_delete', 'comment_delete', 'post_approve',
+        'post_remove', 'comment_approve', 'comment_remove', 'appeal_resolve') NOT NULL,
+    target_id BIGINT UNSIGNED NULL,
+    detail VARCHAR(1000) NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    INDEX audit_log_actor (actor_id, created_at),
+    INDEX audit_log_created_at (created_at)
+);
\ No newline at end of file
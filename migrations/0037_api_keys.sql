@@ -0,0 +1,12 @@
+-- Keys for the public API, stored by the SHA-256 of the key. Requests made with a
+-- key count against its daily_quota, or the configured default when it is NULL
+CREATE TABLE ApiKey (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    account_id BIGINT UNSIGNED NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    key_hash CHAR(64) NOT NULL,
+    daily_quota BIGINT UNSIGNED NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    UNIQUE INDEX api_key_hash (key_hash),
+    FOREIGN KEY (account_id) REFERENCES Account(id) ON DELETE CASCADE
+);
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Threads are read a page at a time, in the order of comment ids
+CREATE INDEX comment_post_id ON Comment (post_id, id);
\ No newline at end of file
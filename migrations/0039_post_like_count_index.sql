@@ -0,0 +1,2 @@
+-- The top listing is read most liked first
+CREATE INDEX post_like_count ON Post (like_count, id);
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- Admin actions are recorded too
+ALTER TABLE AuditLog
+    MODIFY action ENUM('register', 'password_change', 'post_delete', 'comment_delete', 'post_approve',
+        'post_remove', 'comment_approve', 'comment_remove', 'appeal_resolve', 'api_key_quota_set',
+        'legal_hold_create', 'legal_hold_release', 'announcement_create', 'announcement_delete',
+        'config_reload') NOT NULL;
\ No newline at end of file
@@ -0,0 +1,13 @@
+-- Onboarding steps are recorded as they are completed rather than when they are
+-- read, so the steps completed before now are recorded here. Emails are verified
+-- outside of the server, so a trigger records that step
+INSERT IGNORE INTO OnboardingStep (account_id, step)
+    SELECT id, 'verified_email' FROM Account WHERE email_verified;
+
+INSERT IGNORE INTO OnboardingStep (account_id, step, completed_at)
+    SELECT poster_id, 'first_post', MIN(time_stamp) FROM Post GROUP BY poster_id;
+
+CREATE TRIGGER onboarding_verified_email AFTER UPDATE ON Account
+    FOR EACH ROW
+    INSERT IGNORE INTO OnboardingStep (account_id, step)
+        SELECT NEW.id, 'verified_email' FROM DUAL WHERE NEW.email_verified AND NOT OLD.email_verified;
\ No newline at end of file
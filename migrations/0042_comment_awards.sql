@@ -0,0 +1,6 @@
+-- Awards can be given to comments too. post_id stays that of the post the award
+-- was given in, so that an award to a comment is only counted for its comment
+ALTER TABLE AwardGiven
+    ADD COLUMN comment_id BIGINT UNSIGNED NULL AFTER post_id,
+    ADD INDEX award_given_comment (comment_id),
+    ADD FOREIGN KEY (comment_id) REFERENCES Comment(id) ON DELETE CASCADE;
\ No newline at end of file
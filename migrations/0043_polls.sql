@@ -0,0 +1,26 @@
+-- Poll posts, whose question is their title. Each account votes for one option
+-- of a poll, and can change its vote
+ALTER TABLE Post
+    MODIFY COLUMN kind ENUM('text', 'link', 'image', 'poll') NOT NULL DEFAULT 'text';
+
+CREATE TABLE PollOption (
+    id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+    post_id BIGINT UNSIGNED NOT NULL,
+    position TINYINT UNSIGNED NOT NULL,
+    label VARCHAR(100) NOT NULL,
+    PRIMARY KEY (id),
+    UNIQUE KEY poll_option_position (post_id, position),
+    FOREIGN KEY (post_id) REFERENCES Post(id) ON DELETE CASCADE
+) DEFAULT CHARSET = utf8mb4;
+
+CREATE TABLE PollVote (
+    post_id BIGINT UNSIGNED NOT NULL,
+    voter_id BIGINT UNSIGNED NOT NULL,
+    option_id BIGINT UNSIGNED NOT NULL,
+    time_stamp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP(),
+    PRIMARY KEY (post_id, voter_id),
+    INDEX poll_vote_option (option_id),
+    FOREIGN KEY (post_id) REFERENCES Post(id) ON DELETE CASCADE,
+    FOREIGN KEY (voter_id) REFERENCES Account(id) ON DELETE CASCADE,
+    FOREIGN KEY (option_id) REFERENCES PollOption(id) ON DELETE CASCADE
+);
\ No newline at end of file
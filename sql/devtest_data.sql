@@ -7,6 +7,9 @@ INSERT INTO Account (id, username, password_hash) VALUES
     (2, "devtest_2", "super_secret2"),
     (3, "devtest_3", "super_secret3");
 
+INSERT INTO Account (id, username, password_hash, role) VALUES
+    (4, "devtest_moderator", "super_secret4", "moderator");
+
 INSERT INTO Post (id, poster_id, title, body) VALUES
     (1, 1, "test_post_1", "abrakadabra"),
     (2, 1, "test_post_2", "another one by devtest user 1");
@@ -23,4 +26,11 @@ INSERT INTO PostLike (post_id, account_id) VALUES
 INSERT INTO CommentLike (comment_id, account_id) VALUES 
     (1, 1), (1, 2),
     (2, 3),
-    (3, 1);
\ No newline at end of file
+    (3, 1);
+
+-- Likes are inserted directly above, so bring the like counts in line
+UPDATE Post p
+SET p.like_count = (SELECT count(*) FROM PostLike pl WHERE pl.post_id = p.id);
+
+UPDATE Comment c
+SET c.like_count = (SELECT count(*) FROM CommentLike cl WHERE cl.comment_id = c.id);
\ No newline at end of file
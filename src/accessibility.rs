@@ -0,0 +1,84 @@
+use crate::models::PostKind;
+use crate::text;
+
+/// Characters of alt text, as limited by the Post table.
+pub const MAX_ALT_TEXT_LENGTH: usize = 1000;
+/// Characters of a TL;DR, as limited by the Post table.
+pub const MAX_TLDR_LENGTH: usize = 280;
+
+#[derive(Debug, PartialEq)]
+pub enum AccessibilityError {
+    AltTextTooLong,
+    /// An image post without alt text, while `posts.require_alt_text` is set.
+    AltTextMissing,
+    /// Alt text on a post that is not of an image.
+    AltTextUnexpected,
+    TldrTooLong
+}
+
+impl AccessibilityError {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            AccessibilityError::AltTextTooLong => "Alt text is too long",
+            AccessibilityError::AltTextMissing => "Image posts require alt text",
+            AccessibilityError::AltTextUnexpected => "Only image posts can have alt text",
+            AccessibilityError::TldrTooLong => "TL;DR is too long"
+        }
+    }
+}
+
+/// The alt text of a post of `kind` as stored: on one line like a title, or `None`
+/// if there is none. Only image posts have alt text, which they must when `required`.
+pub fn alt_text(kind: PostKind, alt_text: Option<&str>, required: bool) -> Result<Option<String>, AccessibilityError> {
+    let alt_text = optional(alt_text);
+    match (kind, &alt_text) {
+        (PostKind::Image, None) if required => return Err(AccessibilityError::AltTextMissing),
+        (PostKind::Image, _) | (_, None) => {},
+        (_, Some(_)) => return Err(AccessibilityError::AltTextUnexpected)
+    }
+    match alt_text {
+        Some(alt_text) if alt_text.chars().count() > MAX_ALT_TEXT_LENGTH => Err(AccessibilityError::AltTextTooLong),
+        alt_text => Ok(alt_text)
+    }
+}
+
+/// The TL;DR of a post as stored: on one line like a title, or `None` if there is none.
+pub fn tldr(tldr: Option<&str>) -> Result<Option<String>, AccessibilityError> {
+    match optional(tldr) {
+        Some(tldr) if tldr.chars().count() > MAX_TLDR_LENGTH => Err(AccessibilityError::TldrTooLong),
+        tldr => Ok(tldr)
+    }
+}
+
+fn optional(text: Option<&str>) -> Option<String> {
+    text.map(text::normalise_title).filter(|text| !text.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::PostKind;
+    use super::{alt_text, tldr, AccessibilityError, MAX_ALT_TEXT_LENGTH, MAX_TLDR_LENGTH};
+
+    #[test]
+    fn test_alt_text() {
+        assert_eq!(Ok(Some("A cat asleep".to_string())), alt_text(PostKind::Image, Some(" A cat\n asleep "), false));
+        assert_eq!(Ok(None), alt_text(PostKind::Image, None, false));
+        assert_eq!(Ok(None), alt_text(PostKind::Text, Some("\u{200B} "), true));
+        assert_eq!(Ok(None), alt_text(PostKind::Link, None, true));
+
+        assert_eq!(Err(AccessibilityError::AltTextMissing), alt_text(PostKind::Image, None, true));
+        assert_eq!(Err(AccessibilityError::AltTextMissing), alt_text(PostKind::Image, Some("  "), true));
+        assert_eq!(Err(AccessibilityError::AltTextUnexpected), alt_text(PostKind::Link, Some("A cat"), false));
+        let long = "a".repeat(MAX_ALT_TEXT_LENGTH + 1);
+        assert_eq!(Err(AccessibilityError::AltTextTooLong), alt_text(PostKind::Image, Some(&long), false));
+    }
+
+    #[test]
+    fn test_tldr() {
+        assert_eq!(Ok(Some("Short version".to_string())), tldr(Some("Short\nversion")));
+        assert_eq!(Ok(None), tldr(Some("")));
+        assert_eq!(Ok(None), tldr(None));
+        assert_eq!(Ok(Some("é".repeat(MAX_TLDR_LENGTH))), tldr(Some(&"é".repeat(MAX_TLDR_LENGTH))));
+        assert_eq!(Err(AccessibilityError::TldrTooLong), tldr(Some(&"é".repeat(MAX_TLDR_LENGTH + 1))));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,565 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest, HttpResponse};
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Payload, ResourceDef, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::{ErrorForbidden, ErrorUnauthorized};
+use actix_web::web::{Data, Query};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use log::error;
+
+use crate::auth::auth::Identity;
+use crate::auth::shards::AuthShards;
+use crate::config::config::SharedConfig;
+use crate::database::{database::Database, error::DBError};
+use crate::ids;
+use crate::models::Role;
+
+use super::api::{authenticate, verify_terms, verify_token};
+
+const NO_POLICY_REASON: &str = "No access policy for this route";
+const OWNER_REASON: &str = "Only the author can do this";
+const OWNER_OR_MODERATOR_REASON: &str = "Only the author or a moderator can do this";
+/// Content that an account must be the author of.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Owner {
+    /// The post at the `post_id` path parameter
+    Post,
+    /// The comment at the `comment_id` path parameter
+    Comment,
+    /// The post that the comment at the `comment_id` path parameter is on
+    PostOfComment
+}
+
+/// What a request made as an account must satisfy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rule {
+    /// The least role of the account
+    pub role: Role,
+    /// Whether the account must have accepted the current terms of service
+    pub terms: bool,
+    pub owner: Option<Owner>,
+    /// Whether moderators may act on content they are not the author of
+    pub owner_or_moderator: bool
+}
+
+impl Rule {
+    const fn role(self, role: Role) -> Rule {
+        Rule { role, ..self }
+    }
+
+    const fn terms(self) -> Rule {
+        Rule { terms: true, ..self }
+    }
+
+    const fn owner(self, owner: Owner) -> Rule {
+        Rule { owner: Some(owner), ..self }
+    }
+
+    const fn owner_or_moderator(self, owner: Owner) -> Rule {
+        Rule { owner: Some(owner), owner_or_moderator: true, ..self }
+    }
+}
+
+const ACCOUNT: Rule = Rule { role: Role::User, terms: false, owner: None, owner_or_moderator: false };
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    /// Anyone
+    Public,
+    /// Anyone, but when an account is named by the query parameter the request
+    /// must carry its token
+    Optional(&'static str),
+    /// Any account by its token, which must satisfy the rule
+    Account(Rule),
+    /// Checked by the handler, as described
+    Handler(&'static str)
+}
+
+const VIEWER: Access = Access::Optional("viewer_id");
+
+/// Who may use each route under `/api`, by method and route pattern. Routes
+/// missing from here are refused.
+pub const ROUTES: &[(&str, Access)] = &[
+    ("GET /api/challenge", Access::Public),
+    ("GET /api/experiments", Access::Optional("account_id")),
+    ("GET /api/version", Access::Public),
+
+    ("POST /api/account/register", Access::Public),
+    ("POST /api/account/login", Access::Public),
+    ("POST /api/account/refresh", Access::Handler("a refresh token of the account")),
+    ("POST /api/account/sudo", Access::Account(ACCOUNT)),
+    ("POST /api/account/accept_terms", Access::Account(ACCOUNT)),
+    ("PUT /api/account/change_password", Access::Handler("the token of the username, a sudo token and the old password")),
+    ("GET /api/account/settings", Access::Account(ACCOUNT)),
+    ("PUT /api/account/settings", Access::Account(ACCOUNT)),
+    ("GET /api/account/onboarding", Access::Account(ACCOUNT)),
+    ("GET /api/account/export/preview", Access::Account(ACCOUNT)),
+    ("GET /api/account/api_keys", Access::Account(ACCOUNT)),
+    ("POST /api/account/api_keys", Access::Account(ACCOUNT)),
+    ("DELETE /api/account/api_keys/{api_key_id}", Access::Account(ACCOUNT)),
+    ("GET /api/account/api_keys/{api_key_id}/usage", Access::Account(ACCOUNT)),
+
+    ("GET /api/posts", VIEWER),
+    ("GET /api/sync", Access::Public),
+    ("POST /api/posts", Access::Account(ACCOUNT.terms())),
+    ("GET /api/posts/{post_id}", VIEWER),
+    ("PUT /api/posts/{post_id}", Access::Account(ACCOUNT.terms().owner(Owner::Post))),
+    ("PUT /api/posts/{post_id}/comment_mode", Access::Account(ACCOUNT.terms().owner(Owner::Post))),
+    ("GET /api/posts/{post_id}/revisions", VIEWER),
+    ("GET /api/posts/{post_id}/poll", VIEWER),
+    ("POST /api/posts/{post_id}/poll/vote", Access::Account(ACCOUNT.terms())),
+    ("DELETE /api/posts/{post_id}", Access::Account(ACCOUNT.owner_or_moderator(Owner::Post))),
+    ("POST /api/posts/{post_id}/mark_read", Access::Account(ACCOUNT)),
+    ("GET /api/posts/{post_id}/comments", VIEWER),
+    ("GET /api/comment/{comment_id}/context", VIEWER),
+    ("POST /api/posts/{post_id}/like/toggle", Access::Account(ACCOUNT.terms())),
+    ("POST /api/posts/{post_id}/react", Access::Account(ACCOUNT.terms())),
+    ("POST /api/posts/{post_id}/award", Access::Account(ACCOUNT.terms())),
+
+    ("POST /api/comment", Access::Account(ACCOUNT.terms())),
+    ("PUT /api/comment/{comment_id}", Access::Account(ACCOUNT.terms().owner_or_moderator(Owner::Comment))),
+    ("DELETE /api/comment/{comment_id}", Access::Account(ACCOUNT.owner_or_moderator(Owner::Comment))),
+    ("POST /api/comment/{comment_id}/pin", Access::Account(ACCOUNT.terms().owner_or_moderator(Owner::PostOfComment))),
+    ("DELETE /api/comment/{comment_id}/pin", Access::Account(ACCOUNT.terms().owner_or_moderator(Owner::PostOfComment))),
+    ("POST /api/comment/{comment_id}/like/toggle", Access::Account(ACCOUNT.terms())),
+    ("POST /api/comment/{comment_id}/react", Access::Account(ACCOUNT.terms())),
+    ("POST /api/comment/{comment_id}/award", Access::Account(ACCOUNT.terms())),
+
+    ("POST /api/vote/post", Access::Account(ACCOUNT.terms())),
+    ("POST /api/vote/comment", Access::Account(ACCOUNT.terms())),
+    ("GET /api/awards", Access::Public),
+    ("GET /api/announcements", VIEWER),
+    ("GET /api/feed", Access::Account(ACCOUNT)),
+    ("GET /api/discover", VIEWER),
+
+    ("GET /api/users/{user_id}", Access::Public),
+    ("GET /api/users/{user_id}/posts", VIEWER),
+    ("GET /api/users/{user_id}/comments", VIEWER),
+    ("GET /api/users/{user_id}/awards", Access::Public),
+    ("GET /api/users/{user_id}/stats", VIEWER),
+    ("GET /api/users/me/likes", Access::Account(ACCOUNT)),
+    ("GET /api/search/comments", Access::Public),
+    ("POST /api/users/{user_id}/follow", Access::Account(ACCOUNT.terms())),
+    ("DELETE /api/users/{user_id}/follow", Access::Account(ACCOUNT.terms())),
+    ("GET /api/users/{user_id}/moderation", VIEWER),
+    ("POST /api/appeals", Access::Account(ACCOUNT)),
+
+    ("GET /api/admin/schema", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("GET /api/admin/db/health", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("GET /api/admin/analytics", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("GET /api/admin/legal_holds", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("POST /api/admin/legal_holds", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("DELETE /api/admin/legal_holds/{hold_id}", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("GET /api/admin/announcements", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("POST /api/admin/announcements", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("DELETE /api/admin/announcements/{announcement_id}", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("GET /api/admin/audit", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("PUT /api/admin/api_keys/{api_key_id}/quota", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("GET /api/admin/retention", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("GET /api/admin/requests/{request_id}", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("POST /api/admin/config/reload", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("GET /api/admin/integrity", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("POST /api/admin/integrity", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("GET /api/admin/reports/duplicates", Access::Account(ACCOUNT.role(Role::Admin))),
+    ("GET /api/admin/moderation", Access::Account(ACCOUNT.role(Role::Moderator))),
+    ("POST /api/admin/moderation/posts/{post_id}", Access::Account(ACCOUNT.role(Role::Moderator))),
+    ("POST /api/admin/moderation/comments/{comment_id}", Access::Account(ACCOUNT.role(Role::Moderator))),
+    ("GET /api/admin/appeals", Access::Account(ACCOUNT.role(Role::Moderator))),
+    ("POST /api/admin/appeals/{appeal_id}", Access::Account(ACCOUNT.role(Role::Moderator)))
+];
+
+/// The access to a route, e.g. `POST /api/posts`, if it has a policy.
+pub fn policy(route: &str) -> Option<Access> {
+    ROUTES.iter().find(|(policy_route, _)| *policy_route == route).map(|(_, access)| *access)
+}
+
+/// The account a request was authenticated as by `Authorize`, resolved from its
+/// token. Refused with 401 for requests not made as an account.
+#[derive(Debug, Clone)]
+pub struct Authenticated(pub Identity);
+
+impl FromRequest for Authenticated {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(req.extensions().get::<Identity>()
+            .map(|identity| Authenticated(identity.clone()))
+            .ok_or_else(|| ErrorUnauthorized("Not authenticated")))
+    }
+}
+
+/// How the account of a request may act on the content of a rule with an `owner`:
+/// as its author, or as a moderator acting on the content of another account.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Acting {
+    Author(u64),
+    Moderator
+}
+
+impl Acting {
+    /// The author that the content must still have for a change to apply, which
+    /// moderators are not limited to.
+    pub fn author_id(self) -> Option<u64> {
+        match self {
+            Acting::Author(account_id) => Some(account_id),
+            Acting::Moderator => None
+        }
+    }
+
+    /// How the account acted, as recorded in the audit log.
+    pub fn name(self) -> &'static str {
+        match self {
+            Acting::Author(_) => "author",
+            Acting::Moderator => "moderator"
+        }
+    }
+}
+
+impl FromRequest for Acting {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    /// Fails on routes without an `owner` rule, where nothing is known of how the
+    /// account may act.
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(req.extensions().get::<Acting>()
+            .copied()
+            .ok_or_else(|| ErrorForbidden(OWNER_REASON)))
+    }
+}
+
+/// Middleware enforcing `ROUTES`, so that handlers can rely on the account a
+/// request is made as being that of its token.
+pub struct Authorize;
+
+impl<S, B> Transform<S, ServiceRequest> for Authorize
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = AuthorizeMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthorizeMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct AuthorizeMiddleware<S> {
+    service: Rc<S>
+}
+
+impl<S, B> Service<ServiceRequest> for AuthorizeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        Box::pin(async move {
+            if let Err(response) = authorize(&req).await {
+                return Ok(req.into_response(response).map_into_right_body())
+            }
+            Ok(service.call(req).await?.map_into_left_body())
+        })
+    }
+}
+
+async fn authorize(req: &ServiceRequest) -> Result<(), HttpResponse> {
+    // Unmatched requests are left to be answered with 404
+    let Some(pattern) = req.match_pattern() else {
+        return Ok(())
+    };
+    let route = format!("{} {}", req.method(), pattern);
+    let Some(access) = policy(&route) else {
+        error!("access: no policy for {}", route);
+        return Err(HttpResponse::Forbidden().reason(NO_POLICY_REASON).finish())
+    };
+
+    match access {
+        Access::Public | Access::Handler(_) => Ok(()),
+        Access::Optional(name) => {
+            let query = Query::<HashMap<String, String>>::from_query(req.query_string())
+                .map_err(|_| HttpResponse::BadRequest().reason("Invalid query").finish())?;
+            let Some(id) = query.get(name) else {
+                return Ok(())
+            };
+            let Some(account_id) = ids::parse(id) else {
+                return Err(HttpResponse::BadRequest().reason("Invalid account id format").finish())
+            };
+            verify_bearer(req, Some(account_id)).await.map(|_| ())
+        },
+        Access::Account(rule) => {
+            let account_id = verify_bearer(req, None).await?;
+            verify_rule(req, &pattern, account_id, &rule).await
+        }
+    }
+}
+
+/// Checks the role, authorship and acceptance of terms required by `rule`, of an
+/// account whose token has been verified.
+async fn verify_rule(req: &ServiceRequest, pattern: &str, account_id: u64, rule: &Rule) -> Result<(), HttpResponse> {
+    let db = req.app_data::<Data<Database>>().ok_or_else(|| HttpResponse::InternalServerError().finish())?;
+    if rule.role > Role::User && read_role(db, account_id).await? < rule.role {
+        return Err(HttpResponse::Forbidden().finish())
+    }
+    if let Some(owner) = rule.owner {
+        let mut acting = Acting::Author(account_id);
+        if read_author(req, pattern, owner, db).await? != account_id {
+            if !rule.owner_or_moderator {
+                return Err(HttpResponse::Forbidden().reason(OWNER_REASON).finish())
+            }
+            if read_role(db, account_id).await? < Role::Moderator {
+                return Err(HttpResponse::Forbidden().reason(OWNER_OR_MODERATOR_REASON).finish())
+            }
+            acting = Acting::Moderator;
+        }
+        req.extensions_mut().insert(acting);
+    }
+    if rule.terms {
+        let config = req.app_data::<Data<SharedConfig>>().ok_or_else(|| HttpResponse::InternalServerError().finish())?;
+        verify_terms(account_id, &config.load(), db).await?;
+    }
+    Ok(())
+}
+
+/// Resolves the account of the bearer token of `req`, which must be `account_id`
+/// when one is named, and returns its id.
+async fn verify_bearer(req: &ServiceRequest, account_id: Option<u64>) -> Result<u64, HttpResponse> {
+    let Ok(bearer) = BearerAuth::extract(req.request()).await else {
+        return Err(HttpResponse::Unauthorized().finish())
+    };
+    let Some(auth) = req.app_data::<Data<AuthShards>>() else {
+        return Err(HttpResponse::InternalServerError().finish())
+    };
+    let identity = match account_id {
+        Some(account_id) => verify_token(account_id, bearer.token(), auth.clone()).await?,
+        None => authenticate(bearer.token(), auth).await?
+    };
+    let user_id = identity.user_id;
+    req.extensions_mut().insert(identity);
+    Ok(user_id)
+}
+
+async fn read_role(db: &Database, account_id: u64) -> Result<Role, HttpResponse> {
+    db.read_account_role(account_id).await.map_err(|_| HttpResponse::InternalServerError().finish())
+}
+
+/// The author of the content `owner` refers to, by the path of `req`.
+async fn read_author(req: &ServiceRequest, pattern: &str, owner: Owner, db: &Database) -> Result<u64, HttpResponse> {
+    // Path parameters are only captured once routed, which is after middleware
+    let mut path = req.match_info().clone();
+    ResourceDef::new(pattern).capture_match_info(&mut path);
+    let (name, invalid_format, invalid) = match owner {
+        Owner::Post => ("post_id", "Invalid post_id format", "Invalid post_id"),
+        Owner::Comment | Owner::PostOfComment => ("comment_id", "Invalid comment_id format", "Invalid comment_id")
+    };
+    let Some(id) = path.get(name).and_then(ids::parse) else {
+        return Err(HttpResponse::BadRequest().reason(invalid_format).finish())
+    };
+
+    let author = match owner {
+        Owner::Post => db.read_post_owner(id).await,
+        Owner::Comment => db.read_comment_owner(id).await,
+        Owner::PostOfComment => db.read_comment_thread(id).await.map(|(_, poster_id)| poster_id)
+    };
+    match author {
+        Ok(author) => Ok(author),
+        Err(DBError::NoResult) => Err(HttpResponse::BadRequest().reason(invalid).finish()),
+        Err(_) => Err(HttpResponse::InternalServerError().finish())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use actix_web::{web, App, FromRequest, HttpMessage, HttpResponse};
+    use actix_web::dev::ResourceDef;
+    use actix_web::http::{Method, StatusCode};
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use arc_swap::ArcSwap;
+    use chrono::Utc;
+
+    use crate::api::api::{comment_location, post_location};
+    use crate::auth::jwt::{Claims, JwtKeys};
+    use crate::auth::shards::AuthShards;
+    use crate::config::config::{ServerConfig, SharedConfig};
+    use crate::database::database::{Database, DEFAULT_MAX_CONNECTIONS};
+    use crate::ids::PublicId;
+    use crate::metrics::metrics::Metrics;
+    use super::{policy, Access, Acting, Authenticated, Authorize, Owner, ROUTES};
+
+    /// Every route under `/api` has exactly one policy, and every policy a route.
+    #[test]
+    fn test_routes_have_policies() {
+        let routes = [include_str!("api.rs"), include_str!("admin.rs")].iter()
+            .flat_map(|source| source.lines())
+            .filter_map(|line| line.strip_prefix("#[")?.strip_suffix("\")]")?.split_once("(\""))
+            .filter(|(method, _)| ["get", "post", "put", "delete"].contains(method))
+            .map(|(method, path)| (method.to_uppercase(), path.to_string()))
+            // Served outside of `/api`
+            .filter(|(_, path)| path != "/metrics")
+            .map(|(method, path)| format!("{} /api{}", method, path))
+            .collect::<Vec<String>>();
+
+        for route in &routes {
+            assert!(policy(route).is_some(), "no policy for {}", route);
+        }
+        for (route, _) in ROUTES {
+            assert_eq!(1, routes.iter().filter(|served| served == route).count(), "{} is not served once", route);
+        }
+    }
+
+    /// The `Location` of created content is a route that it can be fetched from.
+    #[test]
+    fn test_created_locations_are_served() {
+        for location in [post_location(7), comment_location(7)] {
+            let served = ROUTES.iter()
+                .filter_map(|(route, _)| route.strip_prefix("GET "))
+                .any(|path| ResourceDef::new(path).is_match(&location));
+            assert!(served, "GET {} is not served", location);
+        }
+    }
+
+    #[test]
+    fn test_comment_ownership() {
+        for route in ["PUT /api/comment/{comment_id}", "DELETE /api/comment/{comment_id}"] {
+            let Some(Access::Account(rule)) = policy(route) else {
+                panic!("{} is not made as an account", route)
+            };
+            assert_eq!(Some(Owner::Comment), rule.owner, "{}", route);
+            assert!(rule.owner_or_moderator, "{}", route);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_acting() {
+        let req = TestRequest::default().to_http_request();
+        assert!(Acting::extract(&req).await.is_err());
+
+        req.extensions_mut().insert(Acting::Author(1));
+        assert_eq!(Some(1), Acting::extract(&req).await.unwrap().author_id());
+        assert_eq!(None, Acting::Moderator.author_id());
+    }
+
+    async fn ok() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    async fn account(_: Authenticated) -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    async fn acting(acting: Acting) -> HttpResponse {
+        HttpResponse::Ok().body(acting.name())
+    }
+
+    /// Requires the MySql database with at least `devtest_data.sql`, in which comment
+    /// 1 is by devtest_2 and devtest_moderator is a moderator.
+    #[actix_web::test]
+    async fn test_comment_owner_or_moderator() {
+        const COMMENT_ID: u64 = 1;
+        const AUTHOR_ID: u64 = 2;
+        const OTHER_ID: u64 = 3;
+        const MODERATOR_ID: u64 = 4;
+
+        dotenv::dotenv().ok();
+        let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL is not set");
+        let db = Database::new(&db_url, DEFAULT_MAX_CONNECTIONS).await;
+        let config: Arc<SharedConfig> = Arc::new(ArcSwap::from_pointee(ServerConfig::default()));
+        // Nothing listens on port 1, so tokens are verified offline
+        let keys = Arc::new(JwtKeys::hs256(b"test"));
+        let auth = AuthShards::new("redis://127.0.0.1:1", config.clone(), keys.clone(), 1, Arc::new(Metrics::new()));
+        let now = Utc::now().timestamp();
+        let token = |account_id: u64| keys.sign(&Claims {
+            sub: account_id.to_string(),
+            name: "devtest".to_string(),
+            iat: now,
+            exp: now + 60,
+            gen: now as u64
+        }).unwrap();
+        let (author, other, moderator) = (token(AUTHOR_ID), token(OTHER_ID), token(MODERATOR_ID));
+
+        let app = init_service(App::new()
+            .app_data(web::Data::new(db))
+            .app_data(web::Data::from(config))
+            .app_data(web::Data::new(auth))
+            .service(web::scope("/api")
+                .wrap(Authorize)
+                .route("/comment/{comment_id}", web::put().to(acting))
+                .route("/comment/{comment_id}", web::delete().to(acting))
+            )).await;
+        let uri = format!("/api/comment/{}", PublicId(COMMENT_ID));
+
+        for method in [Method::PUT, Method::DELETE] {
+            let call = |token: &String| {
+                let req = TestRequest::default()
+                    .method(method.clone())
+                    .uri(&uri)
+                    .insert_header(("Authorization", format!("Bearer {}", token)));
+                call_service(&app, req.to_request())
+            };
+
+            // Neither the author nor a moderator
+            let res = call(&other).await;
+            assert_eq!(StatusCode::FORBIDDEN, res.status(), "{}", method);
+            assert_eq!(Some(super::OWNER_OR_MODERATOR_REASON), res.response().head().reason, "{}", method);
+
+            // The author and a moderator are let through, acting as such
+            let res = call(&author).await;
+            assert_eq!(StatusCode::OK, res.status(), "{}", method);
+            assert_eq!("author", read_body(res).await);
+            let res = call(&moderator).await;
+            assert_eq!(StatusCode::OK, res.status(), "{}", method);
+            assert_eq!("moderator", read_body(res).await);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_middleware() {
+        let app = init_service(App::new().service(web::scope("/api")
+            .wrap(Authorize)
+            .route("/challenge", web::get().to(ok))
+            .route("/version", web::get().to(account))
+            .route("/posts", web::get().to(ok))
+            .route("/posts", web::post().to(ok))
+            .route("/account/settings", web::get().to(ok))
+            .route("/unlisted", web::get().to(ok))
+        )).await;
+        let status = |req: TestRequest| {
+            let app = &app;
+            async move { call_service(app, req.to_request()).await.status() }
+        };
+
+        assert_eq!(StatusCode::OK, status(TestRequest::get().uri("/api/challenge")).await);
+        // Public routes are not authenticated
+        assert_eq!(StatusCode::UNAUTHORIZED, status(TestRequest::get().uri("/api/version")).await);
+        assert_eq!(StatusCode::NOT_FOUND, status(TestRequest::get().uri("/api/missing")).await);
+        assert_eq!(StatusCode::FORBIDDEN, status(TestRequest::get().uri("/api/unlisted")).await);
+
+        // Viewing as an account needs its token, but anonymous viewing does not
+        assert_eq!(StatusCode::OK, status(TestRequest::get().uri("/api/posts")).await);
+        assert_eq!(StatusCode::UNAUTHORIZED, status(TestRequest::get().uri("/api/posts?viewer_id=1")).await);
+        assert_eq!(StatusCode::BAD_REQUEST, status(TestRequest::get().uri("/api/posts?viewer_id=!")).await);
+
+        // The account is that of the token, whichever account the request names
+        assert_eq!(StatusCode::UNAUTHORIZED, status(TestRequest::get().uri("/api/account/settings")).await);
+        assert_eq!(StatusCode::UNAUTHORIZED, status(TestRequest::get().uri("/api/account/settings?account_id=1")).await);
+        let req = TestRequest::post().uri("/api/posts").set_json(serde_json::json!({"poster_id": 1}));
+        assert_eq!(StatusCode::UNAUTHORIZED, status(req).await);
+    }
+}
\ No newline at end of file
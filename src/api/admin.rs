@@ -0,0 +1,488 @@
+use actix_web::{delete, get, post, put, HttpResponse};
+use actix_web::web::{Data, Json, Path, Query};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+use crate::config::config::{self as server_config, SharedConfig};
+use crate::database::{database::Database, error::DBError, migrations::pending_migrations};
+use crate::jobs::duplicates::LastDuplicateReport;
+use crate::jobs::integrity::{self, LastIntegrityReport};
+use crate::metrics::metrics::Metrics;
+use crate::ids::{self, PublicId};
+use crate::models::{AnalyticsQuery, ApiKeyQuota, AppealDecision, AuditAction, AuditQuery, ModerationDecision, NewAnnouncement, NewLegalHold, SchemaReport, VersionInfo};
+use super::access::Authenticated;
+
+/// 2: Requests are made as the account of their bearer token, rather than one
+///    named by an `account_id`, `poster_id` or `commenter_id` field.
+/// 3: Creating a post or comment responds 201 Created with its id, rather than
+///    200 OK without a body.
+/// 4: Tokens are signed JWTs, rather than UUIDs.
+/// 5: The comments of a post are a page with a `next_after` cursor, rather than
+///    an array of every comment.
+pub const API_VERSION: u32 = 5;
+
+const REMOVAL_REASON_REQUIRED: &str = "A reason is required to remove content";
+/// Characters of a removal reason, as limited by the ModerationAction table.
+const MAX_REASON_LENGTH: usize = 1000;
+/// Characters of an announcement, as limited by the Announcement table.
+const MAX_ANNOUNCEMENT_LENGTH: usize = 1000;
+/// Most recent retention purges returned.
+const MAX_RETENTION_PURGES: u64 = 500;
+/// Most recent recorded requests returned for a request id.
+const MAX_REQUEST_TRACES: u64 = 20;
+/// Days of daily stats returned at once.
+const MAX_ANALYTICS_DAYS: i64 = 366;
+/// Entries of the audit log returned at once.
+const DEFAULT_AUDIT_PAGE_SIZE: u64 = 100;
+const MAX_AUDIT_PAGE_SIZE: u64 = 1000;
+
+#[get("/metrics")]
+pub async fn get_metrics(metrics: Data<Metrics>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics.render())
+}
+
+#[get("/version")]
+pub async fn get_version() -> HttpResponse {
+    let build_time = env!("BUILD_TIMESTAMP").parse::<i64>()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0));
+    HttpResponse::Ok().json(VersionInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        api_version: API_VERSION,
+        git_sha: env!("GIT_SHA"),
+        build_time
+    })
+}
+
+#[get("/admin/schema")]
+pub async fn get_schema_report(
+    db: Data<Database>
+) -> HttpResponse {
+    let applied = match db.read_applied_migrations().await {
+        Ok(applied) => applied,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let pending = pending_migrations(&applied);
+    HttpResponse::Ok().json(SchemaReport { applied, pending })
+}
+
+#[get("/admin/db/health")]
+pub async fn get_db_health(
+    db: Data<Database>
+) -> HttpResponse {
+    match db.explain_health().await {
+        Ok(health) => HttpResponse::Ok().json(health),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/admin/analytics")]
+pub async fn get_analytics(
+    db: Data<Database>,
+    query: Query<AnalyticsQuery>
+) -> HttpResponse {
+    if query.to < query.from {
+        return HttpResponse::BadRequest().reason("`to` is before `from`").finish()
+    }
+    if (query.to - query.from).num_days() >= MAX_ANALYTICS_DAYS {
+        return HttpResponse::BadRequest().reason("Too many days requested").finish()
+    }
+
+    match db.read_daily_stats(query.from, query.to).await {
+        Ok(stats) => HttpResponse::Ok().json(stats),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/admin/audit")]
+pub async fn get_audit_log(
+    db: Data<Database>,
+    query: Query<AuditQuery>
+) -> HttpResponse {
+    if query.from.zip(query.to).is_some_and(|(from, to)| to < from) {
+        return HttpResponse::BadRequest().reason("`to` is before `from`").finish()
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_AUDIT_PAGE_SIZE).min(MAX_AUDIT_PAGE_SIZE);
+
+    match db.read_audit_log(&query, limit).await {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+/// Gives an API key its own daily quota, e.g. for a partner with more traffic, or
+/// returns it to the configured default.
+#[put("/admin/api_keys/{api_key_id}/quota")]
+pub async fn set_api_key_quota(
+    db: Data<Database>,
+    account: Authenticated,
+    path: Path<String>,
+    data: Json<ApiKeyQuota>
+) -> HttpResponse {
+    let api_key_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid api_key_id format").finish()
+    };
+    match db.read_api_key(api_key_id).await {
+        Ok(_) => (),
+        Err(DBError::NoResult) => return HttpResponse::BadRequest().reason("Invalid api_key_id").finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    }
+
+    match db.update_api_key_quota(api_key_id, data.daily_quota).await {
+        Ok(()) => {
+            let quota = data.daily_quota.map_or("default".to_string(), |quota| quota.to_string());
+            let _ = db.record_audit(account.0.user_id, AuditAction::ApiKeyQuotaSet, Some(api_key_id), Some(&quota)).await;
+            HttpResponse::Ok().finish()
+        },
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/admin/legal_holds")]
+pub async fn get_legal_holds(
+    db: Data<Database>
+) -> HttpResponse {
+    match db.read_legal_holds().await {
+        Ok(holds) => HttpResponse::Ok().json(holds),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/admin/legal_holds")]
+pub async fn create_legal_hold(
+    db: Data<Database>,
+    account: Authenticated,
+    data: Json<NewLegalHold>
+) -> HttpResponse {
+    if data.post_id.is_some() == data.comment_id.is_some() {
+        return HttpResponse::BadRequest().reason("Exactly one of post_id and comment_id is required").finish()
+    }
+    let reason = data.reason.trim();
+    if reason.is_empty() {
+        return HttpResponse::BadRequest().reason("A reason is required").finish()
+    }
+    if reason.chars().count() > MAX_REASON_LENGTH {
+        return HttpResponse::BadRequest().reason("Reason is too long").finish()
+    }
+
+    match db.create_legal_hold(data.post_id, data.comment_id, account.0.user_id, reason).await {
+        Ok(hold_id) => {
+            let _ = db.record_audit(account.0.user_id, AuditAction::LegalHoldCreate, Some(hold_id), Some(reason)).await;
+            HttpResponse::Created().json(json!({ "id": PublicId(hold_id) }))
+        },
+        Err(DBError::NoResult) => HttpResponse::BadRequest().reason("Invalid post_id or comment_id").finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[delete("/admin/legal_holds/{hold_id}")]
+pub async fn release_legal_hold(
+    db: Data<Database>,
+    account: Authenticated,
+    path: Path<String>
+) -> HttpResponse {
+    let hold_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid hold_id format").finish()
+    };
+
+    match db.release_legal_hold(hold_id, account.0.user_id).await {
+        Ok(()) => {
+            let _ = db.record_audit(account.0.user_id, AuditAction::LegalHoldRelease, Some(hold_id), None).await;
+            HttpResponse::Ok().finish()
+        },
+        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
+            HttpResponse::BadRequest().reason("Invalid or released hold_id").finish()
+        },
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/admin/announcements")]
+pub async fn get_announcements(
+    db: Data<Database>
+) -> HttpResponse {
+    match db.read_announcements().await {
+        Ok(announcements) => HttpResponse::Ok().json(announcements),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/admin/announcements")]
+pub async fn create_announcement(
+    db: Data<Database>,
+    account: Authenticated,
+    data: Json<NewAnnouncement>
+) -> HttpResponse {
+    let body = data.body.trim();
+    if body.is_empty() {
+        return HttpResponse::BadRequest().reason("A body is required").finish()
+    }
+    if body.chars().count() > MAX_ANNOUNCEMENT_LENGTH {
+        return HttpResponse::BadRequest().reason("Body is too long").finish()
+    }
+    if let Some(ends_at) = data.ends_at {
+        if ends_at <= data.starts_at.unwrap_or_else(Utc::now) {
+            return HttpResponse::BadRequest().reason("`ends_at` is not after `starts_at`").finish()
+        }
+    }
+
+    match db.create_announcement(body, data.role, data.starts_at, data.ends_at, account.0.user_id).await {
+        Ok(announcement_id) => {
+            let _ = db.record_audit(account.0.user_id, AuditAction::AnnouncementCreate, Some(announcement_id), None).await;
+            HttpResponse::Created().json(json!({ "id": PublicId(announcement_id) }))
+        },
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[delete("/admin/announcements/{announcement_id}")]
+pub async fn delete_announcement(
+    db: Data<Database>,
+    account: Authenticated,
+    path: Path<String>
+) -> HttpResponse {
+    let announcement_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid announcement_id format").finish()
+    };
+
+    match db.delete_announcement(announcement_id).await {
+        Ok(()) => {
+            let _ = db.record_audit(account.0.user_id, AuditAction::AnnouncementDelete, Some(announcement_id), None).await;
+            HttpResponse::Ok().finish()
+        },
+        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
+            HttpResponse::BadRequest().reason("Invalid announcement_id").finish()
+        },
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/admin/retention")]
+pub async fn get_retention_purges(
+    db: Data<Database>
+) -> HttpResponse {
+    match db.read_retention_purges(MAX_RETENTION_PURGES).await {
+        Ok(purges) => HttpResponse::Ok().json(purges),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/admin/requests/{request_id}")]
+pub async fn get_request_traces(
+    db: Data<Database>,
+    path: Path<String>
+) -> HttpResponse {
+    match db.read_request_traces(&path, MAX_REQUEST_TRACES).await {
+        Ok(traces) => HttpResponse::Ok().json(traces),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/admin/config/reload")]
+pub async fn reload_config(
+    db: Data<Database>,
+    account: Authenticated,
+    config: Data<SharedConfig>
+) -> HttpResponse {
+    match server_config::reload(&config) {
+        Ok(()) => {
+            let _ = db.record_audit(account.0.user_id, AuditAction::ConfigReload, None, None).await;
+            HttpResponse::Ok().finish()
+        },
+        Err(e) => HttpResponse::BadRequest().body(e.to_string())
+    }
+}
+
+#[get("/admin/integrity")]
+pub async fn get_integrity_report(
+    last_report: Data<LastIntegrityReport>
+) -> HttpResponse {
+    match last_report.lock().unwrap().as_ref() {
+        Some(report) => HttpResponse::Ok().json(report),
+        None => HttpResponse::NoContent().finish()
+    }
+}
+
+#[post("/admin/integrity")]
+pub async fn run_integrity_check(
+    db: Data<Database>,
+    metrics: Data<Metrics>,
+    last_report: Data<LastIntegrityReport>
+) -> HttpResponse {
+    match integrity::run(&db, &metrics, &last_report).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/admin/reports/duplicates")]
+pub async fn get_duplicates_report(
+    last_report: Data<LastDuplicateReport>
+) -> HttpResponse {
+    match last_report.lock().unwrap().as_ref() {
+        Some(report) => HttpResponse::Ok().json(report),
+        None => HttpResponse::NoContent().finish()
+    }
+}
+
+#[get("/admin/moderation")]
+pub async fn get_moderation_queue(
+    db: Data<Database>
+) -> HttpResponse {
+    match db.read_moderation_queue().await {
+        Ok(queue) => HttpResponse::Ok().json(queue),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/admin/moderation/posts/{post_id}")]
+pub async fn moderate_post(
+    db: Data<Database>,
+    account: Authenticated,
+    path: Path<String>,
+    data: Json<ModerationDecision>
+) -> HttpResponse {
+    let post_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
+    };
+    let reason = data.reason.as_deref().map(str::trim).unwrap_or_default();
+    if !data.approve && reason.is_empty() {
+        return HttpResponse::BadRequest().reason(REMOVAL_REASON_REQUIRED).finish()
+    }
+    if reason.chars().count() > MAX_REASON_LENGTH {
+        return HttpResponse::BadRequest().reason("Reason is too long").finish()
+    }
+
+    let result = match data.approve {
+        true  => db.approve_held_post(post_id).await,
+        false => db.remove_post(post_id, account.0.user_id, reason).await
+    };
+    match result {
+        Ok(()) => {
+            let (action, detail) = match data.approve {
+                true  => (AuditAction::PostApprove, None),
+                false => (AuditAction::PostRemove, Some(reason))
+            };
+            let _ = db.record_audit(account.0.user_id, action, Some(post_id), detail).await;
+            HttpResponse::Ok().finish()
+        },
+        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) if data.approve => {
+            HttpResponse::BadRequest().reason("Post is not held").finish()
+        },
+        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
+            HttpResponse::BadRequest().reason("Post is already removed").finish()
+        },
+        Err(DBError::NoResult) => HttpResponse::BadRequest().reason("Invalid post_id").finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/admin/moderation/comments/{comment_id}")]
+pub async fn moderate_comment(
+    db: Data<Database>,
+    account: Authenticated,
+    path: Path<String>,
+    data: Json<ModerationDecision>
+) -> HttpResponse {
+    let comment_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid comment_id format").finish()
+    };
+    let reason = data.reason.as_deref().map(str::trim).unwrap_or_default();
+    if !data.approve && reason.is_empty() {
+        return HttpResponse::BadRequest().reason(REMOVAL_REASON_REQUIRED).finish()
+    }
+    if reason.chars().count() > MAX_REASON_LENGTH {
+        return HttpResponse::BadRequest().reason("Reason is too long").finish()
+    }
+
+    let result = match data.approve {
+        true  => db.approve_held_comment(comment_id).await,
+        false => db.remove_comment(comment_id, account.0.user_id, reason).await
+    };
+    match result {
+        Ok(()) => {
+            let (action, detail) = match data.approve {
+                true  => (AuditAction::CommentApprove, None),
+                false => (AuditAction::CommentRemove, Some(reason))
+            };
+            let _ = db.record_audit(account.0.user_id, action, Some(comment_id), detail).await;
+            HttpResponse::Ok().finish()
+        },
+        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) if data.approve => {
+            HttpResponse::BadRequest().reason("Comment is not held").finish()
+        },
+        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
+            HttpResponse::BadRequest().reason("Comment is already removed").finish()
+        },
+        Err(DBError::NoResult) => HttpResponse::BadRequest().reason("Invalid comment_id").finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/admin/appeals")]
+pub async fn get_appeals(
+    db: Data<Database>
+) -> HttpResponse {
+    match db.read_open_appeals().await {
+        Ok(appeals) => HttpResponse::Ok().json(appeals),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/admin/appeals/{appeal_id}")]
+pub async fn resolve_appeal(
+    db: Data<Database>,
+    account: Authenticated,
+    path: Path<String>,
+    data: Json<AppealDecision>
+) -> HttpResponse {
+    let appeal_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid appeal_id format").finish()
+    };
+
+    match db.resolve_appeal(appeal_id, account.0.user_id, data.overturn).await {
+        Ok(()) => {
+            let detail = match data.overturn {
+                true  => "overturned",
+                false => "upheld"
+            };
+            let _ = db.record_audit(account.0.user_id, AuditAction::AppealResolve, Some(appeal_id), Some(detail)).await;
+            HttpResponse::Ok().finish()
+        },
+        Err(DBError::NoResult) => HttpResponse::BadRequest().reason("Invalid appeal_id").finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{test, App};
+    use actix_web::http::StatusCode;
+    use actix_web::web::Data;
+
+    use crate::database::database::Database;
+
+    use super::get_audit_log;
+
+    #[actix_web::test]
+    async fn test_audit_log_range() {
+        let db = Database::connect_lazy("mysql://localhost/posted").unwrap();
+        let app = test::init_service(App::new().app_data(Data::new(db)).service(get_audit_log)).await;
+
+        // Rejected before the database is reached
+        let req = test::TestRequest::get()
+            .uri("/admin/audit?from=2024-02-01T00:00:00Z&to=2024-01-01T00:00:00Z")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+        assert_eq!(Some("`to` is before `from`"), res.response().head().reason);
+    }
+}
\ No newline at end of file
@@ -1,57 +1,294 @@
-use std::sync::Mutex;
+use std::collections::HashSet;
+use std::future::{ready, Future};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
-use actix_web::{delete, get, post, put, web, HttpResponse};
-use actix_web::web::{Data, Json, Path, ServiceConfig};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, HttpResponseBuilder};
+use actix_web::http::header::LOCATION;
+use actix_web::web::{Data, Json, Path, Query, ServiceConfig};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 
+use chrono::{DateTime, Utc};
 use log::warn;
 use serde_json::json;
 
-use crate::auth::auth::AuthService;
-use crate::database::{database::Database, error::DBError};
+use crate::accessibility;
+use crate::auth::auth::{Identity, IssuedToken};
+use crate::auth::challenge::{ChallengeError, ChallengeStore};
+use crate::auth::shards::AuthShards;
+use crate::auth::token::{ct_eq_bytes, ct_eq_u64, TokenHash};
+use crate::cache::{profile::ProfileCache, submissions::{RecentSubmissions, Repeat}, ttl::TtlCache};
+use crate::cache::{keys::TranslationKey, translations::TranslationCache};
+use crate::config::config::{ModerationConfig, ServerConfig, SharedConfig, TranslationConfig};
+use crate::database::{database::Database, error::DBError, karma::GatedAction, search::Terms};
+use crate::experiments;
+use crate::format;
+use crate::geoip::geoip::{self, Country};
+use crate::ids;
+use crate::links;
 use crate::models::*;
+use crate::policy::policy;
+use crate::policy::scoring;
+use crate::polls;
+use crate::ranking;
+use crate::ratelimit::{ratelimit::{self as rate_limit, TranslationLimiter}, quota::Quotas};
+use crate::signals;
+use crate::text::{self, summarise};
+use crate::threads::{self, Limits};
+use crate::translate;
 
 use argon2::{
     password_hash::{
-        rand_core::OsRng,
+        rand_core::{OsRng, RngCore},
         PasswordHash, PasswordHasher, PasswordVerifier, SaltString
     },
     Argon2
 };
 
+use super::{access::{self, Acting, Authenticated}, admin, negotiate};
+
+const FILTERED_WORD_REASON: &str = "Content contains a filtered word";
+const INVALID_CREDENTIALS_REASON: &str = "Invalid username or password";
+const INVALID_REFRESH_REASON: &str = "Invalid or expired refresh token";
+const HELD_REASON: &str = "Held for moderator review";
+const TERMS_REASON: &str = "The current terms must be accepted";
+const REPEAT_REASON: &str = "An identical post was just made";
+const SELF_LIKE_REASON: &str = "Cannot like your own content";
+const LEGAL_HOLD_REASON: &str = "Content is under a legal hold";
+const NOT_A_POLL_REASON: &str = "Post is not a poll";
+const SUDO_TOKEN_HEADER: &str = "X-Sudo-Token";
+const DEFAULT_PAGE_SIZE: u64 = 20;
+const MAX_PAGE_SIZE: u64 = 100;
+/// Posts sampled by `GET /api/discover` by default, and at most.
+/// Posts in `GET /api/posts`.
+const LISTING_SIZE: u64 = 64;
+const DEFAULT_DISCOVER_SIZE: u64 = 20;
+const MAX_DISCOVER_SIZE: u64 = 50;
+/// Characters of an appeal message, as limited by the Appeal table.
+const MAX_APPEAL_LENGTH: usize = 2000;
+/// Clients that last synced longer ago than this must re-download the listings.
+const MAX_SYNC_WINDOW_SEC: i64 = 60 * 60 * 24 * 7;
+/// Characters of a search query.
+const MAX_SEARCH_LENGTH: usize = 200;
+/// Characters of the name of an API key, as limited by the ApiKey table.
+const MAX_API_KEY_NAME_LENGTH: usize = 100;
+
+/// How long the stats of a user are served from `UserStatsCache` before being
+/// re-aggregated.
+pub const USER_STATS_TTL: Duration = Duration::from_secs(60);
+
+pub type UserStatsCache = TtlCache<u64, UserStats>;
+
+/// How long a listing of `GET /api/posts` is served from `PostListingCache`, so
+/// that a burst of requests for it reads the posts once.
+pub const POST_LISTING_TTL: Duration = Duration::from_secs(5);
+
+/// How long the candidates of the hot listing are reused for. Their scores are
+/// worked out afresh for each listing.
+pub const HOT_CANDIDATES_TTL: Duration = Duration::from_secs(30);
+
+/// The posts of `GET /api/posts` by viewer and sort, and the newest posts that the
+/// hot listing of any viewer is ranked out of.
+pub struct PostListingCache {
+    pub posts: TtlCache<(Option<u64>, PostSort), Vec<Post>>,
+    pub hot_candidates: TtlCache<(), Arc<Vec<HotCandidate>>>
+}
+
+impl Default for PostListingCache {
+    fn default() -> Self {
+        PostListingCache {
+            posts: TtlCache::new(POST_LISTING_TTL),
+            hot_candidates: TtlCache::new(HOT_CANDIDATES_TTL)
+        }
+    }
+}
+
+/// How long the total count of a paginated listing is reused for.
+pub const TOTAL_COUNT_TTL: Duration = Duration::from_secs(60);
+
+/// Paginated listings whose totals are kept in `TotalCountCache`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CountedListing {
+    UserPosts(PostAudience),
+    UserComments,
+    UserAwards,
+    LikesGiven(LikeKind),
+    Feed
+}
+
+pub type TotalCountCache = TtlCache<(CountedListing, u64), u64>;
+
+static DUMMY_PASSWORD_HASH: OnceLock<String> = OnceLock::new();
+
 pub fn config(config: &mut ServiceConfig) -> () {
+    config.service(admin::get_metrics);
     config.service(web::scope("/api")
+            .wrap(access::Authorize)
+            .service(get_challenge)
+            .service(get_experiments)
             .service(create_account)
             .service(login)
+            .service(refresh_token)
+            .service(create_sudo_token)
+            .service(accept_terms)
             .service(change_password)
+            .service(get_account_settings)
+            .service(update_account_settings)
+            .service(get_onboarding)
+            .service(get_export_preview)
+            .service(get_api_keys)
+            .service(create_api_key)
+            .service(delete_api_key)
+            .service(get_api_key_usage)
             .service(get_posts)
+            .service(sync)
             .service(create_post)
             .service(get_post)
             .service(update_post)
+            .service(update_comment_mode)
+            .service(get_post_revisions)
+            .service(get_poll)
+            .service(vote_in_poll)
             .service(delete_post)
+            .service(mark_post_read)
             .service(get_post_comments)
+            .service(get_comment_context)
             .service(make_post_comment)
             .service(update_comment)
+            .service(pin_comment)
+            .service(unpin_comment)
             .service(delete_comment)
+            .service(get_user_profile)
             .service(get_user_posts)
+            .service(get_feed)
+            .service(discover)
             .service(get_user_comments)
+            .service(get_user_stats)
+            .service(get_likes_given)
+            .service(search_comments)
             .service(vote_on_post)
             .service(vote_on_comment)
+            .service(toggle_post_like)
+            .service(toggle_comment_like)
+            .service(react_to_post)
+            .service(react_to_comment)
+            .service(get_awards)
+            .service(get_announcements)
+            .service(give_post_award)
+            .service(give_comment_award)
+            .service(get_user_awards)
+            .service(follow_user)
+            .service(unfollow_user)
+            .service(get_user_moderation)
+            .service(create_appeal)
+            .service(admin::get_version)
+            .service(admin::get_schema_report)
+            .service(admin::get_db_health)
+            .service(admin::get_analytics)
+            .service(admin::get_legal_holds)
+            .service(admin::create_legal_hold)
+            .service(admin::release_legal_hold)
+            .service(admin::get_audit_log)
+            .service(admin::set_api_key_quota)
+            .service(admin::get_announcements)
+            .service(admin::create_announcement)
+            .service(admin::delete_announcement)
+            .service(admin::get_retention_purges)
+            .service(admin::get_request_traces)
+            .service(admin::reload_config)
+            .service(admin::get_integrity_report)
+            .service(admin::run_integrity_check)
+            .service(admin::get_duplicates_report)
+            .service(admin::get_moderation_queue)
+            .service(admin::moderate_post)
+            .service(admin::moderate_comment)
+            .service(admin::get_appeals)
+            .service(admin::resolve_appeal)
         );
 }
 
+#[get("/challenge")]
+pub async fn get_challenge(config: Data<SharedConfig>, challenges: Data<ChallengeStore>) -> HttpResponse {
+    let registration = &config.load().registration;
+    if !registration.challenge {
+        return HttpResponse::NotFound().reason("Challenges are not required").finish()
+    }
+
+    match challenges.issue(registration.challenge_difficulty, registration.challenge_ttl_sec).await {
+        Ok(challenge) => HttpResponse::Ok().json(challenge),
+        Err(_) => HttpResponse::ServiceUnavailable().finish()
+    }
+}
+
+#[get("/experiments")]
+pub async fn get_experiments(
+    db: Data<Database>,
+    config: Data<SharedConfig>,
+    query: Query<ExperimentQuery>
+) -> HttpResponse {
+    let Some(subject) = experiments::subject(query.account_id, query.anon_id.as_deref()) else {
+        return HttpResponse::BadRequest().reason("A valid account_id or anon_id is required").finish()
+    };
+
+    let config = config.load();
+    let assignments = experiments::assignments(&config.experiments, &subject);
+    // Serving the variants matters more than recording the exposure
+    if db.log_exposures(&subject, query.account_id, &assignments).await.is_err() {
+        warn!("experiments: failed to log exposures for {}", subject);
+    }
+    HttpResponse::Ok().json(assignments)
+}
+
 #[post("/account/register")]
 pub async fn create_account(
+    req: HttpRequest,
     db: Data<Database>,
     argon2: Data<Argon2<'_>>,
+    config: Data<SharedConfig>,
+    challenges: Data<ChallengeStore>,
+    profiles: Data<ProfileCache>,
     account: Json<Account>
 ) -> HttpResponse {
+    let config = config.load();
+    if config.feature_enabled("registration_closed") {
+        return HttpResponse::Forbidden().reason("Registration is closed").finish();
+    }
+    if geoip::country(&req).is_some_and(|Country(country)| config.compliance.registration_blocked(&country)) {
+        return HttpResponse::UnavailableForLegalReasons().reason("Registration is not available in your region").finish();
+    }
     if account.username.is_empty() {
         return HttpResponse::BadRequest().reason("The provided username was empty").finish();
     }
     if account.password.is_empty() {
         return HttpResponse::BadRequest().reason("The provided password hash was empty").finish();
     }
+    let email = match account.email.as_deref().map(normalise_email) {
+        Some(Some(email)) => Some(email),
+        Some(None) => return HttpResponse::BadRequest().reason("Invalid email address").finish(),
+        None if config.registration.require_email => {
+            return HttpResponse::BadRequest().reason("An email address is required").finish()
+        },
+        None => None
+    };
+    if email.as_ref().is_some_and(|email| config.registration.email_domain_blocked(email)) {
+        return HttpResponse::BadRequest().reason("Email addresses from this domain are not accepted").finish();
+    }
+    let terms_version = config.terms.version.as_deref();
+    if terms_version.is_some() && account.accepted_terms.as_deref() != terms_version {
+        return HttpResponse::BadRequest().reason(TERMS_REASON).finish();
+    }
+    if config.registration.challenge {
+        let Some(solved) = &account.challenge else {
+            return HttpResponse::BadRequest().reason("A solved challenge is required").finish();
+        };
+        match challenges.check(&solved.nonce, &solved.solution).await {
+            Ok(()) => (),
+            Err(ChallengeError::Invalid) => {
+                return HttpResponse::BadRequest().reason("Invalid or expired challenge").finish();
+            },
+            Err(ChallengeError::Unavailable) => return HttpResponse::ServiceUnavailable().finish()
+        }
+    }
 
     let username = account.username.clone();
     let salt = SaltString::generate(&mut OsRng);
@@ -62,11 +299,16 @@ pub async fn create_account(
     std::mem::drop(account);  // TODO: Zeroize Account struct or just the password
     std::mem::drop(salt);
 
-    let result = db.create_account(&username, &pw_hash).await;
+    let result = db.create_account(&username, email.as_deref(), &pw_hash, terms_version).await;
     match result {
-        Ok(()) => HttpResponse::Ok().json(json!({"status": "Success"})),
+        Ok(account_id) => {
+            // The username may have been cached as having no account
+            profiles.invalidate_username(&username).await;
+            let _ = db.record_audit(account_id, AuditAction::Register, Some(account_id), None).await;
+            HttpResponse::Ok().json(json!({"status": "Success"}))
+        },
         Err(DBError::UnexpectedRowsAffected { expected: 1, actual: 0 } ) => {
-            HttpResponse::BadRequest().reason("Username is taken").finish()
+            HttpResponse::BadRequest().reason("Unable to register with the provided details").finish()
         }
         Err(_) => HttpResponse::InternalServerError().finish()
     }
@@ -74,9 +316,12 @@ pub async fn create_account(
 
 #[post("/account/login")]
 pub async fn login(
+    req: HttpRequest,
     db: Data<Database>,
-    auth: Data<Mutex<AuthService>>,
+    auth: Data<AuthShards>,
     argon2: Data<Argon2<'_>>,
+    config: Data<SharedConfig>,
+    profiles: Data<ProfileCache>,
     data: Json<Account>
 ) -> HttpResponse {
     if data.username.is_empty() {
@@ -86,13 +331,18 @@ pub async fn login(
         return HttpResponse::BadRequest().reason("The provided password was empty").finish()
     }
 
-    let account_details = match db.read_account_by_username(&data.username).await{
+    // Unknown usernames are verified against a dummy hash, so that they cannot be
+    // told apart from a wrong password by the response or its timing
+    let account_details = match read_login_account(&data.username, &db, &profiles).await {
         Ok(details) => details,
-        Err(DBError::NoResult) => return HttpResponse::BadRequest().reason("Username doesn't exist").finish(),
         Err(_) => return HttpResponse::InternalServerError().finish()
     };
+    let stored_hash = match &account_details {
+        Some(details) => details.password_hash.as_str(),
+        None => dummy_password_hash(&argon2)
+    };
 
-    let parsed_pw_hash = match PasswordHash::new(&account_details.password_hash) {
+    let parsed_pw_hash = match PasswordHash::new(stored_hash) {
         Ok(parsed) => parsed,
         Err(_) => {
             warn!("login: PasswordHash could not be created for user '{}'", data.username);
@@ -100,23 +350,130 @@ pub async fn login(
         }
     };
 
-    match argon2.verify_password(data.password.as_bytes(), &parsed_pw_hash) {
-        Ok(()) => {
-            let token = match auth.lock().unwrap().generate_user_token(account_details.id, &account_details.username).await {
-                Ok(token) => token,
-                Err(_) => return HttpResponse::InternalServerError().finish()
-            };
-            HttpResponse::Ok().json(json!({"id": account_details.id, "token": token}))
+    match (argon2.verify_password(data.password.as_bytes(), &parsed_pw_hash), account_details) {
+        (Ok(()), Some(account_details)) => {
+            let login_signals = &config.load().login_signals;
+            if login_signals.enabled {
+                // Not recording a signal doesn't fail the login, the error is logged
+                for (kind, signal_hash) in signals::of_login(&req, &login_signals.salt) {
+                    let _ = db.record_login_signal(account_details.id, kind, &signal_hash).await;
+                }
+            }
+            match auth.shard(account_details.id).generate_user_token(account_details.id, &account_details.username).await {
+                Ok(issued) => HttpResponse::Ok().json(token_response(account_details.id, issued)),
+                Err(_) => HttpResponse::InternalServerError().finish()
+            }
         },
-        Err(_) => HttpResponse::BadRequest().finish()
+        _ => HttpResponse::BadRequest().reason(INVALID_CREDENTIALS_REASON).finish()
+    }
+}
+
+/// The account logged in to as `username`, if any. Which account a username has,
+/// if any, is cached, so that repeated logins (e.g. during a login storm) only
+/// read the account by its id, and unknown usernames are not read at all. The
+/// password hash is always read from the database.
+async fn read_login_account(username: &str, db: &Database, profiles: &ProfileCache) -> Result<Option<AccountFromDB>, DBError> {
+    let account = match profiles.get_account_id(username).await {
+        Some(None) => return Ok(None),
+        Some(Some(account_id)) => db.read_account_by_id(account_id).await,
+        None => {
+            let account = db.read_account_by_username(username).await;
+            match &account {
+                Ok(details) => profiles.set_account_id(username, Some(details.id)).await,
+                Err(DBError::NoResult) => profiles.set_account_id(username, None).await,
+                Err(_) => {}
+            }
+            account
+        }
+    };
+    match account {
+        Ok(details) => Ok(Some(details)),
+        Err(DBError::NoResult) => Ok(None),
+        Err(e) => Err(e)
+    }
+}
+
+#[post("/account/refresh")]
+pub async fn refresh_token(
+    db: Data<Database>,
+    auth: Data<AuthShards>,
+    data: Json<RefreshRequest>
+) -> HttpResponse {
+    let account_details = match db.read_account_by_id(data.id).await {
+        Ok(details) => details,
+        Err(DBError::NoResult) => return HttpResponse::Unauthorized().reason(INVALID_REFRESH_REASON).finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+
+    match auth.shard(data.id).refresh_user_token(data.id, &account_details.username, &data.refresh_token).await {
+        Ok(Some(issued)) => HttpResponse::Ok().json(token_response(data.id, issued)),
+        Ok(None) => HttpResponse::Unauthorized().reason(INVALID_REFRESH_REASON).finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/account/sudo")]
+pub async fn create_sudo_token(
+    db: Data<Database>,
+    auth: Data<AuthShards>,
+    argon2: Data<Argon2<'_>>,
+    config: Data<SharedConfig>,
+    account: Authenticated,
+    data: Json<SudoRequest>
+) -> HttpResponse {
+    if data.password.is_empty() {
+        return HttpResponse::BadRequest().reason("The provided password was empty").finish()
+    }
+
+    let account_details = match db.read_account_by_id(account.0.user_id).await {
+        Ok(details) => details,
+        Err(DBError::NoResult) => return HttpResponse::BadRequest().reason(INVALID_CREDENTIALS_REASON).finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+
+    let parsed_pw_hash = match PasswordHash::new(&account_details.password_hash) {
+        Ok(parsed) => parsed,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    if argon2.verify_password(data.password.as_bytes(), &parsed_pw_hash).is_err() {
+        return HttpResponse::BadRequest().reason(INVALID_CREDENTIALS_REASON).finish()
+    }
+
+    let sudo_token = match auth.shard(account_details.id).generate_sudo_token(account_details.id).await {
+        Ok(token) => token,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    HttpResponse::Ok().json(json!({
+        "sudo_token": sudo_token,
+        "expires_in": config.load().tokens.sudo_ttl_sec
+    }))
+}
+
+#[post("/account/accept_terms")]
+pub async fn accept_terms(
+    db: Data<Database>,
+    account: Authenticated,
+    data: Json<TermsAcceptance>,
+    config: Data<SharedConfig>
+) -> HttpResponse {
+    match &config.load().terms.version {
+        Some(version) if *version == data.version => (),
+        Some(_) => return HttpResponse::BadRequest().reason("Not the current version of the terms").finish(),
+        None => return HttpResponse::NotFound().reason("There are no terms to accept").finish()
+    }
+
+    match db.accept_terms(account.0.user_id, &data.version).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
 #[put("/account/change_password")]
 pub async fn change_password(
     db: Data<Database>,
-    auth: Data<Mutex<AuthService>>,
+    auth: Data<AuthShards>,
     argon2: Data<Argon2<'_>>,
+    req: HttpRequest,
     bearer: BearerAuth,
     data: Json<AccountPasswordUpdate>
 ) -> HttpResponse {
@@ -139,11 +496,22 @@ pub async fn change_password(
 
     let old_account_details = match db.read_account_by_username(&username).await {
         Ok(account_details) => account_details,
-        Err(DBError::NoResult) => return HttpResponse::BadRequest().reason("Username does not exist").finish(),
+        Err(DBError::NoResult) => {
+            // Same work and response as an invalid old password
+            if let Ok(hash) = PasswordHash::new(dummy_password_hash(&argon2)) {
+                let _ = argon2.verify_password(old_pw.as_bytes(), &hash);
+            }
+            return HttpResponse::BadRequest().reason(INVALID_CREDENTIALS_REASON).finish()
+        },
         Err(_) => return HttpResponse::InternalServerError().finish(),
     };
 
-    if let Err(response) = verify_username_token(old_account_details.id, &username, bearer.token(), auth).await {
+    match verify_token(old_account_details.id, bearer.token(), auth.clone()).await {
+        Ok(identity) if ct_eq_bytes(identity.username.as_bytes(), username.as_bytes()) => (),
+        Ok(_) => return HttpResponse::Unauthorized().finish(),
+        Err(response) => return response
+    }
+    if let Err(response) = verify_sudo_token(old_account_details.id, &req, auth.clone()).await {
         return response
     }
 
@@ -153,331 +521,1993 @@ pub async fn change_password(
     };
     
     if argon2.verify_password(old_pw.as_bytes(), &old_pw_hash).is_err() {
-        return HttpResponse::BadRequest().reason("Invalid old password").finish()
+        return HttpResponse::BadRequest().reason(INVALID_CREDENTIALS_REASON).finish()
     }
     std::mem::drop(old_pw);  // TODO: Zeroize struct or just new and old passwords
 
     match db.update_account_password(old_account_details.id, &old_account_details.password_hash, &new_pw_hash.to_string()).await {
-        Ok(()) => HttpResponse::Ok().finish(),
+        Ok(()) => (),
         Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
-            HttpResponse::BadRequest().finish()
+            return HttpResponse::BadRequest().finish()
         },
-        Err(_) => HttpResponse::InternalServerError().finish()
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    }
+    let _ = db.record_audit(old_account_details.id, AuditAction::PasswordChange, Some(old_account_details.id), None).await;
+
+    // No session from before the change survives it, including this one
+    match auth.shard(old_account_details.id).renew_user_token(old_account_details.id, &username).await {
+        Ok(issued) => HttpResponse::Ok().json(token_response(old_account_details.id, issued)),
+        Err(_) => {
+            warn!("change_password: failed to revoke the sessions of user '{}'", username);
+            HttpResponse::InternalServerError().reason("Password changed, but existing sessions could not be revoked").finish()
+        }
     }
 }
 
-#[get("/posts")]
-pub async fn get_posts(db: Data<Database>) -> HttpResponse {
-    let result = db.read_posts(64).await;
-    match result {
-        Ok(posts) => HttpResponse::Ok().json(posts),
+#[get("/account/settings")]
+pub async fn get_account_settings(
+    db: Data<Database>,
+    account: Authenticated
+) -> HttpResponse {
+    match db.read_account_settings(account.0.user_id).await {
+        Ok(settings) => HttpResponse::Ok().json(settings),
         Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
-#[post("/posts")]
-pub async fn create_post(
+#[get("/account/onboarding")]
+pub async fn get_onboarding(
     db: Data<Database>,
-    data: Json<NewPost>,
-    auth: Data<Mutex<AuthService>>,
-    bearer: BearerAuth
+    account: Authenticated
 ) -> HttpResponse {
-    if data.title.is_empty() {
-        return HttpResponse::BadRequest().reason("Post has no title").finish()
-    }
-    if data.body.is_empty() {
-        return HttpResponse::BadRequest().reason("Post has no body/content").finish()
+    match db.read_onboarding_steps(account.0.user_id).await {
+        Ok(completed) => HttpResponse::Ok().json(OnboardingProgress::of(&completed)),
+        Err(_) => HttpResponse::InternalServerError().finish()
     }
+}
 
-    if let Err(err_response) = verify_token(data.poster_id, bearer.token(), auth).await {
-        return err_response;
+#[get("/account/export/preview")]
+pub async fn get_export_preview(
+    db: Data<Database>,
+    account: Authenticated
+) -> HttpResponse {
+    match db.read_export_counts(account.0.user_id).await {
+        Ok(counts) => HttpResponse::Ok().json(ExportPreview::of(&counts)),
+        Err(_) => HttpResponse::InternalServerError().finish()
     }
+}
 
-    let new_post = NewPost {
-        poster_id: data.poster_id, title: data.title.clone(),
-        body: data.body.clone()
-    };
-    
-    let result = db.create_post(new_post).await;
-    match result {
-        Ok(()) => HttpResponse::Ok().finish(),
+#[get("/account/api_keys")]
+pub async fn get_api_keys(
+    db: Data<Database>,
+    account: Authenticated
+) -> HttpResponse {
+    match db.read_api_keys(account.0.user_id).await {
+        Ok(keys) => HttpResponse::Ok().json(keys),
         Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
-#[get("/posts/{post_id}")]
-pub async fn get_post(db: Data<Database>, path: Path<String>) -> HttpResponse {
-    let post_id = match path.parse::<u64>() {
-        Ok(id) => id,
-        Err(_) => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
-    };
+/// Responds with the key itself, which is only stored as a hash and can't be
+/// retrieved again.
+#[post("/account/api_keys")]
+pub async fn create_api_key(
+    db: Data<Database>,
+    config: Data<SharedConfig>,
+    account: Authenticated,
+    data: Json<NewApiKey>
+) -> HttpResponse {
+    let name = data.name.trim();
+    if name.is_empty() {
+        return HttpResponse::BadRequest().reason("The provided name was empty").finish()
+    }
+    if name.chars().count() > MAX_API_KEY_NAME_LENGTH {
+        return HttpResponse::BadRequest().reason("Name is too long").finish()
+    }
+    match db.read_api_keys(account.0.user_id).await {
+        Ok(keys) if keys.len() as u64 >= config.load().api_keys.max_per_account => {
+            return HttpResponse::BadRequest().reason("Too many API keys").finish()
+        },
+        Ok(_) => (),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    }
 
-    let result = db.read_post_by_id(post_id).await;
-    match result {
-        Ok(post) => HttpResponse::Ok().json(post),
-        Err(DBError::NoResult) => HttpResponse::BadRequest().reason("Invalid post_id").finish(),
+    let key = uuid::Uuid::new_v4();
+    match db.create_api_key(account.0.user_id, name, &TokenHash::of(&key)).await {
+        Ok(api_key_id) => HttpResponse::Created().json(json!({"id": ids::PublicId(api_key_id), "key": key})),
         Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
-#[put("/posts/{post_id}")]
-pub async fn update_post(
+#[delete("/account/api_keys/{api_key_id}")]
+pub async fn delete_api_key(
     db: Data<Database>,
-    path: Path<String>,
-    data: Json<PostCommentUpdate>,
-    auth: Data<Mutex<AuthService>>,
-    bearer: BearerAuth
+    account: Authenticated,
+    path: Path<String>
 ) -> HttpResponse {
-    let post_id = match path.parse::<u64>() {
-        Ok(id) => id,
-        Err(_) => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
+    let api_key_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid api_key_id format").finish()
     };
 
-    if let Err(err_response) = verify_token(data.account_id, bearer.token(), auth).await {
-        return err_response;
-    }
-
-    match db.update_post_body(post_id, data.new_body.clone()).await {
+    match db.delete_api_key(api_key_id, account.0.user_id).await {
         Ok(()) => HttpResponse::Ok().finish(),
         Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
-            HttpResponse::BadRequest().reason("Invalid post_id").finish()
+            HttpResponse::BadRequest().reason("Invalid api_key_id").finish()
         },
         Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
-#[delete("/posts/{post_id}")]
-pub async fn delete_post(
+#[get("/account/api_keys/{api_key_id}/usage")]
+pub async fn get_api_key_usage(
     db: Data<Database>,
-    path: Path<String>,
-    data: Json<AccountID>,
-    auth: Data<Mutex<AuthService>>,
-    bearer: BearerAuth
+    config: Data<SharedConfig>,
+    quotas: Data<Quotas>,
+    account: Authenticated,
+    path: Path<String>
 ) -> HttpResponse {
-    let post_id = match path.parse::<u64>() {
-        Ok(id) => id,
-        Err(_) => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
+    let api_key_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid api_key_id format").finish()
+    };
+    let api_key = match db.read_api_key(api_key_id).await {
+        Ok(api_key) if api_key.account_id == account.0.user_id => api_key,
+        Ok(_) | Err(DBError::NoResult) => return HttpResponse::BadRequest().reason("Invalid api_key_id").finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
     };
 
-    if let Err(err_response) = verify_token(data.account_id, bearer.token(), auth).await {
-        return err_response;
+    let daily_quota = api_key.daily_quota.unwrap_or(config.load().api_keys.daily_quota);
+    match quotas.usage(api_key_id).await {
+        Ok((used, resets_at)) => HttpResponse::Ok().json(ApiKeyUsage {
+            used,
+            daily_quota,
+            remaining: daily_quota.saturating_sub(used),
+            resets_at
+        }),
+        Err(()) => HttpResponse::ServiceUnavailable().finish()
     }
+}
 
-    let result = db.delete_post(post_id).await;
-    match result {
-        Ok(()) => HttpResponse::Ok().finish(),
-        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
-            HttpResponse::BadRequest().reason("Invalid post_id").finish()
+#[put("/account/settings")]
+pub async fn update_account_settings(
+    db: Data<Database>,
+    account: Authenticated,
+    data: Json<AccountSettingsUpdate>,
+    profiles: Data<ProfileCache>
+) -> HttpResponse {
+    match db.update_account_settings(account.0.user_id, &data).await {
+        Ok(()) => {
+            profiles.invalidate(account.0.user_id).await;
+            HttpResponse::Ok().finish()
         },
         Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
-#[get("/posts/{post_id}/comments")]
-pub async fn get_post_comments(db: Data<Database>, path: Path<String>) -> HttpResponse {
-    let post_id = match path.parse::<u64>() {
-        Ok(id) => id,
-        Err(_) => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
+#[get("/posts")]
+pub async fn get_posts(
+    req: HttpRequest,
+    db: Data<Database>,
+    query: Query<PostsQuery>,
+    viewer: Query<ViewerQuery>,
+    config: Data<SharedConfig>,
+    listings: Data<PostListingCache>
+) -> HttpResponse {
+    let read = async {
+        match query.sort {
+            PostSort::Hot => read_hot_posts(LISTING_SIZE, viewer.viewer_id, &listings.hot_candidates, &db).await,
+            PostSort::New | PostSort::Top => db.read_posts(LISTING_SIZE, viewer.viewer_id, query.sort).await
+        }
     };
-    let result = db.read_comments_of_post(post_id).await;
-    match result {
-        Ok(comments) => HttpResponse::Ok().json(comments),
+    let mut posts = match listings.posts.get_or_load((viewer.viewer_id, query.sort), read).await {
+        Ok(posts) => posts,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    if query.body == BodyFormat::Summary {
+        let length = config.load().posts.summary_length;
+        posts.iter_mut().for_each(|post| {
+            // Summaries never cut through markup, as HTML is reduced to its text
+            if post.body_format == TextFormat::Html {
+                post.body = format::to_plain(&post.body, post.body_format);
+                post.body_format = TextFormat::Plain;
+            }
+            post.body = summarise(&post.body, length)
+        });
+    }
+    match listing_of(&db, posts, viewer.viewer_id).await {
+        Ok(listing) => negotiate::ok(&req, &listing),
         Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
-#[post("/comment")]
-pub async fn make_post_comment(
+#[get("/sync")]
+pub async fn sync(req: HttpRequest, db: Data<Database>, query: Query<SyncQuery>) -> HttpResponse {
+    // `since` is a previous `synced_at`, so is compared by the same clock as the
+    // `updated_at` it is compared to
+    let now = match db.read_current_time().await {
+        Ok(now) => now,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    if query.since > now.timestamp() {
+        return HttpResponse::BadRequest().reason("since is in the future").finish()
+    }
+    if now.timestamp().saturating_sub(query.since) > MAX_SYNC_WINDOW_SEC {
+        return HttpResponse::BadRequest().reason("since is too far in the past").finish()
+    }
+    let Some(since) = DateTime::from_timestamp(query.since, 0) else {
+        return HttpResponse::BadRequest().reason("Invalid since").finish()
+    };
+
+    let posts = match db.read_posts_updated_since(since).await {
+        Ok(posts) => posts,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let comments = match db.read_comments_updated_since(since).await {
+        Ok(comments) => comments,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    negotiate::ok(&req, &SyncDelta { posts, comments, synced_at: now.timestamp() })
+}
+
+#[post("/posts")]
+pub async fn create_post(
     db: Data<Database>,
-    data: Json<NewComment>,
-    auth: Data<Mutex<AuthService>>,
-    bearer: BearerAuth
+    account: Authenticated,
+    mut data: Json<NewPost>,
+    config: Data<SharedConfig>,
+    recent: Data<RecentSubmissions>
 ) -> HttpResponse {
-    if data.body.is_empty() {
-        return HttpResponse::BadRequest().reason("Comment without body").finish()
+    data.title = text::normalise_title(&data.title);
+    data.body = text::normalise_body(&data.body);
+    if data.title.is_empty() {
+        return HttpResponse::BadRequest().reason("Post has no title").finish()
+    }
+    // Link and image posts are of their url, and polls of their options, so the body is optional
+    if data.body.is_empty() && data.kind == PostKind::Text {
+        return HttpResponse::BadRequest().reason("Post has no body/content").finish()
+    }
+    let url = match links::post_url(data.kind, data.url.as_deref()) {
+        Ok(url) => url,
+        Err(e) => return HttpResponse::BadRequest().reason(e.reason()).finish()
+    };
+    let poll_options = match polls::options(data.kind, &data.poll_options) {
+        Ok(poll_options) => poll_options,
+        Err(e) => return HttpResponse::BadRequest().reason(e.reason()).finish()
+    };
+    let config = config.load();
+    let alt_text = match accessibility::alt_text(data.kind, data.alt_text.as_deref(), config.posts.require_alt_text) {
+        Ok(alt_text) => alt_text,
+        Err(e) => return HttpResponse::BadRequest().reason(e.reason()).finish()
+    };
+    let tldr = match accessibility::tldr(data.tldr.as_deref()) {
+        Ok(tldr) => tldr,
+        Err(e) => return HttpResponse::BadRequest().reason(e.reason()).finish()
+    };
+    let texts = [Some(&data.title), Some(&data.body), alt_text.as_ref(), tldr.as_ref()];
+    if texts.into_iter().flatten().chain(&poll_options).any(|text| config.contains_filtered_word(text)) {
+        return HttpResponse::BadRequest().reason(FILTERED_WORD_REASON).finish()
+    }
+    if let Some(expires_at) = data.expires_at {
+        match db.read_current_time().await {
+            Ok(now) if expires_at <= now => {
+                return HttpResponse::BadRequest().reason("Post expiry is in the past").finish()
+            },
+            Ok(_) => {},
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        }
     }
 
-    if let Err(err_response) = verify_token(data.commenter_id, bearer.token(), auth).await {
+    if let Err(err_response) = verify_karma(account.0.user_id, GatedAction::Post, &config, &db).await {
         return err_response;
     }
+    if config.onboarding.enabled {
+        let activity = match db.read_account_activity(account.0.user_id).await {
+            Ok(activity) => activity,
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        };
+        let texts = [data.title.as_str(), data.body.as_str(), url.as_deref().unwrap_or_default()];
+        if let Err(violation) = policy::check_post(&config.onboarding, &activity, &texts, Utc::now().timestamp()) {
+            return HttpResponse::Forbidden().reason(violation.reason()).finish()
+        }
+    }
 
-    let new_comment = NewComment {
-        post_id: data.post_id, commenter_id: data.commenter_id,
-        comment_reply_id: data.comment_reply_id, body: data.body.clone()
+    // Earlier discussions of a link, so that the poster can join one instead
+    let duplicates = match (data.kind, url.as_deref()) {
+        (PostKind::Link, Some(url)) => match db.read_link_submissions(url, account.0.user_id).await {
+            Ok(submissions) => Some(submissions),
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        },
+        _ => None
     };
-    
-    let result = db.create_comment(new_comment).await;
-    match result {
-        Ok(()) => HttpResponse::Ok().finish(),
-        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
-            HttpResponse::BadRequest().reason("Comment data was invalid").finish()
+
+    // A repeat of a post just made is most likely a retried request
+    let options = poll_options.join("\n");
+    let content = [data.title.as_str(), url.as_deref().unwrap_or_default(), data.body.as_str(), options.as_str()];
+    let window_sec = config.posts.repeat_window_sec;
+    match recent.claim(account.0.user_id, &content, window_sec).await {
+        Ok(()) => {},
+        Err(Repeat::Of(post_id)) => {
+            return HttpResponse::Conflict().reason(REPEAT_REASON).json(json!({"id": ids::PublicId(post_id)}))
         },
-        Err(_) => HttpResponse::InternalServerError().finish()
+        Err(Repeat::Pending) => return HttpResponse::Conflict().reason(REPEAT_REASON).finish()
+    }
+
+    let new_post = NewPost {
+        kind: data.kind, title: data.title.clone(), url: url.clone(), alt_text,
+        body: format::prepare(&data.body, data.body_format), tldr, poll_options, body_format: data.body_format,
+        comment_mode: data.comment_mode, visibility: data.visibility, expires_at: data.expires_at
+    };
+    let texts = [Some(&new_post.title), Some(&data.body), new_post.alt_text.as_ref(), new_post.tldr.as_ref()];
+    let text = texts.into_iter().flatten().chain(&new_post.poll_options).map(String::as_str).collect::<Vec<&str>>().join("\n");
+    let moderation = moderate(&config.moderation, text).await;
+
+    let post_id = match db.create_post(account.0.user_id, new_post, moderation).await {
+        Ok(post_id) => {
+            recent.record(account.0.user_id, &content, post_id, window_sec).await;
+            post_id
+        },
+        Err(_) => {
+            recent.release(account.0.user_id, &content).await;
+            return HttpResponse::InternalServerError().finish()
+        }
+    };
+    let mut response = created(moderation, &post_location(post_id));
+    match duplicates {
+        Some(duplicates) => response.json(json!({"id": ids::PublicId(post_id), "duplicates": duplicates})),
+        None => response.json(json!({"id": ids::PublicId(post_id)}))
     }
 }
 
-#[put("/comment/{comment_id}")]
-pub async fn update_comment(
+#[get("/posts/{post_id}")]
+pub async fn get_post(
     db: Data<Database>,
     path: Path<String>,
-    data: Json<PostCommentUpdate>,
-    auth: Data<Mutex<AuthService>>,
-    bearer: BearerAuth
+    viewer: Query<ViewerQuery>,
+    query: Query<TranslateQuery>,
+    config: Data<SharedConfig>,
+    translations: Data<TranslationCache>,
+    req: HttpRequest
 ) -> HttpResponse {
-    let comment_id = match path.parse::<u64>() {
-        Ok(id) => id,
-        Err(_) => return HttpResponse::BadRequest().reason("Invalid comment_id format").finish()
+    let post_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
+    };
+    let config = config.load();
+    let language = match query.translate.as_deref() {
+        Some(language) => match translate::configured_language(&config.translation, language) {
+            Some(language) => Some(language),
+            None => return HttpResponse::BadRequest().reason("Translation into this language is not available").finish()
+        },
+        None => None
     };
 
-    if let Err(err_response) = verify_token(data.account_id, bearer.token(), auth).await {
-        return err_response;
+    let post = match read_visible_post(post_id, viewer.viewer_id, &db).await {
+        Ok(post) => post,
+        Err(err_response) => return err_response
+    };
+    let Some(language) = language else {
+        return HttpResponse::Ok().json(post)
+    };
+    match translate_post(&post, language, &config.translation, &translations, &req).await {
+        Ok(body) => HttpResponse::Ok().json(TranslatedPost { post, translation: Translation { language: language.to_string(), body } }),
+        Err(err_response) => err_response
+    }
+}
+
+#[put("/posts/{post_id}")]
+pub async fn update_post(
+    db: Data<Database>,
+    acting: Acting,
+    path: Path<String>,
+    mut data: Json<PostUpdate>,
+    config: Data<SharedConfig>
+) -> HttpResponse {
+    let post_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
+    };
+    data.new_title = data.new_title.as_deref().map(text::normalise_title);
+    data.new_body = data.new_body.as_deref().map(text::normalise_body);
+    if data.new_title.is_none() && data.new_body.is_none() && data.new_alt_text.is_none() && data.new_tldr.is_none() {
+        return HttpResponse::BadRequest().reason("No new title, body, alt text or TL;DR provided").finish()
+    }
+    if data.new_title.as_ref().is_some_and(|title| title.is_empty()) {
+        return HttpResponse::BadRequest().reason("Post has no title").finish()
+    }
+    let new_tldr = match data.new_tldr.as_deref().map(|tldr| accessibility::tldr(Some(tldr))).transpose() {
+        Ok(tldr) => tldr,
+        Err(e) => return HttpResponse::BadRequest().reason(e.reason()).finish()
+    };
+    let config = config.load();
+    let texts = [data.new_title.as_ref(), data.new_body.as_ref(), data.new_alt_text.as_ref(), data.new_tldr.as_ref()];
+    if texts.into_iter().flatten().any(|text| config.contains_filtered_word(text)) {
+        return HttpResponse::BadRequest().reason(FILTERED_WORD_REASON).finish()
     }
 
-    match db.update_comment_body(comment_id, data.new_body.clone()).await {
+    let post = match db.read_post_by_id(post_id).await {
+        Ok(post) => post,
+        Err(DBError::NoResult) => {
+            return missing_post(post_id, &db, HttpResponse::BadRequest().reason("Invalid post_id").finish()).await
+        },
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    if data.new_title.is_some() {
+        let now = match db.read_current_time().await {
+            Ok(now) => now,
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        };
+        if !config.posts.title_editable(post.time_stamp.timestamp(), now.timestamp()) {
+            return HttpResponse::Forbidden().reason("The title can no longer be edited").finish()
+        }
+    }
+    let new_alt_text = data.new_alt_text.as_deref()
+        .map(|alt_text| accessibility::alt_text(post.kind, Some(alt_text), config.posts.require_alt_text))
+        .transpose();
+    let new_alt_text = match new_alt_text {
+        Ok(alt_text) => alt_text,
+        Err(e) => return HttpResponse::BadRequest().reason(e.reason()).finish()
+    };
+
+    let new_body = data.new_body.as_deref().map(|body| format::prepare(body, post.body_format));
+    let new_alt_text = new_alt_text.as_ref().map(Option::as_deref);
+    let new_tldr = new_tldr.as_ref().map(Option::as_deref);
+    match db.update_post(post_id, acting.author_id(), data.new_title.as_deref(), new_body.as_deref(), new_alt_text, new_tldr).await {
         Ok(()) => HttpResponse::Ok().finish(),
         Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
-            HttpResponse::BadRequest().reason("Invalid comment_id").finish()
+            HttpResponse::BadRequest().reason("Invalid post_id").finish()
         },
         Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
-#[delete("/comment/{comment_id}")]
-pub async fn delete_comment(
+#[put("/posts/{post_id}/comment_mode")]
+pub async fn update_comment_mode(
     db: Data<Database>,
+    acting: Acting,
     path: Path<String>,
-    data: Json<AccountID>,
-    auth: Data<Mutex<AuthService>>,
-    bearer: BearerAuth
+    data: Json<CommentModeUpdate>
 ) -> HttpResponse {
-    let comment_id: u64 = match path.parse::<u64>() {
-        Ok(id) => id,
-        Err(_) => return HttpResponse::BadRequest().reason("Invalid comment_id format").finish()
+    let post_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
     };
 
-    if let Err(err_response) = verify_token(data.account_id, bearer.token(), auth).await {
-        return err_response;
+    match db.read_post_by_id(post_id).await {
+        Ok(_) => {},
+        Err(DBError::NoResult) => {
+            return missing_post(post_id, &db, HttpResponse::BadRequest().reason("Invalid post_id").finish()).await
+        },
+        Err(_) => return HttpResponse::InternalServerError().finish()
     }
 
-    // Mark post as "deleted" by overwriting the body
-    let result = db.update_comment_body(comment_id, "[DELETED]".to_string()).await;
-    match result {
+    match db.update_comment_mode(post_id, acting.author_id(), data.comment_mode).await {
         Ok(()) => HttpResponse::Ok().finish(),
-        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
-            HttpResponse::BadRequest().reason("Invalid comment_id").finish()
-        },
         Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
-#[get("/users/{user_id}/posts")]
-pub async fn get_user_posts(db: Data<Database>, path: Path<String>) -> HttpResponse {
-    let user_id = match path.parse::<u64>() {
-        Ok(id) => id,
-        Err(_) => return HttpResponse::BadRequest().reason("Invalid user_id format").finish()
+#[get("/posts/{post_id}/revisions")]
+pub async fn get_post_revisions(db: Data<Database>, path: Path<String>, viewer: Query<ViewerQuery>) -> HttpResponse {
+    let post_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
     };
-    let result = db.read_posts_by_user(user_id).await;
-    match result {
-        Ok(posts) => HttpResponse::Ok().json(posts),
+    // The revisions of a post the viewer can't see would reveal its body
+    if let Err(err_response) = read_visible_post(post_id, viewer.viewer_id, &db).await {
+        return err_response
+    }
+    match db.read_post_revisions(post_id).await {
+        Ok(revisions) => HttpResponse::Ok().json(revisions),
         Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
-#[get("/users/{user_id}/comments")]
-pub async fn get_user_comments(db: Data<Database>, path: Path<String>) -> HttpResponse {
-    let user_id = match path.parse::<u64>() {
-        Ok(id) => id,
-        Err(_) => return HttpResponse::BadRequest().reason("Invalid user_id format").finish()
+#[get("/posts/{post_id}/poll")]
+pub async fn get_poll(db: Data<Database>, path: Path<String>, viewer: Query<ViewerQuery>) -> HttpResponse {
+    let post_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
     };
-    let result = db.read_comments_by_user(user_id).await;
-    match result {
-        Ok(comments) => HttpResponse::Ok().json(comments),
+    match read_visible_post(post_id, viewer.viewer_id, &db).await {
+        Ok(post) if post.kind != PostKind::Poll => return HttpResponse::NotFound().reason(NOT_A_POLL_REASON).finish(),
+        Ok(_) => {},
+        Err(err_response) => return err_response
+    }
+
+    let options = match db.read_poll_options(post_id).await {
+        Ok(options) => options,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let voted_option_id = match viewer.viewer_id {
+        Some(viewer_id) => match db.read_poll_vote(post_id, viewer_id).await {
+            Ok(option_id) => option_id,
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        },
+        None => None
+    };
+    HttpResponse::Ok().json(PollResults { options, voted_option_id })
+}
+
+#[post("/posts/{post_id}/poll/vote")]
+pub async fn vote_in_poll(
+    db: Data<Database>,
+    account: Authenticated,
+    path: Path<String>,
+    data: Json<PollVoteRequest>
+) -> HttpResponse {
+    let post_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
+    };
+    match read_interactable_post(post_id, account.0.user_id, &db).await {
+        Ok(post) if post.kind != PostKind::Poll => return HttpResponse::NotFound().reason(NOT_A_POLL_REASON).finish(),
+        Ok(_) => {},
+        Err(err_response) => return err_response
+    }
+
+    match db.vote_in_poll(post_id, data.option_id, account.0.user_id).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(DBError::NoResult) => HttpResponse::BadRequest().reason("Invalid option_id").finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[delete("/posts/{post_id}")]
+pub async fn delete_post(
+    db: Data<Database>,
+    account: Authenticated,
+    acting: Acting,
+    path: Path<String>
+) -> HttpResponse {
+    let post_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
+    };
+
+    let result = db.delete_post(post_id, acting.author_id()).await;
+    match result {
+        Ok(()) => {
+            let _ = db.record_audit(account.0.user_id, AuditAction::PostDelete, Some(post_id), Some(acting.name())).await;
+            HttpResponse::Ok().finish()
+        },
+        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
+            HttpResponse::BadRequest().reason("Invalid post_id").finish()
+        },
+        Err(DBError::LegalHold) => HttpResponse::Conflict().reason(LEGAL_HOLD_REASON).finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/posts/{post_id}/mark_read")]
+pub async fn mark_post_read(
+    db: Data<Database>,
+    account: Authenticated,
+    path: Path<String>
+) -> HttpResponse {
+    let post_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
+    };
+
+    match db.read_post_by_id(post_id).await {
+        Ok(_) => {},
+        Err(DBError::NoResult) => {
+            return missing_post(post_id, &db, HttpResponse::BadRequest().reason("Invalid post_id").finish()).await
+        },
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    }
+
+    match db.mark_post_read(post_id, account.0.user_id).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/posts/{post_id}/comments")]
+pub async fn get_post_comments(
+    req: HttpRequest,
+    db: Data<Database>,
+    path: Path<String>,
+    viewer: Query<ViewerQuery>,
+    query: Query<ThreadQuery>
+) -> HttpResponse {
+    let post_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
+    };
+
+    if let Err(err_response) = read_visible_post(post_id, viewer.viewer_id, &db).await {
+        return err_response;
+    }
+    let pinned_id = match db.read_pinned_comment(post_id).await {
+        Ok(pinned_id) => pinned_id,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let result = match query.format {
+        ThreadFormat::Flat => read_thread_page(post_id, pinned_id, false, limit, query.after, &db).await
+            .map(|page| negotiate::ok(&req, &page)),
+        ThreadFormat::Tree => read_thread_tree(post_id, pinned_id, limit, query.after, Limits::default(), &db).await
+            .map(|page| negotiate::ok(&req, &page))
+    };
+    result.unwrap_or_else(|_| HttpResponse::InternalServerError().finish())
+}
+
+/// A comment in the tree format of its thread, for continuing the `MoreReplies`
+/// of a tree.
+#[get("/comment/{comment_id}/context")]
+pub async fn get_comment_context(
+    req: HttpRequest,
+    db: Data<Database>,
+    path: Path<String>,
+    viewer: Query<ViewerQuery>,
+    query: Query<ContextQuery>
+) -> HttpResponse {
+    let comment_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid comment_id format").finish()
+    };
+    let post_id = match db.read_comment_thread(comment_id).await {
+        Ok((post_id, _)) => post_id,
+        Err(DBError::NoResult) => return HttpResponse::BadRequest().reason("Invalid comment_id").finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+
+    if let Err(err_response) = read_visible_post(post_id, viewer.viewer_id, &db).await {
+        return err_response;
+    }
+    let thread = match read_thread(post_id, &db).await {
+        Ok(thread) => thread,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    match threads::context(thread, comment_id, query.after, Limits::default()) {
+        Some(context) => negotiate::ok(&req, &context),
+        None => HttpResponse::BadRequest().reason("Invalid comment_id").finish()
+    }
+}
+
+#[post("/comment")]
+pub async fn make_post_comment(
+    db: Data<Database>,
+    account: Authenticated,
+    mut data: Json<NewComment>,
+    config: Data<SharedConfig>
+) -> HttpResponse {
+    data.body = text::normalise_body(&data.body);
+    if data.body.is_empty() {
+        return HttpResponse::BadRequest().reason("Comment without body").finish()
+    }
+    let config = config.load();
+    if config.contains_filtered_word(&data.body) {
+        return HttpResponse::BadRequest().reason(FILTERED_WORD_REASON).finish()
+    }
+
+    if let Err(err_response) = verify_karma(account.0.user_id, GatedAction::Comment, &config, &db).await {
+        return err_response;
+    }
+    let post = match db.read_post_by_id(data.post_id).await {
+        Ok(post) => post,
+        Err(DBError::NoResult) => {
+            return missing_post(data.post_id, &db, HttpResponse::BadRequest().reason("Invalid post_id").finish()).await
+        },
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    match can_view(&post, Some(account.0.user_id), &db).await {
+        Ok(true) => {},
+        Ok(false) => return HttpResponse::NotFound().reason("Invalid post_id").finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    }
+    if let Err(err_response) = verify_comment_mode(&post, account.0.user_id, &db).await {
+        return err_response;
+    }
+    if config.onboarding.enabled {
+        let activity = match db.read_account_activity(account.0.user_id).await {
+            Ok(activity) => activity,
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        };
+        if let Err(violation) = policy::check_comment(&config.onboarding, &activity, Utc::now().timestamp()) {
+            return HttpResponse::Forbidden().reason(violation.reason()).finish()
+        }
+    }
+
+    let new_comment = NewComment {
+        post_id: data.post_id, comment_reply_id: data.comment_reply_id,
+        body: format::prepare(&data.body, data.body_format), body_format: data.body_format
+    };
+    let moderation = moderate(&config.moderation, data.body.clone()).await;
+    
+    let result = db.create_comment(account.0.user_id, new_comment, moderation).await;
+    match result {
+        Ok(comment_id) => {
+            created(moderation, &comment_location(comment_id))
+                .json(json!({"id": ids::PublicId(comment_id)}))
+        },
+        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
+            HttpResponse::BadRequest().reason("Comment data was invalid").finish()
+        },
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[put("/comment/{comment_id}")]
+pub async fn update_comment(
+    db: Data<Database>,
+    acting: Acting,
+    path: Path<String>,
+    mut data: Json<PostCommentUpdate>,
+    config: Data<SharedConfig>
+) -> HttpResponse {
+    let comment_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid comment_id format").finish()
+    };
+    data.new_body = text::normalise_body(&data.new_body);
+    if data.new_body.is_empty() {
+        return HttpResponse::BadRequest().reason("Comment without body").finish()
+    }
+    if config.load().contains_filtered_word(&data.new_body) {
+        return HttpResponse::BadRequest().reason(FILTERED_WORD_REASON).finish()
+    }
+
+    let body_format = match db.read_comment_format(comment_id).await {
+        Ok(format) => format,
+        Err(DBError::NoResult) => return HttpResponse::BadRequest().reason("Invalid comment_id").finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+
+    match db.update_comment_body(comment_id, acting.author_id(), format::prepare(&data.new_body, body_format)).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
+            HttpResponse::BadRequest().reason("Invalid comment_id").finish()
+        },
+        Err(DBError::LegalHold) => HttpResponse::Conflict().reason(LEGAL_HOLD_REASON).finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/comment/{comment_id}/pin")]
+pub async fn pin_comment(
+    db: Data<Database>,
+    path: Path<String>
+) -> HttpResponse {
+    let comment_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid comment_id format").finish()
+    };
+
+    // Only the author of the post or a moderator gets here, as checked by `access`
+    let post_id = match db.read_comment_thread(comment_id).await {
+        Ok((post_id, _)) => post_id,
+        Err(DBError::NoResult) => return HttpResponse::BadRequest().reason("Invalid comment_id").finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+
+    match db.pin_comment(post_id, comment_id).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[delete("/comment/{comment_id}/pin")]
+pub async fn unpin_comment(
+    db: Data<Database>,
+    path: Path<String>
+) -> HttpResponse {
+    let comment_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid comment_id format").finish()
+    };
+
+    // Only the author of the post or a moderator gets here, as checked by `access`
+    let post_id = match db.read_comment_thread(comment_id).await {
+        Ok((post_id, _)) => post_id,
+        Err(DBError::NoResult) => return HttpResponse::BadRequest().reason("Invalid comment_id").finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+
+    match db.unpin_comment(post_id, comment_id).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
+            HttpResponse::BadRequest().reason("Comment is not pinned").finish()
+        },
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[delete("/comment/{comment_id}")]
+pub async fn delete_comment(
+    db: Data<Database>,
+    account: Authenticated,
+    acting: Acting,
+    path: Path<String>
+) -> HttpResponse {
+    let comment_id: u64 = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid comment_id format").finish()
+    };
+
+    // Mark post as "deleted" by overwriting the body
+    let result = db.update_comment_body(comment_id, acting.author_id(), "[DELETED]".to_string()).await;
+    match result {
+        Ok(()) => {
+            let _ = db.record_audit(account.0.user_id, AuditAction::CommentDelete, Some(comment_id), Some(acting.name())).await;
+            HttpResponse::Ok().finish()
+        },
+        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
+            HttpResponse::BadRequest().reason("Invalid comment_id").finish()
+        },
+        Err(DBError::LegalHold) => HttpResponse::Conflict().reason(LEGAL_HOLD_REASON).finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/users/{user_id}")]
+pub async fn get_user_profile(
+    db: Data<Database>,
+    path: Path<String>,
+    profiles: Data<ProfileCache>
+) -> HttpResponse {
+    let user_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid user_id format").finish()
+    };
+    if let Some(profile) = profiles.get(user_id).await {
+        return HttpResponse::Ok().json(profile)
+    }
+    // Only one request at a time loads a missing profile, the rest read it from
+    // the cache once loaded
+    let _flight = profiles.begin_load(user_id).await;
+    if let Some(profile) = profiles.get(user_id).await {
+        return HttpResponse::Ok().json(profile)
+    }
+
+    let account = match db.read_public_account(user_id).await {
+        Ok(account) => account,
+        Err(DBError::NoResult) => return HttpResponse::BadRequest().reason("Invalid user_id").finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let settings = match db.read_account_settings(user_id).await {
+        Ok(settings) => settings,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let profile = PublicProfile { account, settings };
+    profiles.set(&profile).await;
+    HttpResponse::Ok().json(profile)
+}
+
+#[get("/users/{user_id}/posts")]
+pub async fn get_user_posts(
+    req: HttpRequest,
+    db: Data<Database>,
+    path: Path<String>,
+    page: Query<PageQuery>,
+    viewer: Query<ViewerQuery>,
+    counts: Data<TotalCountCache>
+) -> HttpResponse {
+    let user_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid user_id format").finish()
+    };
+
+    let privileged = match viewer_is_privileged(user_id, &viewer, &db).await {
+        Ok(privileged) => privileged,
+        Err(err_response) => return err_response
+    };
+    if !privileged {
+        match db.read_account_settings(user_id).await {
+            Ok(settings) if !settings.show_posts.0 => {
+                return HttpResponse::Forbidden().reason("User has hidden their posts").finish()
+            },
+            Ok(_) => {},
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        }
+    }
+
+    let audience = match (privileged, viewer.viewer_id) {
+        (true, _) => PostAudience::ALL,
+        (false, Some(viewer_id)) => match db.is_following(viewer_id, user_id).await {
+            Ok(following) => PostAudience { unlisted: false, followers: following },
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        },
+        (false, None) => PostAudience::PUBLIC
+    };
+
+    let limit = page_limit(&page);
+    let posts = match db.read_posts_by_user(user_id, limit, page.before, audience).await {
+        Ok(posts) => posts,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let listing = match listing_of(&db, posts, viewer.viewer_id).await {
+        Ok(listing) => listing,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let count = db.count_posts_by_user(user_id, audience);
+    match total_count(&counts, (CountedListing::UserPosts(audience), user_id), count).await {
+        Ok(total) => negotiate::ok(&req, &page_of(listing, total, limit, |listed| listed.post.id)),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+/// The posts of the account and the accounts it follows, from the Feed table
+/// maintained by `jobs::feed`.
+#[get("/feed")]
+pub async fn get_feed(
+    req: HttpRequest,
+    db: Data<Database>,
+    account: Authenticated,
+    page: Query<PageQuery>,
+    counts: Data<TotalCountCache>
+) -> HttpResponse {
+    let limit = page_limit(&page);
+    let posts = match db.read_feed(account.0.user_id, limit, page.before).await {
+        Ok(posts) => posts,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let listing = match listing_of(&db, posts, Some(account.0.user_id)).await {
+        Ok(listing) => listing,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let count = db.count_feed(account.0.user_id);
+    match total_count(&counts, (CountedListing::Feed, account.0.user_id), count).await {
+        Ok(total) => negotiate::ok(&req, &page_of(listing, total, limit, |listed| listed.post.id)),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+/// A random sample of the newest public posts that the viewer has not read, more
+/// likely to include a post the higher its hot score.
+#[get("/discover")]
+pub async fn discover(
+    req: HttpRequest,
+    db: Data<Database>,
+    query: Query<DiscoverQuery>,
+    viewer: Query<ViewerQuery>
+) -> HttpResponse {
+    let limit = query.limit.unwrap_or(DEFAULT_DISCOVER_SIZE).min(MAX_DISCOVER_SIZE);
+    let candidates = match db.read_discover_candidates(viewer.viewer_id, ranking::DISCOVER_CANDIDATES).await {
+        Ok(candidates) => candidates,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let now = match db.read_current_time().await {
+        Ok(now) => now,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let posts = ranking::sample_weighted(candidates, now, limit as usize, || {
+        // 53 random bits, the precision of an f64, in 0..1
+        (OsRng.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    });
+    match listing_of(&db, posts, viewer.viewer_id).await {
+        Ok(listing) => negotiate::ok(&req, &listing),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/users/{user_id}/comments")]
+pub async fn get_user_comments(
+    req: HttpRequest,
+    db: Data<Database>,
+    path: Path<String>,
+    viewer: Query<ViewerQuery>,
+    page: Query<PageQuery>,
+    counts: Data<TotalCountCache>
+) -> HttpResponse {
+    let user_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid user_id format").finish()
+    };
+    let limit = page_limit(&page);
+    let comments = match db.read_comments_by_user(user_id, viewer.viewer_id, limit, page.before).await {
+        Ok(comments) => comments,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let count = db.count_comments_by_user(user_id);
+    match total_count(&counts, (CountedListing::UserComments, user_id), count).await {
+        Ok(total) => negotiate::ok(&req, &page_of(comments, total, limit, |comment| comment.id)),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/search/comments")]
+pub async fn search_comments(
+    req: HttpRequest,
+    db: Data<Database>,
+    query: Query<CommentSearchQuery>,
+    page: Query<PageQuery>
+) -> HttpResponse {
+    let terms = query.q.trim();
+    if terms.is_empty() {
+        return HttpResponse::BadRequest().reason("Empty search").finish()
+    }
+    if terms.chars().count() > MAX_SEARCH_LENGTH {
+        return HttpResponse::BadRequest().reason("Search is too long").finish()
+    }
+    let Some(terms) = Terms::of(terms) else {
+        return HttpResponse::BadRequest().reason("Search has no words long enough to match").finish()
+    };
+
+    let limit = page_limit(&page);
+    let comments = match db.search_comments(&terms, query.post_id, query.user_id, limit, page.before).await {
+        Ok(comments) => comments,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    match db.count_search_comments(&terms, query.post_id, query.user_id).await {
+        Ok(total) => negotiate::ok(&req, &page_of(comments, total, limit, |comment| comment.id)),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+/// The posts or comments the account has liked, for clients to restore which
+/// content is liked.
+#[get("/users/me/likes")]
+pub async fn get_likes_given(
+    req: HttpRequest,
+    db: Data<Database>,
+    account: Authenticated,
+    query: Query<LikesQuery>,
+    page: Query<PageQuery>,
+    counts: Data<TotalCountCache>
+) -> HttpResponse {
+    let limit = page_limit(&page);
+    let likes = match db.read_likes_given(account.0.user_id, query.kind, limit, page.before).await {
+        Ok(likes) => likes,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let count = db.count_likes_given(account.0.user_id, query.kind);
+    match total_count(&counts, (CountedListing::LikesGiven(query.kind), account.0.user_id), count).await {
+        Ok(total) => negotiate::ok(&req, &page_of(likes, total, limit, |like| like.id)),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/users/{user_id}/awards")]
+pub async fn get_user_awards(
+    req: HttpRequest,
+    db: Data<Database>,
+    path: Path<String>,
+    page: Query<PageQuery>,
+    counts: Data<TotalCountCache>
+) -> HttpResponse {
+    let user_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid user_id format").finish()
+    };
+    let limit = page_limit(&page);
+    let awards = match db.read_awards_received(user_id, limit, page.before).await {
+        Ok(awards) => awards,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let count = db.count_awards_received(user_id);
+    match total_count(&counts, (CountedListing::UserAwards, user_id), count).await {
+        Ok(total) => negotiate::ok(&req, &page_of(awards, total, limit, |award| award.id)),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/users/{user_id}/stats")]
+pub async fn get_user_stats(
+    db: Data<Database>,
+    path: Path<String>,
+    viewer: Query<ViewerQuery>,
+    stats_cache: Data<UserStatsCache>
+) -> HttpResponse {
+    let user_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid user_id format").finish()
+    };
+    let mut stats = match stats_cache.get_or_load(user_id, db.read_user_stats(user_id)).await {
+        Ok(stats) => stats,
+        Err(DBError::NoResult) => return HttpResponse::BadRequest().reason("Invalid user_id").finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+
+    // The stats are cached for everyone, so likes are hidden from each response
+    let privileged = match viewer_is_privileged(user_id, &viewer, &db).await {
+        Ok(privileged) => privileged,
+        Err(err_response) => return err_response
+    };
+    if !privileged {
+        match db.read_account_settings(user_id).await {
+            Ok(settings) if !settings.show_likes.0 => {
+                stats.likes_received = None;
+                stats.likes_given = None;
+            },
+            Ok(_) => {},
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        }
+    }
+    HttpResponse::Ok().json(stats)
+}
+
+#[post("/vote/post")]
+pub async fn vote_on_post(
+    db: Data<Database>,
+    account: Authenticated,
+    data: Json<PostLike>,
+    config: Data<SharedConfig>
+) -> HttpResponse {
+    if data.post_id == 0 {
+        return HttpResponse::BadRequest().finish()
+    }
+    let post = match read_interactable_post(data.post_id, account.0.user_id, &db).await {
+        Ok(post) => post,
+        Err(err_response) => return err_response
+    };
+
+    // Taking a like back is never gated
+    if data.liked {
+        let config = config.load();
+        if let Err(err_response) = verify_karma(account.0.user_id, GatedAction::Like, &config, &db).await {
+            return err_response;
+        }
+        if let Err(err_response) = verify_not_own(account.0.user_id, &config, ready(Ok(post.poster_id))).await {
+            return err_response;
+        }
+    }
+
+    let result = match data.liked {
+        true  => db.create_post_like(data.post_id, account.0.user_id).await,
+        false => db.delete_post_like(data.post_id, account.0.user_id).await
+    };
+    match result {
+        Ok(state) => HttpResponse::Ok().json(state),
+        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
+            HttpResponse::AlreadyReported().finish()
+        },
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/vote/comment")]
+pub async fn vote_on_comment(
+    db: Data<Database>,
+    account: Authenticated,
+    data: Json<CommentLike>,
+    config: Data<SharedConfig>
+) -> HttpResponse {
+    if data.comment_id == 0 {
+        return HttpResponse::BadRequest().finish()
+    }
+    if let Err(err_response) = read_interactable_comment_post(data.comment_id, account.0.user_id, &db).await {
+        return err_response;
+    }
+
+    // Taking a like back is never gated
+    if data.liked {
+        let config = config.load();
+        if let Err(err_response) = verify_karma(account.0.user_id, GatedAction::Like, &config, &db).await {
+            return err_response;
+        }
+        if let Err(err_response) = verify_not_own(account.0.user_id, &config, db.read_comment_owner(data.comment_id)).await {
+            return err_response;
+        }
+    }
+
+    let result = match data.liked {
+        true  => db.create_comment_like(data.comment_id, account.0.user_id).await,
+        false => db.delete_comment_like(data.comment_id, account.0.user_id).await
+    };
+    match result {
+        Ok(state) => HttpResponse::Ok().json(state),
+        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
+            HttpResponse::AlreadyReported().finish()
+        },
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/posts/{post_id}/like/toggle")]
+pub async fn toggle_post_like(
+    db: Data<Database>,
+    account: Authenticated,
+    path: Path<String>,
+    config: Data<SharedConfig>
+) -> HttpResponse {
+    let post_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
+    };
+
+    let config = config.load();
+    if let Err(err_response) = verify_karma(account.0.user_id, GatedAction::Like, &config, &db).await {
+        return err_response;
+    }
+    let post = match read_interactable_post(post_id, account.0.user_id, &db).await {
+        Ok(post) => post,
+        Err(err_response) => return err_response
+    };
+    // Refused even when it would take back a like, which the vote endpoints can still do
+    if let Err(err_response) = verify_not_own(account.0.user_id, &config, ready(Ok(post.poster_id))).await {
+        return err_response;
+    }
+
+    match db.toggle_post_like(post_id, account.0.user_id).await {
+        Ok(state) => HttpResponse::Ok().json(state),
+        Err(DBError::NoResult) => HttpResponse::BadRequest().reason("Invalid post_id").finish(),
         Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
-#[post("/vote/post")]
-pub async fn vote_on_post(
+#[post("/comment/{comment_id}/like/toggle")]
+pub async fn toggle_comment_like(
     db: Data<Database>,
-    data: Json<PostLike>,
-    auth: Data<Mutex<AuthService>>,
-    bearer: BearerAuth
+    account: Authenticated,
+    path: Path<String>,
+    config: Data<SharedConfig>
 ) -> HttpResponse {
-    if data.account_id == 0 || data.post_id == 0 {
-        return HttpResponse::BadRequest().finish()
+    let comment_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid comment_id format").finish()
+    };
+
+    let config = config.load();
+    if let Err(err_response) = verify_karma(account.0.user_id, GatedAction::Like, &config, &db).await {
+        return err_response;
+    }
+    if let Err(err_response) = read_interactable_comment_post(comment_id, account.0.user_id, &db).await {
+        return err_response;
+    }
+    // Refused even when it would take back a like, which the vote endpoints can still do
+    if let Err(err_response) = verify_not_own(account.0.user_id, &config, db.read_comment_owner(comment_id)).await {
+        return err_response;
     }
 
-    if let Err(err_response) = verify_token(data.account_id, bearer.token(), auth).await {
+    match db.toggle_comment_like(comment_id, account.0.user_id).await {
+        Ok(state) => HttpResponse::Ok().json(state),
+        Err(DBError::NoResult) => HttpResponse::BadRequest().reason("Invalid comment_id").finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/posts/{post_id}/react")]
+pub async fn react_to_post(
+    db: Data<Database>,
+    account: Authenticated,
+    path: Path<String>,
+    data: Json<ReactionRequest>,
+    config: Data<SharedConfig>
+) -> HttpResponse {
+    let post_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
+    };
+    let config = config.load();
+    if !config.reactions.allows(&data.emoji) {
+        return HttpResponse::BadRequest().reason("Reaction not allowed").finish()
+    }
+
+    if let Err(err_response) = verify_karma(account.0.user_id, GatedAction::React, &config, &db).await {
+        return err_response;
+    }
+    if let Err(err_response) = read_interactable_post(post_id, account.0.user_id, &db).await {
         return err_response;
     }
 
-    let result = match data.liked {
-        true  => db.create_post_like(data.post_id, data.account_id).await,
-        false => db.delete_post_like(data.post_id, data.account_id).await
+    match db.toggle_post_reaction(post_id, account.0.user_id, &data.emoji).await {
+        Ok(state) => HttpResponse::Ok().json(state),
+        Err(DBError::NoResult) => HttpResponse::BadRequest().reason("Invalid post_id").finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/comment/{comment_id}/react")]
+pub async fn react_to_comment(
+    db: Data<Database>,
+    account: Authenticated,
+    path: Path<String>,
+    data: Json<ReactionRequest>,
+    config: Data<SharedConfig>
+) -> HttpResponse {
+    let comment_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid comment_id format").finish()
     };
-    match result {
+    let config = config.load();
+    if !config.reactions.allows(&data.emoji) {
+        return HttpResponse::BadRequest().reason("Reaction not allowed").finish()
+    }
+
+    if let Err(err_response) = verify_karma(account.0.user_id, GatedAction::React, &config, &db).await {
+        return err_response;
+    }
+    if let Err(err_response) = read_interactable_comment_post(comment_id, account.0.user_id, &db).await {
+        return err_response;
+    }
+
+    match db.toggle_comment_reaction(comment_id, account.0.user_id, &data.emoji).await {
+        Ok(state) => HttpResponse::Ok().json(state),
+        Err(DBError::NoResult) => HttpResponse::BadRequest().reason("Invalid comment_id").finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/awards")]
+pub async fn get_awards(db: Data<Database>) -> HttpResponse {
+    match db.read_awards().await {
+        Ok(awards) => HttpResponse::Ok().json(awards),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/announcements")]
+pub async fn get_announcements(
+    db: Data<Database>,
+    viewer: Query<ViewerQuery>
+) -> HttpResponse {
+    match db.read_active_announcements(viewer.viewer_id).await {
+        Ok(announcements) => HttpResponse::Ok().json(announcements),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/posts/{post_id}/award")]
+pub async fn give_post_award(
+    db: Data<Database>,
+    account: Authenticated,
+    path: Path<String>,
+    data: Json<AwardRequest>,
+    config: Data<SharedConfig>
+) -> HttpResponse {
+    let post_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid post_id format").finish()
+    };
+
+    if let Err(err_response) = verify_karma(account.0.user_id, GatedAction::Award, &config.load(), &db).await {
+        return err_response;
+    }
+
+    match read_interactable_post(post_id, account.0.user_id, &db).await {
+        Ok(post) if post.poster_id == account.0.user_id => {
+            return HttpResponse::BadRequest().reason("Cannot award your own post").finish()
+        },
+        Ok(_) => {},
+        Err(err_response) => return err_response
+    }
+
+    match db.create_post_award(post_id, account.0.user_id, data.award_id).await {
         Ok(()) => HttpResponse::Ok().finish(),
         Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
-            HttpResponse::AlreadyReported().finish()
+            HttpResponse::BadRequest().reason("Invalid award_id").finish()
         },
         Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
-#[post("/vote/comment")]
-pub async fn vote_on_comment(
+#[post("/comment/{comment_id}/award")]
+pub async fn give_comment_award(
     db: Data<Database>,
-    data: Json<CommentLike>,
-    auth: Data<Mutex<AuthService>>,
-    bearer: BearerAuth
+    account: Authenticated,
+    path: Path<String>,
+    data: Json<AwardRequest>,
+    config: Data<SharedConfig>
 ) -> HttpResponse {
-    if data.account_id == 0 || data.comment_id == 0 {
-        return HttpResponse::BadRequest().finish()
-    }
+    let comment_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid comment_id format").finish()
+    };
 
-    if let Err(err_response) = verify_token(data.account_id, bearer.token(), auth).await {
+    if let Err(err_response) = verify_karma(account.0.user_id, GatedAction::Award, &config.load(), &db).await {
+        return err_response;
+    }
+    if let Err(err_response) = read_interactable_comment_post(comment_id, account.0.user_id, &db).await {
         return err_response;
     }
+    match db.read_comment_owner(comment_id).await {
+        Ok(commenter_id) if commenter_id == account.0.user_id => {
+            return HttpResponse::BadRequest().reason("Cannot award your own comment").finish()
+        },
+        Ok(_) => {},
+        Err(DBError::NoResult) => return HttpResponse::NotFound().reason("Invalid comment_id").finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    }
 
-    let result = match data.liked {
-        true  => db.create_comment_like(data.comment_id, data.account_id).await,
-        false => db.delete_comment_like(data.comment_id, data.account_id).await
+    match db.create_comment_award(comment_id, account.0.user_id, data.award_id).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
+            HttpResponse::BadRequest().reason("Invalid award_id").finish()
+        },
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/users/{user_id}/follow")]
+pub async fn follow_user(
+    db: Data<Database>,
+    account: Authenticated,
+    path: Path<String>
+) -> HttpResponse {
+    let user_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid user_id format").finish()
     };
-    match result {
+    if user_id == account.0.user_id {
+        return HttpResponse::BadRequest().reason("Cannot follow yourself").finish()
+    }
+
+    match db.read_account_role(user_id).await {
+        Ok(_) => {},
+        Err(DBError::NoResult) => return HttpResponse::BadRequest().reason("Invalid user_id").finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    }
+
+    match db.follow(account.0.user_id, user_id).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[delete("/users/{user_id}/follow")]
+pub async fn unfollow_user(
+    db: Data<Database>,
+    account: Authenticated,
+    path: Path<String>
+) -> HttpResponse {
+    let user_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid user_id format").finish()
+    };
+
+    match db.unfollow(account.0.user_id, user_id).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[get("/users/{user_id}/moderation")]
+pub async fn get_user_moderation(
+    db: Data<Database>,
+    path: Path<String>,
+    viewer: Query<ViewerQuery>
+) -> HttpResponse {
+    let user_id = match ids::parse(&path) {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().reason("Invalid user_id format").finish()
+    };
+
+    match viewer_is_privileged(user_id, &viewer, &db).await {
+        Ok(true) => {},
+        Ok(false) => return HttpResponse::Forbidden().finish(),
+        Err(err_response) => return err_response
+    }
+
+    match db.read_moderation_actions(user_id).await {
+        Ok(actions) => HttpResponse::Ok().json(actions),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[post("/appeals")]
+pub async fn create_appeal(
+    db: Data<Database>,
+    account: Authenticated,
+    data: Json<NewAppeal>
+) -> HttpResponse {
+    if data.message.trim().is_empty() {
+        return HttpResponse::BadRequest().reason("Appeal has no message").finish()
+    }
+    if data.message.trim().chars().count() > MAX_APPEAL_LENGTH {
+        return HttpResponse::BadRequest().reason("Appeal message is too long").finish()
+    }
+
+    match db.create_appeal(data.action_id, account.0.user_id, data.message.trim()).await {
         Ok(()) => HttpResponse::Ok().finish(),
         Err(DBError::UnexpectedRowsAffected{ expected: 1, actual: 0 }) => {
-            HttpResponse::AlreadyReported().finish()
+            HttpResponse::BadRequest().reason("Invalid or already appealed action_id").finish()
         },
         Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
-/// Check that a `token_str` is valid for an `account_id` in the `auth` AuthService.
-/// 
-/// Note: The MutexGuard for AuthService that is acquired is dropped at the end
-///       of the function, releasing the lock on the AuthService.
+/// A hash of a random password, created on first use, to verify passwords against
+/// when the account being accessed does not exist.
+fn dummy_password_hash(argon2: &Argon2<'_>) -> &'static str {
+    DUMMY_PASSWORD_HASH.get_or_init(|| {
+        let salt = SaltString::generate(&mut OsRng);
+        argon2.hash_password(uuid::Uuid::new_v4().as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .unwrap_or_default()
+    })
+}
+
+/// Resolve the account that `token_str` was issued to in the `auth` AuthService.
+pub async fn authenticate(token_str: &str, auth: &AuthShards) -> Result<Identity, HttpResponse> {
+    match auth.authenticate(token_str).await {
+        Ok(Some(identity)) => Ok(identity),
+        Ok(None) => Err(HttpResponse::Unauthorized().finish()),
+        Err(_)   => Err(HttpResponse::Unauthorized().reason("Invalid token").finish()),
+    }
+}
+
+/// Check that a `token_str` is valid for an `account_id` in the `auth` AuthService,
+/// returning the account it was issued to.
 pub async fn verify_token(
     account_id: u64,
     token_str: &str,
-    auth: Data<Mutex<AuthService>>
+    auth: Data<AuthShards>
+) -> Result<Identity, HttpResponse> {
+    match auth.authenticate(token_str).await {
+        Ok(Some(identity)) if ct_eq_u64(identity.user_id, account_id) => Ok(identity),
+        Ok(_)  => Err(HttpResponse::Unauthorized().finish()),
+        Err(_) => Err(HttpResponse::Unauthorized().reason("Invalid token").finish()),
+    }
+}
+
+/// Check that `account_id` has the karma configured for `action`.
+pub async fn verify_karma(
+    account_id: u64,
+    action: GatedAction,
+    config: &ServerConfig,
+    db: &Database
 ) -> Result<(), HttpResponse> {
-    todo!();
-    match auth.lock().unwrap().validate(account_id, token_str, "a").await {
-        Ok(true)  => Ok(()),
-        Ok(false) => Err(HttpResponse::Unauthorized().finish()),
-        Err(_)    => Err(HttpResponse::Unauthorized().reason("Invalid token").finish()),
+    match db.check_karma(account_id, action, &config.karma).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(unmet)) => Err(HttpResponse::Forbidden().reason("Not enough karma").json(unmet)),
+        Err(_) => Err(HttpResponse::InternalServerError().finish())
     }
 }
 
-async fn verify_username_token(
-    user_id: u64,
-    username: &str,
-    token_str: &str,
-    auth: Data<Mutex<AuthService>>
+/// Check that content by `author` is not `account_id`'s own, when the
+/// `self_likes_disabled` feature is enabled. Content that does not exist is left
+/// to be refused by the like itself.
+async fn verify_not_own(
+    account_id: u64,
+    config: &ServerConfig,
+    author: impl Future<Output = Result<u64, DBError>>
+) -> Result<(), HttpResponse> {
+    if !config.feature_enabled("self_likes_disabled") {
+        return Ok(())
+    }
+    match author.await {
+        Ok(author) if author == account_id => {
+            Err(HttpResponse::Forbidden().reason(SELF_LIKE_REASON).json(json!({"code": "self_like"})))
+        },
+        Ok(_) | Err(DBError::NoResult) => Ok(()),
+        Err(_) => Err(HttpResponse::InternalServerError().finish())
+    }
+}
+
+/// Checks that the account has accepted the current terms, if there are any.
+/// Checked by `access` for the routes that create or change content, votes and
+/// follows, but not for those managing the account or deleting its content.
+pub async fn verify_terms(account_id: u64, config: &ServerConfig, db: &Database) -> Result<(), HttpResponse> {
+    let Some(version) = &config.terms.version else {
+        return Ok(())
+    };
+    match db.has_accepted_terms(account_id, version).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(HttpResponse::UnavailableForLegalReasons()
+            .reason(TERMS_REASON)
+            .json(json!({"version": version, "url": config.terms.url}))),
+        Err(_) => Err(HttpResponse::InternalServerError().finish())
+    }
+}
+
+/// Where a created post can be fetched.
+pub(super) fn post_location(post_id: u64) -> String {
+    format!("/api/posts/{}", ids::PublicId(post_id))
+}
+
+/// Where a created comment can be fetched, along with its thread.
+pub(super) fn comment_location(comment_id: u64) -> String {
+    format!("/api/comment/{}/context", ids::PublicId(comment_id))
+}
+
+/// The response to creating content at `location`: 201 Created, or 202 Accepted
+/// without a location while it is held for review.
+fn created(moderation: Moderation, location: &str) -> HttpResponseBuilder {
+    match moderation.held {
+        true => {
+            let mut response = HttpResponse::Accepted();
+            response.reason(HELD_REASON);
+            response
+        },
+        false => {
+            let mut response = HttpResponse::Created();
+            response.insert_header((LOCATION, location));
+            response
+        }
+    }
+}
+
+/// Scores new content with the configured scorers, on the blocking thread pool
+/// as a scorer may wait on an external API. Nothing is held if scoring fails.
+async fn moderate(config: &ModerationConfig, text: String) -> Moderation {
+    if !config.enabled {
+        return Moderation::default()
+    }
+    let scoring_config = config.clone();
+    let score = match web::block(move || scoring::score(&scoring::scorers(&scoring_config), &text)).await {
+        Ok(score) => score,
+        Err(e) => {
+            warn!("scoring: {}", e);
+            return Moderation::default()
+        }
+    };
+    Moderation { score: Some(score), held: score >= config.hold_threshold }
+}
+
+/// Whether the viewer, already verified, may see `post` as set by its visibility.
+/// Followers-only posts are visible to the author, their followers and moderators.
+async fn can_view(post: &Post, viewer_id: Option<u64>, db: &Database) -> Result<bool, DBError> {
+    if post.visibility != PostVisibility::Followers {
+        return Ok(true)
+    }
+    let Some(viewer_id) = viewer_id else {
+        return Ok(false)
+    };
+    if viewer_id == post.poster_id || db.is_following(viewer_id, post.poster_id).await? {
+        return Ok(true)
+    }
+    Ok(db.read_account_role(viewer_id).await? >= Role::Moderator)
+}
+
+/// Reads a post for a verified viewer, or the response for a post that the viewer
+/// cannot see. A followers-only post is not found by others, so that it is not
+/// known to exist.
+async fn read_visible_post(post_id: u64, viewer_id: Option<u64>, db: &Database) -> Result<Post, HttpResponse> {
+    let post = match db.read_post_by_id(post_id).await {
+        Ok(post) => post,
+        Err(DBError::NoResult) => {
+            return Err(missing_post(post_id, db, HttpResponse::BadRequest().reason("Invalid post_id").finish()).await)
+        },
+        Err(_) => return Err(HttpResponse::InternalServerError().finish())
+    };
+    match can_view(&post, viewer_id, db).await {
+        Ok(true) => Ok(post),
+        Ok(false) => Err(HttpResponse::NotFound().reason("Invalid post_id").finish()),
+        Err(_) => Err(HttpResponse::InternalServerError().finish())
+    }
+}
+
+/// The response for a post that `read_post_by_id` did not find: 410 if it has
+/// expired, including once the expiry job has soft-deleted it, otherwise `missing`.
+async fn missing_post(post_id: u64, db: &Database, missing: HttpResponse) -> HttpResponse {
+    match db.read_post_expired(post_id).await {
+        Ok(true) => HttpResponse::Gone().finish(),
+        Ok(false) => missing,
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+/// Reads a post that the account may like, react to or award, or the response for
+/// a post that has expired or that the account cannot see.
+async fn read_interactable_post(post_id: u64, account_id: u64, db: &Database) -> Result<Post, HttpResponse> {
+    let post = match db.read_post_by_id(post_id).await {
+        Ok(post) => post,
+        Err(DBError::NoResult) => {
+            return Err(missing_post(post_id, db, HttpResponse::NotFound().reason("Invalid post_id").finish()).await)
+        },
+        Err(_) => return Err(HttpResponse::InternalServerError().finish())
+    };
+    match can_view(&post, Some(account_id), db).await {
+        Ok(true) => Ok(post),
+        Ok(false) => Err(HttpResponse::NotFound().reason("Invalid post_id").finish()),
+        Err(_) => Err(HttpResponse::InternalServerError().finish())
+    }
+}
+
+/// As `read_interactable_post`, for the post of a comment.
+async fn read_interactable_comment_post(comment_id: u64, account_id: u64, db: &Database) -> Result<Post, HttpResponse> {
+    match db.read_comment_post(comment_id).await {
+        Ok(post_id) => read_interactable_post(post_id, account_id, db).await,
+        Err(DBError::NoResult) => Err(HttpResponse::NotFound().reason("Invalid comment_id").finish()),
+        Err(_) => Err(HttpResponse::InternalServerError().finish())
+    }
+}
+
+/// The body of `post` translated into `language`, from the cache or else the
+/// configured translator. Requests to the translator count against the limit of
+/// the client of `req`.
+async fn translate_post(
+    post: &Post,
+    language: &str,
+    config: &TranslationConfig,
+    translations: &TranslationCache,
+    req: &HttpRequest
+) -> Result<String, HttpResponse> {
+    if config.api_url.is_none() {
+        return Err(HttpResponse::NotImplemented().reason("Translation is not available").finish())
+    }
+    let key = TranslationKey(post.id, post.updated_at.timestamp(), language);
+    if let Some(body) = translations.get(&key).await {
+        return Ok(body)
+    }
+    let limiter = req.app_data::<Data<TranslationLimiter>>().filter(|_| config.rate_limit.enabled);
+    if let Some((limiter, client)) = limiter.zip(req.peer_addr()) {
+        let status = limiter.0.check_client(client.ip(), &config.rate_limit);
+        if !status.allowed {
+            let mut response = HttpResponse::TooManyRequests().reason("Too many translations").finish();
+            rate_limit::insert_headers(response.headers_mut(), &status);
+            return Err(response)
+        }
+    }
+
+    let translate_config = config.clone();
+    let text = post.body.clone();
+    let target = language.to_string();
+    let translated = web::block(move || match translate::translator(&translate_config) {
+        Some(translator) => translator.translate(&text, &target).map_err(|e| format!("{} translator failed: {}", translator.name(), e)),
+        None => Err("no translator".to_string())
+    }).await;
+    match translated {
+        Ok(Ok(body)) => {
+            translations.set(&key, &body).await;
+            Ok(body)
+        },
+        Ok(Err(e)) => {
+            warn!("translate: {}", e);
+            Err(HttpResponse::BadGateway().reason("Translation failed").finish())
+        },
+        Err(e) => {
+            warn!("translate: {}", e);
+            Err(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+/// Check that `account_id` may comment on `post`, as set by its comment mode.
+async fn verify_comment_mode(post: &Post, account_id: u64, db: &Database) -> Result<(), HttpResponse> {
+    match post.comment_mode {
+        CommentMode::Open => Ok(()),
+        CommentMode::Disabled => {
+            Err(HttpResponse::Forbidden().reason("Comments are disabled on this post").finish())
+        },
+        CommentMode::Followers if post.poster_id == account_id => Ok(()),
+        CommentMode::Followers => match db.is_following(account_id, post.poster_id).await {
+            Ok(true) => Ok(()),
+            Ok(false) => {
+                Err(HttpResponse::Forbidden().reason("Only followers of the author can comment on this post").finish())
+            },
+            Err(_) => Err(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+/// Whether the viewer, already verified, may see what `owner_id` has hidden from
+/// the public, which is the case for the owner and moderators. Anonymous viewers
+/// are the public.
+async fn viewer_is_privileged(
+    owner_id: u64,
+    viewer: &ViewerQuery,
+    db: &Database
+) -> Result<bool, HttpResponse> {
+    let Some(viewer_id) = viewer.viewer_id else {
+        return Ok(false)
+    };
+    if viewer_id == owner_id {
+        return Ok(true)
+    }
+    match db.read_account_role(viewer_id).await {
+        Ok(role) => Ok(role >= Role::Moderator),
+        Err(_) => Err(HttpResponse::InternalServerError().finish())
+    }
+}
+
+fn token_response(account_id: u64, issued: IssuedToken) -> TokenResponse {
+    TokenResponse {
+        id: account_id,
+        token: issued.token,
+        token_type: BEARER_TOKEN_TYPE,
+        expires_at: issued.expires_at,
+        refresh_token: Some(issued.refresh_token),
+        refresh_expires_at: Some(issued.refresh_expires_at)
+    }
+}
+
+/// Trims and lowercases an email address, or `None` if it is clearly not one.
+fn normalise_email(email: &str) -> Option<String> {
+    let email = email.trim().to_lowercase();
+    let (local, domain) = email.rsplit_once('@')?;
+    if local.is_empty() || !domain.contains('.') || domain.starts_with('.') || domain.ends_with('.') {
+        return None
+    }
+    Some(email)
+}
+
+fn page_limit(page: &PageQuery) -> u64 {
+    page.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE)
+}
+
+/// Wraps a page of `items` read with `limit`, where `id` is the cursor of an item.
+fn page_of<T>(items: Vec<T>, total: u64, limit: u64, id: impl Fn(&T) -> u64) -> Page<T> {
+    let next_before = match items.len() as u64 == limit {
+        true  => items.last().map(id),
+        false => None
+    };
+    Page { items, total, next_before }
+}
+
+/// The comments of a post, pinned first and then oldest first.
+async fn read_thread(post_id: u64, db: &Database) -> Result<Vec<ThreadComment>, DBError> {
+    let comments = db.read_comments_of_post(post_id).await?;
+    let pinned_id = db.read_pinned_comment(post_id).await?;
+    let mut thread = thread_comments(comments, pinned_id, db).await?;
+    // Stable, so the rest of the thread stays oldest first
+    thread.sort_by_key(|listed| !listed.pinned);
+    Ok(thread)
+}
+
+/// Up to `max_posts` of the hot candidates that `viewer_id` may see, ranked by
+/// their hot score.
+async fn read_hot_posts(
+    max_posts: u64,
+    viewer_id: Option<u64>,
+    candidates: &TtlCache<(), Arc<Vec<HotCandidate>>>,
+    db: &Database
+) -> Result<Vec<Post>, DBError> {
+    let read = async { db.read_hot_candidates(ranking::HOT_CANDIDATES).await.map(Arc::new) };
+    let candidates = candidates.get_or_load((), read).await?;
+    let followed = match viewer_id {
+        Some(viewer_id) => {
+            let posters = candidates.iter()
+                .filter(|candidate| candidate.visibility == PostVisibility::Followers && candidate.poster_id != viewer_id)
+                .map(|candidate| candidate.poster_id)
+                .collect::<HashSet<u64>>();
+            db.read_followed(viewer_id, &posters.into_iter().collect::<Vec<u64>>()).await?
+        },
+        None => HashSet::new()
+    };
+    let mut visible = candidates.iter()
+        .filter(|candidate| match candidate.visibility {
+            PostVisibility::Followers => viewer_id == Some(candidate.poster_id) || followed.contains(&candidate.poster_id),
+            _ => true
+        })
+        .cloned()
+        .collect::<Vec<HotCandidate>>();
+    ranking::rank_hot(&mut visible, db.read_current_time().await?);
+
+    // Posts deleted since the candidates were read are left out
+    let post_ids = visible.iter().take(max_posts as usize).map(|candidate| candidate.id).collect::<Vec<u64>>();
+    db.read_listed_posts(&post_ids, viewer_id).await
+}
+
+/// A page of up to `limit` comments of a post after the `after` id, oldest first.
+/// The pinned comment is put first on the first page rather than in its place.
+/// With `top_level`, the page is of comments that are not replies.
+async fn read_thread_page(
+    post_id: u64,
+    pinned_id: Option<u64>,
+    top_level: bool,
+    limit: u64,
+    after: Option<u64>,
+    db: &Database
+) -> Result<ThreadPage<ThreadComment>, DBError> {
+    let comments = db.read_thread_comments(post_id, top_level, limit, after, pinned_id).await?;
+    let next_after = match comments.len() as u64 == limit {
+        true  => comments.last().map(|comment| comment.id),
+        false => None
+    };
+    let pinned = match (pinned_id, after) {
+        (Some(pinned_id), None) => db.read_thread_comment(post_id, pinned_id, top_level).await?,
+        _ => None
+    };
+    let comments = pinned.into_iter().chain(comments).collect();
+    Ok(ThreadPage { items: thread_comments(comments, pinned_id, db).await?, next_after })
+}
+
+/// A page of the top-level comments of a post in the tree format, with as many of
+/// their replies as `limits` nest. Only those replies are read, level by level.
+async fn read_thread_tree(
+    post_id: u64,
+    pinned_id: Option<u64>,
+    limit: u64,
+    after: Option<u64>,
+    limits: Limits,
+    db: &Database
+) -> Result<ThreadPage<CommentNode>, DBError> {
+    let ThreadPage { items: mut thread, next_after } = read_thread_page(post_id, pinned_id, true, limit, after, db).await?;
+    let mut parent_ids = thread.iter().map(|listed| listed.comment.id).collect::<Vec<u64>>();
+    for _ in 0..limits.depth {
+        if parent_ids.is_empty() {
+            break
+        }
+        let replies = db.read_replies(&parent_ids, limits.replies as u64).await?;
+        parent_ids = replies.iter().map(|reply| reply.id).collect();
+        thread.extend(thread_comments(replies, pinned_id, db).await?);
+    }
+
+    let comment_ids = thread.iter().map(|listed| listed.comment.id).collect::<Vec<u64>>();
+    let reply_counts = db.read_reply_counts(&comment_ids).await?;
+    Ok(ThreadPage { items: threads::tree(thread, &reply_counts, limits), next_after })
+}
+
+/// Lists `comments` in a thread with their reactions and awards, each counted in
+/// one query.
+async fn thread_comments(comments: Vec<Comment>, pinned_id: Option<u64>, db: &Database) -> Result<Vec<ThreadComment>, DBError> {
+    let comment_ids = comments.iter().map(|comment| comment.id).collect::<Vec<u64>>();
+    let mut reactions = db.read_comment_reactions(&comment_ids).await?;
+    let mut awards = db.read_comment_awards(&comment_ids).await?;
+    Ok(comments.into_iter()
+        .map(|comment| {
+            let pinned = Some(comment.id) == pinned_id;
+            let reactions = reactions.remove(&comment.id).unwrap_or_default();
+            let awards = awards.remove(&comment.id).unwrap_or_default();
+            ThreadComment { comment, pinned, reactions, awards }
+        })
+        .collect())
+}
+
+/// Pairs each of `posts` with its number of comments, reactions and awards, each
+/// counted in one query. With a `viewer_id`, also marks which posts are unread.
+async fn listing_of(db: &Database, posts: Vec<Post>, viewer_id: Option<u64>) -> Result<Vec<PostListing>, DBError> {
+    let post_ids = posts.iter().map(|post| post.id).collect::<Vec<u64>>();
+    let counts = db.read_comment_counts(&post_ids).await?;
+    let mut reactions = db.read_post_reactions(&post_ids).await?;
+    let mut awards = db.read_post_awards(&post_ids).await?;
+    let read = match viewer_id {
+        Some(viewer_id) => Some(db.read_posts_read(viewer_id, &post_ids).await?),
+        None => None
+    };
+    Ok(posts.into_iter()
+        .map(|post| {
+            let comment_count = counts.get(&post.id).copied().unwrap_or(0);
+            let reactions = reactions.remove(&post.id).unwrap_or_default();
+            let awards = awards.remove(&post.id).unwrap_or_default();
+            let unread = read.as_ref().map(|read| !read.contains(&post.id));
+            PostListing { post, comment_count, reactions, awards, unread }
+        })
+        .collect())
+}
+
+/// The cached total of a listing, running `count` when it is not cached. The
+/// count future is only awaited on a miss.
+async fn total_count(
+    counts: &TotalCountCache,
+    key: (CountedListing, u64),
+    count: impl Future<Output = Result<u64, DBError>>
+) -> Result<u64, DBError> {
+    counts.get_or_load(key, count).await
+}
+
+/// Check that the `X-Sudo-Token` header of `req` holds a sudo token for `account_id`,
+/// as issued by `POST /api/account/sudo` after re-entering the password.
+pub async fn verify_sudo_token(
+    account_id: u64,
+    req: &HttpRequest,
+    auth: Data<AuthShards>
 ) -> Result<(), HttpResponse> {
-    match auth.lock().unwrap().validate(user_id, username, token_str).await {
+    let Some(token_str) = req.headers().get(SUDO_TOKEN_HEADER).and_then(|v| v.to_str().ok()) else {
+        return Err(HttpResponse::Forbidden().reason("A sudo token is required").finish())
+    };
+    let Ok(token) = uuid::Uuid::parse_str(token_str) else {
+        return Err(HttpResponse::BadRequest().reason("Invalid sudo token format").finish())
+    };
+    match auth.shard(account_id).validate_sudo(account_id, &token).await {
         Ok(true)  => Ok(()),
-        Ok(false) => Err(HttpResponse::Unauthorized().finish()),
-        Err(_)    => Err(HttpResponse::BadRequest().reason("Invalid token format").finish())
+        Ok(false) => Err(HttpResponse::Forbidden().reason("Invalid or expired sudo token").finish()),
+        Err(_)    => Err(HttpResponse::ServiceUnavailable().finish())
+    }
+}
+
+#[cfg(all(test, feature = "chaos"))]
+mod test {
+    use std::time::Duration;
+
+    use actix_web::{test, App};
+    use actix_web::http::StatusCode;
+    use actix_web::web::Data;
+
+    use crate::chaos;
+    use crate::database::database::Database;
+
+    use super::get_post_revisions;
+
+    #[actix_web::test]
+    async fn test_database_errors_are_500() {
+        let _scenario = chaos::scenario();
+        chaos::DATABASE.configure(1, 1.0, Duration::ZERO);
+        let db = Database::connect_lazy("mysql://localhost/posted").unwrap();
+        let app = test::init_service(App::new().app_data(Data::new(db)).service(get_post_revisions)).await;
+
+        // Failed by the injected fault, not by there being no database to connect to
+        let req = test::TestRequest::get().uri("/posts/1/revisions").to_request();
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, test::call_service(&app, req).await.status());
+        assert_eq!(1, chaos::DATABASE.injected());
+
+        // Rejected before the database is reached
+        let req = test::TestRequest::get().uri("/posts/!/revisions").to_request();
+        assert_eq!(StatusCode::BAD_REQUEST, test::call_service(&app, req).await.status());
+        assert_eq!(1, chaos::DATABASE.injected());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,104 @@
+use std::future::Future;
+
+use actix_web::Error;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue, LINK};
+use actix_web::web::Data;
+use chrono::NaiveTime;
+
+use crate::config::config::{DeprecationConfig, SharedConfig};
+use crate::metrics::metrics::Metrics;
+
+const DEPRECATION_HEADER: HeaderName = HeaderName::from_static("deprecation");
+const SUNSET_HEADER: HeaderName = HeaderName::from_static("sunset");
+
+/// `wrap_fn` middleware announcing the deprecation of the routes configured under
+/// `deprecations` to clients, and counting the requests still made to them. Routes
+/// are only known once matched, so this is done to the response.
+pub fn middleware<S, B>(
+    req: ServiceRequest,
+    srv: &S
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+{
+    let call = srv.call(req);
+    async move {
+        let mut res = call.await?;
+        let Some(route) = route(&res) else {
+            return Ok(res)
+        };
+        let Some(config) = res.request().app_data::<Data<SharedConfig>>().map(|config| config.load()) else {
+            return Ok(res)
+        };
+        let Some(deprecation) = config.deprecations.get(&route) else {
+            return Ok(res)
+        };
+
+        if let Some(metrics) = res.request().app_data::<Data<Metrics>>() {
+            metrics.increment_labelled("deprecated_requests_total", &[("route", &route)], 1);
+        }
+        let headers = res.headers_mut();
+        for (name, value) in deprecation_headers(deprecation) {
+            headers.append(name, value);
+        }
+        Ok(res)
+    }
+}
+
+/// The method and pattern of the route that handled a request, e.g.
+/// `POST /api/vote/post`, as `deprecations` are configured by.
+fn route<B>(res: &ServiceResponse<B>) -> Option<String> {
+    let pattern = res.request().match_pattern()?;
+    Some(format!("{} {}", res.request().method(), pattern))
+}
+
+/// The `Deprecation` (RFC 9745), `Sunset` (RFC 8594) and `Link` headers for a
+/// deprecated route. Dates are taken as the start of the day in UTC.
+fn deprecation_headers(deprecation: &DeprecationConfig) -> Vec<(HeaderName, HeaderValue)> {
+    let mut headers = Vec::new();
+    let since = deprecation.since.and_time(NaiveTime::MIN).and_utc();
+    headers.push((DEPRECATION_HEADER, HeaderValue::from_str(&format!("@{}", since.timestamp()))));
+    if let Some(sunset) = deprecation.sunset {
+        let sunset = sunset.and_time(NaiveTime::MIN).and_utc();
+        headers.push((SUNSET_HEADER, HeaderValue::from_str(&sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string())));
+    }
+    if let Some(link) = &deprecation.link {
+        headers.push((LINK, HeaderValue::from_str(&format!("<{}>; rel=\"deprecation\"; type=\"text/html\"", link))));
+    }
+    headers.into_iter()
+        .filter_map(|(name, value)| Some((name, value.ok()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use crate::config::config::DeprecationConfig;
+    use super::deprecation_headers;
+
+    #[test]
+    fn test_deprecation_headers() {
+        let deprecation = DeprecationConfig {
+            since: NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(),
+            sunset: Some(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()),
+            link: Some("https://example.com/migrating".to_string())
+        };
+        let headers: Vec<(String, String)> = deprecation_headers(&deprecation).into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_str().unwrap().to_string()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("deprecation".to_string(), "@1717200000".to_string()),
+                ("sunset".to_string(), "Wed, 01 Jan 2025 00:00:00 GMT".to_string()),
+                ("link".to_string(), "<https://example.com/migrating>; rel=\"deprecation\"; type=\"text/html\"".to_string())
+            ],
+            headers
+        );
+
+        // Links that cannot be a header value are left out
+        let bare = DeprecationConfig { sunset: None, link: Some("bad\nlink".to_string()), ..deprecation };
+        assert_eq!(1, deprecation_headers(&bare).len());
+    }
+}
\ No newline at end of file
@@ -1 +1,6 @@
-pub mod api;
\ No newline at end of file
+pub mod access;
+pub mod admin;
+pub mod api;
+pub mod deprecation;
+pub mod negotiate;
+pub mod recorder;
\ No newline at end of file
@@ -0,0 +1,57 @@
+use actix_web::{HttpRequest, HttpResponse};
+use actix_web::http::header::ACCEPT;
+use log::warn;
+use serde::Serialize;
+
+const MSGPACK: &str = "application/msgpack";
+const MSGPACK_LEGACY: &str = "application/x-msgpack";
+
+/// A 200 response holding `body`, as MessagePack if the request accepts it, or
+/// JSON otherwise. Used by the list heavy endpoints, for clients where parsing
+/// JSON is costly.
+pub fn ok<T: Serialize>(req: &HttpRequest, body: &T) -> HttpResponse {
+    if !accepts_msgpack(req) {
+        return HttpResponse::Ok().json(body)
+    }
+    match rmp_serde::to_vec_named(body) {
+        Ok(bytes) => HttpResponse::Ok().content_type(MSGPACK).body(bytes),
+        Err(e) => {
+            warn!("negotiate: failed to encode MessagePack: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Whether MessagePack is one of the accepted media types, and not refused
+/// with `q=0`.
+fn accepts_msgpack(req: &HttpRequest) -> bool {
+    let Some(accept) = req.headers().get(ACCEPT).and_then(|value| value.to_str().ok()) else {
+        return false
+    };
+    accept.split(',').any(|media_range| {
+        let mut params = media_range.split(';').map(str::trim);
+        let media_type = params.next().unwrap_or_default();
+        let refused = params.any(|param| param.replace(' ', "") == "q=0");
+        (media_type.eq_ignore_ascii_case(MSGPACK) || media_type.eq_ignore_ascii_case(MSGPACK_LEGACY)) && !refused
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::test::TestRequest;
+
+    use super::accepts_msgpack;
+
+    #[test]
+    fn test_accepts_msgpack() {
+        let accepts = |accept: &str| accepts_msgpack(&TestRequest::default()
+            .insert_header(("Accept", accept))
+            .to_http_request());
+
+        assert!(!accepts_msgpack(&TestRequest::default().to_http_request()));
+        assert!(!accepts("application/json"));
+        assert!(accepts("application/msgpack"));
+        assert!(accepts("application/json;q=0.5, application/x-msgpack"));
+        assert!(!accepts("application/msgpack; q=0, application/json"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,353 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use actix_web::{rt, Error, HttpMessage};
+use actix_web::body::{self, EitherBody, MessageBody};
+use actix_web::dev::{Payload, Service, ServiceRequest, ServiceResponse};
+use actix_web::error::ErrorInternalServerError;
+use actix_web::http::Uri;
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use actix_web::web::{Bytes, Data};
+use futures_util::StreamExt;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::config::config::SharedConfig;
+use crate::database::database::Database;
+use crate::models::NewRequestTrace;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+/// Longest request id accepted from a client, as limited by the RequestTrace table.
+const MAX_REQUEST_ID_LENGTH: usize = 64;
+/// Longest uri recorded, as limited by the RequestTrace table.
+const MAX_URI_LENGTH: usize = 2048;
+/// Bodies longer than this are noted by their length only.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+/// Request headers recorded. Any others may carry credentials or identify the client.
+const KEPT_HEADERS: [&str; 5] = ["accept", "accept-language", "content-length", "content-type", "user-agent"];
+/// Parts of field and query parameter names whose values are never recorded.
+const SENSITIVE: [&str; 5] = ["password", "token", "secret", "email", "key"];
+const REDACTED: &str = "[redacted]";
+
+/// The id of a request, as sent by the client in `X-Request-Id` or generated.
+/// Added to the extensions of each request by `middleware`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestId(pub String);
+
+/// `wrap_fn` middleware giving each request an id, returned in `X-Request-Id`,
+/// and recording the sampled requests and their responses as configured under
+/// `request_log`. Recorded request bodies are what the handler read.
+pub fn middleware<S, B>(
+    mut req: ServiceRequest,
+    srv: &S
+) -> impl Future<Output = Result<ServiceResponse<EitherBody<B, Bytes>>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody + 'static
+{
+    let request_id = request_id(&req);
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+    let recording = Recording::start(&mut req, &request_id);
+    let call = srv.call(req);
+
+    async move {
+        let res = call.await?;
+        let mut res = match recording {
+            Some(recording) => recording.finish(res).await?.map_into_right_body(),
+            None => res.map_into_left_body()
+        };
+        if let Ok(value) = HeaderValue::from_str(&request_id) {
+            res.headers_mut().insert(REQUEST_ID_HEADER, value);
+        }
+        Ok(res)
+    }
+}
+
+/// A request body as it is read, and its full length.
+type BodySink = Arc<Mutex<(Vec<u8>, usize)>>;
+
+struct Recording {
+    request_id: String,
+    method: String,
+    uri: String,
+    request_headers: String,
+    request_content_type: Option<HeaderValue>,
+    request_body: BodySink,
+    started: Instant
+}
+
+impl Recording {
+    /// Starts recording `req` if its id is sampled, copying its body as it is read.
+    fn start(req: &mut ServiceRequest, request_id: &str) -> Option<Recording> {
+        let config = req.app_data::<Data<SharedConfig>>()?.load();
+        if !config.request_log.enabled || !sampled(request_id, config.request_log.sample_percent) {
+            return None
+        }
+
+        let request_body: BodySink = Arc::new(Mutex::new((Vec::new(), 0)));
+        let sink = request_body.clone();
+        let payload = req.take_payload().inspect(move |chunk| {
+            if let Ok(chunk) = chunk {
+                let (body, length) = &mut *sink.lock().unwrap();
+                *length += chunk.len();
+                if *length <= MAX_BODY_BYTES {
+                    body.extend_from_slice(chunk);
+                }
+            }
+        });
+        req.set_payload(Payload::from(payload.boxed_local()));
+
+        Some(Recording {
+            request_id: request_id.to_string(),
+            method: req.method().to_string(),
+            uri: redact_uri(req.uri()),
+            request_headers: kept_headers(req.headers()),
+            request_content_type: req.headers().get(CONTENT_TYPE).cloned(),
+            request_body,
+            started: Instant::now()
+        })
+    }
+
+    /// Reads the whole response body to record it, returning the response with
+    /// the body in place. The trace is stored in the background.
+    async fn finish<B>(self, res: ServiceResponse<B>) -> Result<ServiceResponse<Bytes>, Error>
+    where
+        B: MessageBody
+    {
+        let (req, res) = res.into_parts();
+        let (res, response_body) = res.into_parts();
+        let response_body = match body::to_bytes(response_body).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                let e: Box<dyn std::error::Error> = e.into();
+                return Err(ErrorInternalServerError(e.to_string()))
+            }
+        };
+
+        let request_body = {
+            let (body, length) = &*self.request_body.lock().unwrap();
+            recorded_body(body, *length, self.request_content_type.as_ref())
+        };
+        let trace = NewRequestTrace {
+            request_id: self.request_id,
+            method: self.method,
+            uri: self.uri,
+            request_headers: self.request_headers,
+            request_body,
+            status: res.status().as_u16(),
+            response_body: recorded_body(&response_body, response_body.len(), res.headers().get(CONTENT_TYPE)),
+            duration_ms: self.started.elapsed().as_millis().min(u32::MAX as u128) as u32
+        };
+        if let Some(db) = req.app_data::<Data<Database>>().cloned() {
+            rt::spawn(async move {
+                // Failures are logged by the database
+                let _ = db.create_request_trace(&trace).await;
+            });
+        }
+
+        Ok(ServiceResponse::new(req, res.set_body(response_body)))
+    }
+}
+
+/// The client's `X-Request-Id` if it is sensible, otherwise a new one.
+fn request_id(req: &ServiceRequest) -> String {
+    req.headers().get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+fn valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Whether `request_id` is within the sampled `percent` of ids. Decided by the id
+/// alone, so retries of a request are recorded alike.
+fn sampled(request_id: &str, percent: f64) -> bool {
+    let digest = Sha256::digest(request_id.as_bytes());
+    let bucket = u64::from_be_bytes(digest[..8].try_into().unwrap()) % 10_000;
+    (bucket as f64) < percent * 100.0
+}
+
+fn sensitive(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SENSITIVE.iter().any(|part| name.contains(part))
+}
+
+/// Replaces the values of sensitive fields, at any depth.
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, value) in fields.iter_mut() {
+                if sensitive(name) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        },
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => ()
+    }
+}
+
+fn redact_uri(uri: &Uri) -> String {
+    let recorded = match uri.query() {
+        Some(query) => {
+            let query = query.split('&')
+                .map(|pair| match pair.split_once('=') {
+                    Some((name, _)) if sensitive(name) => format!("{}={}", name, REDACTED),
+                    _ => pair.to_string()
+                })
+                .collect::<Vec<String>>()
+                .join("&");
+            format!("{}?{}", uri.path(), query)
+        },
+        None => uri.path().to_string()
+    };
+    recorded.chars().take(MAX_URI_LENGTH).collect()
+}
+
+/// The `KEPT_HEADERS` of a request, as a JSON object.
+fn kept_headers(headers: &HeaderMap) -> String {
+    let kept = KEPT_HEADERS.iter()
+        .filter_map(|name| {
+            let value = headers.get(*name)?.to_str().ok()?;
+            Some((name.to_string(), Value::String(value.to_string())))
+        })
+        .collect::<Map<String, Value>>();
+    Value::Object(kept).to_string()
+}
+
+/// A JSON or MessagePack body as redacted JSON. Other bodies, which can't be
+/// redacted, are noted by their length and type only.
+fn recorded_body(body: &[u8], length: usize, content_type: Option<&HeaderValue>) -> Option<String> {
+    if length == 0 {
+        return None
+    }
+    let content_type = content_type.and_then(|value| value.to_str().ok()).unwrap_or("unknown");
+    if length > MAX_BODY_BYTES {
+        return Some(format!("<{} bytes of {}>", length, content_type))
+    }
+    let parsed = match content_type.split(';').next().unwrap_or("").trim() {
+        "application/json" => serde_json::from_slice::<Value>(body).ok(),
+        "application/msgpack" | "application/x-msgpack" => rmp_serde::from_slice::<Value>(body).ok(),
+        _ => None
+    };
+    match parsed {
+        Some(mut value) => {
+            redact(&mut value);
+            Some(value.to_string())
+        },
+        None => Some(format!("<{} bytes of {}>", length, content_type))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{web, App, HttpMessage, HttpRequest, HttpResponse};
+    use actix_web::http::header::HeaderValue;
+    use actix_web::http::Uri;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use arc_swap::ArcSwap;
+    use serde_json::json;
+
+    use crate::config::config::{RequestLogConfig, ServerConfig, SharedConfig};
+    use super::{kept_headers, recorded_body, redact_uri, sampled, RequestId, REQUEST_ID_HEADER};
+
+    #[test]
+    fn test_recorded_body() {
+        let body = json!({
+            "username": "one",
+            "password": "hunter2",
+            "account": { "email": "one@example.com", "token": "abc" },
+            "items": [{ "new_password": "hunter3", "id": 1 }]
+        });
+        let json = HeaderValue::from_static("application/json; charset=utf-8");
+        let bytes = serde_json::to_vec(&body).unwrap();
+        let recorded: serde_json::Value = serde_json::from_str(
+            &recorded_body(&bytes, bytes.len(), Some(&json)).unwrap()
+        ).unwrap();
+        assert_eq!(json!({
+            "username": "one",
+            "password": "[redacted]",
+            "account": { "email": "[redacted]", "token": "[redacted]" },
+            "items": [{ "new_password": "[redacted]", "id": 1 }]
+        }), recorded);
+
+        let msgpack = HeaderValue::from_static("application/msgpack");
+        let bytes = rmp_serde::to_vec_named(&json!({ "token": "abc" })).unwrap();
+        assert_eq!(Some(r#"{"token":"[redacted]"}"#.to_string()), recorded_body(&bytes, bytes.len(), Some(&msgpack)));
+
+        // The one response an API key is returned in
+        let bytes = serde_json::to_vec(&json!({ "id": "x1", "key": "0b6c5c0e-0c4b-4d3c-8f8e-3c1f3c1f3c1f" })).unwrap();
+        assert_eq!(Some(r#"{"id":"x1","key":"[redacted]"}"#.to_string()), recorded_body(&bytes, bytes.len(), Some(&json)));
+
+        let text = HeaderValue::from_static("text/plain");
+        assert_eq!(Some("<6 bytes of text/plain>".to_string()), recorded_body(b"secret", 6, Some(&text)));
+        assert_eq!(None, recorded_body(b"", 0, Some(&json)));
+    }
+
+    #[test]
+    fn test_redact_uri() {
+        let uri: Uri = "/api/posts?viewer_id=3&token=abc&Email=x".parse().unwrap();
+        assert_eq!("/api/posts?viewer_id=3&token=[redacted]&Email=[redacted]", redact_uri(&uri));
+        let uri: Uri = "/api/posts/1".parse().unwrap();
+        assert_eq!("/api/posts/1", redact_uri(&uri));
+    }
+
+    #[test]
+    fn test_kept_headers() {
+        let req = TestRequest::default()
+            .insert_header(("authorization", "Bearer abc"))
+            .insert_header(("cookie", "session=abc"))
+            .insert_header(("user-agent", "posted/1.0"))
+            .to_http_request();
+        assert_eq!(r#"{"user-agent":"posted/1.0"}"#, kept_headers(req.headers()));
+    }
+
+    #[test]
+    fn test_sampled() {
+        let ids = (0..1000).map(|i| format!("request-{}", i)).collect::<Vec<String>>();
+        assert!(ids.iter().all(|id| !sampled(id, 0.0)));
+        assert!(ids.iter().all(|id| sampled(id, 100.0)));
+        let sampled_ids = ids.iter().filter(|id| sampled(id, 10.0)).count();
+        assert!(sampled_ids > 50 && sampled_ids < 150, "{} of 1000 sampled", sampled_ids);
+        assert_eq!(sampled("request-1", 10.0), sampled("request-1", 10.0));
+    }
+
+    #[actix_web::test]
+    async fn test_middleware() {
+        let config = ServerConfig {
+            request_log: RequestLogConfig { enabled: true, sample_percent: 100.0, keep_hours: 1 },
+            ..ServerConfig::default()
+        };
+        let app = init_service(
+            App::new()
+                .wrap_fn(super::middleware)
+                .app_data(web::Data::new(ArcSwap::from_pointee(config) as SharedConfig))
+                .route("/echo", web::post().to(|req: HttpRequest, body: String| async move {
+                    let request_id = req.extensions().get::<RequestId>().unwrap().0.clone();
+                    HttpResponse::Ok().body(format!("{} {}", request_id, body))
+                }))
+        ).await;
+
+        // Recorded responses are passed on whole
+        let req = TestRequest::post().uri("/echo").insert_header((REQUEST_ID_HEADER, "abc-123")).set_payload("hi").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!("abc-123", res.headers().get(REQUEST_ID_HEADER).unwrap());
+        assert_eq!("abc-123 hi", read_body(res).await);
+
+        // Ids that are not sensible are replaced
+        let req = TestRequest::post().uri("/echo").insert_header((REQUEST_ID_HEADER, "a b")).set_payload("hi").to_request();
+        let res = call_service(&app, req).await;
+        let request_id = res.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap().to_string();
+        assert_eq!(36, request_id.len());
+        assert_eq!(format!("{} hi", request_id), read_body(res).await);
+    }
+}
\ No newline at end of file
@@ -1,36 +1,104 @@
+use std::collections::HashMap;
 use std::thread;
 
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use log::{info, warn};
 use uuid::Uuid;
 
-use crate::cache::cache::{Cache, Entry};
+use crate::cache::cache::Cache;
+use crate::cache::ttl::TtlCache;
+use crate::clock::{self, Clock};
+use crate::config::config::SharedConfig;
 use super::backup_auth::OfflineAuth;
+use super::jwt::{Claims, JwtKeys};
 use super::redis_auth::RedisAuth;
+use super::token::{ct_eq_u64, TokenHash};
 
 const MAX_CONNECT_TIME: u64 = 1;
 const RECONNECT_FREQUENCY: u64 = 1;
+/// How long the revocation generation of an account is remembered in-process,
+/// sparing a Redis round trip on every authenticated request. Revocations made
+/// through this AuthService apply at once, those of other servers within this.
+const REVOCATION_CACHE_TTL: Duration = Duration::from_secs(5);
 
 enum Store {
     Online(RedisAuth),
     Offline(OfflineAuth)
 }
 
+/// The account a token was issued to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    pub user_id: u64,
+    pub username: String
+}
+
+/// A newly issued token and its refresh token, and when they expire.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IssuedToken {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+    pub refresh_token: Uuid,
+    pub refresh_expires_at: DateTime<Utc>
+}
+
 pub struct AuthService {
     store: Store,
     addr: String,
-    misses: u64
+    misses: u64,
+    config: Arc<SharedConfig>,
+    keys: Arc<JwtKeys>,
+    clock: Arc<dyn Clock>,
+    /// Revocation generations recently read from Redis, by account
+    generations: TtlCache<u64, u64>,
+    /// The last generation known of each account, carried offline when Redis fails
+    /// so that its revocations still apply
+    last_known: HashMap<u64, u64>
 }
 
 impl AuthService {
-    pub fn new(addr: &str) -> AuthService {
+    pub fn new(addr: &str, config: Arc<SharedConfig>, keys: Arc<JwtKeys>) -> AuthService {
+        AuthService::with_clock(addr, config, keys, clock::system())
+    }
+
+    /// An AuthService that expires tokens by `clock`.
+    pub fn with_clock(addr: &str, config: Arc<SharedConfig>, keys: Arc<JwtKeys>, clock: Arc<dyn Clock>) -> AuthService {
         let store = match try_connect(addr) {
             Ok(redis_cache) => Store::Online(RedisAuth::new(redis_cache)),
-            Err(_) => Store::Offline(OfflineAuth::new()),
+            Err(_) => Store::Offline(OfflineAuth::new(clock.now().timestamp())),
         };
 
-        AuthService { store, addr: addr.to_string(), misses: 0 }
+        AuthService {
+            store,
+            addr: addr.to_string(),
+            misses: 0,
+            config,
+            keys,
+            generations: TtlCache::with_clock(REVOCATION_CACHE_TTL, clock.clone()),
+            last_known: HashMap::new(),
+            clock
+        }
+    }
+
+    /// Switches to OfflineAuth after Redis failed to answer, carrying over the last
+    /// known generations.
+    fn go_offline(&mut self) -> &mut OfflineAuth {
+        warn!("AuthService: Switching to OfflineAuth");
+        let offline = OfflineAuth::carrying(self.clock.now().timestamp(), self.last_known.clone());
+        self.store = Store::Offline(offline);
+        self.misses = 1;
+        match &mut self.store {
+            Store::Offline(offline) => offline,
+            Store::Online(_) => unreachable!()
+        }
+    }
+
+    fn remember_generation(&mut self, user_id: u64, generation: u64) {
+        self.generations.insert(user_id, generation);
+        self.last_known.insert(user_id, generation);
     }
 
     async fn maybe_reconnect(&mut self) -> () {
@@ -42,11 +110,14 @@ impl AuthService {
 
         if let Store::Offline(offline) = &self.store {
             if let Ok(redis_cache) = try_connect(&self.addr) {
-                if let Err(_) = migrate_to_online(offline, &redis_cache).await {
+                let online = RedisAuth::new(redis_cache);
+                let ttl_sec = self.config.load().tokens.revocation_ttl_sec();
+                let now = self.clock.now().timestamp();
+                if let Err(_) = migrate_to_online(offline, &online, ttl_sec, now).await {
                     warn!("AuthService: attempted but failed to migrate to Redis server");
                     return
                 }
-                self.store = Store::Online(RedisAuth::new(redis_cache));
+                self.store = Store::Online(online);
                 self.misses = 0;
                 info!("AuthService: re-connected and migrated to Redis server")
             } else {
@@ -56,36 +127,120 @@ impl AuthService {
     
     }
 
-    pub async fn generate_user_token(&mut self, user_id: u64, username: &str) -> Result<Uuid, ()> {
+    /// Issues a token for `user_id`, signed and carrying the account, so that it
+    /// can be verified without asking the store, and a refresh token to exchange
+    /// for the next one.
+    pub async fn generate_user_token(&mut self, user_id: u64, username: &str) -> Result<IssuedToken, ()> {
+        // Redis failing to answer switches to offline, which is asked instead
+        let generation = match self.generation(user_id).await {
+            Ok(generation) => generation,
+            Err(()) => self.generation(user_id).await?
+        };
+
+        let lifetime = self.config.load().tokens.clone();
+        let issued_at = self.clock.now();
+        let claims = Claims {
+            sub: user_id.to_string(),
+            name: username.to_string(),
+            iat: issued_at.timestamp(),
+            exp: lifetime.expires_at(issued_at.timestamp()),
+            gen: generation
+        };
+        let token = self.keys.sign(&claims).map_err(|e| warn!("AuthService: {}", e))?;
+        let expires_at = issued_at + chrono::Duration::seconds(claims.exp - claims.iat);
+
+        let refresh_token = Uuid::new_v4();
+        self.store_refresh_token(TokenHash::of(&refresh_token), user_id, generation, lifetime.refresh_ttl_sec).await;
+        let refresh_expires_at = issued_at + chrono::Duration::seconds(lifetime.refresh_ttl_sec as i64);
+        Ok(IssuedToken { token, expires_at, refresh_token, refresh_expires_at })
+    }
+
+    /// Exchanges a refresh token of `user_id` for a new token and refresh token.
+    /// The refresh token is used up even if it is refused, and is refused if it was
+    /// issued to another account, or before the account's tokens were revoked.
+    pub async fn refresh_user_token(&mut self, user_id: u64, username: &str, refresh_token: &Uuid) -> Result<Option<IssuedToken>, ()> {
+        let token = TokenHash::of(refresh_token);
+
+        if let Store::Offline(_) = &self.store {
+            self.maybe_reconnect().await;
+        }
+
+        let now = self.clock.now().timestamp();
+        let taken = match &mut self.store {
+            Store::Offline(store) => {
+                self.misses += 1;
+                store.take_refresh_token(&token, now)
+            },
+            Store::Online(redis)  => {
+                let result = redis.take_refresh_token(&token).await;
+                if let Ok(taken) = result {
+                    taken
+                } else {
+                    self.go_offline();
+                    return Err(())
+                }
+            },
+        };
+
+        let Some((registered, generation)) = taken else {
+            return Ok(None)
+        };
+        if !ct_eq_u64(registered, user_id) || generation < self.generation(user_id).await? {
+            return Ok(None)
+        }
+        self.generate_user_token(user_id, username).await.map(Some)
+    }
+
+    /// Stores a refresh token, falling back to storing it offline if Redis fails.
+    async fn store_refresh_token(&mut self, token: TokenHash, user_id: u64, generation: u64, ttl_sec: u64) {
+        if let Store::Offline(_) = &self.store {
+            self.maybe_reconnect().await;
+        }
+
+        let now = self.clock.now().timestamp();
+        let expires_at = now.saturating_add(ttl_sec as i64);
+        match &mut self.store {
+            Store::Offline(store) => {
+                self.misses += 1;
+                store.store_refresh_token(token, user_id, generation, expires_at, now)
+            },
+            Store::Online(redis)  => {
+                if redis.store_refresh_token(&token, user_id, generation, ttl_sec).await.is_err() {
+                    self.go_offline().store_refresh_token(token, user_id, generation, expires_at, now);
+                }
+            },
+        }
+    }
+
+    /// Generates a short lived token for `user_id`, required by sensitive operations
+    /// in addition to the regular token.
+    pub async fn generate_sudo_token(&mut self, user_id: u64) -> Result<Uuid, ()> {
         if let Store::Offline(_) = &self.store {
             self.maybe_reconnect().await;
         }
 
+        let expiry_sec = self.config.load().tokens.sudo_ttl_sec;
+        let now = self.clock.now().timestamp();
         match &mut self.store {
             Store::Offline(store) => {
                 self.misses += 1;
-                Ok(store.generate_for_user(user_id))
+                Ok(store.generate_sudo_for_user(user_id, expiry_sec, now))
             },
             Store::Online(redis)  => {
-                let result = redis.generate_for_user(user_id, username).await;
+                let result = redis.generate_sudo_for_user(user_id, expiry_sec).await;
                 if let Ok(stored_uuid) = result {
                     Ok(stored_uuid)
                 } else {
-                    let mut offline = OfflineAuth::new();
-                    let stored_uuid = offline.generate_for_user(user_id);
-                    self.store = Store::Offline(offline);
-                    self.misses = 1;
-                    Ok(stored_uuid)
+                    Ok(self.go_offline().generate_sudo_for_user(user_id, expiry_sec, now))
                 }
             },
         }
     }
 
-    pub async fn validate(&mut self, user_id: u64, username: &str, token_str: &str) -> Result<bool, ()> {
-        let token = match Uuid::parse_str(token_str) {
-            Ok(uuid) => uuid,
-            Err(_) => return Err(()),
-        };
+    /// Whether `token` is a live sudo token of `user_id`. Results in `Err` if the
+    /// token store could not be reached.
+    pub async fn validate_sudo(&mut self, user_id: u64, token: &Uuid) -> Result<bool, ()> {
+        let token = TokenHash::of(token);
 
         if let Store::Offline(_) = &self.store {
             self.maybe_reconnect().await;
@@ -94,25 +249,115 @@ impl AuthService {
         match &self.store {
             Store::Offline(store) => {
                 self.misses += 1;
-                Ok(store.validate(user_id, token))
+                Ok(store.validate_sudo(user_id, &token, self.clock.now().timestamp()))
             },
             Store::Online(redis)  => {
-                let result = redis.validate_username(username, token).await;
+                let result = redis.validate_sudo(user_id, &token).await;
                 if let Ok(is_valid) = result {
-                    return Ok(is_valid)
+                    Ok(is_valid)
                 } else {
-                    warn!("AuthService: Switching to OfflineAuth");
-                    self.store = Store::Offline(OfflineAuth::new());
-                    self.misses = 1;
+                    self.go_offline();
                     Err(())
                 }
             },
         }
     }
 
+    /// The account of verified `claims`, unless the token has expired or been
+    /// revoked since it was issued.
+    pub async fn authenticate(&mut self, claims: &Claims) -> Result<Option<Identity>, ()> {
+        let Some(user_id) = claims.user_id() else {
+            return Ok(None)
+        };
+        if claims.exp <= self.clock.now().timestamp() {
+            return Ok(None)
+        }
+
+        let generation = self.generation(user_id).await?;
+        match claims.gen >= generation {
+            true  => Ok(Some(Identity { user_id, username: claims.name.clone() })),
+            false => Ok(None)
+        }
+    }
+
+    /// Revokes the tokens and refresh tokens of `user_id`, so that no existing session
+    /// of the account outlives e.g. a change of password. Sudo tokens are only revoked
+    /// offline, as Redis keeps them by hash alone, but are of no use without a token.
+    pub async fn revoke_user(&mut self, user_id: u64) -> Result<(), ()> {
+        if let Store::Offline(_) = &self.store {
+            self.maybe_reconnect().await;
+        }
+
+        let now = self.clock.now().timestamp();
+        match &mut self.store {
+            Store::Offline(store) => {
+                self.misses += 1;
+                let generation = store.revoke_user(user_id, now);
+                self.remember_generation(user_id, generation);
+                Ok(())
+            },
+            Store::Online(redis)  => {
+                let ttl_sec = self.config.load().tokens.revocation_ttl_sec();
+                if let Ok(generation) = redis.revoke_user(user_id, now, ttl_sec).await {
+                    self.remember_generation(user_id, generation);
+                    Ok(())
+                } else {
+                    self.go_offline();
+                    Err(())
+                }
+            },
+        }
+    }
+
+    /// Revokes the tokens of `user_id` and issues a new one, failing without issuing
+    /// one if the old tokens might still be valid.
+    pub async fn renew_user_token(&mut self, user_id: u64, username: &str) -> Result<IssuedToken, ()> {
+        self.revoke_user(user_id).await?;
+        self.generate_user_token(user_id, username).await
+    }
+
+    pub fn is_online(&self) -> bool {
+        matches!(self.store, Store::Online(_))
+    }
+
+    /// The revocation generation of `user_id`. Tokens issued at an older one have
+    /// been revoked. Offline, that of an account not known before Redis failed is
+    /// the time it failed, refusing its tokens from before then.
+    async fn generation(&mut self, user_id: u64) -> Result<u64, ()> {
+        if let Some(cached) = self.generations.get(&user_id) {
+            return Ok(cached)
+        }
+
+        if let Store::Offline(_) = &self.store {
+            self.maybe_reconnect().await;
+        }
+
+        match &self.store {
+            Store::Offline(store) => {
+                self.misses += 1;
+                Ok(store.generation(user_id))
+            },
+            Store::Online(redis)  => {
+                if let Ok(generation) = redis.generation(user_id).await {
+                    self.remember_generation(user_id, generation);
+                    Ok(generation)
+                } else {
+                    self.go_offline();
+                    Err(())
+                }
+            },
+        }
+    }
+}
+
+/// The generation an account is revoked at, after `current`. Generations are the
+/// unix time of the revocation where possible, so that those made offline, not
+/// knowing the generation in Redis, still move past it.
+pub(super) fn next_generation(current: u64, now: i64) -> u64 {
+    (now.max(0) as u64).max(current + 1)
 }
 
-fn try_connect(addr: &str) -> Result<Cache, ()> {
+pub fn try_connect(addr: &str) -> Result<Cache, ()> {
     let (sender, receiver) = mpsc::channel();
     
     let _ = thread::scope(|s: &thread::Scope<'_, '_>| {
@@ -130,16 +375,214 @@ fn try_connect(addr: &str) -> Result<Cache, ()> {
     }
 }
 
-async fn migrate_to_online(offline: &OfflineAuth, online: &Cache) -> Result<(), ()> {
-    let entries = offline.tokens.iter()
-                                .map(|entry| Entry {
-                                    key: entry.0.to_string(),
-                                    value: entry.1.to_string(),
-                                    expiry_sec: 120
-                                })
-                                .collect();
-    match online.set_multiple(entries, false, true).await {
-        Ok(_)  => Ok(()),
-        Err(_) => Err(()),
+/// Carries the revocations and refresh tokens made offline over to Redis. Tokens
+/// issued offline need nothing carried over, as they are verified by their signature.
+async fn migrate_to_online(offline: &OfflineAuth, online: &RedisAuth, ttl_sec: u64, now: i64) -> Result<(), ()> {
+    for (user_id, generation) in &offline.generations {
+        online.raise_generation(*user_id, *generation, ttl_sec).await?;
+    }
+    for (token, (user_id, generation, expires_at)) in &offline.refresh_tokens {
+        if *expires_at > now {
+            online.store_refresh_token(token, *user_id, *generation, (*expires_at - now) as u64).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use arc_swap::ArcSwap;
+    use chrono::Utc;
+
+    #[cfg(feature = "chaos")]
+    use crate::chaos;
+    use crate::auth::jwt::{Claims, JwtKeys};
+    use crate::clock::{Clock, ManualClock};
+    use crate::config::config::ServerConfig;
+
+    use super::{next_generation, AuthService, Identity, Store, REVOCATION_CACHE_TTL};
+
+    /// Nothing listens on port 1, so the service starts (and stays) offline.
+    const UNREACHABLE: &str = "redis://127.0.0.1:1";
+
+    fn identity(user_id: u64, username: &str) -> Identity {
+        Identity { user_id, username: username.to_string() }
+    }
+
+    fn keys() -> Arc<JwtKeys> {
+        Arc::new(JwtKeys::hs256(b"test"))
+    }
+
+    fn claims(keys: &JwtKeys, token: &str) -> Claims {
+        keys.verify(token).unwrap().unwrap()
+    }
+
+    #[cfg(feature = "chaos")]
+    fn test_context() -> AuthService {
+        dotenv::dotenv().ok();
+        let cache_url = std::env::var("REDIS_DATABASE_URL").expect("REDIS_DATABASE_URL is not set");
+        AuthService::new(&cache_url, Arc::new(ArcSwap::from_pointee(ServerConfig::default())), keys())
+    }
+
+    #[actix_web::test]
+    async fn test_offline_expiry() {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let config = ServerConfig::default();
+        let ttl_sec = config.tokens.ttl_sec;
+        let sudo_ttl_sec = config.tokens.sudo_ttl_sec;
+        let keys = keys();
+        let mut auth = AuthService::with_clock(UNREACHABLE, Arc::new(ArcSwap::from_pointee(config)), keys.clone(), clock.clone());
+        assert!(matches!(auth.store, Store::Offline(_)));
+
+        let issued = auth.generate_user_token(1, "one").await.unwrap();
+        assert_eq!(clock.now() + chrono::Duration::seconds(ttl_sec as i64), issued.expires_at);
+        let token = claims(&keys, &issued.token);
+        assert_eq!(("1", "one"), (token.sub.as_str(), token.name.as_str()));
+        assert_eq!(issued.expires_at.timestamp(), token.exp);
+        let sudo = auth.generate_sudo_token(1).await.unwrap();
+        clock.advance(Duration::from_secs(sudo_ttl_sec - 1));
+        assert_eq!(Ok(true), auth.validate_sudo(1, &sudo).await);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(Ok(false), auth.validate_sudo(1, &sudo).await);
+
+        clock.advance(Duration::from_secs(ttl_sec - sudo_ttl_sec - 1));
+        assert_eq!(Ok(Some(identity(1, "one"))), auth.authenticate(&token).await);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(Ok(None), auth.authenticate(&token).await);
+    }
+
+    #[actix_web::test]
+    async fn test_offline_revoke() {
+        let keys = keys();
+        let mut auth = AuthService::new(UNREACHABLE, Arc::new(ArcSwap::from_pointee(ServerConfig::default())), keys.clone());
+        let token = claims(&keys, &auth.generate_user_token(1, "one").await.unwrap().token);
+        let sudo = auth.generate_sudo_token(1).await.unwrap();
+        let other = claims(&keys, &auth.generate_user_token(2, "two").await.unwrap().token);
+        assert_eq!(Ok(Some(identity(1, "one"))), auth.authenticate(&token).await);
+
+        assert_eq!(Ok(()), auth.revoke_user(1).await);
+        assert_eq!(Ok(None), auth.authenticate(&token).await);
+        assert_eq!(Ok(false), auth.validate_sudo(1, &sudo).await);
+        assert_eq!(Ok(Some(identity(2, "two"))), auth.authenticate(&other).await);
+
+        let renewed = claims(&keys, &auth.renew_user_token(1, "one").await.unwrap().token);
+        assert_eq!(Ok(Some(identity(1, "one"))), auth.authenticate(&renewed).await);
+        assert_eq!(Ok(None), auth.authenticate(&token).await);
+    }
+
+    #[actix_web::test]
+    async fn test_offline_refresh() {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let config = ServerConfig::default();
+        let refresh_ttl_sec = config.tokens.refresh_ttl_sec;
+        let keys = keys();
+        let mut auth = AuthService::with_clock(UNREACHABLE, Arc::new(ArcSwap::from_pointee(config)), keys.clone(), clock.clone());
+
+        let issued = auth.generate_user_token(1, "one").await.unwrap();
+        assert_eq!(clock.now() + chrono::Duration::seconds(refresh_ttl_sec as i64), issued.refresh_expires_at);
+        let refreshed = auth.refresh_user_token(1, "one", &issued.refresh_token).await.unwrap().unwrap();
+        assert_eq!(Ok(Some(identity(1, "one"))), auth.authenticate(&claims(&keys, &refreshed.token)).await);
+        assert_ne!(issued.refresh_token, refreshed.refresh_token);
+        // Rotated, so the old refresh token is used up
+        assert_eq!(Ok(None), auth.refresh_user_token(1, "one", &issued.refresh_token).await);
+
+        // Another account's refresh token is refused, and used up
+        let other = auth.generate_user_token(2, "two").await.unwrap();
+        assert_eq!(Ok(None), auth.refresh_user_token(1, "one", &other.refresh_token).await);
+        assert_eq!(Ok(None), auth.refresh_user_token(2, "two", &other.refresh_token).await);
+
+        let expiring = auth.generate_user_token(1, "one").await.unwrap();
+        clock.advance(Duration::from_secs(refresh_ttl_sec));
+        assert_eq!(Ok(None), auth.refresh_user_token(1, "one", &expiring.refresh_token).await);
+
+        // A change of password revokes the refresh tokens from before it
+        let revoked = auth.generate_user_token(1, "one").await.unwrap();
+        let renewed = auth.renew_user_token(1, "one").await.unwrap();
+        assert_eq!(Ok(None), auth.refresh_user_token(1, "one", &revoked.refresh_token).await);
+        assert!(auth.refresh_user_token(1, "one", &renewed.refresh_token).await.unwrap().is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_revocations_survive_going_offline() {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let keys = keys();
+        let config = Arc::new(ArcSwap::from_pointee(ServerConfig::default()));
+        let mut auth = AuthService::with_clock(UNREACHABLE, config, keys.clone(), clock.clone());
+        let token = claims(&keys, &auth.generate_user_token(1, "one").await.unwrap().token);
+        let other = claims(&keys, &auth.generate_user_token(2, "two").await.unwrap().token);
+        assert_eq!(Ok(()), auth.revoke_user(1).await);
+
+        // Redis failing again starts a new OfflineAuth, which still knows of the
+        // revocation once it is no longer cached
+        clock.advance(Duration::from_secs(1));
+        auth.go_offline();
+        clock.advance(REVOCATION_CACHE_TTL);
+        assert_eq!(Ok(None), auth.authenticate(&token).await);
+        // Tokens of an account not known from before are not trusted
+        assert_eq!(Ok(None), auth.authenticate(&other).await);
+
+        let renewed = claims(&keys, &auth.generate_user_token(1, "one").await.unwrap().token);
+        assert_eq!(Ok(Some(identity(1, "one"))), auth.authenticate(&renewed).await);
+    }
+
+    #[test]
+    fn test_next_generation() {
+        assert_eq!(100, next_generation(0, 100));
+        assert_eq!(101, next_generation(100, 100));
+        // A clock behind the last revocation still moves past it
+        assert_eq!(101, next_generation(100, 50));
+        assert_eq!(1, next_generation(0, -1));
+    }
+
+    #[cfg(feature = "chaos")]
+    #[actix_web::test]
+    async fn test_fails_over_to_offline() {
+        let _scenario = chaos::scenario();
+        let keys = keys();
+        let mut auth = test_context();
+        assert!(matches!(auth.store, Store::Online(_)));
+
+        chaos::CACHE.configure(1, 1.0, Duration::ZERO);
+        let token = auth.generate_user_token(u64::MAX - 1, "!test_fails_over!").await.unwrap().token;
+        assert!(matches!(auth.store, Store::Offline(_)));
+        assert!(chaos::CACHE.injected() > 0);
+        assert_eq!(Ok(Some(identity(u64::MAX - 1, "!test_fails_over!"))), auth.authenticate(&claims(&keys, &token)).await);
+    }
+
+    #[cfg(feature = "chaos")]
+    #[actix_web::test]
+    async fn test_validation_fails_over_to_offline() {
+        let _scenario = chaos::scenario();
+        let keys = keys();
+        let mut auth = test_context();
+        let token = claims(&keys, &auth.generate_user_token(u64::MAX - 2, "!test_validation_fails_over!").await.unwrap().token);
+        assert!(matches!(auth.store, Store::Online(_)));
+
+        // Revocations can't be checked once Redis is unreachable, but the token
+        // itself stays valid offline
+        chaos::CACHE.configure(1, 1.0, Duration::ZERO);
+        auth.generations.remove_where(|user_id, _| *user_id == u64::MAX - 2);
+        assert_eq!(Err(()), auth.authenticate(&token).await);
+        assert!(matches!(auth.store, Store::Offline(_)));
+        assert_eq!(Ok(Some(identity(u64::MAX - 2, "!test_validation_fails_over!"))), auth.authenticate(&token).await);
+    }
+
+    #[cfg(feature = "chaos")]
+    #[actix_web::test]
+    async fn test_revocation_survives_failing_over() {
+        let _scenario = chaos::scenario();
+        let keys = keys();
+        let mut auth = test_context();
+        let token = claims(&keys, &auth.generate_user_token(u64::MAX - 3, "!test_revocation_survives!").await.unwrap().token);
+        assert_eq!(Ok(()), auth.revoke_user(u64::MAX - 3).await);
+
+        chaos::CACHE.configure(1, 1.0, Duration::ZERO);
+        auth.generations.remove_where(|user_id, _| *user_id == u64::MAX - 3);
+        assert_eq!(Err(()), auth.authenticate(&token).await);
+        assert!(matches!(auth.store, Store::Offline(_)));
+        assert_eq!(Ok(None), auth.authenticate(&token).await);
     }
 }
\ No newline at end of file
@@ -2,36 +2,151 @@ use std::collections::HashMap;
 
 use uuid::Uuid;
 
-type TokenRegistry = HashMap<u64, Uuid>;
+use super::auth::next_generation;
+use super::token::{ct_eq_u64, TokenHash};
 
+/// What AuthService keeps in-process while Redis is unreachable. Tokens verify
+/// by their signature alone, so only revocations, sudo and refresh tokens are kept.
 pub struct OfflineAuth {
-    pub(super) tokens: TokenRegistry
+    /// When Redis became unreachable. Accounts whose generation isn't known offline
+    /// are taken to have been revoked then, so that no token issued before can be
+    /// trusted without Redis.
+    since: u64,
+    /// user_id -> revocation generation, of the accounts last known before going
+    /// offline and those revoked since
+    pub(super) generations: HashMap<u64, u64>,
+    /// Sudo token hash -> (user_id, expires_at)
+    sudo_tokens: HashMap<TokenHash, (u64, i64)>,
+    /// Refresh token hash -> (user_id, generation, expires_at)
+    pub(super) refresh_tokens: HashMap<TokenHash, (u64, u64, i64)>
 }
 
 impl OfflineAuth {
-    pub fn new() -> Self {
-        OfflineAuth { tokens: HashMap::new() }
+    /// An OfflineAuth that went offline at the unix time `since`, knowing nothing of
+    /// the generations in Redis.
+    pub fn new(since: i64) -> Self {
+        OfflineAuth::carrying(since, HashMap::new())
     }
 
-    /// Generates a new v4 uuid and inserts into the token registry with the
-    /// provided `user_id` as the key, and the generated uuid as the value.
-    /// 
-    /// The generated and registered uuid is returned.
-    pub fn generate_for_user(&mut self, user_id: u64) -> Uuid {
+    /// An OfflineAuth that went offline at `since`, knowing the last `generations`
+    /// read from Redis.
+    pub fn carrying(since: i64, generations: HashMap<u64, u64>) -> Self {
+        OfflineAuth {
+            since: since.max(0) as u64,
+            generations,
+            sudo_tokens: HashMap::new(),
+            refresh_tokens: HashMap::new()
+        }
+    }
+
+    /// The revocation generation of `user_id`, or the time the store went offline if
+    /// it is not known.
+    pub fn generation(&self, user_id: u64) -> u64 {
+        self.generations.get(&user_id).copied().unwrap_or(self.since)
+    }
+
+    /// Revokes every token of `user_id` issued until `now`, and its sudo and
+    /// refresh tokens. Returns the new generation of the account.
+    pub fn revoke_user(&mut self, user_id: u64, now: i64) -> u64 {
+        let generation = next_generation(self.generation(user_id), now);
+        self.generations.insert(user_id, generation);
+        self.sudo_tokens.retain(|_, (registered, _)| *registered != user_id);
+        self.refresh_tokens.retain(|_, (registered, _, _)| *registered != user_id);
+        generation
+    }
+
+    /// Generates a sudo token for `user_id` that expires `expiry_sec` from `now`.
+    pub fn generate_sudo_for_user(&mut self, user_id: u64, expiry_sec: u64, now: i64) -> Uuid {
+        self.sudo_tokens.retain(|_, (_, expires_at)| *expires_at > now);
         let uuid = Uuid::new_v4();
-        self.tokens.insert(user_id, uuid);
+        self.sudo_tokens.insert(TokenHash::of(&uuid), (user_id, now + expiry_sec as i64));
         uuid
     }
 
-    /// Verifies whether a provided `token` is a valid token for a `user_id`.
-    /// 
-    /// `false` is returned when the `user_id` has no associated token, or the
-    /// associated token does not match the provided `token_to_check`.
-    pub fn validate(&self, user_id: u64, token: Uuid) -> bool {
-        match self.tokens.get(&user_id) {
-            Some(registered) => registered.eq(&token),
+    pub fn validate_sudo(&self, user_id: u64, token: &TokenHash, now: i64) -> bool {
+        match self.sudo_tokens.get(token) {
+            Some((registered, expires_at)) => ct_eq_u64(*registered, user_id) && *expires_at > now,
             None => false
         }
     }
 
+    /// Stores a refresh token of `user_id`, issued at `generation`, until `expires_at`.
+    pub fn store_refresh_token(&mut self, token: TokenHash, user_id: u64, generation: u64, expires_at: i64, now: i64) {
+        self.refresh_tokens.retain(|_, (_, _, expires_at)| *expires_at > now);
+        self.refresh_tokens.insert(token, (user_id, generation, expires_at));
+    }
+
+    /// Removes a refresh token, returning the account and generation it was issued
+    /// at, unless it has expired.
+    pub fn take_refresh_token(&mut self, token: &TokenHash, now: i64) -> Option<(u64, u64)> {
+        self.refresh_tokens.remove(token)
+            .filter(|(_, _, expires_at)| *expires_at > now)
+            .map(|(user_id, generation, _)| (user_id, generation))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use uuid::Uuid;
+
+    use crate::auth::token::TokenHash;
+    use super::OfflineAuth;
+
+    #[test]
+    fn test_validate_sudo() {
+        let mut auth = OfflineAuth::new(0);
+        let token = TokenHash::of(&auth.generate_sudo_for_user(1, 60, 0));
+
+        assert!(auth.validate_sudo(1, &token, 59));
+        assert!(!auth.validate_sudo(2, &token, 59));
+        assert!(!auth.validate_sudo(1, &token, 60));
+        assert!(!auth.validate_sudo(1, &TokenHash::of(&Uuid::new_v4()), 0));
+    }
+
+    #[test]
+    fn test_take_refresh_token() {
+        let mut auth = OfflineAuth::new(0);
+        let token = TokenHash::of(&Uuid::new_v4());
+        let expired = TokenHash::of(&Uuid::new_v4());
+        auth.store_refresh_token(token, 1, 5, 60, 0);
+        auth.store_refresh_token(expired, 1, 5, 10, 0);
+
+        assert_eq!(None, auth.take_refresh_token(&expired, 10));
+        assert_eq!(Some((1, 5)), auth.take_refresh_token(&token, 59));
+        // Used up by being taken
+        assert_eq!(None, auth.take_refresh_token(&token, 59));
+    }
+
+    #[test]
+    fn test_generation() {
+        let auth = OfflineAuth::carrying(100, HashMap::from([(1, 50), (2, 200)]));
+        assert_eq!(50, auth.generation(1));
+        assert_eq!(200, auth.generation(2));
+        // Unknown accounts are taken to have been revoked when the store went offline
+        assert_eq!(100, auth.generation(3));
+        assert_eq!(0, OfflineAuth::new(-1).generation(3));
+    }
+
+    #[test]
+    fn test_revoke_user() {
+        let mut auth = OfflineAuth::new(0);
+        let sudo = TokenHash::of(&auth.generate_sudo_for_user(1, 60, 0));
+        let other = TokenHash::of(&auth.generate_sudo_for_user(2, 60, 0));
+        let refresh = TokenHash::of(&Uuid::new_v4());
+        auth.store_refresh_token(refresh, 1, 0, 60, 0);
+        assert_eq!(0, auth.generation(1));
+
+        let revoked = auth.revoke_user(1, 10);
+        assert!(revoked > 0);
+        assert_eq!(revoked, auth.generation(1));
+        // Revoking again within the same second still moves past the last revocation
+        assert!(auth.revoke_user(1, 10) > revoked);
+
+        assert!(!auth.validate_sudo(1, &sudo, 1));
+        assert!(auth.validate_sudo(2, &other, 1));
+        assert_eq!(None, auth.take_refresh_token(&refresh, 1));
+        assert_eq!(0, auth.generation(2));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,104 @@
+use log::warn;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::cache::{cache::Cache, error::CacheErr, keys::ChallengeKey};
+
+/// A proof-of-work puzzle: find a `solution` for which `sha256(nonce + solution)`
+/// starts with `difficulty` zero bits.
+#[derive(Debug, Serialize)]
+pub struct Challenge {
+    pub nonce: String,
+    pub difficulty: u32,
+    pub expires_in_sec: u64
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ChallengeError {
+    /// The challenge does not exist, has expired, has been used, or was not solved.
+    Invalid,
+    /// Challenges cannot be issued or checked without Redis.
+    Unavailable
+}
+
+/// Unsolved challenges in Redis. Each challenge can be checked once only, so a
+/// solution cannot be reused for more than one registration.
+pub struct ChallengeStore {
+    cache: Option<Cache>
+}
+
+impl ChallengeStore {
+    pub fn new(cache: Option<Cache>) -> Self {
+        ChallengeStore { cache }
+    }
+
+    pub async fn issue(&self, difficulty: u32, ttl_sec: u64) -> Result<Challenge, ChallengeError> {
+        let cache = self.cache.as_ref().ok_or(ChallengeError::Unavailable)?;
+        let nonce = Uuid::new_v4().simple().to_string();
+        match cache.set_key(&ChallengeKey(&nonce).to_string(), &difficulty.to_string(), ttl_sec).await {
+            Ok(()) => Ok(Challenge { nonce, difficulty, expires_in_sec: ttl_sec }),
+            Err(()) => Err(ChallengeError::Unavailable)
+        }
+    }
+
+    /// Checks `solution` against the challenge of `nonce`, at the difficulty it was
+    /// issued with. The challenge is used up whether or not it was solved.
+    pub async fn check(&self, nonce: &str, solution: &str) -> Result<(), ChallengeError> {
+        let cache = self.cache.as_ref().ok_or(ChallengeError::Unavailable)?;
+        let difficulty = match cache.take(&ChallengeKey(nonce).to_string()).await {
+            Ok(difficulty) => difficulty.parse::<u32>().map_err(|_| ChallengeError::Invalid)?,
+            Err(CacheErr::NilResponse) => return Err(ChallengeError::Invalid),
+            Err(_) => {
+                warn!("ChallengeStore: failed to read challenge {}", nonce);
+                return Err(ChallengeError::Unavailable)
+            }
+        };
+        match solves(nonce, solution, difficulty) {
+            true  => Ok(()),
+            false => Err(ChallengeError::Invalid)
+        }
+    }
+}
+
+/// Whether `sha256(nonce + solution)` starts with at least `difficulty` zero bits.
+pub fn solves(nonce: &str, solution: &str, difficulty: u32) -> bool {
+    let digest = Sha256::new()
+        .chain_update(nonce.as_bytes())
+        .chain_update(solution.as_bytes())
+        .finalize();
+    leading_zero_bits(&digest) >= difficulty
+}
+
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut zeros = 0;
+    for byte in bytes {
+        zeros += byte.leading_zeros();
+        if *byte != 0 {
+            break
+        }
+    }
+    zeros
+}
+
+#[cfg(test)]
+mod test {
+    use super::{leading_zero_bits, solves};
+
+    #[test]
+    fn test_leading_zero_bits() {
+        assert_eq!(0, leading_zero_bits(&[0x80, 0x00]));
+        assert_eq!(3, leading_zero_bits(&[0x1f, 0xff]));
+        assert_eq!(12, leading_zero_bits(&[0x00, 0x0f]));
+        assert_eq!(16, leading_zero_bits(&[0x00, 0x00]));
+    }
+
+    #[test]
+    fn test_solves() {
+        // sha256("0123456789abcdef1020") starts 0008c5fe, 12 zero bits
+        assert!(solves("0123456789abcdef", "1020", 12));
+        assert!(!solves("0123456789abcdef", "1020", 13));
+        assert!(!solves("0123456789abcdef", "0", 1));
+        assert!(solves("0123456789abcdef", "0", 0));
+    }
+}
\ No newline at end of file
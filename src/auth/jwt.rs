@@ -0,0 +1,185 @@
+use std::fmt;
+
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::errors::ErrorKind;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What a login token says of its account. Tokens are signed, so the claims
+/// are trusted once the signature is verified, without asking the store.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Claims {
+    /// The account id
+    pub sub: String,
+    /// The account's username when the token was issued
+    pub name: String,
+    /// Unix time
+    pub iat: i64,
+    /// Unix time
+    pub exp: i64,
+    /// The revocation generation of the account when the token was issued. The
+    /// token is revoked once the account's generation moves past it.
+    pub gen: u64
+}
+
+impl Claims {
+    pub fn user_id(&self) -> Option<u64> {
+        self.sub.parse().ok()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum JwtError {
+    UnknownAlgorithm(String),
+    MissingVar(&'static str),
+    Key(String),
+    /// Not a JWT at all, rather than one that failed verification
+    Malformed,
+    Signing(String),
+    /// An HS256 secret shorter than `MIN_SECRET_LEN`, which could be guessed
+    WeakSecret
+}
+
+impl fmt::Display for JwtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JwtError::UnknownAlgorithm(name) => write!(f, "JWT_ALGORITHM '{}' is not HS256 or RS256", name),
+            JwtError::MissingVar(var) => write!(f, "{} is not set", var),
+            JwtError::Key(e) => write!(f, "invalid key: {}", e),
+            JwtError::Malformed => write!(f, "malformed token"),
+            JwtError::Signing(e) => write!(f, "failed to sign: {}", e),
+            JwtError::WeakSecret => write!(f, "JWT_SECRET must be at least {} bytes", MIN_SECRET_LEN)
+        }
+    }
+}
+
+/// The shortest HS256 secret accepted, the length of the SHA-256 output.
+const MIN_SECRET_LEN: usize = 32;
+
+/// The keys login tokens are signed and verified with.
+pub struct JwtKeys {
+    algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey
+}
+
+impl JwtKeys {
+    pub fn hs256(secret: &[u8]) -> Self {
+        JwtKeys {
+            algorithm: Algorithm::HS256,
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret)
+        }
+    }
+
+    /// From PEM encoded RSA keys. Servers that only verify tokens still need a
+    /// private key, as any of them may issue one.
+    pub fn rs256(private_pem: &[u8], public_pem: &[u8]) -> Result<Self, JwtError> {
+        Ok(JwtKeys {
+            algorithm: Algorithm::RS256,
+            encoding: EncodingKey::from_rsa_pem(private_pem).map_err(|e| JwtError::Key(e.to_string()))?,
+            decoding: DecodingKey::from_rsa_pem(public_pem).map_err(|e| JwtError::Key(e.to_string()))?
+        })
+    }
+
+    /// Keys by `JWT_ALGORITHM` (HS256 by default): `JWT_SECRET` for HS256, or
+    /// `JWT_PRIVATE_KEY_PATH` and `JWT_PUBLIC_KEY_PATH` for RS256. A random secret
+    /// is only used in development, when `JWT_RANDOM_SECRET` is `true`.
+    pub fn from_env() -> Result<Self, JwtError> {
+        let algorithm = std::env::var("JWT_ALGORITHM").unwrap_or("HS256".to_string());
+        match algorithm.as_str() {
+            "HS256" => JwtKeys::hs256_secret(
+                std::env::var("JWT_SECRET").ok(),
+                std::env::var("JWT_RANDOM_SECRET").is_ok_and(|flag| flag == "true")
+            ),
+            "RS256" => {
+                let private_pem = read_key("JWT_PRIVATE_KEY_PATH")?;
+                let public_pem = read_key("JWT_PUBLIC_KEY_PATH")?;
+                JwtKeys::rs256(&private_pem, &public_pem)
+            },
+            _ => Err(JwtError::UnknownAlgorithm(algorithm))
+        }
+    }
+
+    /// HS256 keys from `secret`, which must be at least `MIN_SECRET_LEN` bytes.
+    /// Without one, a random secret is used if `random_allowed`, and tokens only
+    /// last until a restart.
+    fn hs256_secret(secret: Option<String>, random_allowed: bool) -> Result<Self, JwtError> {
+        match secret {
+            Some(secret) if secret.len() < MIN_SECRET_LEN => Err(JwtError::WeakSecret),
+            Some(secret) => Ok(JwtKeys::hs256(secret.as_bytes())),
+            None if random_allowed => {
+                warn!("JwtKeys: using a random secret, tokens will not outlive this process");
+                let secret = [Uuid::new_v4().into_bytes(), Uuid::new_v4().into_bytes()].concat();
+                Ok(JwtKeys::hs256(&secret))
+            },
+            None => Err(JwtError::MissingVar("JWT_SECRET"))
+        }
+    }
+
+    pub fn sign(&self, claims: &Claims) -> Result<String, JwtError> {
+        jsonwebtoken::encode(&Header::new(self.algorithm), claims, &self.encoding)
+            .map_err(|e| JwtError::Signing(e.to_string()))
+    }
+
+    /// The claims of `token` if it was signed by these keys, whether or not it
+    /// has expired: expiry is checked by `AuthService`, against its clock.
+    pub fn verify(&self, token: &str) -> Result<Option<Claims>, JwtError> {
+        let mut validation = Validation::new(self.algorithm);
+        validation.validate_exp = false;
+        validation.set_required_spec_claims(&["sub", "iat", "exp"]);
+        match jsonwebtoken::decode::<Claims>(token, &self.decoding, &validation) {
+            Ok(data) => Ok(Some(data.claims)),
+            Err(e) => match e.kind() {
+                ErrorKind::InvalidToken | ErrorKind::Base64(_) | ErrorKind::Json(_) | ErrorKind::Utf8(_) => Err(JwtError::Malformed),
+                _ => Ok(None)
+            }
+        }
+    }
+}
+
+fn read_key(var: &'static str) -> Result<Vec<u8>, JwtError> {
+    let path = std::env::var(var).map_err(|_| JwtError::MissingVar(var))?;
+    std::fs::read(&path).map_err(|e| JwtError::Key(format!("{}: {}", path, e)))
+}
+
+#[cfg(test)]
+mod test {
+    use jsonwebtoken::{Algorithm, EncodingKey, Header};
+
+    use super::{Claims, JwtError, JwtKeys};
+
+    fn claims() -> Claims {
+        Claims { sub: "7".to_string(), name: "alice".to_string(), iat: 100, exp: 200, gen: 0 }
+    }
+
+    #[test]
+    fn test_verify() {
+        let keys = JwtKeys::hs256(b"secret");
+        let token = keys.sign(&claims()).unwrap();
+        assert_eq!(Ok(Some(claims())), keys.verify(&token));
+        assert_eq!(Some(7), claims().user_id());
+
+        // Signed by another key, by another algorithm, or not at all
+        assert_eq!(Ok(None), JwtKeys::hs256(b"other").verify(&token));
+        let other_algorithm = jsonwebtoken::encode(&Header::new(Algorithm::HS384), &claims(), &EncodingKey::from_secret(b"secret")).unwrap();
+        assert_eq!(Ok(None), keys.verify(&other_algorithm));
+        let (unsigned, _) = token.rsplit_once('.').unwrap();
+        assert_ne!(Ok(Some(claims())), keys.verify(&format!("{}.", unsigned)));
+
+        assert_eq!(Err(JwtError::Malformed), keys.verify("not a token"));
+        assert_eq!(Err(JwtError::Malformed), keys.verify(&uuid::Uuid::new_v4().to_string()));
+    }
+
+    #[test]
+    fn test_hs256_secret() {
+        assert_eq!(Some(JwtError::WeakSecret), JwtKeys::hs256_secret(Some(String::new()), true).err());
+        assert_eq!(Some(JwtError::WeakSecret), JwtKeys::hs256_secret(Some("a".repeat(31)), false).err());
+        assert!(JwtKeys::hs256_secret(Some("a".repeat(32)), false).is_ok());
+
+        // A random secret must be asked for
+        assert_eq!(Some(JwtError::MissingVar("JWT_SECRET")), JwtKeys::hs256_secret(None, false).err());
+        assert!(JwtKeys::hs256_secret(None, true).is_ok());
+    }
+}
\ No newline at end of file
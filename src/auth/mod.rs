@@ -1,3 +1,7 @@
 pub mod backup_auth;
+pub mod challenge;
+pub mod jwt;
 pub mod redis_auth;
-pub mod auth;
\ No newline at end of file
+pub mod auth;
+pub mod shards;
+pub mod token;
\ No newline at end of file
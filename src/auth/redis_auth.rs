@@ -1,9 +1,11 @@
 use uuid::Uuid;
 
-use crate::cache::{cache::{Cache, Entry}, error::CacheErr};
-
-const DAY_IN_SECONDS: u64 = 60 * 60 * 12;
+use crate::cache::{cache::Cache, error::CacheErr};
+use crate::cache::keys::{RefreshKey, RevocationKey, SudoKey};
+use super::auth::next_generation;
+use super::token::{ct_eq_u64, TokenHash};
 
+/// The revocation list, sudo and refresh tokens, shared in Redis by every server.
 pub struct RedisAuth {
     redis_cache: Cache
 }
@@ -13,72 +15,82 @@ impl RedisAuth {
         RedisAuth { redis_cache: redis_cache }
     }
 
-    pub async fn generate_for_user(&self, user_id: u64, username: &str) -> Result<Uuid, ()> {
-        let uuid = Uuid::new_v4();
-        let token_to_user = create_token_to_user_entry(&uuid, username, user_id);
-        let user_to_token = create_user_to_token_entry(username, &uuid, user_id);
-        match self.redis_cache.set_multiple(vec![token_to_user, user_to_token], false, true).await {
-            Ok(_)  => Ok(uuid),
-            Err(_) => Err(()),
+    /// The revocation generation of `user_id`, 0 if it was never revoked, or not
+    /// since its tokens from then expired.
+    pub async fn generation(&self, user_id: u64) -> Result<u64, ()> {
+        match self.redis_cache.get(&RevocationKey(user_id).to_string()).await {
+            Ok(value) => value.parse::<u64>().map_err(|_| ()),
+            Err(CacheErr::NilResponse) => Ok(0),
+            Err(_) => Err(())
         }
     }
 
-    pub async fn validate_username(&self, username: &str, token: Uuid) -> Result<bool, ()> {
-        let value = match self.redis_cache.get(&token.to_string()).await {
-            Ok(value) => value,
-            Err(CacheErr::NilResponse) => return Ok(false),
-            Err(_) => return Err(())
-        };
-
-        let (stored_username, _) = separate_token_result(value)?;
-
-        Ok(stored_username.eq(username))
+    /// Revokes every token of `user_id` issued until `now`, returning the new
+    /// generation of the account. See `raise_generation`.
+    pub async fn revoke_user(&self, user_id: u64, now: i64, ttl_sec: u64) -> Result<u64, ()> {
+        let generation = next_generation(self.generation(user_id).await?, now);
+        self.raise_generation(user_id, generation, ttl_sec).await
     }
 
-    /// Determines whether a `user_id` has a token mapped to it, and if it so, compares
-    /// `token` to it. `true` is returned if the mapped token matches the `token` parameter.
-    /// `false` is returned if there is no mapping, or the provided `token` does not match.
-    pub async fn validate(&self, user_id: u64, token: Uuid) -> Result<bool, ()> {
-        let Ok(user_token) = self.redis_cache.get_token_by_user_id(user_id).await else {
-            return Err(())
-        };
-        // info!("token retrieved from Redis server");
-        Ok(Uuid::eq(&user_token, &token))
+    /// Raises the generation of `user_id` to `generation`, unless it is already
+    /// past it, and returns the generation it is left at. It is kept for `ttl_sec`,
+    /// the lifetime of a token, after which the tokens it revoked have expired.
+    pub async fn raise_generation(&self, user_id: u64, generation: u64, ttl_sec: u64) -> Result<u64, ()> {
+        let current = self.generation(user_id).await?;
+        if generation <= current {
+            return Ok(current)
+        }
+        self.redis_cache.set_key(&RevocationKey(user_id).to_string(), &generation.to_string(), ttl_sec.max(1)).await?;
+        Ok(generation)
     }
-}
-
-fn create_token_to_user_entry(token: &Uuid, username: &str, user_id: u64) -> Entry {
-    Entry::new(token.to_string(), format!("{}!{}", username, user_id), DAY_IN_SECONDS)
-}
 
-fn create_user_to_token_entry(username: &str, token: &Uuid, user_id: u64) -> Entry {
-    Entry::new(username.to_string(), format!("{}!{}", token.to_string(), user_id), DAY_IN_SECONDS)
-}
+    pub async fn generate_sudo_for_user(&self, user_id: u64, expiry_sec: u64) -> Result<Uuid, ()> {
+        let uuid = Uuid::new_v4();
+        self.redis_cache.set_key(&SudoKey(&TokenHash::of(&uuid)).to_string(), &user_id.to_string(), expiry_sec).await?;
+        Ok(uuid)
+    }
 
-/// `value` in the format of: `<username>!<user_id>`
-/// 
-/// If successful, returns: (Username, user_id)
-fn separate_token_result(value: String) -> Result<(String, u64), ()> {
-    let (left, right) = match value.split_once("!") {
-        Some((l, r)) => (l, r),
-        None => return Err(())
-    };
+    pub async fn validate_sudo(&self, user_id: u64, token: &TokenHash) -> Result<bool, ()> {
+        match self.redis_cache.get(&SudoKey(token).to_string()).await {
+            Ok(value) => Ok(value.parse::<u64>().is_ok_and(|id| ct_eq_u64(id, user_id))),
+            Err(CacheErr::NilResponse) => Ok(false),
+            Err(_) => Err(())
+        }
+    }
 
-    if left.is_empty() || right.is_empty() || right.contains("!") {
-        return Err(())
+    /// Stores a refresh token of `user_id`, issued at `generation`, for `ttl_sec`.
+    pub async fn store_refresh_token(&self, token: &TokenHash, user_id: u64, generation: u64, ttl_sec: u64) -> Result<(), ()> {
+        let value = format!("{}:{}", user_id, generation);
+        self.redis_cache.set_key(&RefreshKey(token).to_string(), &value, ttl_sec.max(1)).await
     }
 
-    match right.parse::<u64>() {
-        Ok(id) => Ok((left.to_string(), id)),
-        Err(_) => Err(())
+    /// Removes a refresh token, returning the account and generation it was issued
+    /// at. Only one caller can take a token, so it is exchanged at most once.
+    pub async fn take_refresh_token(&self, token: &TokenHash) -> Result<Option<(u64, u64)>, ()> {
+        match self.redis_cache.take(&RefreshKey(token).to_string()).await {
+            Ok(value) => Ok(parse_refresh_value(&value)),
+            Err(CacheErr::NilResponse) => Ok(None),
+            Err(_) => Err(())
+        }
     }
 }
 
-/// `value` in the format of: `<token>!<user_id>`
-fn _separate_user_result(value: String) -> Result<(Uuid, u64), ()> {
-    let (left, right) = separate_token_result(value)?;
-    match Uuid::parse_str(&left) {
-        Ok(uuid) => Ok((uuid, right)),
-        Err(_) => Err(())
+/// The `user_id:generation` stored for a refresh token.
+fn parse_refresh_value(value: &str) -> Option<(u64, u64)> {
+    let (user_id, generation) = value.split_once(':')?;
+    Some((user_id.parse().ok()?, generation.parse().ok()?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_refresh_value;
+
+    #[test]
+    fn test_parse_refresh_value() {
+        assert_eq!(Some((7, 1700000000)), parse_refresh_value("7:1700000000"));
+        assert_eq!(Some((7, 0)), parse_refresh_value("7:0"));
+        assert_eq!(None, parse_refresh_value("7"));
+        assert_eq!(None, parse_refresh_value("7:"));
+        assert_eq!(None, parse_refresh_value("a:1"));
     }
 }
\ No newline at end of file
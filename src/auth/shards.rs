@@ -0,0 +1,105 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+use crate::clock::{self, Clock};
+use crate::config::config::SharedConfig;
+use crate::metrics::metrics::Metrics;
+use super::auth::{AuthService, Identity};
+use super::jwt::JwtKeys;
+
+pub const DEFAULT_SHARD_COUNT: usize = 4;
+
+/// Independent AuthServices, each behind its own lock. A user's revocations
+/// always live in the same shard (`user_id % N`), so requests from different
+/// users rarely contend on the same lock.
+pub struct AuthShards {
+    shards: Vec<Mutex<AuthService>>,
+    keys: Arc<JwtKeys>,
+    metrics: Arc<Metrics>
+}
+
+impl AuthShards {
+    pub fn new(addr: &str, config: Arc<SharedConfig>, keys: Arc<JwtKeys>, count: usize, metrics: Arc<Metrics>) -> Self {
+        AuthShards::with_clock(addr, config, keys, count, metrics, clock::system())
+    }
+
+    pub fn with_clock(
+        addr: &str,
+        config: Arc<SharedConfig>,
+        keys: Arc<JwtKeys>,
+        count: usize,
+        metrics: Arc<Metrics>,
+        clock: Arc<dyn Clock>
+    ) -> Self {
+        let shards = (0..count.max(1))
+            .map(|_| Mutex::new(AuthService::with_clock(addr, config.clone(), keys.clone(), clock.clone())))
+            .collect();
+        AuthShards { shards, keys, metrics }
+    }
+
+    /// Locks the shard holding `user_id`'s revocations. Time spent waiting on the
+    /// lock is recorded in `auth_lock_wait_microseconds_total`.
+    pub fn shard(&self, user_id: u64) -> MutexGuard<'_, AuthService> {
+        self.lock(shard_index(user_id, self.shards.len()))
+    }
+
+    /// Resolves the account that `token_str` was issued to. Its signature is
+    /// verified without a lock, and only the shard of the account it names is
+    /// asked whether it has been revoked.
+    pub async fn authenticate(&self, token_str: &str) -> Result<Option<Identity>, ()> {
+        let Some(claims) = self.keys.verify(token_str).map_err(|_| ())? else {
+            return Ok(None)
+        };
+        let Some(user_id) = claims.user_id() else {
+            return Ok(None)
+        };
+        self.shard(user_id).authenticate(&claims).await
+    }
+
+    fn lock(&self, index: usize) -> MutexGuard<'_, AuthService> {
+        let started = Instant::now();
+        let guard = self.shards[index].lock().unwrap();
+        self.metrics.increment("auth_lock_wait_microseconds_total", started.elapsed().as_micros() as u64);
+        self.metrics.increment("auth_lock_acquisitions_total", 1);
+        guard
+    }
+}
+
+fn shard_index(user_id: u64, count: usize) -> usize {
+    (user_id % count as u64) as usize
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use arc_swap::ArcSwap;
+
+    use crate::auth::jwt::JwtKeys;
+    use crate::config::config::ServerConfig;
+    use crate::metrics::metrics::Metrics;
+    use super::{shard_index, AuthShards};
+
+    #[test]
+    fn test_shard_index() {
+        assert_eq!(0, shard_index(0, 4));
+        assert_eq!(3, shard_index(7, 4));
+        assert_eq!(shard_index(42, 4), shard_index(42, 4));
+        assert_eq!(0, shard_index(u64::MAX, 1));
+    }
+    #[actix_web::test]
+    async fn test_authenticate() {
+        // Nothing listens on port 1, so every shard is offline
+        let config = Arc::new(ArcSwap::from_pointee(ServerConfig::default()));
+        let auth = AuthShards::new("redis://127.0.0.1:1", config, Arc::new(JwtKeys::hs256(b"test")), 4, Arc::new(Metrics::new()));
+        let token = auth.shard(3).generate_user_token(3, "three").await.unwrap().token;
+
+        assert_eq!(Ok(Some(3)), auth.authenticate(&token).await.map(|found| found.map(|identity| identity.user_id)));
+        assert_eq!(Err(()), auth.authenticate("not a token").await);
+
+        // Signed by another key
+        let claims = JwtKeys::hs256(b"test").verify(&token).unwrap().unwrap();
+        let forged = JwtKeys::hs256(b"other").sign(&claims).unwrap();
+        assert_eq!(Ok(None), auth.authenticate(&forged).await);
+    }
+}
\ No newline at end of file
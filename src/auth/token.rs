@@ -0,0 +1,100 @@
+use std::fmt;
+
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+/// SHA-256 of a token. Only the hash of a token is ever stored, so that a leaked
+/// Redis snapshot (or memory of the offline store) cannot be replayed as bearer
+/// tokens. Presented tokens are hashed before being looked up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TokenHash([u8; 32]);
+
+impl TokenHash {
+    pub fn of(token: &Uuid) -> Self {
+        TokenHash(Sha256::digest(token.as_bytes()).into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Parses the lowercase hex a hash is displayed (and stored) as.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != 64 || !hex.is_ascii() {
+            return None
+        }
+        let mut bytes = [0u8; 32];
+        for (byte, pair) in bytes.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+        }
+        Some(TokenHash(bytes))
+    }
+
+    /// Compares in constant time, unlike `==`.
+    pub fn ct_eq(&self, other: &TokenHash) -> bool {
+        self.0.ct_eq(&other.0).into()
+    }
+}
+
+/// Compares `a` and `b` in time that depends only on their lengths, for values
+/// tied to a token (usernames, user ids) where an early exit would leak how
+/// much of a guess was right.
+pub fn ct_eq_bytes(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+pub fn ct_eq_u64(a: u64, b: u64) -> bool {
+    a.ct_eq(&b).into()
+}
+
+/// Lowercase hex
+impl fmt::Display for TokenHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::{ct_eq_bytes, ct_eq_u64, TokenHash};
+
+    #[test]
+    fn test_token_hash() {
+        let token = Uuid::new_v4();
+        assert_eq!(TokenHash::of(&token), TokenHash::of(&token));
+        assert_ne!(TokenHash::of(&token), TokenHash::of(&Uuid::new_v4()));
+
+        let hex = TokenHash::of(&Uuid::nil()).to_string();
+        assert_eq!(64, hex.len());
+        assert!(!hex.contains(&Uuid::nil().simple().to_string()));
+        // sha256 of 16 zero bytes
+        assert_eq!("374708fff7719dd5979ec875d56cd2286f6d3cf7ec317a3b25632aab28ec37bb", hex);
+
+        assert_eq!(Some(TokenHash::of(&token)), TokenHash::from_hex(&TokenHash::of(&token).to_string()));
+        assert_eq!(None, TokenHash::from_hex(&hex[1..]));
+        assert_eq!(None, TokenHash::from_hex(&hex.replace('3', "g")));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        let token = TokenHash::of(&Uuid::new_v4());
+        assert!(token.ct_eq(&token));
+        assert!(!token.ct_eq(&TokenHash::of(&Uuid::new_v4())));
+
+        assert!(ct_eq_bytes(b"alice", b"alice"));
+        assert!(!ct_eq_bytes(b"alice", b"alicf"));
+        assert!(!ct_eq_bytes(b"alice", b"alic"));
+        assert!(!ct_eq_bytes(b"", b"a"));
+        assert!(ct_eq_bytes(b"", b""));
+
+        assert!(ct_eq_u64(42, 42));
+        assert!(!ct_eq_u64(42, 43));
+        assert!(!ct_eq_u64(0, u64::MAX));
+    }
+}
\ No newline at end of file
@@ -50,7 +50,9 @@ pub struct Cache {
 
 impl Cache {
     pub fn new(url: &str) -> Result<Self, ()> {
-        let mut client = redis::Client::open(url).unwrap();
+        let Ok(mut client) = redis::Client::open(url) else {
+            return Err(())
+        };
         match client.check_connection() {
             true  => Ok(Cache { client: client }),
             false => Err(())
@@ -68,6 +70,18 @@ impl Cache {
         }
     }
 
+    /// Gets and deletes `key` at once, so that only one caller can get its value.
+    pub async fn take(&self, key: &str) -> Result<String, CacheErr> {
+        let mut conn = match self.get_async_conn().await {
+            Ok(conn) => conn,
+            Err(_) => return Err(CacheErr::AsyncConnFailure),
+        };
+        match conn.get_del(key).await {
+            Ok(value) => Ok(value),
+            Err(re) => Err(CacheErr::from(re))
+        }
+    }
+
     /// Set a single user token. Overwrites.
     /// * `key` - user id
     /// * `value` - uuid
@@ -82,6 +96,39 @@ impl Cache {
         }
     }
 
+    /// Sets `key` only if it does not already exist, returning whether it was set.
+    pub async fn set_key_nx(&self, key: &str, value: &str, expiry_sec: u64) -> Result<bool, ()> {
+        let mut conn = self.get_async_conn().await?;
+        let result = redis::cmd("SET").arg(key).arg(value).arg("EX").arg(expiry_sec).arg("NX")
+            .query_async::<MultiplexedConnection, Option<String>>(&mut conn)
+            .await;
+        match result {
+            Ok(set) => Ok(set.is_some()),
+            Err(re) => {
+                warn!("{}", re);
+                Err(())
+            }
+        }
+    }
+
+    /// Increments the counter at `key`, kept for `expiry_sec` from now, returning its
+    /// new value.
+    pub async fn incr(&self, key: &str, expiry_sec: u64) -> Result<u64, ()> {
+        let mut conn = self.get_async_conn().await?;
+        let result = redis::pipe().atomic()
+            .cmd("INCR").arg(key)
+            .cmd("EXPIRE").arg(key).arg(expiry_sec).ignore()
+            .query_async::<MultiplexedConnection, (u64,)>(&mut conn)
+            .await;
+        match result {
+            Ok((count,)) => Ok(count),
+            Err(re) => {
+                warn!("{}", re);
+                Err(())
+            }
+        }
+    }
+
     /// Set an entry in the Redis DB.
     /// * `symmetric` - if true, makes two entries using the provided
     ///                 `entry`, where the extra has the key-value swapped.
@@ -130,7 +177,21 @@ impl Cache {
         Ok(())
     }
 
-    pub async fn _clear_key(&self, key: &str) -> Result<(), ()> {
+    /// Sets the time to live of an existing `key`.
+    pub async fn set_expiry(&self, key: &str, expiry_sec: u64) -> Result<(), ()> {
+        let mut conn = self.get_async_conn().await?;
+
+        match conn.expire::<&str, bool>(key, expiry_sec as i64).await {
+            Ok(true)  => Ok(()),
+            Ok(false) => Err(()),
+            Err(re) => {
+                warn!("{}", re);
+                Err(())
+            }
+        }
+    }
+
+    pub async fn clear_key(&self, key: &str) -> Result<(), ()> {
         let mut conn = self.get_async_conn().await?;
 
         match conn.del::<&str, u32>(key).await {
@@ -147,12 +208,14 @@ impl Cache {
         let mut conn = self.get_async_conn().await?;
         
         match conn.get::<&u64, String>(&key).await {
-            Ok(uuid) => Ok(Uuid::parse_str(&uuid).unwrap()),
+            Ok(uuid) => Uuid::parse_str(&uuid).map_err(|_| ()),
             Err(_) => Err(())
         }
     }
 
     async fn get_async_conn(&self) -> Result<MultiplexedConnection, ()> {
+        #[cfg(feature = "chaos")]
+        crate::chaos::CACHE.inject().await.map_err(|_| ())?;
         match self.client.get_multiplexed_async_connection().await {
             Ok(conn) => Ok(conn),
             Err(_) => Err(())
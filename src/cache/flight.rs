@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+use futures_util::lock::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Coalesces concurrent loads of the same key, so that when a cached value
+/// expires under load only one request goes to the database for it while the
+/// rest wait and read what it cached.
+pub struct SingleFlight<K> {
+    flights: Mutex<HashMap<K, Arc<AsyncMutex<()>>>>
+}
+
+/// Held while loading a key. Other loads of the key wait until it is dropped.
+pub struct Flight<'a, K: Eq + Hash> {
+    flights: &'a SingleFlight<K>,
+    key: K,
+    lock: Arc<AsyncMutex<()>>,
+    _guard: OwnedMutexGuard<()>
+}
+
+impl<K: Eq + Hash + Clone> SingleFlight<K> {
+    pub fn new() -> Self {
+        SingleFlight { flights: Mutex::new(HashMap::new()) }
+    }
+
+    /// Waits for any load of `key` already in flight to finish, then starts one.
+    /// The cache should be checked again once this returns, as the waited on
+    /// load will usually have filled it.
+    pub async fn begin(&self, key: &K) -> Flight<'_, K> {
+        let lock = self.flights.lock().unwrap().entry(key.clone()).or_default().clone();
+        let guard = lock.clone().lock_owned().await;
+        Flight { flights: self, key: key.clone(), lock, _guard: guard }
+    }
+}
+
+impl<K: Eq + Hash + Clone> Default for SingleFlight<K> {
+    fn default() -> Self {
+        SingleFlight::new()
+    }
+}
+
+impl<K: Eq + Hash> Drop for Flight<'_, K> {
+    fn drop(&mut self) {
+        let mut flights = self.flights.flights.lock().unwrap();
+        // Held by the map, this flight and its guard. Any more are waiting to load
+        // the key, and the last of them removes it.
+        if Arc::strong_count(&self.lock) == 3 && flights.get(&self.key).is_some_and(|lock| Arc::ptr_eq(lock, &self.lock)) {
+            flights.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use actix_web::rt;
+    use futures_util::future;
+
+    use super::SingleFlight;
+
+    #[actix_web::test]
+    async fn test_begin() {
+        let flights = SingleFlight::new();
+        let order = std::sync::Mutex::new(Vec::new());
+        let load = |n: u32| {
+            let (flights, order) = (&flights, &order);
+            async move {
+                let _flight = flights.begin(&1).await;
+                order.lock().unwrap().push(n);
+                rt::time::sleep(Duration::from_millis(10)).await;
+                order.lock().unwrap().push(n);
+            }
+        };
+        future::join3(load(1), load(2), load(3)).await;
+
+        // Never interleaved
+        assert_eq!(vec![1, 1, 2, 2, 3, 3], *order.lock().unwrap());
+        assert!(flights.flights.lock().unwrap().is_empty());
+
+        // Other keys are not held up
+        let _flight = flights.begin(&1).await;
+        let _other = flights.begin(&2).await;
+    }
+}
\ No newline at end of file
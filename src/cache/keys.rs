@@ -0,0 +1,129 @@
+use std::fmt;
+
+use chrono::NaiveDate;
+
+use crate::auth::token::TokenHash;
+
+// Every key is prefixed with its namespace, so client-chosen strings (nonces,
+// content) can never be mistaken for a key of another kind, e.g. a nonce that
+// looks like a token.
+
+/// The revocation generation of an account, see `auth::jwt::Claims`.
+pub struct RevocationKey(pub u64);
+
+/// Maps a sudo token (by hash) to the account it was issued to.
+pub struct SudoKey<'a>(pub &'a TokenHash);
+
+/// Maps a refresh token (by hash) to the account and generation it was issued at.
+pub struct RefreshKey<'a>(pub &'a TokenHash);
+
+/// A cached public profile.
+pub struct ProfileKey(pub u64);
+
+/// Maps a username to its account, or to none.
+pub struct AccountIdKey<'a>(pub &'a str);
+
+/// A translated post body, by post, the unix time the post was last edited, and
+/// language.
+pub struct TranslationKey<'a>(pub u64, pub i64, pub &'a str);
+
+/// Maps an API key (by hash) to its id and daily quota.
+pub struct ApiKeyKey<'a>(pub &'a TokenHash);
+
+/// The requests made with an API key on a UTC day.
+pub struct QuotaKey(pub u64, pub NaiveDate);
+
+/// An unsolved registration challenge, by its nonce.
+pub struct ChallengeKey<'a>(pub &'a str);
+
+/// A recent post of an account, by the fingerprint of its content.
+pub struct SubmissionKey<'a>(pub u64, pub &'a str);
+
+impl fmt::Display for RevocationKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "revoked:{}", self.0)
+    }
+}
+
+impl fmt::Display for SudoKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sudo:{}", self.0)
+    }
+}
+
+impl fmt::Display for RefreshKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "refresh:{}", self.0)
+    }
+}
+
+impl fmt::Display for ProfileKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "profile:{}", self.0)
+    }
+}
+
+impl fmt::Display for AccountIdKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "account_id:{}", self.0)
+    }
+}
+
+impl fmt::Display for TranslationKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "translation:{}:{}:{}", self.0, self.1, self.2)
+    }
+}
+
+impl fmt::Display for ApiKeyKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "apikey:{}", self.0)
+    }
+}
+
+impl fmt::Display for QuotaKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "quota:{}:{}", self.0, self.1)
+    }
+}
+
+impl fmt::Display for ChallengeKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "challenge:{}", self.0)
+    }
+}
+
+impl fmt::Display for SubmissionKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "submission:{}:{}", self.0, self.1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use uuid::Uuid;
+
+    use crate::auth::token::TokenHash;
+    use super::{AccountIdKey, ApiKeyKey, ChallengeKey, ProfileKey, QuotaKey, RefreshKey, RevocationKey, SubmissionKey, SudoKey, TranslationKey};
+
+    #[test]
+    fn test_keys_do_not_collide() {
+        let token = TokenHash::of(&Uuid::new_v4());
+        let lookalike = token.to_string();
+        assert_ne!(SudoKey(&token).to_string(), ChallengeKey(&lookalike).to_string());
+        assert_ne!(SudoKey(&token).to_string(), RefreshKey(&token).to_string());
+        assert_ne!(SudoKey(&token).to_string(), ApiKeyKey(&token).to_string());
+        assert_ne!(ProfileKey(7).to_string(), RevocationKey(7).to_string());
+        assert_ne!(RevocationKey(7).to_string(), ChallengeKey("7").to_string());
+        assert_ne!(SubmissionKey(7, "alice").to_string(), ChallengeKey("7:alice").to_string());
+        assert_ne!(AccountIdKey("7").to_string(), ProfileKey(7).to_string());
+
+        assert_eq!(format!("sudo:{}", token), SudoKey(&token).to_string());
+        assert_eq!("revoked:7", RevocationKey(7).to_string());
+        assert_eq!("profile:7", ProfileKey(7).to_string());
+        assert_eq!("account_id:alice", AccountIdKey("alice").to_string());
+        assert_eq!("translation:7:1700000000:de", TranslationKey(7, 1700000000, "de").to_string());
+        assert_eq!("quota:7:2024-03-01", QuotaKey(7, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()).to_string());
+    }
+}
\ No newline at end of file
@@ -1,2 +1,8 @@
 pub mod cache;
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod flight;
+pub mod keys;
+pub mod profile;
+pub mod submissions;
+pub mod translations;
+pub mod ttl;
\ No newline at end of file
@@ -0,0 +1,183 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use arc_swap::ArcSwapOption;
+use log::{info, warn};
+
+use crate::auth::auth::try_connect;
+use crate::models::PublicProfile;
+use super::cache::Cache;
+use super::error::CacheErr;
+use super::flight::{Flight, SingleFlight};
+use super::keys::{AccountIdKey, ProfileKey};
+
+const PROFILE_TTL_SEC: u64 = 60;
+/// Seconds the account of a username is cached for, including that there is none.
+/// Kept short, as registering the username only clears its exact spelling.
+const ACCOUNT_ID_TTL_SEC: u64 = 30;
+/// Value cached for a username without an account. Account ids are never 0.
+const NO_ACCOUNT: &str = "0";
+/// Lookups made without a connection between attempts to reconnect.
+const RECONNECT_FREQUENCY: u64 = 16;
+
+/// Short lived cache of public profiles, and of the account of each username, in
+/// Redis. Only ever public data is cached, never password hashes. Without a Redis
+/// connection nothing is cached and every lookup falls through to the database,
+/// while every `RECONNECT_FREQUENCY`-th lookup tries to reconnect.
+pub struct ProfileCache {
+    addr: String,
+    cache: ArcSwapOption<Cache>,
+    misses: AtomicU64,
+    loads: SingleFlight<u64>
+}
+
+impl ProfileCache {
+    pub fn new(addr: &str) -> Self {
+        ProfileCache {
+            addr: addr.to_string(),
+            cache: ArcSwapOption::from(try_connect(addr).ok().map(Arc::new)),
+            misses: AtomicU64::new(0),
+            loads: SingleFlight::new()
+        }
+    }
+
+    /// Waits for any load of the profile of `account_id` by another request, and
+    /// holds off others until the returned flight is dropped. Loads are not held
+    /// off when there is no cache for them to be read from.
+    pub async fn begin_load(&self, account_id: u64) -> Option<Flight<'_, u64>> {
+        self.cache.load().as_ref()?;
+        Some(self.loads.begin(&account_id).await)
+    }
+
+    pub async fn get(&self, account_id: u64) -> Option<PublicProfile> {
+        let value = self.get_key(&ProfileKey(account_id).to_string()).await?;
+        serde_json::from_str(&value).ok()
+    }
+
+    pub async fn set(&self, profile: &PublicProfile) {
+        let value = match serde_json::to_string(profile) {
+            Ok(value) => value,
+            Err(e) => {
+                warn!("ProfileCache: failed to serialise profile {}: {}", profile.account.id, e);
+                return
+            }
+        };
+        self.set_key(&ProfileKey(profile.account.id).to_string(), &value, PROFILE_TTL_SEC).await;
+    }
+
+    /// Drops the cached profile of `account_id`. Must be called whenever data in
+    /// the profile changes.
+    pub async fn invalidate(&self, account_id: u64) {
+        if let Some(cache) = self.connection() {
+            // Err when nothing was cached, which is fine
+            let _ = cache.clear_key(&ProfileKey(account_id).to_string()).await;
+        }
+    }
+
+    /// The cached account of `username`: `Some(None)` when it is known to have none,
+    /// `None` when not cached.
+    pub async fn get_account_id(&self, username: &str) -> Option<Option<u64>> {
+        let value = self.get_key(&AccountIdKey(username).to_string()).await?;
+        match value.parse::<u64>() {
+            Ok(0) => Some(None),
+            Ok(account_id) => Some(Some(account_id)),
+            Err(_) => None
+        }
+    }
+
+    pub async fn set_account_id(&self, username: &str, account_id: Option<u64>) {
+        let value = account_id.map(|id| id.to_string());
+        let value = value.as_deref().unwrap_or(NO_ACCOUNT);
+        self.set_key(&AccountIdKey(username).to_string(), value, ACCOUNT_ID_TTL_SEC).await;
+    }
+
+    /// Drops the cached account of `username`. Must be called when the username is
+    /// registered, as it may be cached as having none.
+    pub async fn invalidate_username(&self, username: &str) {
+        if let Some(cache) = self.connection() {
+            let _ = cache.clear_key(&AccountIdKey(username).to_string()).await;
+        }
+    }
+
+    async fn get_key(&self, key: &str) -> Option<String> {
+        let cache = self.connection()?;
+        match cache.get(key).await {
+            Ok(value) => Some(value),
+            Err(CacheErr::AsyncConnFailure | CacheErr::ConnectionLost) => {
+                self.go_offline();
+                None
+            },
+            Err(_) => None
+        }
+    }
+
+    async fn set_key(&self, key: &str, value: &str, expiry_sec: u64) {
+        if let Some(cache) = self.connection() {
+            if cache.set_key(key, value, expiry_sec).await.is_err() {
+                self.go_offline();
+            }
+        }
+    }
+
+    /// The Redis connection, if online. While offline, every `RECONNECT_FREQUENCY`-th
+    /// call tries to reconnect first.
+    fn connection(&self) -> Option<Arc<Cache>> {
+        if let Some(cache) = self.cache.load_full() {
+            return Some(cache)
+        }
+        let misses = self.misses.fetch_add(1, Ordering::Relaxed) + 1;
+        if !misses.is_multiple_of(RECONNECT_FREQUENCY) {
+            return None
+        }
+        info!("ProfileCache: Offline & re-connect frequency met. Misses: {}", misses);
+
+        match try_connect(&self.addr) {
+            Ok(cache) => {
+                let cache = Arc::new(cache);
+                self.cache.store(Some(cache.clone()));
+                self.misses.store(0, Ordering::Relaxed);
+                info!("ProfileCache: re-connected to Redis server");
+                Some(cache)
+            },
+            Err(()) => {
+                info!("ProfileCache: failed to re-connect to '{}'", self.addr);
+                None
+            }
+        }
+    }
+
+    /// Stops using Redis after it failed to answer, until reconnected.
+    fn go_offline(&self) {
+        if self.cache.swap(None).is_some() {
+            warn!("ProfileCache: Redis failed to answer, caching nothing until re-connected");
+            self.misses.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ProfileCache, RECONNECT_FREQUENCY};
+
+    #[actix_web::test]
+    async fn test_reconnect() {
+        dotenv::dotenv().ok();
+        let cache_url = std::env::var("REDIS_DATABASE_URL").expect("REDIS_DATABASE_URL is not set");
+
+        // Starts offline, and stays so until the frequency is met
+        let mut profiles = ProfileCache::new("redis://127.0.0.1:1");
+        profiles.addr = cache_url;
+        for _ in 1..RECONNECT_FREQUENCY {
+            assert_eq!(None, profiles.get_account_id("!test_reconnect").await);
+        }
+        assert!(profiles.cache.load().is_none());
+
+        profiles.set_account_id("!test_reconnect", None).await;
+        assert!(profiles.cache.load().is_some());
+        assert_eq!(Some(None), profiles.get_account_id("!test_reconnect").await);
+        profiles.set_account_id("!test_reconnect", Some(101)).await;
+        assert_eq!(Some(Some(101)), profiles.get_account_id("!test_reconnect").await);
+        profiles.invalidate_username("!test_reconnect").await;
+        assert_eq!(None, profiles.get_account_id("!test_reconnect").await);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,96 @@
+use sha2::{Digest, Sha256};
+
+use super::cache::Cache;
+use super::error::CacheErr;
+use super::keys::SubmissionKey;
+
+/// Value of a submission claimed before its post is created.
+const PENDING: &str = "pending";
+
+/// The recent posts of each account in Redis, by the fingerprint of their content,
+/// so that a post submitted twice (e.g. by a client retrying a slow request) is
+/// only created once. Without a Redis connection posts are never found to repeat.
+pub struct RecentSubmissions {
+    cache: Option<Cache>
+}
+
+/// A submission already made within the window.
+#[derive(Debug, PartialEq)]
+pub enum Repeat {
+    /// Of the post with this id
+    Of(u64),
+    /// Of a post still being created
+    Pending
+}
+
+impl RecentSubmissions {
+    pub fn new(cache: Option<Cache>) -> Self {
+        RecentSubmissions { cache }
+    }
+
+    /// Claims the content of a post by `poster_id` for `window_sec`, unless it was
+    /// already submitted within the window. The claim must be `record`ed once the
+    /// post is created, or `release`d if it is not.
+    pub async fn claim(&self, poster_id: u64, content: &[&str], window_sec: u64) -> Result<(), Repeat> {
+        let Some(cache) = self.cache.as_ref().filter(|_| window_sec > 0) else {
+            return Ok(())
+        };
+        let key = SubmissionKey(poster_id, &fingerprint(content)).to_string();
+        match cache.set_key_nx(&key, PENDING, window_sec).await {
+            Ok(true) | Err(()) => Ok(()),
+            Ok(false) => match cache.get(&key).await {
+                Ok(value) => Err(value.parse::<u64>().map(Repeat::Of).unwrap_or(Repeat::Pending)),
+                // Expired in between
+                Err(CacheErr::NilResponse) => Ok(()),
+                Err(_) => Err(Repeat::Pending)
+            }
+        }
+    }
+
+    /// Records that the claimed content was created as `post_id`.
+    pub async fn record(&self, poster_id: u64, content: &[&str], post_id: u64, window_sec: u64) {
+        if let Some(cache) = self.cache.as_ref().filter(|_| window_sec > 0) {
+            let key = SubmissionKey(poster_id, &fingerprint(content)).to_string();
+            let _ = cache.set_key(&key, &post_id.to_string(), window_sec).await;
+        }
+    }
+
+    /// Releases a claim on content that was not created.
+    pub async fn release(&self, poster_id: u64, content: &[&str]) {
+        if let Some(cache) = &self.cache {
+            let _ = cache.clear_key(&SubmissionKey(poster_id, &fingerprint(content)).to_string()).await;
+        }
+    }
+}
+
+/// Lowercase hex of the SHA-256 of `content`, with each part length-prefixed so
+/// that moving text between parts changes the fingerprint.
+fn fingerprint(content: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in content {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fingerprint, RecentSubmissions};
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(fingerprint(&["title", "body"]), fingerprint(&["title", "body"]));
+        assert_ne!(fingerprint(&["title", "body"]), fingerprint(&["title", "other body"]));
+        assert_ne!(fingerprint(&["title ", "body"]), fingerprint(&["title", " body"]));
+        assert_eq!(64, fingerprint(&["title", "body"]).len());
+    }
+
+    #[actix_web::test]
+    async fn test_without_cache() {
+        let recent = RecentSubmissions::new(None);
+        assert_eq!(Ok(()), recent.claim(1, &["title", "body"], 30).await);
+        recent.record(1, &["title", "body"], 2, 30).await;
+        assert_eq!(Ok(()), recent.claim(1, &["title", "body"], 30).await);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,29 @@
+use super::cache::Cache;
+use super::keys::TranslationKey;
+
+/// Seconds a translation is cached for. Translations are keyed by the time the
+/// post was last edited, so edits are never served stale.
+const TRANSLATION_TTL_SEC: u64 = 60 * 60 * 24;
+
+/// Translated post bodies in Redis, so each is only sent to the translator once.
+/// Without a Redis connection nothing is cached and every post is translated.
+pub struct TranslationCache {
+    cache: Option<Cache>
+}
+
+impl TranslationCache {
+    pub fn new(cache: Option<Cache>) -> Self {
+        TranslationCache { cache }
+    }
+
+    pub async fn get(&self, key: &TranslationKey<'_>) -> Option<String> {
+        let cache = self.cache.as_ref()?;
+        cache.get(&key.to_string()).await.ok()
+    }
+
+    pub async fn set(&self, key: &TranslationKey<'_>, body: &str) {
+        if let Some(cache) = &self.cache {
+            let _ = cache.set_key(&key.to_string(), body, TRANSLATION_TTL_SEC).await;
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::clock::{self, Clock};
+use super::flight::SingleFlight;
+
+/// A small in-process cache, for values that are costly to compute and fine to
+/// serve slightly stale. Entries are dropped once they are older than `ttl`.
+pub struct TtlCache<K, V> {
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+    flights: SingleFlight<K>
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> TtlCache<K, V> {
+    pub fn new(ttl: Duration) -> Self {
+        TtlCache::with_clock(ttl, clock::system())
+    }
+
+    pub fn with_clock(ttl: Duration, clock: Arc<dyn Clock>) -> Self {
+        TtlCache { entries: Mutex::new(HashMap::new()), ttl, clock, flights: SingleFlight::new() }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let now = self.clock.instant();
+        match self.entries.lock().unwrap().get(key) {
+            Some((inserted, value)) if now.saturating_duration_since(*inserted) < self.ttl => Some(value.clone()),
+            _ => None
+        }
+    }
+
+    /// The value of `key`, running `load` and caching its value on a miss. Misses
+    /// of the same key at once are coalesced, so that only one of them runs its
+    /// `load` while the rest are given its value. `load` is only awaited if run.
+    pub async fn get_or_load<E>(&self, key: K, load: impl Future<Output = Result<V, E>>) -> Result<V, E> {
+        if let Some(value) = self.get(&key) {
+            return Ok(value)
+        }
+        let _flight = self.flights.begin(&key).await;
+        if let Some(value) = self.get(&key) {
+            return Ok(value)
+        }
+        let value = load.await?;
+        self.insert(key, value.clone());
+        Ok(value)
+    }
+
+    /// Inserts or overwrites `key`, clearing out any expired entries.
+    pub fn insert(&self, key: K, value: V) {
+        let now = self.clock.instant();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (inserted, _)| now.saturating_duration_since(*inserted) < self.ttl);
+        entries.insert(key, (now, value));
+    }
+
+    /// Drops every entry that `matches`, e.g. all entries for a user.
+    pub fn remove_where(&self, matches: impl Fn(&K, &V) -> bool) {
+        self.entries.lock().unwrap().retain(|key, (_, value)| !matches(key, value));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    use chrono::Utc;
+    use futures_util::future;
+
+    use crate::clock::ManualClock;
+    use super::TtlCache;
+
+    #[test]
+    fn test_get_insert() {
+        let cache = TtlCache::new(Duration::from_secs(60));
+        assert_eq!(None, cache.get(&1));
+        cache.insert(1, "one");
+        assert_eq!(Some("one"), cache.get(&1));
+        cache.insert(1, "uno");
+        assert_eq!(Some("uno"), cache.get(&1));
+
+        cache.insert(2, "two");
+        cache.remove_where(|_, value| *value == "uno");
+        assert_eq!(None, cache.get(&1));
+        assert_eq!(Some("two"), cache.get(&2));
+
+        let expired = TtlCache::new(Duration::ZERO);
+        expired.insert(1, "one");
+        assert_eq!(None, expired.get(&1));
+    }
+
+    #[actix_web::test]
+    async fn test_get_or_load() {
+        let cache = TtlCache::new(Duration::from_secs(60));
+        let loads = AtomicU32::new(0);
+        let load = |value: u32| {
+            let loads = &loads;
+            async move {
+                loads.fetch_add(1, Ordering::Relaxed);
+                actix_web::rt::time::sleep(Duration::from_millis(10)).await;
+                Ok::<_, ()>(value)
+            }
+        };
+
+        let (a, b) = future::join(cache.get_or_load(1, load(1)), cache.get_or_load(1, load(2))).await;
+        assert_eq!((Ok(1), Ok(1)), (a, b));
+        assert_eq!(1, loads.load(Ordering::Relaxed));
+
+        // Failed loads are not cached
+        assert_eq!(Err("failed"), cache.get_or_load(2, async { Err("failed") }).await);
+        assert_eq!(Ok(2), cache.get_or_load(2, async { Ok::<_, ()>(2) }).await);
+    }
+
+    #[test]
+    fn test_expiry() {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let cache = TtlCache::with_clock(Duration::from_secs(5), clock.clone());
+        cache.insert(1, "one");
+        clock.advance(Duration::from_secs(4));
+        assert_eq!(Some("one"), cache.get(&1));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(None, cache.get(&1));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,168 @@
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Faults injected into every database query and transaction.
+pub static DATABASE: Faults = Faults::new();
+/// Faults injected into every cache (Redis) command.
+pub static CACHE: Faults = Faults::new();
+
+static SCENARIO_LOCK: Mutex<()> = Mutex::new(());
+
+/// The error an injected fault surfaces as.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fault;
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "injected fault")
+    }
+}
+
+impl std::error::Error for Fault {}
+
+struct Plan {
+    rng: u64,
+    error_rate: f64,
+    latency: Duration,
+    injected: u64
+}
+
+/// Errors and latency injected into calls to a dependency. Whether each call fails
+/// is drawn from a generator seeded by `configure`, so the same seed fails the same
+/// calls every run. Nothing is injected until configured.
+pub struct Faults {
+    plan: Mutex<Option<Plan>>
+}
+
+impl Faults {
+    const fn new() -> Faults {
+        Faults { plan: Mutex::new(None) }
+    }
+
+    /// Fails `error_rate` (0 to 1) of calls from now on, delaying every call by `latency`.
+    pub fn configure(&self, seed: u64, error_rate: f64, latency: Duration) {
+        *lock(&self.plan) = Some(Plan {
+            // xorshift gets stuck on zero
+            rng: seed.max(1),
+            error_rate: error_rate.clamp(0.0, 1.0),
+            latency,
+            injected: 0
+        });
+    }
+
+    /// The number of errors injected since last configured, so that a test can tell
+    /// an injected fault from a real one.
+    pub fn injected(&self) -> u64 {
+        lock(&self.plan).as_ref().map_or(0, |plan| plan.injected)
+    }
+
+    /// Stops injecting faults.
+    pub fn reset(&self) {
+        *lock(&self.plan) = None;
+    }
+
+    /// Called before each call to the dependency, which should fail with the
+    /// returned fault, if any, instead of being made.
+    pub async fn inject(&self) -> Result<(), Fault> {
+        let Some((fail, latency)) = self.roll() else {
+            return Ok(())
+        };
+        if !latency.is_zero() {
+            actix_web::rt::time::sleep(latency).await;
+        }
+        if fail { Err(Fault) } else { Ok(()) }
+    }
+
+    fn roll(&self) -> Option<(bool, Duration)> {
+        let mut plan = lock(&self.plan);
+        let plan = plan.as_mut()?;
+        plan.rng ^= plan.rng << 13;
+        plan.rng ^= plan.rng >> 7;
+        plan.rng ^= plan.rng << 17;
+        // The top 53 bits as a uniform float in [0, 1)
+        let draw = (plan.rng >> 11) as f64 / (1u64 << 53) as f64;
+        let fail = draw < plan.error_rate;
+        plan.injected += fail as u64;
+        Some((fail, plan.latency))
+    }
+}
+
+/// Exclusive use of `DATABASE` and `CACHE` for a test, as tests run concurrently.
+/// Both stop injecting faults when it is dropped.
+pub struct Scenario {
+    _guard: MutexGuard<'static, ()>
+}
+
+/// Waits for any other test's scenario to end, then starts one without faults.
+pub fn scenario() -> Scenario {
+    let guard = lock(&SCENARIO_LOCK);
+    DATABASE.reset();
+    CACHE.reset();
+    Scenario { _guard: guard }
+}
+
+impl Drop for Scenario {
+    fn drop(&mut self) {
+        DATABASE.reset();
+        CACHE.reset();
+    }
+}
+
+/// A test that panicked while holding a lock leaves nothing half-written behind.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn outcomes(faults: &Faults, n: usize) -> Vec<bool> {
+        (0..n).map(|_| faults.roll().is_some_and(|(fail, _)| fail)).collect()
+    }
+
+    #[test]
+    fn test_unconfigured() {
+        let faults = Faults::new();
+        assert!(faults.roll().is_none());
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let faults = Faults::new();
+        faults.configure(42, 0.5, Duration::ZERO);
+        let first = outcomes(&faults, 100);
+        faults.configure(42, 0.5, Duration::ZERO);
+        assert_eq!(first, outcomes(&faults, 100));
+
+        let failures = first.iter().filter(|fail| **fail).count();
+        assert!(failures > 25 && failures < 75, "{} of 100 failed", failures);
+    }
+
+    #[test]
+    fn test_rates() {
+        let faults = Faults::new();
+        faults.configure(0, 1.0, Duration::ZERO);
+        assert!(outcomes(&faults, 100).iter().all(|fail| *fail));
+        faults.configure(7, 0.0, Duration::ZERO);
+        assert!(outcomes(&faults, 100).iter().all(|fail| !*fail));
+        faults.reset();
+        assert!(faults.roll().is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_inject() {
+        let faults = Faults::new();
+        assert_eq!(faults.inject().await, Ok(()));
+        faults.configure(1, 1.0, Duration::from_millis(10));
+        let start = std::time::Instant::now();
+        assert_eq!(faults.inject().await, Err(Fault));
+        assert!(start.elapsed() >= Duration::from_millis(10));
+        assert_eq!(1, faults.injected());
+
+        faults.configure(1, 0.0, Duration::ZERO);
+        assert_eq!(faults.inject().await, Ok(()));
+        assert_eq!(0, faults.injected());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,98 @@
+use serde::Serialize;
+
+use crate::auth::auth::try_connect;
+use crate::auth::jwt::JwtKeys;
+use crate::config::config::ServerConfig;
+use crate::database::{database::Database, migrations::pending_migrations};
+
+#[derive(Debug, Serialize)]
+pub struct CheckResult {
+    pub ok: bool,
+    pub detail: String
+}
+
+impl CheckResult {
+    fn pass(detail: &str) -> Self {
+        CheckResult { ok: true, detail: detail.to_string() }
+    }
+
+    fn fail(detail: String) -> Self {
+        CheckResult { ok: false, detail }
+    }
+}
+
+/// Outcome of the `--check` startup mode. Checks that depend on an earlier
+/// failed check (e.g. migrations on MySQL) are `None`.
+#[derive(Debug, Serialize)]
+pub struct CheckReport {
+    pub ok: bool,
+    pub config: CheckResult,
+    pub mysql: Option<CheckResult>,
+    pub redis: Option<CheckResult>,
+    pub migrations: Option<CheckResult>
+}
+
+const REQUIRED_VARS: [&str; 2] = ["DATABASE_URL", "REDIS_DATABASE_URL"];
+const U64_VARS: [&str; 1] = ["INTEGRITY_JOB_INTERVAL_SEC"];
+
+/// Validates the environment and config file, connects to MySQL and Redis, and verifies that
+/// all embedded migrations are applied.
+pub async fn run() -> CheckReport {
+    let config = check_config();
+    if !config.ok {
+        return CheckReport { ok: false, config, mysql: None, redis: None, migrations: None };
+    }
+
+    let db_url = std::env::var("DATABASE_URL").unwrap_or_default();
+    let redis_url = std::env::var("REDIS_DATABASE_URL").unwrap_or_default();
+
+    // One connection is enough to check the schema
+    let (mysql, migrations) = match Database::try_new(&db_url, 1).await {
+        Ok(db) => (CheckResult::pass("connected"), Some(check_migrations(&db).await)),
+        Err(e) => (CheckResult::fail(e.to_string()), None)
+    };
+
+    let redis = match try_connect(&redis_url) {
+        Ok(_) => CheckResult::pass("connected"),
+        Err(_) => CheckResult::fail("failed to connect".to_string())
+    };
+
+    let ok = mysql.ok && redis.ok && migrations.as_ref().is_some_and(|m| m.ok);
+    CheckReport { ok, config, mysql: Some(mysql), redis: Some(redis), migrations }
+}
+
+fn check_config() -> CheckResult {
+    let mut problems: Vec<String> = REQUIRED_VARS.iter()
+        .filter(|var| std::env::var(var).is_err())
+        .map(|var| format!("{} is not set", var))
+        .collect();
+    problems.extend(U64_VARS.iter()
+        .filter(|var| std::env::var(var).is_ok_and(|value| value.parse::<u64>().is_err()))
+        .map(|var| format!("{} is not a valid u64", var)));
+    if let Err(e) = ServerConfig::load() {
+        problems.push(format!("config file: {}", e));
+    }
+    if let Err(e) = JwtKeys::from_env() {
+        problems.push(format!("JWT keys: {}", e));
+    }
+
+    match problems.is_empty() {
+        true  => CheckResult::pass("valid"),
+        false => CheckResult::fail(problems.join(", "))
+    }
+}
+
+async fn check_migrations(db: &Database) -> CheckResult {
+    let applied = match db.read_applied_migrations().await {
+        Ok(applied) => applied,
+        Err(e) => return CheckResult::fail(e.to_string())
+    };
+    let pending: Vec<String> = pending_migrations(&applied).iter()
+        .map(|m| format!("{}_{}", m.version, m.description))
+        .collect();
+
+    match pending.is_empty() {
+        true  => CheckResult::pass("all applied"),
+        false => CheckResult::fail(format!("pending: {}", pending.join(", ")))
+    }
+}
\ No newline at end of file
@@ -0,0 +1,87 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+/// The source of the current time for token expiry, in-process TTLs and rate
+/// limiting, so that tests can control it rather than sleep.
+pub trait Clock: Send + Sync {
+    /// The wall clock time, for anything stored or compared against stored times.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// A monotonic reading, for measuring time elapsed within the process.
+    fn instant(&self) -> Instant;
+}
+
+/// The real time.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// The clock used unless one is given.
+pub fn system() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// A clock that stands still until advanced, for tests.
+pub struct ManualClock {
+    start: DateTime<Utc>,
+    origin: Instant,
+    elapsed: Mutex<Duration>
+}
+
+impl ManualClock {
+    /// A clock reading `start` until it is advanced.
+    pub fn new(start: DateTime<Utc>) -> ManualClock {
+        ManualClock { start, origin: Instant::now(), elapsed: Mutex::new(Duration::ZERO) }
+    }
+
+    /// Moves both readings forward `by`.
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap() += by;
+    }
+
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.start + chrono::Duration::from_std(self.elapsed()).expect("ManualClock advanced too far")
+    }
+
+    fn instant(&self) -> Instant {
+        self.origin + self.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::{Clock, ManualClock};
+
+    #[test]
+    fn test_manual_clock() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let clock = ManualClock::new(start);
+        let instant = clock.instant();
+        assert_eq!(start, clock.now());
+        assert_eq!(instant, clock.instant());
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(Utc.with_ymd_and_hms(2024, 1, 1, 0, 1, 30).unwrap(), clock.now());
+        assert_eq!(Duration::from_secs(90), clock.instant() - instant);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,672 @@
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use actix_web::web::Data;
+use arc_swap::ArcSwap;
+use chrono::NaiveDate;
+use log::{info, warn, LevelFilter};
+use serde::Deserialize;
+
+use crate::translate;
+use super::error::ConfigError;
+
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+/// Characters of `login_signals.salt`, so that it can't be guessed to reverse the hashes.
+const MIN_SIGNAL_SALT_LENGTH: usize = 16;
+
+/// How log lines are written.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Pretty,
+    Json
+}
+
+/// Lifetime of the tokens issued on login. The expiry is signed into each token,
+/// so a change only applies to tokens issued after it.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TokenConfig {
+    /// Seconds a token is valid for after being issued.
+    pub ttl_sec: u64,
+    /// Seconds a sudo token, issued by re-entering the password, is valid for.
+    pub sudo_ttl_sec: u64,
+    /// Seconds a refresh token, issued with every token, can be exchanged for a
+    /// new token.
+    pub refresh_ttl_sec: u64
+}
+
+impl Default for TokenConfig {
+    fn default() -> Self {
+        TokenConfig {
+            ttl_sec: 60 * 60 * 12,
+            sudo_ttl_sec: 60 * 5,
+            refresh_ttl_sec: 60 * 60 * 24 * 30
+        }
+    }
+}
+
+impl TokenConfig {
+    /// The unix time that a token issued at `issued_at` expires.
+    pub fn expires_at(&self, issued_at: i64) -> i64 {
+        issued_at.saturating_add(self.ttl_sec as i64)
+    }
+
+    /// Seconds a revocation is kept for, until every token and refresh token it
+    /// revoked has expired.
+    pub fn revocation_ttl_sec(&self) -> u64 {
+        self.ttl_sec.max(self.refresh_ttl_sec)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PostConfig {
+    /// Seconds after a post is created that its title may still be edited.
+    pub title_edit_window_sec: u64,
+    /// Characters of the body kept by `?body=summary` listings.
+    pub summary_length: usize,
+    /// Seconds within which a post identical to one the account just made is
+    /// refused as a repeat, 0 to allow them.
+    pub repeat_window_sec: u64,
+    /// Whether image posts must describe their image with alt text.
+    pub require_alt_text: bool
+}
+
+impl Default for PostConfig {
+    fn default() -> Self {
+        PostConfig { title_edit_window_sec: 60 * 15, summary_length: 280, repeat_window_sec: 30, require_alt_text: false }
+    }
+}
+
+impl PostConfig {
+    /// Whether the title of a post created at unix time `created_at` can be edited at `now`.
+    pub fn title_editable(&self, created_at: i64, now: i64) -> bool {
+        now <= created_at.saturating_add(self.title_edit_window_sec as i64)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RegistrationConfig {
+    /// Whether an email address must be given to register.
+    pub require_email: bool,
+    /// Email domains that cannot be registered with, e.g. disposable email providers.
+    /// Subdomains of a listed domain are blocked too.
+    pub blocked_email_domains: Vec<String>,
+    /// Whether registering requires solving a challenge from `GET /api/challenge`.
+    pub challenge: bool,
+    /// Leading zero bits the hash of a challenge's solution must have. Each extra
+    /// bit doubles the expected work.
+    pub challenge_difficulty: u32,
+    /// Seconds a challenge can be solved within.
+    pub challenge_ttl_sec: u64
+}
+
+impl Default for RegistrationConfig {
+    fn default() -> Self {
+        RegistrationConfig {
+            require_email: false,
+            blocked_email_domains: Vec::new(),
+            challenge: false,
+            challenge_difficulty: 20,
+            challenge_ttl_sec: 300
+        }
+    }
+}
+
+impl RegistrationConfig {
+    pub fn email_domain_blocked(&self, email: &str) -> bool {
+        let Some((_, domain)) = email.rsplit_once('@') else {
+            return false
+        };
+        let domain = domain.to_lowercase();
+        self.blocked_email_domains.iter()
+            .map(|blocked| blocked.to_lowercase())
+            .any(|blocked| domain == blocked || domain.ends_with(&format!(".{}", blocked)))
+    }
+}
+
+/// Rules for the regions requests are made from, by the country looked up by `geoip`.
+/// Requests from unknown countries are never blocked.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ComplianceConfig {
+    /// ISO 3166-1 codes of the countries that accounts cannot be registered from.
+    pub blocked_registration_countries: Vec<String>
+}
+
+impl ComplianceConfig {
+    pub fn registration_blocked(&self, country: &str) -> bool {
+        self.blocked_registration_countries.iter().any(|blocked| blocked.eq_ignore_ascii_case(country))
+    }
+}
+
+/// The terms of service (and privacy policy) accounts must accept. When `version`
+/// changes, accounts can only write again once they accept the new version.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TermsConfig {
+    /// The current version, or `None` for no terms.
+    pub version: Option<String>,
+    /// Where the current version can be read.
+    pub url: Option<String>
+}
+
+/// Restrictions on accounts during their first `new_account_days`, see `policy`.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct OnboardingConfig {
+    pub enabled: bool,
+    pub new_account_days: u64,
+    /// Posts allowed per 24 hours while the account is new.
+    pub max_posts_per_day: u64,
+    /// Comments allowed per 24 hours while the account is new.
+    pub max_comments_per_day: u64,
+    /// Number of posts a new account makes before its posts may contain links.
+    pub posts_before_links: u64
+}
+
+impl Default for OnboardingConfig {
+    fn default() -> Self {
+        OnboardingConfig {
+            enabled: false,
+            new_account_days: 3,
+            max_posts_per_day: 5,
+            max_comments_per_day: 30,
+            posts_before_links: 3
+        }
+    }
+}
+
+/// Automatic scoring of new posts and comments, see `policy::scoring`. Content
+/// scoring at least `hold_threshold` is held for moderator review.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ModerationConfig {
+    pub enabled: bool,
+    pub hold_threshold: f64,
+    /// Case-insensitive keywords, and the score each adds to content containing it.
+    pub keywords: HashMap<String, f64>,
+    /// An external scoring API, sent `{"text": ...}` and replying `{"score": ...}`.
+    pub scoring_api_url: Option<String>,
+    pub scoring_api_timeout_ms: u64
+}
+
+impl Default for ModerationConfig {
+    fn default() -> Self {
+        ModerationConfig {
+            enabled: false,
+            hold_threshold: 0.8,
+            keywords: HashMap::new(),
+            scoring_api_url: None,
+            scoring_api_timeout_ms: 2000
+        }
+    }
+}
+
+/// Where `?translate=` translations of posts come from, see `translate`.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TranslationConfig {
+    /// An external translation API, sent `{"text": ..., "target": ...}` and replying
+    /// `{"text": ...}`. Translation is unavailable without one.
+    pub api_url: Option<String>,
+    pub api_timeout_ms: u64,
+    /// BCP 47 tags of the languages posts may be translated into, matched
+    /// case-insensitively. Translation is unavailable without any.
+    pub languages: Vec<String>,
+    /// Limit of translations requested from the API per client address. Cached
+    /// translations are not counted.
+    pub rate_limit: RateLimitConfig
+}
+
+impl Default for TranslationConfig {
+    fn default() -> Self {
+        TranslationConfig {
+            api_url: None,
+            api_timeout_ms: 5000,
+            languages: Vec::new(),
+            rate_limit: RateLimitConfig { enabled: true, requests: 20, window_sec: 60 }
+        }
+    }
+}
+
+/// Where the outbox job relays domain events to, see `jobs::outbox`.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct EventConfig {
+    /// Urls each event is POSTed to as JSON.
+    pub webhook_urls: Vec<String>,
+    pub webhook_timeout_ms: u64
+}
+
+impl Default for EventConfig {
+    fn default() -> Self {
+        EventConfig {
+            webhook_urls: Vec::new(),
+            webhook_timeout_ms: 5000
+        }
+    }
+}
+
+/// An A/B experiment, see `experiments`. Each account or anonymous client is
+/// assigned one of the variants, at random by weight and the same on every request.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ExperimentConfig {
+    pub enabled: bool,
+    /// Variant names and their relative weights.
+    pub variants: BTreeMap<String, u32>
+}
+
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        ExperimentConfig { enabled: true, variants: BTreeMap::new() }
+    }
+}
+
+/// Purging of soft-deleted posts by the retention job, see `jobs::retention`.
+/// Posts and comments under a legal hold are never purged.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RetentionConfig {
+    pub enabled: bool,
+    /// Days after a post is soft-deleted that it is purged, with its comments.
+    pub purge_deleted_after_days: u64
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        RetentionConfig { enabled: false, purge_deleted_after_days: 30 }
+    }
+}
+
+/// Recording of requests and their responses, to debug reports that can't be
+/// reproduced. Credentials are stripped before anything is stored.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RequestLogConfig {
+    pub enabled: bool,
+    /// Percentage (0 to 100) of request ids recorded.
+    pub sample_percent: f64,
+    /// Hours a recorded request is kept for.
+    pub keep_hours: u64
+}
+
+impl Default for RequestLogConfig {
+    fn default() -> Self {
+        RequestLogConfig { enabled: false, sample_percent: 1.0, keep_hours: 72 }
+    }
+}
+
+/// Signals of the clients that accounts log in from, correlated by the duplicates
+/// job, see `jobs::duplicates`. Only salted hashes of the signals are stored.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LoginSignalConfig {
+    pub enabled: bool,
+    /// Mixed into every hash, so that addresses can't be recovered by hashing each
+    /// one in turn. Changing it starts the correlation over.
+    pub salt: String,
+    /// Days a signal is kept for after it was last seen.
+    pub keep_days: u64,
+    /// Signals of more accounts than this (e.g. a shared network, a common browser)
+    /// are ignored rather than linking them all.
+    pub max_accounts_per_signal: u64
+}
+
+impl Default for LoginSignalConfig {
+    fn default() -> Self {
+        LoginSignalConfig { enabled: false, salt: String::new(), keep_days: 30, max_accounts_per_signal: 5 }
+    }
+}
+
+/// Karma (likes received on posts and comments) needed for each action. The
+/// defaults of 0 gate nothing. See `database::karma`.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct KarmaConfig {
+    pub post: u64,
+    pub comment: u64,
+    pub like: u64,
+    pub react: u64,
+    pub award: u64
+}
+
+/// Emoji that posts can be reacted with, in addition to likes.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ReactionConfig {
+    pub emojis: Vec<String>
+}
+
+impl Default for ReactionConfig {
+    fn default() -> Self {
+        ReactionConfig {
+            emojis: ["👍", "❤️", "😂", "😮", "😢", "🎉"].iter().map(|emoji| emoji.to_string()).collect()
+        }
+    }
+}
+
+impl ReactionConfig {
+    pub fn allows(&self, emoji: &str) -> bool {
+        self.emojis.iter().any(|allowed| allowed == emoji)
+    }
+}
+
+/// A deprecated route, announced to clients with the `Deprecation`, `Sunset` and
+/// `Link` headers, see `api::deprecation`.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct DeprecationConfig {
+    /// The date the route was (or will be) deprecated.
+    pub since: NaiveDate,
+    /// The date the route will be removed.
+    pub sunset: Option<NaiveDate>,
+    /// Notes on migrating away from the route.
+    pub link: Option<String>
+}
+
+/// Limit of requests to `/api` per client address, over a fixed window.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// Requests allowed per window
+    pub requests: u64,
+    pub window_sec: u64
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig { enabled: true, requests: 300, window_sec: 60 }
+    }
+}
+
+/// API keys, whose requests count against a daily quota rather than the limit per
+/// client address.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ApiKeyConfig {
+    /// Requests allowed per key each UTC day, unless an admin set its own quota
+    pub daily_quota: u64,
+    pub max_per_account: u64
+}
+
+impl Default for ApiKeyConfig {
+    fn default() -> Self {
+        ApiKeyConfig { daily_quota: 10_000, max_per_account: 5 }
+    }
+}
+
+/// Settings that can be changed without a restart. Loaded from the TOML file at
+/// `CONFIG_PATH` (default: `config.toml`), where every field is optional.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub log_level: String,
+    /// Defaults to `pretty` in debug builds and `json` in release builds.
+    pub log_format: Option<LogFormat>,
+    /// Per-module log levels, e.g. `actix_web = "warn"`. Only read at startup.
+    pub log_modules: HashMap<String, String>,
+    /// Case-insensitive words that posts and comments may not contain.
+    pub word_filter: Vec<String>,
+    /// Named feature flags. Flags that are not present are disabled.
+    pub features: HashMap<String, bool>,
+    /// Named A/B experiments, served to clients alongside the feature flags.
+    pub experiments: BTreeMap<String, ExperimentConfig>,
+    pub tokens: TokenConfig,
+    pub posts: PostConfig,
+    pub rate_limit: RateLimitConfig,
+    pub api_keys: ApiKeyConfig,
+    pub registration: RegistrationConfig,
+    pub terms: TermsConfig,
+    pub compliance: ComplianceConfig,
+    pub onboarding: OnboardingConfig,
+    pub reactions: ReactionConfig,
+    pub karma: KarmaConfig,
+    pub moderation: ModerationConfig,
+    pub translation: TranslationConfig,
+    pub events: EventConfig,
+    pub retention: RetentionConfig,
+    pub request_log: RequestLogConfig,
+    pub login_signals: LoginSignalConfig,
+    /// Deprecated routes, by method and route pattern, e.g. `POST /api/vote/post`.
+    pub deprecations: HashMap<String, DeprecationConfig>
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            log_level: "info".to_string(),
+            log_format: None,
+            log_modules: HashMap::new(),
+            word_filter: Vec::new(),
+            features: HashMap::new(),
+            experiments: BTreeMap::new(),
+            tokens: TokenConfig::default(),
+            posts: PostConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            api_keys: ApiKeyConfig::default(),
+            registration: RegistrationConfig::default(),
+            terms: TermsConfig::default(),
+            compliance: ComplianceConfig::default(),
+            onboarding: OnboardingConfig::default(),
+            reactions: ReactionConfig::default(),
+            karma: KarmaConfig::default(),
+            moderation: ModerationConfig::default(),
+            translation: TranslationConfig::default(),
+            events: EventConfig::default(),
+            retention: RetentionConfig::default(),
+            request_log: RequestLogConfig::default(),
+            login_signals: LoginSignalConfig::default(),
+            deprecations: HashMap::new()
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Loads the config file. A missing file results in the default config.
+    pub fn load() -> Result<Self, ConfigError> {
+        let path = std::env::var("CONFIG_PATH").unwrap_or(DEFAULT_CONFIG_PATH.to_string());
+        let config: ServerConfig = match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ServerConfig::default(),
+            Err(e) => return Err(ConfigError::from(e))
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks the settings that can't be told apart from valid ones when parsed.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.log_level_filter()?;
+        self.log_module_filters()?;
+        if let Some(language) = self.translation.languages.iter().find(|language| !translate::is_language_tag(language)) {
+            return Err(ConfigError::InvalidLanguage(language.clone()))
+        }
+        if self.login_signals.enabled && self.login_signals.salt.chars().count() < MIN_SIGNAL_SALT_LENGTH {
+            return Err(ConfigError::WeakSignalSalt(MIN_SIGNAL_SALT_LENGTH))
+        }
+        Ok(())
+    }
+
+    pub fn log_level_filter(&self) -> Result<LevelFilter, ConfigError> {
+        parse_level(&self.log_level)
+    }
+
+    pub fn log_module_filters(&self) -> Result<Vec<(String, LevelFilter)>, ConfigError> {
+        self.log_modules.iter()
+            .map(|(module, level)| Ok((module.clone(), parse_level(level)?)))
+            .collect()
+    }
+
+    pub fn log_format(&self) -> LogFormat {
+        match (self.log_format, cfg!(debug_assertions)) {
+            (Some(format), _) => format,
+            (None, true)  => LogFormat::Pretty,
+            (None, false) => LogFormat::Json
+        }
+    }
+
+    pub fn feature_enabled(&self, name: &str) -> bool {
+        self.features.get(name).copied().unwrap_or(false)
+    }
+
+    pub fn contains_filtered_word(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        self.word_filter.iter().any(|word| text.contains(&word.to_lowercase()))
+    }
+
+    /// Applies the settings that live outside of the shared config, i.e. the log
+    /// levels.
+    pub fn apply(&self) {
+        super::logging::apply(self);
+    }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, ConfigError> {
+    LevelFilter::from_str(level).map_err(|_| ConfigError::InvalidLogLevel(level.to_string()))
+}
+
+/// The current config, shared by the handlers and swapped out on reload.
+pub type SharedConfig = ArcSwap<ServerConfig>;
+
+/// Re-reads the config file and swaps it into `shared`. The current config is
+/// kept if the file cannot be loaded.
+pub fn reload(shared: &SharedConfig) -> Result<(), ConfigError> {
+    let config = ServerConfig::load()?;
+    config.apply();
+    shared.store(Arc::new(config));
+    info!("config: reloaded");
+    Ok(())
+}
+
+/// Reloads the config into `shared` whenever the process receives SIGHUP.
+#[cfg(unix)]
+pub fn spawn_reload_on_sighup(shared: Data<SharedConfig>) {
+    use actix_web::rt::{self, signal::unix::{signal, SignalKind}};
+
+    rt::spawn(async move {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(e) => {
+                warn!("config: failed to listen for SIGHUP: {}", e);
+                return
+            }
+        };
+        while hangup.recv().await.is_some() {
+            if let Err(e) = reload(&shared) {
+                warn!("config: reload failed, keeping the current config: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        ComplianceConfig, LogFormat, LoginSignalConfig, PostConfig, ReactionConfig, RegistrationConfig, ServerConfig, TokenConfig,
+        TranslationConfig
+    };
+
+    #[test]
+    fn test_parse() {
+        let config: ServerConfig = toml::from_str(r#"
+            log_level = "warn"
+            log_format = "json"
+            word_filter = ["Spam"]
+
+            [log_modules]
+            sqlx = "error"
+
+            [features]
+            registration_closed = true
+
+            [experiments.new_composer]
+            variants = { control = 1, treatment = 1 }
+        "#).unwrap();
+
+        assert_eq!(log::LevelFilter::Warn, config.log_level_filter().unwrap());
+        assert_eq!(LogFormat::Json, config.log_format());
+        assert_eq!(vec![("sqlx".to_string(), log::LevelFilter::Error)], config.log_module_filters().unwrap());
+        assert!(config.feature_enabled("registration_closed"));
+        assert!(!config.feature_enabled("missing"));
+        assert!(config.experiments["new_composer"].enabled);
+        assert_eq!(Some(&1), config.experiments["new_composer"].variants.get("treatment"));
+        assert!(config.contains_filtered_word("buy SPAM now"));
+        assert!(!config.contains_filtered_word("ham"));
+
+        let defaults: ServerConfig = toml::from_str("").unwrap();
+        assert_eq!("info", defaults.log_level);
+        assert!(defaults.word_filter.is_empty());
+        assert!(defaults.log_modules.is_empty());
+    }
+
+    #[test]
+    fn test_token_expiry() {
+        let tokens = TokenConfig { ttl_sec: 10, sudo_ttl_sec: 5, refresh_ttl_sec: 60 };
+        assert_eq!(110, tokens.expires_at(100));
+        assert_eq!(i64::MAX, tokens.expires_at(i64::MAX - 5));
+        assert_eq!(60, tokens.revocation_ttl_sec());
+        assert_eq!(10, TokenConfig { refresh_ttl_sec: 0, ..tokens }.revocation_ttl_sec());
+    }
+
+    #[test]
+    fn test_title_edit_window() {
+        let posts = PostConfig { title_edit_window_sec: 60, ..PostConfig::default() };
+        assert!(posts.title_editable(100, 100));
+        assert!(posts.title_editable(100, 160));
+        assert!(!posts.title_editable(100, 161));
+    }
+
+    #[test]
+    fn test_registration_blocked() {
+        let compliance = ComplianceConfig { blocked_registration_countries: vec!["kp".to_string()] };
+        assert!(compliance.registration_blocked("KP"));
+        assert!(!compliance.registration_blocked("NZ"));
+        assert!(!ComplianceConfig::default().registration_blocked("KP"));
+    }
+
+    #[test]
+    fn test_email_domain_blocked() {
+        let registration = RegistrationConfig {
+            require_email: false,
+            blocked_email_domains: vec!["Mailinator.com".to_string()],
+            ..RegistrationConfig::default()
+        };
+        assert!(registration.email_domain_blocked("someone@mailinator.com"));
+        assert!(registration.email_domain_blocked("someone@eu.MAILINATOR.com"));
+        assert!(!registration.email_domain_blocked("someone@notmailinator.com"));
+        assert!(!registration.email_domain_blocked("someone@example.com"));
+    }
+
+    #[test]
+    fn test_reactions_allowed() {
+        let reactions = ReactionConfig::default();
+        assert!(reactions.allows("👍"));
+        assert!(!reactions.allows("🍆"));
+        assert!(!reactions.allows(""));
+    }
+    #[test]
+    fn test_validate() {
+        assert!(ServerConfig::default().validate().is_ok());
+
+        let signals = |enabled: bool, salt: &str| ServerConfig {
+            login_signals: LoginSignalConfig { enabled, salt: salt.to_string(), ..LoginSignalConfig::default() },
+            ..ServerConfig::default()
+        };
+        assert!(signals(false, "").validate().is_ok());
+        assert!(signals(true, "").validate().is_err());
+        assert!(signals(true, "short salt").validate().is_err());
+        assert!(signals(true, "a long enough random salt").validate().is_ok());
+
+        let languages = |languages: &[&str]| ServerConfig {
+            translation: TranslationConfig { languages: languages.iter().map(|l| l.to_string()).collect(), ..TranslationConfig::default() },
+            ..ServerConfig::default()
+        };
+        assert!(languages(&["de", "pt-BR"]).validate().is_ok());
+        assert!(languages(&["de", "german"]).validate().is_err());
+    }
+}
\ No newline at end of file
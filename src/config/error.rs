@@ -0,0 +1,35 @@
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    InvalidLogLevel(String),
+    InvalidLanguage(String),
+    WeakSignalSalt(usize)
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(err: toml::de::Error) -> Self {
+        ConfigError::Parse(err)
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            ConfigError::Io(err) => err.to_string(),
+            ConfigError::Parse(err) => err.to_string(),
+            ConfigError::InvalidLogLevel(level) => format!("'{}' is not a valid log level", level),
+            ConfigError::InvalidLanguage(language) => format!("'{}' is not a valid language tag", language),
+            ConfigError::WeakSignalSalt(length) => {
+                format!("login_signals.salt must be at least {} characters when login_signals is enabled", length)
+            }
+        };
+        write!(f, "{}", output)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,114 @@
+use std::io::Write;
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+use chrono::{SecondsFormat, Utc};
+use env_logger::filter::{self, Filter};
+use log::{LevelFilter, Log, Metadata, Record};
+use serde_json::json;
+
+use super::config::{LogFormat, ServerConfig};
+
+/// The levels of the global logger, swapped out when the config is reloaded.
+static FILTER: ArcSwapOption<Filter> = ArcSwapOption::const_empty();
+
+/// Formats and writes what `FILTER` lets through. The logger itself lets
+/// everything through, so that a reload can raise verbosity past the levels
+/// at startup.
+struct ReloadableLogger {
+    inner: env_logger::Logger
+}
+
+impl Log for ReloadableLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        FILTER.load().as_ref().is_some_and(|filter| filter.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        if FILTER.load().as_ref().is_some_and(|filter| filter.matches(record)) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Initialises the global logger from the log settings of `config`. Directives in
+/// `RUST_LOG`, when set, take precedence over the configured levels.
+///
+/// Note: The levels are changed by a config reload, but the format is fixed once
+///       the logger is initialised.
+pub fn init(config: &ServerConfig) {
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(LevelFilter::Trace);
+
+    if config.log_format() == LogFormat::Json {
+        builder.format(|buf, record| {
+            let line = json!({
+                "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string()
+            });
+            writeln!(buf, "{}", line)
+        });
+    }
+
+    log::set_boxed_logger(Box::new(ReloadableLogger { inner: builder.build() }))
+        .expect("Failed to initialise the logger");
+    config.apply();
+}
+
+/// Swaps the levels of `config` into the global logger, followed by the directives
+/// in `RUST_LOG` when set.
+pub fn apply(config: &ServerConfig) {
+    let filter = levels(config, std::env::var("RUST_LOG").ok().as_deref());
+    log::set_max_level(filter.filter());
+    FILTER.store(Some(Arc::new(filter)));
+}
+
+fn levels(config: &ServerConfig, directives: Option<&str>) -> Filter {
+    let mut builder = filter::Builder::new();
+    builder.filter_level(config.log_level_filter().unwrap_or(LevelFilter::Info));
+    for (module, level) in config.log_module_filters().unwrap_or_default() {
+        builder.filter_module(&module, level);
+    }
+    if let Some(directives) = directives {
+        builder.parse(directives);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use log::{Level, LevelFilter, Metadata};
+
+    use crate::config::config::ServerConfig;
+    use super::levels;
+
+    #[test]
+    fn test_levels() {
+        let config = ServerConfig {
+            log_level: "warn".to_string(),
+            log_modules: HashMap::from([("posted_server::jobs".to_string(), "trace".to_string())]),
+            ..ServerConfig::default()
+        };
+        let enabled = |filter: &env_logger::filter::Filter, level, target| {
+            filter.enabled(&Metadata::builder().level(level).target(target).build())
+        };
+
+        let filter = levels(&config, None);
+        assert_eq!(LevelFilter::Trace, filter.filter());
+        assert!(enabled(&filter, Level::Trace, "posted_server::jobs::feed"));
+        assert!(!enabled(&filter, Level::Info, "posted_server::api"));
+        assert!(enabled(&filter, Level::Warn, "posted_server::api"));
+
+        // RUST_LOG directives take precedence
+        let filter = levels(&config, Some("posted_server::api=debug"));
+        assert!(enabled(&filter, Level::Debug, "posted_server::api"));
+    }
+}
\ No newline at end of file
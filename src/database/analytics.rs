@@ -0,0 +1,82 @@
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, Utc};
+
+use crate::models::DailyStats;
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+/// Aggregates a day. Bound with the day, then its start & end for each of the
+/// `DAY_BOUNDS` counts that are limited to the day.
+const AGGREGATE_DAY: &str =
+    "INSERT INTO DailyStats (day, active_users, new_accounts, posts, comments, votes)
+    SELECT ?,
+        (SELECT count(DISTINCT active.account_id) FROM (
+            SELECT p.poster_id AS account_id FROM Post p WHERE p.time_stamp >= ? AND p.time_stamp < ?
+            UNION ALL
+            SELECT c.commenter_id FROM Comment c WHERE c.time_stamp >= ? AND c.time_stamp < ?
+            UNION ALL
+            SELECT pl.account_id FROM PostLike pl WHERE pl.time_stamp >= ? AND pl.time_stamp < ?
+            UNION ALL
+            SELECT cl.account_id FROM CommentLike cl WHERE cl.time_stamp >= ? AND cl.time_stamp < ?
+        ) active),
+        (SELECT count(*) FROM Account a WHERE a.created_at >= ? AND a.created_at < ?),
+        (SELECT count(*) FROM Post p WHERE p.time_stamp >= ? AND p.time_stamp < ?),
+        (SELECT count(*) FROM Comment c WHERE c.time_stamp >= ? AND c.time_stamp < ?),
+        (SELECT count(*) FROM PostLike pl WHERE pl.time_stamp >= ? AND pl.time_stamp < ?)
+            + (SELECT count(*) FROM CommentLike cl WHERE cl.time_stamp >= ? AND cl.time_stamp < ?)
+    ON DUPLICATE KEY UPDATE
+        active_users = VALUES(active_users),
+        new_accounts = VALUES(new_accounts),
+        posts = VALUES(posts),
+        comments = VALUES(comments),
+        votes = VALUES(votes);";
+const DAY_BOUNDS: usize = 9;
+
+impl Database {
+    /// Aggregates the activity of the UTC `day` into DailyStats, replacing any
+    /// earlier aggregate of it.
+    pub async fn aggregate_daily_stats(&self, day: NaiveDate) -> DBResult<()> {
+        let start: DateTime<Utc> = day.and_time(NaiveTime::MIN).and_utc();
+        let end = start + Days::new(1);
+        let mut query = sqlx::query(AGGREGATE_DAY).bind(day);
+        for _ in 0..DAY_BOUNDS {
+            query = query.bind(start).bind(end);
+        }
+
+        match query.execute(&self.conn_pool).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// The latest day that has been aggregated, if any.
+    pub async fn read_last_stats_day(&self) -> DBResult<Option<NaiveDate>> {
+        let result = sqlx::query_scalar::<_, Option<NaiveDate>>("SELECT MAX(day) FROM DailyStats;")
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(day) => Ok(day),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads the aggregates of the days from `from` to `to` inclusive, oldest first.
+    /// Days that have not been aggregated are left out.
+    pub async fn read_daily_stats(&self, from: NaiveDate, to: NaiveDate) -> DBResult<Vec<DailyStats>> {
+        let result = sqlx::query_as::<_, DailyStats>(
+            "SELECT day, active_users, new_accounts, posts, comments, votes, computed_at
+            FROM DailyStats
+            WHERE day >= ? AND day <= ?
+            ORDER BY day;")
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(stats) => Ok(stats),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
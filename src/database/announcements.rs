@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+
+use crate::models::{Announcement, Role};
+
+use super::database::{expected_rows_affected, log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Reads the announcements running now that `viewer_id` is in the audience of,
+    /// soonest to end first. Without a `viewer_id`, only those for everyone.
+    pub async fn read_active_announcements(&self, viewer_id: Option<u64>) -> DBResult<Vec<Announcement>> {
+        let result = sqlx::query_as::<_, Announcement>(
+            "SELECT id, body, role, starts_at, ends_at, created_by, created_at
+            FROM Announcement
+            WHERE starts_at <= CURRENT_TIMESTAMP()
+            AND (ends_at IS NULL OR ends_at > CURRENT_TIMESTAMP())
+            AND (role IS NULL OR role + 0 <= (SELECT role + 0 FROM Account WHERE id = ?))
+            ORDER BY ends_at IS NULL, ends_at, id DESC;")
+            .bind(viewer_id)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(announcements) => Ok(announcements),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads every announcement, including scheduled and ended ones, newest first.
+    pub async fn read_announcements(&self) -> DBResult<Vec<Announcement>> {
+        let result = sqlx::query_as::<_, Announcement>(
+            "SELECT id, body, role, starts_at, ends_at, created_by, created_at
+            FROM Announcement
+            ORDER BY id DESC;")
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(announcements) => Ok(announcements),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Creates an announcement, starting now if `starts_at` is not given.
+    pub async fn create_announcement(
+        &self,
+        body: &str,
+        role: Option<Role>,
+        starts_at: Option<DateTime<Utc>>,
+        ends_at: Option<DateTime<Utc>>,
+        created_by: u64
+    ) -> DBResult<u64> {
+        let result = sqlx::query(
+            "INSERT INTO Announcement (body, role, starts_at, ends_at, created_by)
+            VALUES (?, ?, COALESCE(?, CURRENT_TIMESTAMP()), ?, ?);")
+            .bind(body)
+            .bind(role)
+            .bind(starts_at)
+            .bind(ends_at)
+            .bind(created_by)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => Ok(res.last_insert_id()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Deletes an announcement. Results in `DBError::UnexpectedRowsAffected` if it
+    /// does not exist.
+    pub async fn delete_announcement(&self, announcement_id: u64) -> DBResult<()> {
+        let result = sqlx::query(
+            "DELETE FROM Announcement
+            WHERE id = ?;")
+            .bind(announcement_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => expected_rows_affected(res, 1),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,108 @@
+use crate::auth::token::TokenHash;
+use crate::models::ApiKey;
+
+use super::database::{expected_rows_affected, log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Creates an API key of `account_id` with the hash of its key, returning its id.
+    pub async fn create_api_key(&self, account_id: u64, name: &str, key_hash: &TokenHash) -> DBResult<u64> {
+        let result = sqlx::query(
+            "INSERT INTO ApiKey (account_id, name, key_hash)
+            VALUES (?, ?, ?);")
+            .bind(account_id)
+            .bind(name)
+            .bind(key_hash.to_string())
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => Ok(res.last_insert_id()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads the API keys of `account_id`, oldest first.
+    pub async fn read_api_keys(&self, account_id: u64) -> DBResult<Vec<ApiKey>> {
+        let result = sqlx::query_as::<_, ApiKey>(
+            "SELECT id, account_id, name, daily_quota, created_at
+            FROM ApiKey
+            WHERE account_id = ?
+            ORDER BY id;")
+            .bind(account_id)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(keys) => Ok(keys),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads an API key. Results in `DBError::NoResult` if it does not exist.
+    pub async fn read_api_key(&self, api_key_id: u64) -> DBResult<ApiKey> {
+        let result = sqlx::query_as::<_, ApiKey>(
+            "SELECT id, account_id, name, daily_quota, created_at
+            FROM ApiKey
+            WHERE id = ?;")
+            .bind(api_key_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(key) => Ok(key),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads the API key with `key_hash`, as presented with a request, if there is one.
+    pub async fn read_api_key_by_hash(&self, key_hash: &TokenHash) -> DBResult<Option<ApiKey>> {
+        let result = sqlx::query_as::<_, ApiKey>(
+            "SELECT id, account_id, name, daily_quota, created_at
+            FROM ApiKey
+            WHERE key_hash = ?;")
+            .bind(key_hash.to_string())
+            .fetch_optional(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(key) => Ok(key),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Sets the daily quota of an API key, or returns it to the configured default
+    /// with `None`.
+    pub async fn update_api_key_quota(&self, api_key_id: u64, daily_quota: Option<u64>) -> DBResult<()> {
+        let result = sqlx::query(
+            "UPDATE ApiKey
+            SET daily_quota = ?
+            WHERE id = ?;")
+            .bind(daily_quota)
+            .bind(api_key_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Deletes an API key of `account_id`. Results in `DBError::UnexpectedRowsAffected`
+    /// if the account has no such key.
+    pub async fn delete_api_key(&self, api_key_id: u64, account_id: u64) -> DBResult<()> {
+        let result = sqlx::query(
+            "DELETE FROM ApiKey
+            WHERE id = ? AND account_id = ?;")
+            .bind(api_key_id)
+            .bind(account_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => expected_rows_affected(res, 1),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
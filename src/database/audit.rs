@@ -0,0 +1,65 @@
+use sqlx::{MySql, QueryBuilder};
+
+use crate::models::{AuditAction, AuditEntry, AuditQuery};
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Records that `actor_id` took `action` on `target_id`. The action has already
+    /// been taken, so callers don't fail it when this does, the error is logged.
+    pub async fn record_audit(
+        &self,
+        actor_id: u64,
+        action: AuditAction,
+        target_id: Option<u64>,
+        detail: Option<&str>
+    ) -> DBResult<()> {
+        let result = sqlx::query(
+            "INSERT INTO AuditLog (actor_id, action, target_id, detail)
+            VALUES (?, ?, ?, ?);")
+            .bind(actor_id)
+            .bind(action)
+            .bind(target_id)
+            .bind(detail)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads up to `limit` entries of the audit log matching the filters of
+    /// `query`, newest first. `from` is inclusive and `to` exclusive.
+    pub async fn read_audit_log(&self, query: &AuditQuery, limit: u64) -> DBResult<Vec<AuditEntry>> {
+        let mut select = QueryBuilder::<MySql>::new(
+            "SELECT id, actor_id, action, target_id, detail, created_at FROM AuditLog WHERE TRUE");
+        if let Some(actor_id) = query.actor_id {
+            select.push(" AND actor_id = ").push_bind(actor_id);
+        }
+        if let Some(action) = query.action {
+            select.push(" AND action = ").push_bind(action);
+        }
+        if let Some(from) = query.from {
+            select.push(" AND created_at >= ").push_bind(from);
+        }
+        if let Some(to) = query.to {
+            select.push(" AND created_at < ").push_bind(to);
+        }
+        if let Some(before) = query.before {
+            select.push(" AND id < ").push_bind(before);
+        }
+        select.push(" ORDER BY id DESC LIMIT ").push_bind(limit).push(";");
+
+        let result = select.build_query_as::<AuditEntry>()
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(entries) => Ok(entries),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
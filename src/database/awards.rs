@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use sqlx::{MySql, QueryBuilder};
+
+use crate::models::{Award, AwardCounts, ReceivedAward};
+
+use super::database::{expected_rows_affected, log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    pub async fn read_awards(&self) -> DBResult<Vec<Award>> {
+        let result = sqlx::query_as!(Award,
+            "SELECT id, name, description
+            FROM Award
+            ORDER BY id;")
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(awards) => Ok(awards),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Records `giver_id` giving the award to the poster of the post. Results in
+    /// `DBError::UnexpectedRowsAffected` if the post or award does not exist.
+    pub async fn create_post_award(&self, post_id: u64, giver_id: u64, award_id: u64) -> DBResult<()> {
+        let result = sqlx::query(
+            "INSERT INTO AwardGiven (award_id, giver_id, receiver_id, post_id)
+            SELECT a.id, ?, p.poster_id, p.id
+            FROM Post p, Award a
+            WHERE p.id = ? AND a.id = ?;")
+            .bind(giver_id)
+            .bind(post_id)
+            .bind(award_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => expected_rows_affected(res, 1),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Records `giver_id` giving the award to the commenter of the comment. Results
+    /// in `DBError::UnexpectedRowsAffected` if the comment or award does not exist.
+    pub async fn create_comment_award(&self, comment_id: u64, giver_id: u64, award_id: u64) -> DBResult<()> {
+        let result = sqlx::query(
+            "INSERT INTO AwardGiven (award_id, giver_id, receiver_id, post_id, comment_id)
+            SELECT a.id, ?, c.commenter_id, c.post_id, c.id
+            FROM Comment c, Award a
+            WHERE c.id = ? AND a.id = ?;")
+            .bind(giver_id)
+            .bind(comment_id)
+            .bind(award_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => expected_rows_affected(res, 1),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Awards given to each of `post_ids`, by award name, in a single query. Every
+    /// id is present in the result. Awards to the comments of a post are not its own.
+    pub async fn read_post_awards(&self, post_ids: &[u64]) -> DBResult<HashMap<u64, AwardCounts>> {
+        self.read_award_counts("post_id", "g.comment_id IS NULL", post_ids).await
+    }
+
+    /// As `read_post_awards`, for comments.
+    pub async fn read_comment_awards(&self, comment_ids: &[u64]) -> DBResult<HashMap<u64, AwardCounts>> {
+        self.read_award_counts("comment_id", "TRUE", comment_ids).await
+    }
+
+    /// Awards given to each of `ids` of the `column` of AwardGiven, of the awards
+    /// matching `condition`.
+    async fn read_award_counts(&self, column: &str, condition: &str, ids: &[u64]) -> DBResult<HashMap<u64, AwardCounts>> {
+        let mut awards = ids.iter()
+            .map(|id| (*id, AwardCounts::new()))
+            .collect::<HashMap<u64, AwardCounts>>();
+        if ids.is_empty() {
+            return Ok(awards)
+        }
+
+        let mut query = QueryBuilder::<MySql>::new(format!(
+            "SELECT g.{0}, a.name, CAST(count(*) AS UNSIGNED)
+            FROM AwardGiven g
+            INNER JOIN Award a ON g.award_id = a.id
+            WHERE {1} AND g.{0} IN (", column, condition));
+        let mut separated = query.separated(", ");
+        for id in ids {
+            separated.push_bind(*id);
+        }
+        query.push(format!(") GROUP BY g.{}, a.name;", column));
+
+        let result = query.build_query_as::<(u64, String, u64)>()
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(counts) => {
+                for (id, name, count) in counts {
+                    awards.entry(id).or_default().insert(name, count);
+                }
+                Ok(awards)
+            },
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads up to `limit` awards received by a user, newest first. Pages continue
+    /// from the `before` award id.
+    pub async fn read_awards_received(&self, user_id: u64, limit: u64, before: Option<u64>) -> DBResult<Vec<ReceivedAward>> {
+        let result = sqlx::query_as!(ReceivedAward,
+            "SELECT g.id, g.award_id, a.name AS 'award_name', g.giver_id, g.post_id, g.comment_id, g.time_stamp
+            FROM AwardGiven g
+            INNER JOIN Award a ON g.award_id = a.id
+            WHERE g.receiver_id = ?
+            AND g.id < ?
+            ORDER BY g.id DESC
+            LIMIT ?;", user_id, before.unwrap_or(u64::MAX), limit)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(awards) => Ok(awards),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    pub async fn count_awards_received(&self, user_id: u64) -> DBResult<u64> {
+        let result = sqlx::query_scalar::<_, u64>(
+            "SELECT CAST(count(*) AS UNSIGNED) FROM AwardGiven WHERE receiver_id = ?;")
+            .bind(user_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(count) => Ok(count),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
@@ -1,95 +1,166 @@
 use log::warn;
-use sqlx::{MySql, Pool, Row};
+use sqlx::{MySql, Pool, QueryBuilder, Row};
 use sqlx::mysql::{MySqlPoolOptions, MySqlQueryResult};
 
-use crate::models::{AccountFromDB, Comment, NewComment, NewPost, Post};
+use crate::models::{
+    AccountFromDB, Comment, CommentMode, Moderation, NewComment, NewPost, OnboardingStep, Post, PostAudience, PostSort,
+    PublicAccount, Role, TextFormat, UserComment
+};
 use crate::database::error::DBError;
+use crate::events::DomainEvent;
 
-type DBResult<T> = Result<T, DBError>;
+use super::{onboarding, outbox, polls};
+use super::retention::{COMMENT_HELD, HELD};
+
+pub(super) type DBResult<T> = Result<T, DBError>;
+
+/// The columns of a `Post`, of the Post table aliased as `p`.
+pub(super) const POST_COLUMNS: &str = "p.id, p.poster_id, p.kind, p.title, p.url, p.alt_text, p.body, p.tldr, p.body_format,
+    p.comment_mode, p.visibility, p.time_stamp, p.updated_at, p.expires_at, p.body_edited, p.title_edited,
+    p.like_count AS likes";
+
+/// Connections the pool opens at most when `DB_MAX_CONNECTIONS` is not set.
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 10;
+
+#[cfg(not(feature = "chaos"))]
+type ConnPool = super::pool::TimedPool;
+#[cfg(feature = "chaos")]
+type ConnPool = super::faulty::FaultyPool;
 
 pub struct Database {
-    conn_pool: Pool<MySql>
+    pub(super) conn_pool: ConnPool
 }
 
 impl Database {
-    pub async fn new(url: &str) -> Self {
-        let pool = MySqlPoolOptions::new().connect(url)
-            .await
-            .expect("Failed to connect to the database");
-        Database { conn_pool: pool }
+    pub async fn new(url: &str, max_connections: u32) -> Self {
+        Self::try_new(url, max_connections).await.expect("Failed to connect to the database")
+    }
+
+    pub async fn try_new(url: &str, max_connections: u32) -> DBResult<Self> {
+        let pool = MySqlPoolOptions::new()
+            .max_connections(max_connections)
+            .connect(url)
+            .await?;
+        Ok(Database::from_pool(pool))
+    }
+
+    /// Connects when first used, so that tests injecting faults into every query,
+    /// or rejecting requests before any, need no database.
+    #[cfg(any(test, feature = "chaos"))]
+    pub fn connect_lazy(url: &str) -> DBResult<Self> {
+        let pool = MySqlPoolOptions::new().connect_lazy(url)?;
+        Ok(Database::from_pool(pool))
+    }
+
+    #[cfg(not(feature = "chaos"))]
+    fn from_pool(pool: Pool<MySql>) -> Self {
+        Database { conn_pool: super::pool::TimedPool::new(pool) }
+    }
+
+    #[cfg(feature = "chaos")]
+    fn from_pool(pool: Pool<MySql>) -> Self {
+        Database { conn_pool: super::faulty::FaultyPool::new(super::pool::TimedPool::new(pool)) }
     }
 
     // Create
 
-    pub async fn create_account(&self, username: &str, password_hash: &str) -> DBResult<()> {
-        match sqlx::query("INSERT INTO Account (username, password_hash) VALUES (?, ?);")
+    /// Creates an account with an unverified `email`, recording that it accepted
+    /// `terms_version` of the terms if there are any. A username or email that is
+    /// already taken results in no rows being affected. Returns the id of the account.
+    pub async fn create_account(
+        &self,
+        username: &str,
+        email: Option<&str>,
+        password_hash: &str,
+        terms_version: Option<&str>
+    ) -> DBResult<u64> {
+        let mut tx = self.conn_pool.begin().await.map_err(|e| log_error(DBError::from(e)))?;
+        let result = sqlx::query("INSERT IGNORE INTO Account (username, email, password_hash) VALUES (?, ?, ?);")
             .bind(username)
+            .bind(email)
             .bind(password_hash)
-            .execute(&self.conn_pool)
+            .execute(&mut *tx)
             .await
-        {
-            Ok(res) => expected_rows_affected(res, 1),
-            Err(e) => Err(log_error(DBError::from(e)))
+            .map_err(|e| log_error(DBError::from(e)))?;
+        let account_id = result.last_insert_id();
+        expected_rows_affected(result, 1)?;
+
+        if let Some(version) = terms_version {
+            sqlx::query("INSERT INTO TermsAcceptance (account_id, version) VALUES (?, ?);")
+                .bind(account_id)
+                .bind(version)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| log_error(DBError::from(e)))?;
         }
+        tx.commit().await.map_err(|e| log_error(DBError::from(e)))?;
+        Ok(account_id)
     }
 
-    pub async fn create_post(&self, post: NewPost) -> DBResult<()> {
-        match sqlx::query("INSERT INTO Post (poster_id, title, body) VALUES (?, ?, ?);")
-            .bind(post.poster_id)
+    /// Creates a post by `poster_id`, returning its id.
+    pub async fn create_post(&self, poster_id: u64, post: NewPost, moderation: Moderation) -> DBResult<u64> {
+        let mut tx = self.conn_pool.begin().await.map_err(|e| log_error(DBError::from(e)))?;
+        let visibility = post.visibility;
+        let result = sqlx::query("INSERT INTO Post (poster_id, kind, title, url, alt_text, body, tldr, body_format, comment_mode,
+                visibility, expires_at, moderation_score, held)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);")
+            .bind(poster_id)
+            .bind(post.kind)
             .bind(post.title)
+            .bind(post.url)
+            .bind(post.alt_text)
             .bind(post.body)
-            .execute(&self.conn_pool)
+            .bind(post.tldr)
+            .bind(post.body_format)
+            .bind(post.comment_mode)
+            .bind(post.visibility)
+            .bind(post.expires_at)
+            .bind(moderation.score)
+            .bind(moderation.held)
+            .execute(&mut *tx)
             .await
-        {
-            Ok(res) => expected_rows_affected(res, 1),
-            Err(e) => Err(log_error(DBError::from(e)))
-        }
+            .map_err(|e| log_error(DBError::from(e)))?;
+        let post_id = result.last_insert_id();
+        expected_rows_affected(result, 1)?;
+
+        polls::record_options(&mut tx, post_id, &post.poll_options).await?;
+        onboarding::record_step(&mut tx, poster_id, OnboardingStep::FirstPost).await?;
+        let event = DomainEvent::PostCreated { post_id, poster_id, visibility, held: moderation.held };
+        outbox::record_event(&mut tx, &event).await?;
+        tx.commit().await.map_err(|e| log_error(DBError::from(e)))?;
+        Ok(post_id)
     }
 
-    pub async fn create_comment(&self, comment: NewComment) -> DBResult<()> {
-        match sqlx::query("INSERT INTO Comment (post_id, commenter_id, body, comment_reply_id) VALUES (?, ?, ?, ?);")
+    /// Creates a comment by `commenter_id`, returning its id.
+    pub async fn create_comment(&self, commenter_id: u64, comment: NewComment, moderation: Moderation) -> DBResult<u64> {
+        let mut tx = self.conn_pool.begin().await.map_err(|e| log_error(DBError::from(e)))?;
+        let post_id = comment.post_id;
+        let result = sqlx::query("INSERT INTO Comment (post_id, commenter_id, body, body_format, comment_reply_id, moderation_score, held)
+            VALUES (?, ?, ?, ?, ?, ?, ?);")
             .bind(comment.post_id)
-            .bind(comment.commenter_id)
+            .bind(commenter_id)
             .bind(comment.body)
+            .bind(comment.body_format)
             .bind(comment.comment_reply_id)
-            .execute(&self.conn_pool)
-            .await
-        {
-            Ok(res) => expected_rows_affected(res, 1),
-            Err(e) => Err(log_error(DBError::from(e)))
-        }
-    }
-
-    pub async fn create_post_like(&self, post_id: u64, account_id: u64) -> DBResult<()> {
-        match sqlx::query("INSERT IGNORE INTO PostLike (post_id, account_id) values (?, ?);")
-            .bind(post_id)
-            .bind(account_id)
-            .execute(&self.conn_pool)
-            .await
-        {
-            Ok(res) => expected_rows_affected(res, 1),
-            Err(e) => Err(log_error(DBError::from(e)))
-        }
-    }
-
-    pub async fn create_comment_like(&self, comment_id: u64, account_id: u64) -> DBResult<()> {
-        match sqlx::query("INSERT IGNORE INTO CommentLike (comment_id, account_id) values (?, ?);")
-            .bind(comment_id)
-            .bind(account_id)
-            .execute(&self.conn_pool)
+            .bind(moderation.score)
+            .bind(moderation.held)
+            .execute(&mut *tx)
             .await
-        {
-            Ok(res) => expected_rows_affected(res, 1),
-            Err(e) => Err(log_error(DBError::from(e)))
-        }
+            .map_err(|e| log_error(DBError::from(e)))?;
+        let comment_id = result.last_insert_id();
+        expected_rows_affected(result, 1)?;
+
+        let event = DomainEvent::CommentCreated { comment_id, post_id, commenter_id, held: moderation.held };
+        outbox::record_event(&mut tx, &event).await?;
+        tx.commit().await.map_err(|e| log_error(DBError::from(e)))?;
+        Ok(comment_id)
     }
 
     // Read
 
-    pub async fn _read_account_by_id(&self, id: u64) -> DBResult<AccountFromDB> {
-        // TODO, avoid cast and return null for an None for id
+    pub async fn read_account_by_id(&self, id: u64) -> DBResult<AccountFromDB> {
         let result = sqlx::query_as!(AccountFromDB,
-            "SELECT CAST(0 AS UNSIGNED) as 'id', username, password_hash
+            "SELECT CAST(id AS UNSIGNED) as 'id', username, password_hash
             FROM Account
             WHERE id = ?
             LIMIT 1;", id)
@@ -117,32 +188,71 @@ impl Database {
         }
     }
 
-    pub async fn read_posts(&self, max_posts: u64) -> DBResult<Vec<Post>> {
-        let result = sqlx::query_as!(Post,
-            "SELECT p.id, p.poster_id, p.title, p.body, p.time_stamp, p.edited as `edited: _`,
-                CAST(count(pl.account_id) AS UNSIGNED) AS 'likes'
-            FROM Post p
-            LEFT JOIN PostLike pl
-            ON p.id = pl.post_id
-            GROUP BY p.id
-            LIMIT ?;", max_posts)
+    pub async fn read_public_account(&self, id: u64) -> DBResult<PublicAccount> {
+        let result = sqlx::query_as!(PublicAccount,
+            "SELECT CAST(id AS UNSIGNED) as 'id', username, role as `role: _`, created_at
+            FROM Account
+            WHERE id = ?;", id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(account) => Ok(account),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    pub async fn read_account_role(&self, account_id: u64) -> DBResult<Role> {
+        let result = sqlx::query_scalar::<_, Role>(
+            "SELECT role
+            FROM Account
+            WHERE id = ?;")
+            .bind(account_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(role) => Ok(role),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads up to `max_posts` listed posts, in the order of `sort`. Followers-only
+    /// posts are included for their author and followers when read for a `viewer_id`.
+    /// Hot posts are read newest first, as they are ranked out of `read_hot_candidates`.
+    pub async fn read_posts(&self, max_posts: u64, viewer_id: Option<u64>, sort: PostSort) -> DBResult<Vec<Post>> {
+        // Served by the primary key and by post_like_count
+        let order = match sort {
+            PostSort::New | PostSort::Hot => "p.id DESC",
+            PostSort::Top => "p.like_count DESC, p.id DESC"
+        };
+        let mut query = QueryBuilder::<MySql>::new(format!("SELECT {} FROM Post p", POST_COLUMNS));
+        push_listed(&mut query, viewer_id);
+        query.push(format!(" ORDER BY {} LIMIT ", order)).push_bind(max_posts);
+
+        let result = query.build_query_as::<Post>()
             .fetch_all(&self.conn_pool)
             .await;
+
         match result {
             Ok(posts) => Ok(posts),
             Err(e)  => Err(log_error(DBError::from(e)))
         }
     }
 
+    /// Reads a post, unless it is held, deleted or has expired by the database clock.
+    /// Results in `DBError::NoResult` if there is no such post.
     pub async fn read_post_by_id(&self, post_id: u64) -> DBResult<Post> {
         let result = sqlx::query_as!(Post,
-            "SELECT p.id, p.poster_id, p.title, p.body, p.time_stamp, p.edited as `edited: _`,
-                CAST(count(pl.account_id) AS UNSIGNED) AS 'likes'
+            "SELECT p.id, p.poster_id, p.kind as `kind: _`, p.title, p.url, p.alt_text, p.body, p.tldr, p.body_format as `body_format: _`,
+                p.comment_mode as `comment_mode: _`, p.visibility as `visibility: _`, p.time_stamp, p.updated_at, p.expires_at,
+                p.body_edited as `body_edited: _`, p.title_edited as `title_edited: _`,
+                p.like_count AS 'likes'
             FROM Post p
-            LEFT JOIN PostLike pl
-            ON p.id = pl.post_id
             WHERE p.id = ?
-            GROUP BY p.id;", post_id)
+            AND NOT p.held
+            AND p.deleted_at IS NULL
+            AND (p.expires_at IS NULL OR p.expires_at > CURRENT_TIMESTAMP());", post_id)
             .fetch_one(&self.conn_pool)
             .await;
         match result {
@@ -151,16 +261,31 @@ impl Database {
         }
     }
 
-    pub async fn read_posts_by_user(&self, user_id: u64) -> DBResult<Vec<Post>> {
+    /// Reads up to `limit` posts of a user that are public or in `audience`, newest
+    /// first. Passing the id of the last post of a page as `before` reads the next page.
+    pub async fn read_posts_by_user(
+        &self,
+        user_id: u64,
+        limit: u64,
+        before: Option<u64>,
+        audience: PostAudience
+    ) -> DBResult<Vec<Post>> {
         let result = sqlx::query_as!(Post,
-            "SELECT p.id, p.poster_id, p.title, p.body, p.time_stamp,
-                p.edited as `edited: _`,
-                CAST(count(pl.account_id) AS UNSIGNED) AS 'likes'
+            "SELECT p.id, p.poster_id, p.kind as `kind: _`, p.title, p.url, p.alt_text, p.body, p.tldr, p.body_format as `body_format: _`,
+                p.comment_mode as `comment_mode: _`, p.visibility as `visibility: _`, p.time_stamp, p.updated_at, p.expires_at,
+                p.body_edited as `body_edited: _`, p.title_edited as `title_edited: _`,
+                p.like_count AS 'likes'
             FROM Post p
-            LEFT JOIN PostLike pl
-            ON p.id = pl.post_id
             WHERE p.poster_id = ?
-            GROUP BY p.id;", user_id)
+            AND p.id < ?
+            AND p.deleted_at IS NULL
+            AND (p.expires_at IS NULL OR p.expires_at > CURRENT_TIMESTAMP())
+            AND NOT p.held
+            AND (p.visibility = 'public'
+                OR (p.visibility = 'unlisted' AND ?)
+                OR (p.visibility = 'followers' AND ?))
+            ORDER BY p.id DESC
+            LIMIT ?;", user_id, before.unwrap_or(u64::MAX), audience.unlisted, audience.followers, limit)
             .fetch_all(&self.conn_pool)
             .await;
         match result {
@@ -171,14 +296,13 @@ impl Database {
 
     pub async fn read_comments_of_post(&self, post_id: u64) -> DBResult<Vec<Comment>> {
         let result = sqlx::query_as!(Comment,
-            "SELECT c.id, c.post_id, c.commenter_id, c.body, c.comment_reply_id,
-                c.time_stamp, c.edited as `edited: _`,
-                CAST(count(cl.comment_id) AS UNSIGNED) AS 'likes'
+            "SELECT c.id, c.post_id, c.commenter_id, c.body, c.body_format as `body_format: _`, c.comment_reply_id,
+                c.time_stamp, c.updated_at, c.edited as `edited: _`,
+                c.like_count AS 'likes'
             FROM Comment c
-            LEFT JOIN CommentLike cl
-            ON c.id = cl.comment_id
             WHERE c.post_id = ?
-            GROUP BY c.id", post_id)
+            AND NOT c.held
+            ORDER BY c.time_stamp", post_id)
             .fetch_all(&self.conn_pool)
             .await;
 
@@ -189,16 +313,99 @@ impl Database {
         }
     }
 
-    pub async fn read_comments_by_user(&self, user_id: u64) -> DBResult<Vec<Comment>> {
-        let result = sqlx::query_as!(Comment,
-            "SELECT c.id, c.post_id, c.commenter_id, c.body, c.comment_reply_id,
-                c.time_stamp, c.edited as `edited: _`,
-                CAST(count(cl.comment_id) AS UNSIGNED) AS 'likes'
+    pub async fn read_comment_format(&self, comment_id: u64) -> DBResult<TextFormat> {
+        let result = sqlx::query_scalar::<_, TextFormat>(
+            "SELECT body_format
+            FROM Comment
+            WHERE id = ?;")
+            .bind(comment_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(format) => Ok(format),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// The id of the account that made the post.
+    pub async fn read_post_owner(&self, post_id: u64) -> DBResult<u64> {
+        let result = sqlx::query_scalar::<_, u64>(
+            "SELECT poster_id
+            FROM Post
+            WHERE id = ?;")
+            .bind(post_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(poster_id) => Ok(poster_id),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// The id of the account that made the comment.
+    pub async fn read_comment_owner(&self, comment_id: u64) -> DBResult<u64> {
+        let result = sqlx::query_scalar::<_, u64>(
+            "SELECT commenter_id
+            FROM Comment
+            WHERE id = ?;")
+            .bind(comment_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(commenter_id) => Ok(commenter_id),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// The id of the post of the comment, unless the comment is held.
+    pub async fn read_comment_post(&self, comment_id: u64) -> DBResult<u64> {
+        let result = sqlx::query_scalar::<_, u64>(
+            "SELECT post_id
+            FROM Comment
+            WHERE id = ? AND NOT held;")
+            .bind(comment_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(post_id) => Ok(post_id),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads up to `limit` comments of a user along with the title of the post
+    /// commented on, newest first, of the posts listed to `viewer_id` as by
+    /// `read_posts`. Pages continue from the `before` comment id.
+    pub async fn read_comments_by_user(
+        &self,
+        user_id: u64,
+        viewer_id: Option<u64>,
+        limit: u64,
+        before: Option<u64>
+    ) -> DBResult<Vec<UserComment>> {
+        // No account has the id 0
+        let viewer_id = viewer_id.unwrap_or(0);
+        let result = sqlx::query_as!(UserComment,
+            "SELECT c.id, c.post_id, p.title AS 'post_title', c.commenter_id, c.body, c.body_format as `body_format: _`,
+                c.comment_reply_id, c.time_stamp, c.updated_at, c.edited as `edited: _`,
+                c.like_count AS 'likes'
             FROM Comment c
-            LEFT JOIN CommentLike cl
-            ON c.id = cl.comment_id
+            INNER JOIN Post p
+            ON c.post_id = p.id
             WHERE c.commenter_id = ?
-            GROUP BY c.id", user_id)
+            AND c.id < ?
+            AND NOT c.held
+            AND p.deleted_at IS NULL
+            AND (p.expires_at IS NULL OR p.expires_at > CURRENT_TIMESTAMP())
+            AND NOT p.held
+            AND (p.visibility = 'public'
+                OR (p.visibility = 'followers' AND (p.poster_id = ? OR EXISTS (
+                    SELECT 1 FROM Follow f WHERE f.follower_id = ? AND f.followee_id = p.poster_id))))
+            ORDER BY c.id DESC
+            LIMIT ?;", user_id, before.unwrap_or(u64::MAX), viewer_id, viewer_id, limit)
             .fetch_all(&self.conn_pool)
             .await;
 
@@ -224,7 +431,7 @@ impl Database {
 
     pub async fn _read_comment_likes(&self, comment_id: u64) -> DBResult<u64> {
         let result = sqlx::query(
-            "SELECT CAST(count(post_id) AS UNSIGNED)
+            "SELECT CAST(count(comment_id) AS UNSIGNED)
             FROM CommentLike
             WHERE comment_id = ?;")
             .bind(comment_id)
@@ -256,87 +463,149 @@ impl Database {
         }
     }
 
-    pub async fn update_post_body(&self, post_id: u64, new_body: String) -> DBResult<()> {
+    /// Updates the title and/or body of a post, where `None` leaves the field as
+    /// is. The title & body prior to the edit are kept as a revision. With an
+    /// `author_id`, only a post of that author is updated.
+    pub async fn update_post(
+        &self,
+        post_id: u64,
+        author_id: Option<u64>,
+        new_title: Option<&str>,
+        new_body: Option<&str>,
+        new_alt_text: Option<Option<&str>>,
+        new_tldr: Option<Option<&str>>
+    ) -> DBResult<()> {
+        let mut tx = match self.conn_pool.begin().await {
+            Ok(tx) => tx,
+            Err(e) => return Err(log_error(DBError::from(e)))
+        };
+
         let result = sqlx::query(
-            "UPDATE Post
-            SET body = ?, edited = true
-            WHERE id = ?")
-            .bind(new_body)
+            "INSERT INTO PostRevision (post_id, title, body)
+            SELECT id, title, body
+            FROM Post
+            WHERE id = ? AND poster_id = COALESCE(?, poster_id);")
             .bind(post_id)
-            .execute(&self.conn_pool)
+            .bind(author_id)
+            .execute(&mut *tx)
             .await;
-        
+
         match result {
-            Ok(res) => expected_rows_affected(res, 1),
-            Err(err) => Err(log_error(DBError::from(err)))
+            Ok(res) => expected_rows_affected(res, 1)?,
+            Err(err) => return Err(log_error(DBError::from(err)))
         }
-    }
 
-    pub async fn update_comment_body(&self, comment_id: u64, new_body: String) -> DBResult<()> {
         let result = sqlx::query(
-            "UPDATE Comment
-            SET body = ?, edited = true
-            WHERE id = ?")
+            "UPDATE Post
+            SET title = COALESCE(?, title), title_edited = title_edited OR ?,
+                body = COALESCE(?, body), body_edited = body_edited OR ?,
+                alt_text = IF(?, ?, alt_text), tldr = IF(?, ?, tldr)
+            WHERE id = ? AND poster_id = COALESCE(?, poster_id)")
+            .bind(new_title)
+            .bind(new_title.is_some())
             .bind(new_body)
-            .bind(comment_id)
-            .execute(&self.conn_pool)
+            .bind(new_body.is_some())
+            .bind(new_alt_text.is_some())
+            .bind(new_alt_text.flatten())
+            .bind(new_tldr.is_some())
+            .bind(new_tldr.flatten())
+            .bind(post_id)
+            .bind(author_id)
+            .execute(&mut *tx)
             .await;
-        
+
         match result {
-            Ok(res) => expected_rows_affected(res, 1),
+            Ok(res) => expected_rows_affected(res, 1)?,
+            Err(err) => return Err(log_error(DBError::from(err)))
+        }
+
+        match tx.commit().await {
+            Ok(()) => Ok(()),
             Err(err) => Err(log_error(DBError::from(err)))
         }
     }
 
-    // Delete
-
-    pub async fn delete_post(&self, post_id: u64) -> DBResult<()> {
+    /// With an `author_id`, only a post of that author is updated.
+    /// 
+    /// Note: MySQL reports 0 rows affected when the mode is unchanged, so the
+    ///       affected row count is not checked.
+    pub async fn update_comment_mode(&self, post_id: u64, author_id: Option<u64>, mode: CommentMode) -> DBResult<()> {
         let result = sqlx::query(
-            "DELETE FROM Post WHERE id = ?;")
+            "UPDATE Post SET comment_mode = ? WHERE id = ? AND poster_id = COALESCE(?, poster_id);")
+            .bind(mode)
             .bind(post_id)
+            .bind(author_id)
             .execute(&self.conn_pool)
             .await;
+
         match result {
-            Ok(res) => expected_rows_affected(res, 1),
+            Ok(_) => Ok(()),
             Err(e) => Err(log_error(DBError::from(e)))
         }
     }
 
-    #[cfg(test)]
-    pub async fn delete_comment(&self, comment_id: u64) -> DBResult<()> {
-        let result = sqlx::query(
-            "DELETE FROM Comment WHERE id = ?;")
+    /// With an `author_id`, only a comment of that author is updated. Comments keep
+    /// no revisions, so one under a legal hold results in `DBError::LegalHold`.
+    pub async fn update_comment_body(&self, comment_id: u64, author_id: Option<u64>, new_body: String) -> DBResult<()> {
+        let mut tx = self.conn_pool.begin().await.map_err(|e| log_error(DBError::from(e)))?;
+        let held = sqlx::query_scalar::<_, u64>(&format!(
+            "SELECT CAST({} AS UNSIGNED) FROM Comment c WHERE c.id = ? FOR UPDATE;", COMMENT_HELD))
             .bind(comment_id)
-            .execute(&self.conn_pool)
-            .await;
-        match result {
-            Ok(res) => expected_rows_affected(res, 1),
-            Err(e) => Err(log_error(DBError::from(e)))
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        if held == Some(1) {
+            return Err(DBError::LegalHold)
         }
-    }
 
-    pub async fn delete_post_like(&self, post_id: u64, account_id: u64) -> DBResult<()> {
         let result = sqlx::query(
-            "DELETE FROM PostLike
-            WHERE post_id = ?
-            AND account_id = ?;")
+            "UPDATE Comment
+            SET body = ?, edited = true
+            WHERE id = ? AND commenter_id = COALESCE(?, commenter_id)")
+            .bind(new_body)
+            .bind(comment_id)
+            .bind(author_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        expected_rows_affected(result, 1)?;
+        tx.commit().await.map_err(|e| log_error(DBError::from(e)))
+    }
+
+    // Delete
+
+    /// With an `author_id`, only a post of that author is deleted. A post under a
+    /// legal hold, or with a comment under one, results in `DBError::LegalHold`.
+    pub async fn delete_post(&self, post_id: u64, author_id: Option<u64>) -> DBResult<()> {
+        let mut tx = self.conn_pool.begin().await.map_err(|e| log_error(DBError::from(e)))?;
+        let held = sqlx::query_scalar::<_, u64>(&format!(
+            "SELECT CAST({} AS UNSIGNED) FROM Post p WHERE p.id = ? FOR UPDATE;", HELD))
             .bind(post_id)
-            .bind(account_id)
-            .execute(&self.conn_pool)
-            .await;
-        match result {
-            Ok(res) => expected_rows_affected(res, 1),
-            Err(e) => Err(log_error(DBError::from(e)))
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        if held == Some(1) {
+            return Err(DBError::LegalHold)
         }
+
+        let result = sqlx::query(
+            "DELETE FROM Post WHERE id = ? AND poster_id = COALESCE(?, poster_id);")
+            .bind(post_id)
+            .bind(author_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        expected_rows_affected(result, 1)?;
+
+        outbox::record_event(&mut tx, &DomainEvent::PostDeleted { post_id }).await?;
+        tx.commit().await.map_err(|e| log_error(DBError::from(e)))
     }
 
-    pub async fn delete_comment_like(&self, comment_id: u64, account_id: u64) -> DBResult<()> {
+    #[cfg(test)]
+    pub async fn delete_comment(&self, comment_id: u64) -> DBResult<()> {
         let result = sqlx::query(
-            "DELETE FROM CommentLike
-            WHERE comment_id = ?
-            AND account_id = ?;")
+            "DELETE FROM Comment WHERE id = ?;")
             .bind(comment_id)
-            .bind(account_id)
             .execute(&self.conn_pool)
             .await;
         match result {
@@ -378,7 +647,22 @@ impl Database {
     }
 }
 
-fn expected_rows_affected(result: MySqlQueryResult, expected_rows: u64) -> DBResult<()> {
+/// Filters the Post table, aliased as `p`, to the posts that are listed for
+/// `viewer_id`: public ones, and followers-only ones of the viewer or of accounts
+/// it follows.
+pub(super) fn push_listed(query: &mut QueryBuilder<'_, MySql>, viewer_id: Option<u64>) {
+    // No account has the id 0
+    let viewer_id = viewer_id.unwrap_or(0);
+    query.push(" WHERE p.deleted_at IS NULL
+        AND (p.expires_at IS NULL OR p.expires_at > CURRENT_TIMESTAMP())
+        AND NOT p.held
+        AND (p.visibility = 'public'
+            OR (p.visibility = 'followers' AND (p.poster_id = ").push_bind(viewer_id);
+    query.push(" OR EXISTS (SELECT 1 FROM Follow f WHERE f.follower_id = ").push_bind(viewer_id);
+    query.push(" AND f.followee_id = p.poster_id))))");
+}
+
+pub(super) fn expected_rows_affected(result: MySqlQueryResult, expected_rows: u64) -> DBResult<()> {
     if result.rows_affected() == expected_rows {
         Ok(())
     } else {
@@ -388,22 +672,37 @@ fn expected_rows_affected(result: MySqlQueryResult, expected_rows: u64) -> DBRes
     }
 }
 
-fn log_error(err: DBError) -> DBError {
+pub(super) fn log_error(err: DBError) -> DBError {
     warn!("{}", err);
+    super::pool::record_timeout(&err);
     err
 }
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashSet;
     use std::mem::discriminant;
+    use std::sync::Arc;
     use std::mem::Discriminant;
+    use crate::models::AuditAction;
+    use crate::models::AuditQuery;
     use crate::models::Comment;
+    use crate::models::CommentMode;
+    use crate::models::GivenLike;
+    use crate::models::LikeKind;
+    use crate::models::LikeState;
+    use crate::models::Moderation;
     use crate::models::MySqlBool;
     use crate::models::NewComment;
     use crate::models::NewPost;
+    use crate::models::OnboardingStep;
     use crate::models::Post;
+    use crate::models::PostAudience;
+    use crate::models::PostKind;
+    use crate::models::PostVisibility;
+    use crate::models::TextFormat;
 
-    use super::Database;
+    use super::{Database, DEFAULT_MAX_CONNECTIONS};
     use super::DBError;
     use dotenv;
     
@@ -416,7 +715,7 @@ mod test {
     async fn test_context() -> Database {
         dotenv::dotenv().ok();
         let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL is not set");
-        Database::new(&db_url).await
+        Database::new(&db_url, DEFAULT_MAX_CONNECTIONS).await
     }
 
     // The below test(s) require that the MySql database is not empty. At minimum, the
@@ -430,28 +729,36 @@ mod test {
 
         // Create
         let post_invalid_poster_id = NewPost {
-            poster_id: 0,
+            kind: PostKind::Text,
             title: "bad_posted_id".to_string(),
+            url: None,
+            alt_text: None,
             body: "bad_posted_id".to_string(),
+            tldr: None,
+            poll_options: Vec::new(),
+            body_format: TextFormat::Markdown,
+            comment_mode: CommentMode::Open,
+            visibility: PostVisibility::Public,
+            expires_at: None
         };
-        assert_eq!(DB_ERR_SQLX, discriminant(&db.create_post(post_invalid_poster_id).await.unwrap_err()));
+        assert_eq!(DB_ERR_SQLX, discriminant(&db.create_post(0, post_invalid_poster_id, Moderation::default()).await.unwrap_err()));
 
         let comment_on_invalid_post_id = NewComment {
             post_id: 0,  // all ids start from 1
-            commenter_id: 1,
             comment_reply_id: None,
-            body: "".into()
+            body: "".into(),
+            body_format: TextFormat::Markdown
         };
 
-        assert_eq!(DB_ERR_SQLX, discriminant(&db.create_comment(comment_on_invalid_post_id).await.unwrap_err()));
+        assert_eq!(DB_ERR_SQLX, discriminant(&db.create_comment(1, comment_on_invalid_post_id, Moderation::default()).await.unwrap_err()));
 
         let comment_by_invalid_commenter_id = NewComment {
             post_id: 1,
-            commenter_id: 0, // all ids start from 1
             comment_reply_id: None,
-            body: "".into()
+            body: "".into(),
+            body_format: TextFormat::Markdown
         };
-        assert_eq!(DB_ERR_SQLX, discriminant(&db.create_comment(comment_by_invalid_commenter_id).await.unwrap_err()));
+        assert_eq!(DB_ERR_SQLX, discriminant(&db.create_comment(0, comment_by_invalid_commenter_id, Moderation::default()).await.unwrap_err()));
 
         // Invalid post_id
         assert_eq!(DB_ERR_URA, discriminant(&db.create_post_like(0, 1).await.unwrap_err()));
@@ -470,11 +777,11 @@ mod test {
 
         // Update
         assert_eq!(DB_ERR_URA, discriminant(&db.update_account_password(0, "", "").await.unwrap_err()));
-        assert_eq!(DB_ERR_URA, discriminant(&db.update_post_body(0, "".to_string()).await.unwrap_err()));
-        assert_eq!(DB_ERR_URA, discriminant(&db.update_comment_body(0, "".to_string()).await.unwrap_err()));
+        assert_eq!(DB_ERR_URA, discriminant(&db.update_post(0, None, None, Some(""), None, None).await.unwrap_err()));
+        assert_eq!(DB_ERR_URA, discriminant(&db.update_comment_body(0, None, "".to_string()).await.unwrap_err()));
     
         // Delete
-        assert_eq!(DB_ERR_URA, discriminant(&db.delete_post(0).await.unwrap_err()));
+        assert_eq!(DB_ERR_URA, discriminant(&db.delete_post(0, None).await.unwrap_err()));
         assert_eq!(DB_ERR_URA, discriminant(&db.delete_post_like(0, 0).await.unwrap_err()));
         assert_eq!(DB_ERR_URA, discriminant(&db.delete_comment(0).await.unwrap_err()));
         assert_eq!(DB_ERR_URA, discriminant(&db.delete_comment_like(0, 0).await.unwrap_err()));
@@ -496,17 +803,25 @@ mod test {
         assert_eq!(Ok(()), db.delete_post_by_title_and_body(TITLE, SECOND_BODY).await, "failed to setup 2");
         
         // Ensure test post is not present
-        let before_posting = db.read_posts_by_user(POSTER_ID).await.unwrap();
+        let before_posting = db.read_posts_by_user(POSTER_ID, 64, None, PostAudience::ALL).await.unwrap();
         assert_eq!(0, before_posting.iter().filter(|p| predicate(p)).count());
         
         // Create, add, and check that the test post was added
         let new_post = NewPost {
-            poster_id: POSTER_ID,
+            kind: PostKind::Text,
             title: TITLE.to_string(),
-            body: FIRST_BODY.to_string()
+            url: None,
+            alt_text: None,
+            body: FIRST_BODY.to_string(),
+            tldr: None,
+            poll_options: Vec::new(),
+            body_format: TextFormat::Markdown,
+            comment_mode: CommentMode::Open,
+            visibility: PostVisibility::Public,
+            expires_at: None
         };
-        assert_eq!(Ok(()), db.create_post(new_post).await);
-        let after_posting = db.read_posts_by_user(POSTER_ID).await.unwrap();
+        assert!(db.create_post(POSTER_ID, new_post, Moderation::default()).await.is_ok());
+        let after_posting = db.read_posts_by_user(POSTER_ID, 64, None, PostAudience::ALL).await.unwrap();
         assert_eq!(1, after_posting.iter().filter(|p| predicate(p)).count());
         let retrieved_post_before_edit = after_posting.iter().find(|p| predicate(p)).unwrap();
         
@@ -514,25 +829,89 @@ mod test {
         assert_eq!(TITLE, retrieved_post_before_edit.title);
         assert_eq!(FIRST_BODY, retrieved_post_before_edit.body);
         assert_eq!(0, retrieved_post_before_edit.likes);
-        assert_eq!(MySqlBool(false), retrieved_post_before_edit.edited);
+        assert_eq!(MySqlBool(false), retrieved_post_before_edit.body_edited);
+        assert_eq!(MySqlBool(false), retrieved_post_before_edit.title_edited);
 
         let test_post_id = retrieved_post_before_edit.id;
 
+        // Posting completes the first post step of onboarding
+        let steps = db.read_onboarding_steps(POSTER_ID).await.unwrap();
+        assert!(steps.iter().any(|(step, _)| *step == OnboardingStep::FirstPost));
+
+        // Toggling a like flips it, reporting the new state. Karma follows likes received
+        let karma_before = db.read_karma(POSTER_ID).await.unwrap();
+        assert_eq!(Ok(LikeState { liked: true, likes: 1 }), db.toggle_post_like(test_post_id, POSTER_ID).await);
+        assert_eq!(Ok(karma_before + 1), db.read_karma(POSTER_ID).await);
+        assert_eq!(Ok(LikeState { liked: false, likes: 0 }), db.toggle_post_like(test_post_id, POSTER_ID).await);
+        assert_eq!(Ok(karma_before), db.read_karma(POSTER_ID).await);
+        assert_eq!(DB_ERR_NR, discriminant(&db.toggle_post_like(0, POSTER_ID).await.unwrap_err()));
+
+        // Marking a post read is idempotent, and read state is per account
+        assert_eq!(Ok(HashSet::new()), db.read_posts_read(POSTER_ID, &[test_post_id]).await);
+        assert_eq!(Ok(()), db.mark_post_read(test_post_id, POSTER_ID).await);
+        assert_eq!(Ok(()), db.mark_post_read(test_post_id, POSTER_ID).await);
+        assert_eq!(Ok(HashSet::from([test_post_id])), db.read_posts_read(POSTER_ID, &[test_post_id, u64::MAX]).await);
+        assert_eq!(Ok(HashSet::new()), db.read_posts_read(u64::MAX, &[test_post_id]).await);
+        assert_eq!(Ok(HashSet::new()), db.read_posts_read(POSTER_ID, &[]).await);
+
+        // Reactions are toggled per emoji
+        let reacted = db.toggle_post_reaction(test_post_id, POSTER_ID, "🎉").await.unwrap();
+        assert!(reacted.reacted);
+        assert_eq!(Some(&1), reacted.reactions.get("🎉"));
+        let unreacted = db.toggle_post_reaction(test_post_id, POSTER_ID, "🎉").await.unwrap();
+        assert!(!unreacted.reacted);
+        assert!(unreacted.reactions.is_empty());
+        assert_eq!(DB_ERR_NR, discriminant(&db.toggle_post_reaction(0, POSTER_ID, "🎉").await.unwrap_err()));
+
+        // Awards are recorded against the poster
+        let silver = db.read_awards().await.unwrap().into_iter().find(|award| award.name == "Silver").unwrap();
+        assert_eq!(Ok(()), db.create_post_award(test_post_id, POSTER_ID + 1, silver.id).await);
+        let awards = db.read_post_awards(&[test_post_id]).await.unwrap();
+        assert_eq!(Some(&1), awards[&test_post_id].get("Silver"));
+        let received = db.read_awards_received(POSTER_ID, 1, None).await.unwrap();
+        assert_eq!((test_post_id, POSTER_ID + 1), (received[0].post_id, received[0].giver_id));
+        assert!(db.create_post_award(test_post_id, POSTER_ID + 1, 0).await.is_err());
+
+        // Newest first, and pages continue from the `before` id
+        let first_page = db.read_posts_by_user(POSTER_ID, 1, None, PostAudience::ALL).await.unwrap();
+        assert_eq!(vec![test_post_id], first_page.iter().map(|p| p.id).collect::<Vec<u64>>());
+        let next_page = db.read_posts_by_user(POSTER_ID, 64, Some(test_post_id), PostAudience::ALL).await.unwrap();
+        assert!(!next_page.iter().any(|p| p.id >= test_post_id));
+
         // Edit the test post and re-check
-        assert_eq!(Ok(()), db.update_post_body(test_post_id, SECOND_BODY.into()).await);
+        // Only by its author
+        assert_eq!(DB_ERR_URA, discriminant(&db.update_post(test_post_id, Some(0), None, Some(SECOND_BODY), None, None).await.unwrap_err()));
+        assert_eq!(Ok(()), db.update_post(test_post_id, Some(POSTER_ID), None, Some(SECOND_BODY), None, Some(Some("tl;dr"))).await);
         let retrieved_post_after_edit = db.read_post_by_id(test_post_id).await.unwrap();
+        assert_eq!(Some("tl;dr"), retrieved_post_after_edit.tldr.as_deref());
+        assert_eq!(Ok(()), db.update_post(test_post_id, Some(POSTER_ID), None, None, None, Some(None)).await);
+        assert_eq!(None, db.read_post_by_id(test_post_id).await.unwrap().tldr);
 
         assert_eq!(POSTER_ID, retrieved_post_after_edit.poster_id);
         assert_eq!(TITLE, retrieved_post_after_edit.title);
         assert_eq!(SECOND_BODY, retrieved_post_after_edit.body);
         assert_eq!(0, retrieved_post_after_edit.likes);
-        assert_eq!(MySqlBool(true), retrieved_post_after_edit.edited);
+        assert_eq!(MySqlBool(true), retrieved_post_after_edit.body_edited);
+        assert_eq!(MySqlBool(false), retrieved_post_after_edit.title_edited);
+
+        // The pre-edit title & body are kept as a revision
+        let revisions = db.read_post_revisions(test_post_id).await.unwrap();
+        assert_eq!(1, revisions.len());
+        assert_eq!(TITLE, revisions[0].title);
+        assert_eq!(FIRST_BODY, revisions[0].body);
 
         // Delete the test post and check that it cannot be read
-        assert_eq!(Ok(()), db.delete_post(test_post_id).await);
+        assert_eq!(Ok(()), db.delete_post(test_post_id, None).await);
         let after_delete = db.read_post_by_id(test_post_id).await;
         assert_eq!(true, after_delete.is_err());
         assert_eq!(DB_ERR_NR, discriminant(&after_delete.unwrap_err()));
+
+        // Creating and deleting the post were recorded in the outbox
+        let events_of_post: Vec<String> = db.read_pending_events(u64::MAX).await.unwrap().into_iter()
+            .filter(|e| serde_json::from_str::<serde_json::Value>(&e.payload).unwrap()["post_id"] == test_post_id)
+            .map(|e| e.event_type)
+            .collect();
+        assert_eq!(vec!["post_created", "post_deleted"], events_of_post);
     }
 
     #[actix_web::test]
@@ -563,12 +942,12 @@ mod test {
         // Create, add and check first test comment
         let first_comment = NewComment {
             post_id: POST_ID,
-            commenter_id: COMMENTER_ID_ONE,
             comment_reply_id: None,
-            body: FIRST_BODY.to_string()
+            body: FIRST_BODY.to_string(),
+            body_format: TextFormat::Markdown
         };
 
-        assert_eq!(Ok(()), db.create_comment(first_comment).await);
+        let created_comment_one_id = db.create_comment(COMMENTER_ID_ONE, first_comment, Moderation::default()).await.unwrap();
         let after_comment_one = db.read_comments_of_post(POST_ID).await.unwrap();
         assert_eq!(1, after_comment_one.iter().filter(|c| predicate(c)).count());
         let retrieved_comment_one = after_comment_one.iter().find(|c| predicate(c)).unwrap();
@@ -581,9 +960,73 @@ mod test {
         assert_eq!(MySqlBool(false), retrieved_comment_one.edited);
 
         let comment_one_id = retrieved_comment_one.id;
+        assert_eq!(created_comment_one_id, comment_one_id);
+        assert_eq!(Ok(COMMENTER_ID_ONE), db.read_comment_owner(comment_one_id).await);
+        assert_eq!(DB_ERR_NR, discriminant(&db.read_comment_owner(0).await.unwrap_err()));
+
+        // Newest comment of the commenter, with the title of the post
+        let by_commenter = db.read_comments_by_user(COMMENTER_ID_ONE, None, 1, None).await.unwrap();
+        assert_eq!(1, by_commenter.len());
+        assert_eq!(comment_one_id, by_commenter[0].id);
+        assert_eq!(db.read_post_by_id(POST_ID).await.unwrap().title, by_commenter[0].post_title);
+
+        // Counted along with posts without comments
+        let all_comments = db.read_comments_of_post(POST_ID).await.unwrap().len() as u64;
+        let counts = db.read_comment_counts(&[POST_ID, u64::MAX]).await.unwrap();
+        assert_eq!(Some(&all_comments), counts.get(&POST_ID));
+        assert_eq!(Some(&0), counts.get(&u64::MAX));
+        assert!(db.read_comment_counts(&[]).await.unwrap().is_empty());
+
+        // Reactions to comments are toggled per emoji, apart from those to posts
+        let reacted = db.toggle_comment_reaction(comment_one_id, COMMENTER_ID_TWO, "🎉").await.unwrap();
+        assert!(reacted.reacted);
+        assert_eq!(Some(&1), reacted.reactions.get("🎉"));
+        assert_eq!(None, db.read_post_reactions(&[POST_ID]).await.unwrap()[&POST_ID].get("🎉"));
+        let reactions = db.read_comment_reactions(&[comment_one_id, u64::MAX]).await.unwrap();
+        assert_eq!(Some(&1), reactions[&comment_one_id].get("🎉"));
+        assert!(reactions[&u64::MAX].is_empty());
+        assert!(!db.toggle_comment_reaction(comment_one_id, COMMENTER_ID_TWO, "🎉").await.unwrap().reacted);
+        assert_eq!(DB_ERR_NR, discriminant(&db.toggle_comment_reaction(0, COMMENTER_ID_TWO, "🎉").await.unwrap_err()));
+
+        // Awards to comments are recorded against the commenter, apart from those to posts
+        let silver = db.read_awards().await.unwrap().into_iter().find(|award| award.name == "Silver").unwrap();
+        let post_awards = db.read_post_awards(&[POST_ID]).await.unwrap();
+        assert_eq!(Ok(()), db.create_comment_award(comment_one_id, COMMENTER_ID_TWO, silver.id).await);
+        assert_eq!(post_awards, db.read_post_awards(&[POST_ID]).await.unwrap());
+        let awards = db.read_comment_awards(&[comment_one_id, u64::MAX]).await.unwrap();
+        assert_eq!(Some(&1), awards[&comment_one_id].get("Silver"));
+        assert!(awards[&u64::MAX].is_empty());
+        let received = db.read_awards_received(COMMENTER_ID_ONE, 1, None).await.unwrap();
+        assert_eq!((POST_ID, Some(comment_one_id)), (received[0].post_id, received[0].comment_id));
+        assert!(db.create_comment_award(comment_one_id, COMMENTER_ID_TWO, 0).await.is_err());
+        assert!(db.create_comment_award(0, COMMENTER_ID_TWO, silver.id).await.is_err());
+
+        // Following is one way, and idempotent
+        assert_eq!(Ok(()), db.unfollow(COMMENTER_ID_ONE, COMMENTER_ID_TWO).await);
+        assert_eq!(Ok(false), db.is_following(COMMENTER_ID_ONE, COMMENTER_ID_TWO).await);
+        assert_eq!(Ok(()), db.follow(COMMENTER_ID_ONE, COMMENTER_ID_TWO).await);
+        assert_eq!(Ok(()), db.follow(COMMENTER_ID_ONE, COMMENTER_ID_TWO).await);
+        assert_eq!(Ok(true), db.is_following(COMMENTER_ID_ONE, COMMENTER_ID_TWO).await);
+        assert_eq!(Ok(false), db.is_following(COMMENTER_ID_TWO, COMMENTER_ID_ONE).await);
+        assert_eq!(Ok(()), db.unfollow(COMMENTER_ID_ONE, COMMENTER_ID_TWO).await);
+        assert_eq!(Ok(false), db.is_following(COMMENTER_ID_ONE, COMMENTER_ID_TWO).await);
+
+        // Pinned to the thread of its post, until unpinned
+        let poster_id = db.read_post_by_id(POST_ID).await.unwrap().poster_id;
+        assert_eq!(Ok((POST_ID, poster_id)), db.read_comment_thread(comment_one_id).await);
+        let pinned_before = db.read_pinned_comment(POST_ID).await.unwrap();
+        assert_eq!(Ok(()), db.pin_comment(POST_ID, comment_one_id).await);
+        assert_eq!(Ok(Some(comment_one_id)), db.read_pinned_comment(POST_ID).await);
+        assert_eq!(Ok(()), db.unpin_comment(POST_ID, comment_one_id).await);
+        assert_eq!(DB_ERR_URA, discriminant(&db.unpin_comment(POST_ID, comment_one_id).await.unwrap_err()));
+        assert_eq!(Ok(None), db.read_pinned_comment(u64::MAX).await);
+        if let Some(pinned_id) = pinned_before {
+            assert_eq!(Ok(()), db.pin_comment(POST_ID, pinned_id).await);
+        }
 
         // Update/edit first test comment and check
-        assert_eq!(Ok(()), db.update_comment_body(comment_one_id, SECOND_BODY.into()).await);
+        assert_eq!(DB_ERR_URA, discriminant(&db.update_comment_body(comment_one_id, Some(COMMENTER_ID_TWO), SECOND_BODY.into()).await.unwrap_err()));
+        assert_eq!(Ok(()), db.update_comment_body(comment_one_id, Some(COMMENTER_ID_ONE), SECOND_BODY.into()).await);
         let after_comment_one_edit = db.read_comments_of_post(POST_ID).await.unwrap();
         assert_eq!(1, after_comment_one.iter().filter(|c| predicate(c)).count());
         let retrieved_comment_one_edited = after_comment_one_edit.iter().find(|c| predicate(c)).unwrap();
@@ -598,12 +1041,12 @@ mod test {
         // Create, add, and check second test comment
         let comment_two = NewComment {
             post_id: POST_ID,
-            commenter_id: COMMENTER_ID_TWO,
             comment_reply_id: Some(comment_one_id),
-            body: FIRST_BODY.to_string()
+            body: FIRST_BODY.to_string(),
+            body_format: TextFormat::Markdown
         };
 
-        assert_eq!(Ok(()), db.create_comment(comment_two).await);
+        let created_comment_two_id = db.create_comment(COMMENTER_ID_TWO, comment_two, Moderation::default()).await.unwrap();
         let after_comment_two = db.read_comments_of_post(POST_ID).await.unwrap();
         assert_eq!(2, after_comment_two.iter().filter(|c| predicate(c)).count());
         assert_eq!(1, after_comment_two
@@ -624,9 +1067,10 @@ mod test {
         assert_eq!(MySqlBool(false), retrieved_comment_two.edited);
 
         let comment_two_id = retrieved_comment_two.id;
+        assert_eq!(created_comment_two_id, comment_two_id);
 
         // set first test comment as "[DELETED]", where second test comment is a reply to it
-        assert_eq!(Ok(()), db.update_comment_body(comment_one_id, "[DELETED]".to_string()).await);
+        assert_eq!(Ok(()), db.update_comment_body(comment_one_id, None, "[DELETED]".to_string()).await);
         let comments_after_delete = db.read_comments_of_post(POST_ID).await.unwrap();
         let comment_one_deleted = comments_after_delete
             .iter()
@@ -651,4 +1095,387 @@ mod test {
         );
     }
 
+    #[actix_web::test]
+    async fn test_legal_hold_blocks_deletion() {
+        const POSTER_ID: u64 = 1;
+        const MODERATOR_ID: u64 = 2;
+        const TITLE: &str = "#@!test_legal_hold_blocks_deletion";
+        const BODY: &str = "held test post body";
+
+        let db: Database = test_context().await;
+        assert_eq!(Ok(()), db.delete_post_by_title_and_body(TITLE, BODY).await);
+
+        let new_post = NewPost {
+            kind: PostKind::Text,
+            title: TITLE.to_string(),
+            url: None,
+            alt_text: None,
+            body: BODY.to_string(),
+            tldr: None,
+            poll_options: Vec::new(),
+            body_format: TextFormat::Markdown,
+            comment_mode: CommentMode::Open,
+            visibility: PostVisibility::Public,
+            expires_at: None
+        };
+        let post_id = db.create_post(POSTER_ID, new_post, Moderation::default()).await.unwrap();
+        let comment = NewComment {
+            post_id,
+            comment_reply_id: None,
+            body: BODY.to_string(),
+            body_format: TextFormat::Markdown
+        };
+        let comment_id = db.create_comment(POSTER_ID, comment, Moderation::default()).await.unwrap();
+
+        // A hold on the comment keeps both it and its post
+        let hold_id = db.create_legal_hold(None, Some(comment_id), MODERATOR_ID, "test").await.unwrap();
+        assert_eq!(Err(DBError::LegalHold), db.update_comment_body(comment_id, None, "[DELETED]".to_string()).await);
+        assert_eq!(Err(DBError::LegalHold), db.delete_post(post_id, None).await);
+        assert_eq!(BODY, db.read_comments_of_post(post_id).await.unwrap()[0].body);
+        assert_eq!(post_id, db.read_post_by_id(post_id).await.unwrap().id);
+
+        // Both can be deleted once it is released
+        assert_eq!(Ok(()), db.release_legal_hold(hold_id, MODERATOR_ID).await);
+        assert_eq!(Ok(()), db.update_comment_body(comment_id, None, "[DELETED]".to_string()).await);
+        assert_eq!(Ok(()), db.delete_post(post_id, None).await);
+    }
+
+    #[actix_web::test]
+    async fn test_audit_log() {
+        // No account needs to exist, the entries of an actor outlive it
+        const ACTOR_ID: u64 = 1_000_001_514;
+        const DETAIL: &str = "#@!test_audit_log";
+
+        let db: Database = test_context().await;
+        let started_at = db.read_current_time().await.unwrap() - chrono::Duration::minutes(1);
+        db.record_audit(ACTOR_ID, AuditAction::Register, Some(ACTOR_ID), None).await.unwrap();
+        db.record_audit(ACTOR_ID, AuditAction::PostDelete, Some(1), Some(DETAIL)).await.unwrap();
+        db.record_audit(ACTOR_ID, AuditAction::ConfigReload, None, None).await.unwrap();
+
+        let query = |action, from, to, before| AuditQuery {
+            actor_id: Some(ACTOR_ID), action, from, to, limit: None, before
+        };
+
+        // Newest first, by the actor
+        let entries = db.read_audit_log(&query(None, None, None, None), 3).await.unwrap();
+        let actions: Vec<AuditAction> = entries.iter().map(|entry| entry.action).collect();
+        assert_eq!(vec![AuditAction::ConfigReload, AuditAction::PostDelete, AuditAction::Register], actions);
+        assert!(entries.iter().all(|entry| entry.actor_id == ACTOR_ID));
+        assert_eq!(Some(DETAIL), entries[1].detail.as_deref());
+        assert_eq!(None, entries[0].target_id);
+
+        // By action
+        let deletes = db.read_audit_log(&query(Some(AuditAction::PostDelete), None, None, None), 1).await.unwrap();
+        assert_eq!(entries[1].id, deletes[0].id);
+
+        // Continuing before the last entry of a page
+        let next = db.read_audit_log(&query(None, None, None, Some(entries[0].id)), 2).await.unwrap();
+        assert_eq!(vec![entries[1].id, entries[2].id], next.iter().map(|entry| entry.id).collect::<Vec<u64>>());
+
+        // `from` is inclusive and `to` exclusive
+        let recent = db.read_audit_log(&query(None, Some(started_at), None, None), 3).await.unwrap();
+        assert_eq!(3, recent.len());
+        let none = db.read_audit_log(&query(None, Some(started_at), Some(started_at), None), 3).await.unwrap();
+        assert!(none.is_empty());
+        let earlier = db.read_audit_log(&query(None, None, Some(started_at), None), 3).await.unwrap();
+        assert!(earlier.iter().all(|entry| entry.created_at < started_at));
+    }
+
+    #[actix_web::test]
+    async fn test_expired_posts() {
+        const POSTER_ID: u64 = 1;
+        const TITLE: &str = "#@!test_expired_posts";
+        const BODY: &str = "expired test post body";
+
+        let db: Database = test_context().await;
+        assert_eq!(Ok(()), db.delete_post_by_title_and_body(TITLE, BODY).await);
+
+        let new_post = NewPost {
+            kind: PostKind::Text,
+            title: TITLE.to_string(),
+            url: None,
+            alt_text: None,
+            body: BODY.to_string(),
+            tldr: None,
+            poll_options: Vec::new(),
+            body_format: TextFormat::Markdown,
+            comment_mode: CommentMode::Open,
+            visibility: PostVisibility::Public,
+            expires_at: Some(chrono::Utc::now() - chrono::Duration::hours(1))
+        };
+        let post_id = db.create_post(POSTER_ID, new_post, Moderation::default()).await.unwrap();
+        assert_eq!(DB_ERR_NR, discriminant(&db.read_post_by_id(post_id).await.unwrap_err()));
+        assert_eq!(Ok(true), db.read_post_expired(post_id).await);
+        assert_eq!(Ok(false), db.read_post_expired(1).await);
+        assert_eq!(Ok(false), db.read_post_expired(u64::MAX).await);
+
+        // Still known to have expired once the expiry job has soft-deleted it
+        assert!(db.soft_delete_expired_posts().await.unwrap() >= 1);
+        assert_eq!(DB_ERR_NR, discriminant(&db.read_post_by_id(post_id).await.unwrap_err()));
+        assert_eq!(Ok(true), db.read_post_expired(post_id).await);
+
+        assert_eq!(Ok(()), db.delete_post_by_title_and_body(TITLE, BODY).await);
+    }
+
+    #[actix_web::test]
+    async fn test_polls() {
+        const POSTER_ID: u64 = 1;
+        const VOTER_ID: u64 = 2;
+        const TITLE: &str = "#@!test_polls";
+        const BODY: &str = "";
+
+        let db: Database = test_context().await;
+        assert_eq!(Ok(()), db.delete_post_by_title_and_body(TITLE, BODY).await);
+
+        let new_post = NewPost {
+            kind: PostKind::Poll,
+            title: TITLE.to_string(),
+            url: None,
+            alt_text: None,
+            body: BODY.to_string(),
+            tldr: None,
+            poll_options: vec!["Tabs".to_string(), "Spaces".to_string()],
+            body_format: TextFormat::Markdown,
+            comment_mode: CommentMode::Open,
+            visibility: PostVisibility::Public,
+            expires_at: None
+        };
+        let post_id = db.create_post(POSTER_ID, new_post, Moderation::default()).await.unwrap();
+        assert_eq!(PostKind::Poll, db.read_post_by_id(post_id).await.unwrap().kind);
+
+        // Options keep their order, and start without votes
+        let options = db.read_poll_options(post_id).await.unwrap();
+        assert_eq!(vec![("Tabs", 0), ("Spaces", 0)], options.iter().map(|o| (o.label.as_str(), o.votes)).collect::<Vec<_>>());
+        assert_eq!(Ok(None), db.read_poll_vote(post_id, VOTER_ID).await);
+        assert!(db.read_poll_options(1).await.unwrap().is_empty());
+
+        // One vote per account, which can be changed
+        let (tabs, spaces) = (options[0].id, options[1].id);
+        assert_eq!(Ok(()), db.vote_in_poll(post_id, tabs, VOTER_ID).await);
+        assert_eq!(Ok(()), db.vote_in_poll(post_id, spaces, VOTER_ID).await);
+        assert_eq!(Ok(Some(spaces)), db.read_poll_vote(post_id, VOTER_ID).await);
+        let votes = db.read_poll_options(post_id).await.unwrap().iter().map(|o| o.votes).collect::<Vec<u64>>();
+        assert_eq!(vec![0, 1], votes);
+
+        // Only for the options of the poll
+        assert_eq!(DB_ERR_NR, discriminant(&db.vote_in_poll(1, tabs, VOTER_ID).await.unwrap_err()));
+        assert_eq!(DB_ERR_NR, discriminant(&db.vote_in_poll(post_id, 0, VOTER_ID).await.unwrap_err()));
+
+        assert_eq!(Ok(()), db.delete_post_by_title_and_body(TITLE, BODY).await);
+    }
+
+    #[actix_web::test]
+    async fn test_thread_pages() {
+        const POSTER_ID: u64 = 1;
+        const TITLE: &str = "#@!test_thread_pages";
+        const BODY: &str = "thread test post body";
+
+        let db: Database = test_context().await;
+        assert_eq!(Ok(()), db.delete_post_by_title_and_body(TITLE, BODY).await);
+
+        let new_post = NewPost {
+            kind: PostKind::Text,
+            title: TITLE.to_string(),
+            url: None,
+            alt_text: None,
+            body: BODY.to_string(),
+            tldr: None,
+            poll_options: Vec::new(),
+            body_format: TextFormat::Markdown,
+            comment_mode: CommentMode::Open,
+            visibility: PostVisibility::Public,
+            expires_at: None
+        };
+        let post_id = db.create_post(POSTER_ID, new_post, Moderation::default()).await.unwrap();
+        let comment = |comment_reply_id| NewComment {
+            post_id,
+            comment_reply_id,
+            body: BODY.to_string(),
+            body_format: TextFormat::Markdown
+        };
+        let first_id = db.create_comment(POSTER_ID, comment(None), Moderation::default()).await.unwrap();
+        let second_id = db.create_comment(POSTER_ID, comment(None), Moderation::default()).await.unwrap();
+        let mut reply_ids = Vec::new();
+        for _ in 0..3 {
+            reply_ids.push(db.create_comment(POSTER_ID, comment(Some(first_id)), Moderation::default()).await.unwrap());
+        }
+        let ids = |comments: Vec<Comment>| comments.iter().map(|comment| comment.id).collect::<Vec<u64>>();
+
+        // Top-level pages continue from the last id, and can leave out the pinned comment
+        assert_eq!(vec![first_id], ids(db.read_thread_comments(post_id, true, 1, None, None).await.unwrap()));
+        assert_eq!(vec![second_id], ids(db.read_thread_comments(post_id, true, 1, Some(first_id), None).await.unwrap()));
+        assert_eq!(vec![second_id], ids(db.read_thread_comments(post_id, true, 5, None, Some(first_id)).await.unwrap()));
+        assert_eq!(5, db.read_thread_comments(post_id, false, 10, None, None).await.unwrap().len());
+        assert!(db.read_thread_comment(post_id, reply_ids[0], true).await.unwrap().is_none());
+        assert!(db.read_thread_comment(post_id, reply_ids[0], false).await.unwrap().is_some());
+
+        // Replies are read up to the limit per comment, and counted in full
+        assert_eq!(reply_ids[..2], ids(db.read_replies(&[first_id, second_id], 2).await.unwrap()));
+        let reply_counts = db.read_reply_counts(&[first_id, second_id]).await.unwrap();
+        assert_eq!((Some(&3), Some(&0)), (reply_counts.get(&first_id), reply_counts.get(&second_id)));
+
+        // Comments hold on to their post, and replies to their comment
+        for comment_id in reply_ids.into_iter().chain([first_id, second_id]) {
+            assert_eq!(Ok(()), db.delete_comment(comment_id).await);
+        }
+        assert_eq!(Ok(()), db.delete_post(post_id, None).await);
+    }
+
+    #[actix_web::test]
+    async fn test_feed_pages() {
+        const POSTER_ID: u64 = 1;
+        const READER_ID: u64 = 3;
+        const TITLE: &str = "#@!test_feed_pages";
+        const BODY: &str = "feed test post body";
+        // Ahead of every projected score, which are the unix times of past events
+        const SCORE: i64 = 4_000_000_000;
+
+        let db: Database = test_context().await;
+        assert_eq!(Ok(()), db.delete_post_by_title_and_body(TITLE, BODY).await);
+
+        let new_post = || NewPost {
+            kind: PostKind::Text,
+            title: TITLE.to_string(),
+            url: None,
+            alt_text: None,
+            body: BODY.to_string(),
+            tldr: None,
+            poll_options: Vec::new(),
+            body_format: TextFormat::Markdown,
+            comment_mode: CommentMode::Open,
+            visibility: PostVisibility::Public,
+            expires_at: None
+        };
+        let mut post_ids = Vec::new();
+        for _ in 0..3 {
+            post_ids.push(db.create_post(POSTER_ID, new_post(), Moderation::default()).await.unwrap());
+        }
+        // The oldest post scores highest, and the other two tie
+        for (post_id, score) in post_ids.iter().zip([SCORE + 1, SCORE, SCORE]) {
+            sqlx::query("INSERT INTO Feed (user_id, post_id, score, reason) VALUES (?, ?, ?, 'followed');")
+                .bind(READER_ID)
+                .bind(post_id)
+                .bind(score)
+                .execute(&db.conn_pool)
+                .await
+                .unwrap();
+        }
+        let ids = |posts: Vec<Post>| posts.iter().map(|post| post.id).collect::<Vec<u64>>();
+        let (first, second, third) = (post_ids[0], post_ids[1], post_ids[2]);
+
+        // Highest score first, then newest post first, and pages continue through a tie
+        assert_eq!(vec![first, third], ids(db.read_feed(READER_ID, 2, None).await.unwrap()));
+        assert_eq!(vec![second], ids(db.read_feed(READER_ID, 1, Some(third)).await.unwrap()));
+        assert_eq!(vec![third, second], ids(db.read_feed(READER_ID, 2, Some(first)).await.unwrap()));
+
+        // Deleted posts leave the feed and its count
+        let count = db.count_feed(READER_ID).await.unwrap();
+        assert_eq!(Ok(()), db.delete_post(first, None).await);
+        assert_eq!(vec![third, second], ids(db.read_feed(READER_ID, 2, None).await.unwrap()));
+        assert_eq!(Ok(count - 1), db.count_feed(READER_ID).await);
+
+        assert_eq!(Ok(()), db.delete_post_by_title_and_body(TITLE, BODY).await);
+    }
+
+    #[actix_web::test]
+    async fn test_likes_given_pages() {
+        const POSTER_ID: u64 = 1;
+        const LIKER_ID: u64 = 3;
+        const TITLE: &str = "#@!test_likes_given_pages";
+        const BODY: &str = "likes given test post body";
+
+        let db: Database = test_context().await;
+        assert_eq!(Ok(()), db.delete_post_by_title_and_body(TITLE, BODY).await);
+        let posts_liked = db.count_likes_given(LIKER_ID, LikeKind::Post).await.unwrap();
+
+        let new_post = || NewPost {
+            kind: PostKind::Text,
+            title: TITLE.to_string(),
+            url: None,
+            alt_text: None,
+            body: BODY.to_string(),
+            tldr: None,
+            poll_options: Vec::new(),
+            body_format: TextFormat::Markdown,
+            comment_mode: CommentMode::Open,
+            visibility: PostVisibility::Public,
+            expires_at: None
+        };
+        let mut post_ids = Vec::new();
+        for _ in 0..3 {
+            let post_id = db.create_post(POSTER_ID, new_post(), Moderation::default()).await.unwrap();
+            db.create_post_like(post_id, LIKER_ID).await.unwrap();
+            post_ids.push(post_id);
+        }
+        let ids = |likes: Vec<GivenLike>| likes.iter().map(|like| like.id).collect::<Vec<u64>>();
+
+        // Newest first, and pages continue from the `before` id
+        assert_eq!(vec![post_ids[2], post_ids[1]], ids(db.read_likes_given(LIKER_ID, LikeKind::Post, 2, None).await.unwrap()));
+        let next_page = ids(db.read_likes_given(LIKER_ID, LikeKind::Post, 2, Some(post_ids[1])).await.unwrap());
+        assert_eq!(Some(&post_ids[0]), next_page.first());
+        assert!(next_page.iter().all(|id| *id < post_ids[1]));
+        assert_eq!(Ok(posts_liked + 3), db.count_likes_given(LIKER_ID, LikeKind::Post).await);
+
+        // Likes of comments are listed apart from those of posts
+        let comment = NewComment {
+            post_id: post_ids[0],
+            comment_reply_id: None,
+            body: BODY.to_string(),
+            body_format: TextFormat::Markdown
+        };
+        let comment_id = db.create_comment(POSTER_ID, comment, Moderation::default()).await.unwrap();
+        db.create_comment_like(comment_id, LIKER_ID).await.unwrap();
+        assert_eq!(vec![comment_id], ids(db.read_likes_given(LIKER_ID, LikeKind::Comment, 1, None).await.unwrap()));
+        assert_eq!(vec![post_ids[2]], ids(db.read_likes_given(LIKER_ID, LikeKind::Post, 1, None).await.unwrap()));
+
+        // Likes taken back leave the listing
+        db.delete_comment_like(comment_id, LIKER_ID).await.unwrap();
+        assert_ne!(vec![comment_id], ids(db.read_likes_given(LIKER_ID, LikeKind::Comment, 1, None).await.unwrap()));
+        for post_id in &post_ids {
+            db.delete_post_like(*post_id, LIKER_ID).await.unwrap();
+        }
+        assert_eq!(Ok(posts_liked), db.count_likes_given(LIKER_ID, LikeKind::Post).await);
+
+        assert_eq!(Ok(()), db.delete_comment(comment_id).await);
+        assert_eq!(Ok(()), db.delete_post_by_title_and_body(TITLE, BODY).await);
+    }
+
+    #[actix_web::test]
+    async fn test_concurrent_likes() {
+        const POST_ID: u64 = 2;
+        const ACCOUNT_IDS: [u64; 3] = [1, 2, 3];
+        const TOGGLES_PER_ACCOUNT: usize = 8;
+
+        let db = Arc::new(test_context().await);
+        let likes_before = db.read_post_by_id(POST_ID).await.unwrap().likes;
+
+        // Interleave likes, unlikes, and toggles of the same post by several accounts
+        let handles = (0..TOGGLES_PER_ACCOUNT).flat_map(|i| ACCOUNT_IDS.map(|account_id| (i, account_id)))
+            .map(|(i, account_id)| {
+                let db = db.clone();
+                actix_web::rt::spawn(async move {
+                    match i % 4 {
+                        0 => { let _ = db.create_post_like(POST_ID, account_id).await; },
+                        1 => { let _ = db.delete_post_like(POST_ID, account_id).await; },
+                        _ => { db.toggle_post_like(POST_ID, account_id).await.unwrap(); }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        // The like count matches the like rows, whatever order the above ran in
+        let likes_after = db.read_post_by_id(POST_ID).await.unwrap().likes;
+        assert_eq!(db._read_post_likes(POST_ID).await.unwrap(), likes_after);
+
+        // Restore the devtest likes of the post
+        for account_id in ACCOUNT_IDS {
+            let _ = db.delete_post_like(POST_ID, account_id).await;
+        }
+        let restored = db.create_post_like(POST_ID, 1).await.unwrap();
+        assert_eq!(LikeState { liked: true, likes: likes_before }, restored);
+        assert_eq!(likes_before, db.read_post_by_id(POST_ID).await.unwrap().likes);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,33 @@
+use crate::models::Post;
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Reads up to `limit` of the newest public posts that `viewer_id` neither made
+    /// nor has read, to sample discoveries from.
+    pub async fn read_discover_candidates(&self, viewer_id: Option<u64>, limit: u64) -> DBResult<Vec<Post>> {
+        let viewer_id = viewer_id.unwrap_or(0);
+        let result = sqlx::query_as!(Post,
+            "SELECT p.id, p.poster_id, p.kind as `kind: _`, p.title, p.url, p.alt_text, p.body, p.tldr, p.body_format as `body_format: _`,
+                p.comment_mode as `comment_mode: _`, p.visibility as `visibility: _`, p.time_stamp, p.updated_at, p.expires_at,
+                p.body_edited as `body_edited: _`, p.title_edited as `title_edited: _`,
+                p.like_count AS 'likes'
+            FROM Post p
+            WHERE p.deleted_at IS NULL
+            AND (p.expires_at IS NULL OR p.expires_at > CURRENT_TIMESTAMP())
+            AND NOT p.held
+            AND p.visibility = 'public'
+            AND p.poster_id <> ?
+            AND NOT EXISTS (SELECT 1 FROM PostRead r WHERE r.account_id = ? AND r.post_id = p.id)
+            ORDER BY p.id DESC
+            LIMIT ?;", viewer_id, viewer_id, limit)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(posts) => Ok(posts),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
@@ -2,7 +2,9 @@
 pub enum DBError {
     SQLXError(sqlx::Error),
     UnexpectedRowsAffected { expected: u64, actual: u64 },
-    NoResult
+    NoResult,
+    /// The content is under a legal hold, which it must outlast unchanged
+    LegalHold
 }
 
 impl From<sqlx::Error> for DBError {
@@ -33,7 +35,8 @@ impl std::fmt::Display for DBError {
             DBError::UnexpectedRowsAffected{ expected, actual } => {
                 format!("Expected '{}' rows to change, saw '{}'", expected, actual)
             },
-            DBError::NoResult => "A query resulted in no rows being returned".to_string()
+            DBError::NoResult => "A query resulted in no rows being returned".to_string(),
+            DBError::LegalHold => "The content is under a legal hold".to_string()
         };
         write!(f, "{}", output)
     }
@@ -0,0 +1,39 @@
+use std::collections::BTreeMap;
+
+use sqlx::{MySql, QueryBuilder};
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Records that `subject` was served each of the assigned variants. Only the
+    /// first exposure to an experiment is kept.
+    pub async fn log_exposures(
+        &self,
+        subject: &str,
+        account_id: Option<u64>,
+        assignments: &BTreeMap<&str, &str>
+    ) -> DBResult<()> {
+        if assignments.is_empty() {
+            return Ok(())
+        }
+
+        let mut query = QueryBuilder::<MySql>::new(
+            "INSERT IGNORE INTO ExperimentExposure (experiment, subject, variant, account_id) ");
+        query.push_values(assignments, |mut row, (experiment, variant)| {
+            row.push_bind(*experiment)
+                .push_bind(subject)
+                .push_bind(*variant)
+                .push_bind(account_id);
+        });
+
+        let result = query.build()
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
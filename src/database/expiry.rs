@@ -0,0 +1,38 @@
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Soft-deletes the posts that have passed their `expires_at`.
+    /// 
+    /// Returns the number of posts deleted.
+    pub async fn soft_delete_expired_posts(&self) -> DBResult<u64> {
+        let result = sqlx::query(
+            "UPDATE Post SET deleted_at = CURRENT_TIMESTAMP()
+            WHERE deleted_at IS NULL
+            AND expires_at <= CURRENT_TIMESTAMP();")
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => Ok(res.rows_affected()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Whether the post has passed its `expires_at`, whether or not it has been
+    /// soft-deleted since. False if there is no such post.
+    pub async fn read_post_expired(&self, post_id: u64) -> DBResult<bool> {
+        let result = sqlx::query_scalar::<_, u64>(
+            "SELECT CAST(expires_at <= CURRENT_TIMESTAMP() AS UNSIGNED)
+            FROM Post
+            WHERE id = ? AND expires_at IS NOT NULL;")
+            .bind(post_id)
+            .fetch_optional(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(expired) => Ok(expired == Some(1)),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
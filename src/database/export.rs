@@ -0,0 +1,52 @@
+use crate::models::ExportCategory;
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Counts what an export of `account_id` would hold in each category, with the
+    /// bytes of text (titles, bodies, urls, emoji) in it.
+    pub async fn read_export_counts(&self, account_id: u64) -> DBResult<Vec<(ExportCategory, u64, u64)>> {
+        let result = sqlx::query_as::<_, (ExportCategory, u64, u64)>(
+            "SELECT 'posts', CAST(count(*) AS UNSIGNED),
+                CAST(COALESCE(SUM(LENGTH(title) + LENGTH(body) + COALESCE(LENGTH(url), 0)), 0) AS UNSIGNED)
+            FROM Post WHERE poster_id = ?
+            UNION ALL
+            SELECT 'revisions', CAST(count(*) AS UNSIGNED),
+                CAST(COALESCE(SUM(LENGTH(r.title) + LENGTH(r.body)), 0) AS UNSIGNED)
+            FROM PostRevision r INNER JOIN Post p ON r.post_id = p.id WHERE p.poster_id = ?
+            UNION ALL
+            SELECT 'comments', CAST(count(*) AS UNSIGNED), CAST(COALESCE(SUM(LENGTH(body)), 0) AS UNSIGNED)
+            FROM Comment WHERE commenter_id = ?
+            UNION ALL
+            SELECT 'likes', CAST(
+                (SELECT count(*) FROM PostLike WHERE account_id = ?)
+                + (SELECT count(*) FROM CommentLike WHERE account_id = ?)
+            AS UNSIGNED), CAST(0 AS UNSIGNED)
+            UNION ALL
+            SELECT 'reactions', CAST(count(*) AS UNSIGNED), CAST(COALESCE(SUM(LENGTH(emoji)), 0) AS UNSIGNED)
+            FROM Reaction WHERE account_id = ?
+            UNION ALL
+            SELECT 'awards', CAST(count(*) AS UNSIGNED), CAST(0 AS UNSIGNED)
+            FROM AwardGiven WHERE giver_id = ? OR receiver_id = ?
+            UNION ALL
+            SELECT 'follows', CAST(count(*) AS UNSIGNED), CAST(0 AS UNSIGNED)
+            FROM Follow WHERE follower_id = ?;")
+            .bind(account_id)
+            .bind(account_id)
+            .bind(account_id)
+            .bind(account_id)
+            .bind(account_id)
+            .bind(account_id)
+            .bind(account_id)
+            .bind(account_id)
+            .bind(account_id)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(counts) => Ok(counts),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
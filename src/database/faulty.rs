@@ -0,0 +1,97 @@
+use std::io;
+
+use futures_util::future::{self, BoxFuture};
+use futures_util::stream::{self, BoxStream, StreamExt};
+use sqlx::{Describe, Either, Execute, Executor, MySql, Transaction};
+use sqlx::mysql::{MySqlQueryResult, MySqlRow, MySqlStatement, MySqlTypeInfo};
+
+use crate::chaos::{self, Fault};
+
+use super::pool::TimedPool;
+
+/// The connection pool, with `chaos::DATABASE` faults injected into each query and
+/// transaction. Used in place of `TimedPool` when built with the `chaos` feature.
+#[derive(Debug)]
+pub struct FaultyPool {
+    pool: TimedPool
+}
+
+impl FaultyPool {
+    pub fn new(pool: TimedPool) -> FaultyPool {
+        FaultyPool { pool }
+    }
+
+    pub fn inner(&self) -> &TimedPool {
+        &self.pool
+    }
+
+    pub async fn begin(&self) -> Result<Transaction<'static, MySql>, sqlx::Error> {
+        chaos::DATABASE.inject().await.map_err(injected)?;
+        self.pool.begin().await
+    }
+}
+
+/// Surfaces as an I/O error, as a dropped connection would.
+fn injected(fault: Fault) -> sqlx::Error {
+    sqlx::Error::Io(io::Error::other(fault))
+}
+
+impl<'p> Executor<'p> for &'_ FaultyPool {
+    type Database = MySql;
+
+    fn fetch_many<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxStream<'e, Result<Either<MySqlQueryResult, MySqlRow>, sqlx::Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, MySql>,
+    {
+        let pool = self.pool.clone();
+        Box::pin(stream::once(async move {
+            let results: BoxStream<'e, _> = match chaos::DATABASE.inject().await {
+                Ok(()) => pool.fetch_many(query),
+                Err(fault) => Box::pin(stream::once(future::ready(Err(injected(fault)))))
+            };
+            results
+        }).flatten())
+    }
+
+    fn fetch_optional<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxFuture<'e, Result<Option<MySqlRow>, sqlx::Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, MySql>,
+    {
+        let pool = self.pool.clone();
+        Box::pin(async move {
+            chaos::DATABASE.inject().await.map_err(injected)?;
+            pool.fetch_optional(query).await
+        })
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [MySqlTypeInfo],
+    ) -> BoxFuture<'e, Result<MySqlStatement<'q>, sqlx::Error>>
+    where
+        'p: 'e,
+    {
+        let pool = self.pool.clone();
+        Box::pin(async move {
+            chaos::DATABASE.inject().await.map_err(injected)?;
+            pool.prepare_with(sql, parameters).await
+        })
+    }
+
+    fn describe<'e, 'q: 'e>(self, sql: &'q str) -> BoxFuture<'e, Result<Describe<MySql>, sqlx::Error>>
+    where
+        'p: 'e,
+    {
+        let pool = self.pool.clone();
+        Box::pin(async move { pool.describe(sql).await })
+    }
+}
\ No newline at end of file
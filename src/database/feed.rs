@@ -0,0 +1,170 @@
+use log::warn;
+use sqlx::{MySql, Transaction};
+
+use crate::events::DomainEvent;
+use crate::models::{OutboxEvent, Post, PostVisibility};
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+/// Name of the feed projection's ProjectionCursor.
+const FEED_PROJECTION: &str = "feed";
+/// Seconds an outbox event must have existed for before it is projected. Events
+/// get their id on insert but become visible on commit, so a younger event may
+/// still be preceded by one that has yet to commit, which the cursor would skip.
+const SETTLE_SEC: u64 = 10;
+/// Most recent posts of a newly followed account that are added to the feed.
+const FOLLOW_BACKFILL: u64 = 100;
+
+impl Database {
+    /// Applies up to `limit` outbox events after the feed projection's cursor to
+    /// the Feed table, advancing the cursor in the same transaction.
+    /// 
+    /// Returns the number of events applied.
+    pub async fn project_feed(&self, limit: u64) -> DBResult<u64> {
+        let mut tx = self.conn_pool.begin().await.map_err(|e| log_error(DBError::from(e)))?;
+        let position = sqlx::query_scalar::<_, u64>("SELECT position FROM ProjectionCursor WHERE name = ? FOR UPDATE;")
+            .bind(FEED_PROJECTION)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        let events = sqlx::query_as::<_, OutboxEvent>(
+            "SELECT id, event_type, payload, time_stamp, attempts
+            FROM Outbox
+            WHERE id > ?
+            AND time_stamp <= CURRENT_TIMESTAMP() - INTERVAL ? SECOND
+            ORDER BY id
+            LIMIT ?;")
+            .bind(position)
+            .bind(SETTLE_SEC)
+            .bind(limit)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+
+        for event in &events {
+            match serde_json::from_str::<DomainEvent>(&event.payload) {
+                Ok(domain_event) => {
+                    apply_to_feed(&mut tx, &domain_event, event.time_stamp.timestamp()).await
+                        .map_err(|e| log_error(DBError::from(e)))?
+                },
+                Err(e) => warn!("feed: skipping unreadable outbox event {}: {}", event.id, e)
+            }
+        }
+        if let Some(last) = events.last() {
+            sqlx::query("UPDATE ProjectionCursor SET position = ? WHERE name = ?;")
+                .bind(last.id)
+                .bind(FEED_PROJECTION)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| log_error(DBError::from(e)))?;
+        }
+
+        tx.commit().await.map_err(|e| log_error(DBError::from(e)))?;
+        Ok(events.len() as u64)
+    }
+
+    /// Reads the feed of `user_id`, newest first. `before` is the id of the last post
+    /// of the previous page.
+    pub async fn read_feed(&self, user_id: u64, limit: u64, before: Option<u64>) -> DBResult<Vec<Post>> {
+        let before = before.unwrap_or(u64::MAX);
+        let result = sqlx::query_as!(Post,
+            "SELECT p.id, p.poster_id, p.kind as `kind: _`, p.title, p.url, p.alt_text, p.body, p.tldr, p.body_format as `body_format: _`,
+                p.comment_mode as `comment_mode: _`, p.visibility as `visibility: _`, p.time_stamp, p.updated_at, p.expires_at,
+                p.body_edited as `body_edited: _`, p.title_edited as `title_edited: _`,
+                p.like_count AS 'likes'
+            FROM Feed f
+            INNER JOIN Post p ON p.id = f.post_id
+            WHERE f.user_id = ?
+            AND (f.score, f.post_id) < (
+                COALESCE((SELECT b.score FROM Feed b WHERE b.user_id = f.user_id AND b.post_id = ?), 9223372036854775807),
+                ?)
+            AND p.deleted_at IS NULL
+            AND (p.expires_at IS NULL OR p.expires_at > CURRENT_TIMESTAMP())
+            AND NOT p.held
+            ORDER BY f.score DESC, f.post_id DESC
+            LIMIT ?;", user_id, before, before, limit)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(posts) => Ok(posts),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    pub async fn count_feed(&self, user_id: u64) -> DBResult<u64> {
+        let result = sqlx::query_scalar::<_, u64>(
+            "SELECT CAST(count(*) AS UNSIGNED)
+            FROM Feed f
+            INNER JOIN Post p ON p.id = f.post_id
+            WHERE f.user_id = ?
+            AND p.deleted_at IS NULL
+            AND (p.expires_at IS NULL OR p.expires_at > CURRENT_TIMESTAMP())
+            AND NOT p.held;")
+            .bind(user_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(count) => Ok(count),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
+
+/// Updates the Feed table for `event`, which happened at unix time `score`.
+/// Applying an event more than once changes nothing further.
+async fn apply_to_feed(tx: &mut Transaction<'_, MySql>, event: &DomainEvent, score: i64) -> Result<(), sqlx::Error> {
+    match event {
+        DomainEvent::PostCreated { post_id, poster_id, visibility, .. } => {
+            sqlx::query("INSERT IGNORE INTO Feed (user_id, post_id, score, reason) VALUES (?, ?, ?, 'own');")
+                .bind(poster_id)
+                .bind(post_id)
+                .bind(score)
+                .execute(&mut **tx)
+                .await?;
+            // Unlisted posts are only found by their link
+            if *visibility != PostVisibility::Unlisted {
+                sqlx::query(
+                    "INSERT IGNORE INTO Feed (user_id, post_id, score, reason)
+                    SELECT f.follower_id, ?, ?, 'followed' FROM Follow f WHERE f.followee_id = ?;")
+                    .bind(post_id)
+                    .bind(score)
+                    .bind(poster_id)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+        },
+        DomainEvent::AccountFollowed { follower_id, followee_id } => {
+            sqlx::query(
+                "INSERT IGNORE INTO Feed (user_id, post_id, score, reason)
+                SELECT ?, p.id, UNIX_TIMESTAMP(p.time_stamp), 'followed'
+                FROM Post p
+                WHERE p.poster_id = ?
+                AND p.visibility <> 'unlisted'
+                ORDER BY p.id DESC
+                LIMIT ?;")
+                .bind(follower_id)
+                .bind(followee_id)
+                .bind(FOLLOW_BACKFILL)
+                .execute(&mut **tx)
+                .await?;
+        },
+        DomainEvent::AccountUnfollowed { follower_id, followee_id } => {
+            sqlx::query(
+                "DELETE FROM Feed
+                WHERE user_id = ?
+                AND reason = 'followed'
+                AND post_id IN (SELECT p.id FROM Post p WHERE p.poster_id = ?);")
+                .bind(follower_id)
+                .bind(followee_id)
+                .execute(&mut **tx)
+                .await?;
+        },
+        // Deleted posts leave every feed through the Feed foreign key, and comments
+        // and votes do not change what is in a feed
+        DomainEvent::PostDeleted { .. } | DomainEvent::CommentCreated { .. } | DomainEvent::VoteChanged { .. } => ()
+    }
+    Ok(())
+}
\ No newline at end of file
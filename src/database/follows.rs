@@ -0,0 +1,80 @@
+use std::collections::HashSet;
+
+use sqlx::{MySql, QueryBuilder};
+
+use crate::events::DomainEvent;
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+use super::outbox;
+
+impl Database {
+    /// Follows `followee_id` as `follower_id`. Following an account that is already
+    /// followed changes nothing.
+    pub async fn follow(&self, follower_id: u64, followee_id: u64) -> DBResult<()> {
+        let query = "INSERT IGNORE INTO Follow (follower_id, followee_id) VALUES (?, ?);";
+        let event = DomainEvent::AccountFollowed { follower_id, followee_id };
+        self.change_follow(query, follower_id, followee_id, event).await
+    }
+
+    /// Unfollows `followee_id` as `follower_id`. Unfollowing an account that is not
+    /// followed changes nothing.
+    pub async fn unfollow(&self, follower_id: u64, followee_id: u64) -> DBResult<()> {
+        let query = "DELETE FROM Follow WHERE follower_id = ? AND followee_id = ?;";
+        let event = DomainEvent::AccountUnfollowed { follower_id, followee_id };
+        self.change_follow(query, follower_id, followee_id, event).await
+    }
+
+    /// Runs the follow or unfollow `query`, recording `event` if it changed anything.
+    async fn change_follow(&self, query: &str, follower_id: u64, followee_id: u64, event: DomainEvent) -> DBResult<()> {
+        let mut tx = self.conn_pool.begin().await.map_err(|e| log_error(DBError::from(e)))?;
+        let result = sqlx::query(query)
+            .bind(follower_id)
+            .bind(followee_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        if result.rows_affected() > 0 {
+            outbox::record_event(&mut tx, &event).await?;
+        }
+        tx.commit().await.map_err(|e| log_error(DBError::from(e)))
+    }
+
+    pub async fn is_following(&self, follower_id: u64, followee_id: u64) -> DBResult<bool> {
+        let result = sqlx::query_scalar::<_, u64>(
+            "SELECT CAST(count(*) AS UNSIGNED) FROM Follow WHERE follower_id = ? AND followee_id = ?;")
+            .bind(follower_id)
+            .bind(followee_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(count) => Ok(count > 0),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Which of `followee_ids` the account follows.
+    pub async fn read_followed(&self, follower_id: u64, followee_ids: &[u64]) -> DBResult<HashSet<u64>> {
+        if followee_ids.is_empty() {
+            return Ok(HashSet::new())
+        }
+
+        let mut query = QueryBuilder::<MySql>::new("SELECT followee_id FROM Follow WHERE follower_id = ");
+        query.push_bind(follower_id).push(" AND followee_id IN (");
+        let mut ids = query.separated(", ");
+        for followee_id in followee_ids {
+            ids.push_bind(*followee_id);
+        }
+        query.push(");");
+
+        let result = query.build_query_scalar::<u64>()
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(followed) => Ok(followed.into_iter().collect()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
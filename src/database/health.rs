@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+
+use crate::models::{IndexHealth, MissingIndex};
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+/// Indexes the hot queries rely on, as (table, columns). An index is present if
+/// any index of the table starts with the columns, in order.
+/// 
+/// Note: PostLike/CommentLike are covered by their primary keys, which start with
+///       the liked id.
+const EXPECTED_INDEXES: &[(&str, &[&str])] = &[
+    ("Post", &["poster_id"]),
+    ("Post", &["updated_at"]),
+    ("Post", &["expires_at"]),
+    ("Post", &["held"]),
+    ("Post", &["url"]),
+    ("Comment", &["post_id", "time_stamp"]),
+    ("Comment", &["commenter_id"]),
+    ("Comment", &["updated_at"]),
+    ("Comment", &["held"]),
+    ("Comment", &["body"]),
+    ("PostLike", &["post_id"]),
+    ("PostLike", &["account_id"]),
+    ("CommentLike", &["comment_id"]),
+    ("CommentLike", &["account_id"]),
+    ("Outbox", &["dispatched_at"]),
+    ("Feed", &["user_id", "score"]),
+    ("LegalHold", &["post_id"]),
+    ("LegalHold", &["comment_id"]),
+    ("RequestTrace", &["request_id"]),
+    ("RequestTrace", &["recorded_at"]),
+    ("LoginSignal", &["signal_hash"]),
+    ("LoginSignal", &["last_seen"]),
+    ("Announcement", &["ends_at"]),
+    ("AuditLog", &["actor_id", "created_at"]),
+    ("AuditLog", &["created_at"]),
+    ("ApiKey", &["key_hash"]),
+];
+
+impl Database {
+    /// Reports the expected indexes that are missing from the database, e.g. after
+    /// a schema was created by hand rather than by the migrations.
+    pub async fn explain_health(&self) -> DBResult<IndexHealth> {
+        let result = sqlx::query_as::<_, (String, String, String)>(
+            "SELECT TABLE_NAME, INDEX_NAME, COLUMN_NAME
+            FROM information_schema.STATISTICS
+            WHERE TABLE_SCHEMA = DATABASE()
+            ORDER BY TABLE_NAME, INDEX_NAME, SEQ_IN_INDEX;")
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(rows) => Ok(index_health(&rows)),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
+
+/// Checks the `EXPECTED_INDEXES` against the (table, index, column) rows of the
+/// existing indexes, ordered by their position in the index.
+fn index_health(rows: &[(String, String, String)]) -> IndexHealth {
+    let mut existing: BTreeMap<(&str, &str), Vec<&str>> = BTreeMap::new();
+    for (table, index, column) in rows {
+        existing.entry((table, index)).or_default().push(column);
+    }
+
+    let missing = EXPECTED_INDEXES.iter()
+        .filter(|(table, columns)| !existing.iter().any(|((t, _), index_columns)| {
+            t.eq_ignore_ascii_case(table) && index_columns.starts_with(columns)
+        }))
+        .map(|(table, columns)| MissingIndex {
+            table: table.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect()
+        })
+        .collect::<Vec<MissingIndex>>();
+    IndexHealth { ok: missing.is_empty(), missing }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{index_health, EXPECTED_INDEXES};
+
+    fn rows(indexes: &[(&str, &str, &[&str])]) -> Vec<(String, String, String)> {
+        indexes.iter()
+            .flat_map(|(table, index, columns)| columns.iter()
+                .map(|column| (table.to_string(), index.to_string(), column.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn test_index_health() {
+        let all = EXPECTED_INDEXES.iter()
+            .enumerate()
+            .flat_map(|(i, (table, columns))| columns.iter()
+                .map(move |column| (table.to_string(), format!("index_{}", i), column.to_string())))
+            .collect::<Vec<_>>();
+        assert!(index_health(&all).ok);
+
+        // Only the leading column of (post_id, time_stamp), and a longer index of PostLike
+        let health = index_health(&rows(&[
+            ("Comment", "comment_post", &["post_id"]),
+            ("PostLike", "PRIMARY", &["post_id", "account_id"]),
+        ]));
+        assert!(!health.ok);
+        assert!(health.missing.iter().any(|m| m.table == "Comment" && m.columns == ["post_id", "time_stamp"]));
+        assert!(!health.missing.iter().any(|m| m.table == "PostLike" && m.columns == ["post_id"]));
+    }
+}
\ No newline at end of file
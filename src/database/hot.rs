@@ -0,0 +1,60 @@
+use sqlx::{MySql, QueryBuilder};
+
+use crate::models::{HotCandidate, Post};
+
+use super::database::{log_error, push_listed, Database, DBResult, POST_COLUMNS};
+use super::error::DBError;
+
+impl Database {
+    /// Reads up to `limit` of the newest public and followers-only posts, whether or
+    /// not any one viewer may see them, to rank the hot listing out of.
+    pub async fn read_hot_candidates(&self, limit: u64) -> DBResult<Vec<HotCandidate>> {
+        let result = sqlx::query_as::<_, HotCandidate>(
+            "SELECT p.id, p.poster_id, p.visibility, p.like_count AS likes, p.time_stamp
+            FROM Post p
+            WHERE p.deleted_at IS NULL
+            AND (p.expires_at IS NULL OR p.expires_at > CURRENT_TIMESTAMP())
+            AND NOT p.held
+            AND p.visibility IN ('public', 'followers')
+            ORDER BY p.id DESC
+            LIMIT ?;")
+            .bind(limit)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(candidates) => Ok(candidates),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads the posts of `post_ids` that are still listed for `viewer_id`, in the
+    /// order of `post_ids`.
+    pub async fn read_listed_posts(&self, post_ids: &[u64], viewer_id: Option<u64>) -> DBResult<Vec<Post>> {
+        if post_ids.is_empty() {
+            return Ok(Vec::new())
+        }
+
+        let mut query = QueryBuilder::<MySql>::new(format!("SELECT {} FROM Post p", POST_COLUMNS));
+        push_listed(&mut query, viewer_id);
+        query.push(" AND p.id IN (");
+        let mut ids = query.separated(", ");
+        for post_id in post_ids {
+            ids.push_bind(*post_id);
+        }
+        query.push(");");
+
+        let result = query.build_query_as::<Post>()
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(mut posts) => {
+                posts.sort_by_key(|post| post_ids.iter().position(|post_id| *post_id == post.id));
+                Ok(posts)
+            },
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,88 @@
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Deletes PostLike rows whose post or account no longer exists.
+    /// 
+    /// Returns the number of deleted rows.
+    pub async fn delete_orphaned_post_likes(&self) -> DBResult<u64> {
+        let result = sqlx::query(
+            "DELETE pl FROM PostLike pl
+            LEFT JOIN Post p ON pl.post_id = p.id
+            LEFT JOIN Account a ON pl.account_id = a.id
+            WHERE p.id IS NULL OR a.id IS NULL;")
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => Ok(res.rows_affected()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Deletes CommentLike rows whose comment or account no longer exists.
+    /// 
+    /// Returns the number of deleted rows.
+    pub async fn delete_orphaned_comment_likes(&self) -> DBResult<u64> {
+        let result = sqlx::query(
+            "DELETE cl FROM CommentLike cl
+            LEFT JOIN Comment c ON cl.comment_id = c.id
+            LEFT JOIN Account a ON cl.account_id = a.id
+            WHERE c.id IS NULL OR a.id IS NULL;")
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => Ok(res.rows_affected()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Clears the `comment_reply_id` of comments replying to a comment that no
+    /// longer exists, turning them into top level comments.
+    /// 
+    /// Returns the number of updated rows.
+    pub async fn clear_dangling_comment_replies(&self) -> DBResult<u64> {
+        let result = sqlx::query(
+            "UPDATE Comment c
+            LEFT JOIN Comment r ON c.comment_reply_id = r.id
+            SET c.comment_reply_id = NULL
+            WHERE c.comment_reply_id IS NOT NULL
+            AND r.id IS NULL;")
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => Ok(res.rows_affected()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Corrects the `like_count` of posts and comments that no longer matches
+    /// their number of likes.
+    /// 
+    /// Returns the number of corrected rows.
+    pub async fn recount_drifted_likes(&self) -> DBResult<u64> {
+        let mut corrected = 0;
+        for (table, like_table, like_column) in [("Post", "PostLike", "post_id"), ("Comment", "CommentLike", "comment_id")] {
+            let result = sqlx::query(&format!(
+                "UPDATE {table} t
+                INNER JOIN (
+                    SELECT t.id, count(l.account_id) AS likes
+                    FROM {table} t
+                    LEFT JOIN {like_table} l ON l.{like_column} = t.id
+                    GROUP BY t.id
+                ) counted ON t.id = counted.id
+                SET t.like_count = counted.likes, t.updated_at = t.updated_at
+                WHERE t.like_count <> counted.likes;"))
+                .execute(&self.conn_pool)
+                .await;
+
+            match result {
+                Ok(res) => corrected += res.rows_affected(),
+                Err(e) => return Err(log_error(DBError::from(e)))
+            }
+        }
+        Ok(corrected)
+    }
+}
\ No newline at end of file
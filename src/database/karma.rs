@@ -0,0 +1,100 @@
+use serde::Serialize;
+
+use crate::config::config::KarmaConfig;
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+/// An action that can be gated behind a karma threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GatedAction {
+    Post,
+    Comment,
+    Like,
+    React,
+    Award
+}
+
+/// A karma threshold that an account has not reached, named in 403 responses.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct UnmetRequirement {
+    pub action: GatedAction,
+    pub required_karma: u64,
+    pub karma: u64
+}
+
+pub fn threshold(config: &KarmaConfig, action: GatedAction) -> u64 {
+    match action {
+        GatedAction::Post => config.post,
+        GatedAction::Comment => config.comment,
+        GatedAction::Like => config.like,
+        GatedAction::React => config.react,
+        GatedAction::Award => config.award
+    }
+}
+
+pub fn check(config: &KarmaConfig, action: GatedAction, karma: u64) -> Result<(), UnmetRequirement> {
+    let required_karma = threshold(config, action);
+    match karma >= required_karma {
+        true  => Ok(()),
+        false => Err(UnmetRequirement { action, required_karma, karma })
+    }
+}
+
+impl Database {
+    /// Likes received on the account's posts and comments.
+    pub async fn read_karma(&self, account_id: u64) -> DBResult<u64> {
+        let result = sqlx::query_scalar::<_, u64>(
+            "SELECT CAST(
+                (SELECT COALESCE(SUM(like_count), 0) FROM Post WHERE poster_id = ?)
+                + (SELECT COALESCE(SUM(like_count), 0) FROM Comment WHERE commenter_id = ?)
+            AS UNSIGNED);")
+            .bind(account_id)
+            .bind(account_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(karma) => Ok(karma),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Checks that `account_id` has the karma `config` requires for `action`. The
+    /// account's karma is only read when the action has a threshold.
+    pub async fn check_karma(
+        &self,
+        account_id: u64,
+        action: GatedAction,
+        config: &KarmaConfig
+    ) -> DBResult<Result<(), UnmetRequirement>> {
+        if threshold(config, action) == 0 {
+            return Ok(Ok(()))
+        }
+        let karma = self.read_karma(account_id).await?;
+        Ok(check(config, action, karma))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::config::KarmaConfig;
+    use super::{check, GatedAction, UnmetRequirement};
+
+    #[test]
+    fn test_check() {
+        let config = KarmaConfig { react: 10, award: 50, ..KarmaConfig::default() };
+
+        assert_eq!(Ok(()), check(&config, GatedAction::Post, 0));
+        assert_eq!(Ok(()), check(&config, GatedAction::React, 10));
+        assert_eq!(
+            Err(UnmetRequirement { action: GatedAction::React, required_karma: 10, karma: 9 }),
+            check(&config, GatedAction::React, 9)
+        );
+        assert_eq!(
+            Err(UnmetRequirement { action: GatedAction::Award, required_karma: 50, karma: 49 }),
+            check(&config, GatedAction::Award, 49)
+        );
+    }
+}
\ No newline at end of file
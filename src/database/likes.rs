@@ -0,0 +1,254 @@
+use sqlx::{MySql, Transaction};
+
+use crate::events::DomainEvent;
+use crate::models::{GivenLike, LikeKind, LikeState};
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+use super::outbox;
+
+/// A likeable table, which keeps its likes in a separate table and the number
+/// of them in its `like_count` column.
+#[derive(Clone, Copy)]
+enum LikeTarget {
+    Post,
+    Comment
+}
+
+impl LikeTarget {
+    fn table(self) -> &'static str {
+        match self {
+            LikeTarget::Post => "Post",
+            LikeTarget::Comment => "Comment"
+        }
+    }
+
+    fn like_table(self) -> &'static str {
+        match self {
+            LikeTarget::Post => "PostLike",
+            LikeTarget::Comment => "CommentLike"
+        }
+    }
+
+    fn like_column(self) -> &'static str {
+        match self {
+            LikeTarget::Post => "post_id",
+            LikeTarget::Comment => "comment_id"
+        }
+    }
+
+    fn of(kind: LikeKind) -> Self {
+        match kind {
+            LikeKind::Post => LikeTarget::Post,
+            LikeKind::Comment => LikeTarget::Comment
+        }
+    }
+
+    fn vote_changed(self, target_id: u64, account_id: u64, state: &LikeState) -> DomainEvent {
+        let (post_id, comment_id) = match self {
+            LikeTarget::Post => (Some(target_id), None),
+            LikeTarget::Comment => (None, Some(target_id))
+        };
+        DomainEvent::VoteChanged { post_id, comment_id, account_id, liked: state.liked, likes: state.likes }
+    }
+}
+
+// Every change to likes locks the liked row first, so that the like rows and the
+// `like_count` of a post/comment are updated together, one transaction at a time.
+
+impl Database {
+    /// Likes the post for the account, returning the resulting `LikeState`.
+    pub async fn create_post_like(&self, post_id: u64, account_id: u64) -> DBResult<LikeState> {
+        self.set_like(LikeTarget::Post, post_id, account_id, true).await
+    }
+
+    pub async fn create_comment_like(&self, comment_id: u64, account_id: u64) -> DBResult<LikeState> {
+        self.set_like(LikeTarget::Comment, comment_id, account_id, true).await
+    }
+
+    pub async fn delete_post_like(&self, post_id: u64, account_id: u64) -> DBResult<LikeState> {
+        self.set_like(LikeTarget::Post, post_id, account_id, false).await
+    }
+
+    pub async fn delete_comment_like(&self, comment_id: u64, account_id: u64) -> DBResult<LikeState> {
+        self.set_like(LikeTarget::Comment, comment_id, account_id, false).await
+    }
+
+    /// Likes the post for the account if it has not been liked by them, otherwise
+    /// removes the like. Results in `DBError::NoResult` if the post does not exist.
+    pub async fn toggle_post_like(&self, post_id: u64, account_id: u64) -> DBResult<LikeState> {
+        self.toggle_like(LikeTarget::Post, post_id, account_id).await
+    }
+
+    /// Like `toggle_post_like`, for comments.
+    pub async fn toggle_comment_like(&self, comment_id: u64, account_id: u64) -> DBResult<LikeState> {
+        self.toggle_like(LikeTarget::Comment, comment_id, account_id).await
+    }
+
+    /// Returns the like count as of the change, read under the same lock.
+    /// 
+    /// Results in `DBError::UnexpectedRowsAffected` when nothing changed, i.e. the
+    /// like already was as requested or the target/account does not exist.
+    async fn set_like(&self, target: LikeTarget, target_id: u64, account_id: u64, liked: bool) -> DBResult<LikeState> {
+        let mut tx = self.conn_pool.begin().await.map_err(|e| log_error(DBError::from(e)))?;
+        let state = match lock_like_count(&mut tx, target, target_id).await {
+            Ok(Some(likes)) => {
+                let changed = match liked {
+                    true  => insert_like(&mut tx, target, target_id, account_id).await,
+                    false => delete_like(&mut tx, target, target_id, account_id).await
+                };
+                let changed = changed.map_err(|e| log_error(DBError::from(e)))?;
+                let state = changed.then(|| LikeState { liked, likes: if liked { likes + 1 } else { likes - 1 } });
+                if let Some(state) = &state {
+                    outbox::record_event(&mut tx, &target.vote_changed(target_id, account_id, state)).await?;
+                }
+                state
+            },
+            Ok(None) => None,
+            Err(e) => return Err(log_error(DBError::from(e)))
+        };
+        tx.commit().await.map_err(|e| log_error(DBError::from(e)))?;
+
+        match state {
+            Some(state) => Ok(state),
+            None => Err(log_error(DBError::UnexpectedRowsAffected { expected: 1, actual: 0 }))
+        }
+    }
+
+    /// Reads up to `limit` of the posts or comments liked by `account_id`, by
+    /// descending id. Pages continue from the `before` id.
+    pub async fn read_likes_given(
+        &self,
+        account_id: u64,
+        kind: LikeKind,
+        limit: u64,
+        before: Option<u64>
+    ) -> DBResult<Vec<GivenLike>> {
+        let target = LikeTarget::of(kind);
+        let result = sqlx::query_as::<_, GivenLike>(&format!(
+            "SELECT {column} AS id, time_stamp AS liked_at
+            FROM {table}
+            WHERE account_id = ?
+            AND {column} < ?
+            ORDER BY {column} DESC
+            LIMIT ?;", column = target.like_column(), table = target.like_table()))
+            .bind(account_id)
+            .bind(before.unwrap_or(u64::MAX))
+            .bind(limit)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(likes) => Ok(likes),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    pub async fn count_likes_given(&self, account_id: u64, kind: LikeKind) -> DBResult<u64> {
+        let target = LikeTarget::of(kind);
+        let result = sqlx::query_scalar(&format!(
+            "SELECT CAST(count(*) AS UNSIGNED) FROM {} WHERE account_id = ?;", target.like_table()))
+            .bind(account_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(count) => Ok(count),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    async fn toggle_like(&self, target: LikeTarget, target_id: u64, account_id: u64) -> DBResult<LikeState> {
+        let mut tx = self.conn_pool.begin().await.map_err(|e| log_error(DBError::from(e)))?;
+        let likes = match lock_like_count(&mut tx, target, target_id).await {
+            Ok(Some(likes)) => likes,
+            Ok(None) => return Err(DBError::NoResult),
+            Err(e) => return Err(log_error(DBError::from(e)))
+        };
+
+        let state = match delete_like(&mut tx, target, target_id, account_id).await {
+            Ok(true) => LikeState { liked: false, likes: likes - 1 },
+            Ok(false) => match insert_like(&mut tx, target, target_id, account_id).await {
+                Ok(true) => LikeState { liked: true, likes: likes + 1 },
+                // Not liked, yet cannot be liked: the account does not exist
+                Ok(false) => return Err(DBError::NoResult),
+                Err(e) => return Err(log_error(DBError::from(e)))
+            },
+            Err(e) => return Err(log_error(DBError::from(e)))
+        };
+        outbox::record_event(&mut tx, &target.vote_changed(target_id, account_id, &state)).await?;
+        tx.commit().await.map_err(|e| log_error(DBError::from(e)))?;
+        Ok(state)
+    }
+}
+
+/// Locks the post/comment for the rest of the transaction, returning its current
+/// like count, or `None` if it does not exist.
+async fn lock_like_count(
+    tx: &mut Transaction<'_, MySql>,
+    target: LikeTarget,
+    target_id: u64
+) -> Result<Option<u64>, sqlx::Error> {
+    sqlx::query_scalar(&format!("SELECT like_count FROM {} WHERE id = ? FOR UPDATE;", target.table()))
+        .bind(target_id)
+        .fetch_optional(&mut **tx)
+        .await
+}
+
+/// Inserts the like and increments the like count, returning whether the like
+/// was inserted. The liked row must be locked by `lock_like_count`.
+async fn insert_like(
+    tx: &mut Transaction<'_, MySql>,
+    target: LikeTarget,
+    target_id: u64,
+    account_id: u64
+) -> Result<bool, sqlx::Error> {
+    let inserted = sqlx::query(&format!(
+        "INSERT IGNORE INTO {} ({}, account_id) VALUES (?, ?);", target.like_table(), target.like_column()))
+        .bind(target_id)
+        .bind(account_id)
+        .execute(&mut **tx)
+        .await?;
+    if inserted.rows_affected() == 0 {
+        return Ok(false)
+    }
+    adjust_like_count(tx, target, target_id, "+").await?;
+    Ok(true)
+}
+
+/// Deletes the like and decrements the like count, returning whether there was
+/// a like to delete. The liked row must be locked by `lock_like_count`.
+async fn delete_like(
+    tx: &mut Transaction<'_, MySql>,
+    target: LikeTarget,
+    target_id: u64,
+    account_id: u64
+) -> Result<bool, sqlx::Error> {
+    let deleted = sqlx::query(&format!(
+        "DELETE FROM {} WHERE {} = ? AND account_id = ?;", target.like_table(), target.like_column()))
+        .bind(target_id)
+        .bind(account_id)
+        .execute(&mut **tx)
+        .await?;
+    if deleted.rows_affected() == 0 {
+        return Ok(false)
+    }
+    adjust_like_count(tx, target, target_id, "-").await?;
+    Ok(true)
+}
+
+/// `updated_at` is set to itself so that a like does not count as an edit.
+async fn adjust_like_count(
+    tx: &mut Transaction<'_, MySql>,
+    target: LikeTarget,
+    target_id: u64,
+    operator: &'static str
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!(
+        "UPDATE {} SET like_count = like_count {} 1, updated_at = updated_at WHERE id = ?;",
+        target.table(), operator))
+        .bind(target_id)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
\ No newline at end of file
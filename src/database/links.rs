@@ -0,0 +1,31 @@
+use crate::models::LinkSubmission;
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Reads the link posts of the canonical `url` that are public or by `account_id`,
+    /// newest first.
+    pub async fn read_link_submissions(&self, url: &str, account_id: u64) -> DBResult<Vec<LinkSubmission>> {
+        let result = sqlx::query_as::<_, LinkSubmission>(
+            "SELECT p.id, p.poster_id, p.title, p.time_stamp,
+                (SELECT CAST(count(*) AS UNSIGNED) FROM Comment c WHERE c.post_id = p.id AND NOT c.held) AS comment_count
+            FROM Post p
+            WHERE p.url = ?
+            AND p.kind = 'link'
+            AND p.deleted_at IS NULL
+            AND (p.expires_at IS NULL OR p.expires_at > CURRENT_TIMESTAMP())
+            AND NOT p.held
+            AND (p.visibility = 'public' OR p.poster_id = ?)
+            ORDER BY p.id DESC;")
+            .bind(url)
+            .bind(account_id)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(submissions) => Ok(submissions),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,42 @@
+use sqlx::migrate::Migrator;
+
+use crate::models::{AppliedMigration, PendingMigration};
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+/// The migrations in `./migrations`, embedded at compile time.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// MySQL error code for a table that does not exist.
+const NO_SUCH_TABLE: &str = "42S02";
+
+impl Database {
+    /// Reads the migrations recorded by sqlx as applied. A database that has
+    /// never been migrated has no `_sqlx_migrations` table, and has no applied
+    /// migrations.
+    pub async fn read_applied_migrations(&self) -> DBResult<Vec<AppliedMigration>> {
+        let result = sqlx::query_as::<_, AppliedMigration>(
+            "SELECT version, description, installed_on, success
+            FROM _sqlx_migrations
+            ORDER BY version;")
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(migrations) => Ok(migrations),
+            Err(sqlx::Error::Database(e)) if e.code().is_some_and(|code| code == NO_SUCH_TABLE) => {
+                Ok(Vec::new())
+            },
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
+
+/// The embedded migrations that are not in `applied`, or failed to apply.
+pub fn pending_migrations(applied: &[AppliedMigration]) -> Vec<PendingMigration> {
+    MIGRATOR.iter()
+        .filter(|m| !applied.iter().any(|a| a.version == m.version && a.success.0))
+        .map(|m| PendingMigration { version: m.version, description: m.description.to_string() })
+        .collect()
+}
\ No newline at end of file
@@ -1,2 +1,40 @@
+pub mod analytics;
+pub mod announcements;
+pub mod api_keys;
+pub mod audit;
+pub mod awards;
 pub mod database;
-pub mod error;
\ No newline at end of file
+pub mod discover;
+pub mod error;
+pub mod experiments;
+pub mod expiry;
+pub mod export;
+#[cfg(feature = "chaos")]
+pub mod faulty;
+pub mod feed;
+pub mod follows;
+pub mod health;
+pub mod hot;
+pub mod integrity;
+pub mod karma;
+pub mod likes;
+pub mod links;
+pub mod migrations;
+pub mod moderation;
+pub mod onboarding;
+pub mod outbox;
+pub mod pins;
+pub mod polls;
+pub mod pool;
+pub mod reactions;
+pub mod reads;
+pub mod request_log;
+pub mod retention;
+pub mod revisions;
+pub mod search;
+pub mod settings;
+pub mod signals;
+pub mod stats;
+pub mod sync;
+pub mod terms;
+pub mod threads;
\ No newline at end of file
@@ -0,0 +1,255 @@
+use sqlx::{MySql, Transaction};
+
+use crate::models::{Appeal, HeldContent, ModerationAction, ModerationQueue};
+
+use super::database::{expected_rows_affected, log_error, Database, DBResult};
+use super::error::DBError;
+
+/// A table of content that moderators can hold, approve and remove.
+#[derive(Clone, Copy)]
+enum Moderated {
+    Post,
+    Comment
+}
+
+impl Moderated {
+    fn table(self) -> &'static str {
+        match self {
+            Moderated::Post => "Post",
+            Moderated::Comment => "Comment"
+        }
+    }
+
+    fn author_column(self) -> &'static str {
+        match self {
+            Moderated::Post => "poster_id",
+            Moderated::Comment => "commenter_id"
+        }
+    }
+
+    /// The column of ModerationAction that refers to the table.
+    fn action_column(self) -> &'static str {
+        match self {
+            Moderated::Post => "post_id",
+            Moderated::Comment => "comment_id"
+        }
+    }
+}
+
+// Removed content is held, so that it is hidden wherever held content is, and can
+// be restored by overturning the removal on appeal. Content held by scoring has no
+// ModerationAction, which is what keeps removed content out of the review queue.
+
+impl Database {
+    /// Reads the posts and comments held for review, oldest first.
+    pub async fn read_moderation_queue(&self) -> DBResult<ModerationQueue> {
+        let posts = sqlx::query_as::<_, HeldContent>(
+            "SELECT p.id, p.poster_id AS author_id, p.title, p.body, p.moderation_score, p.time_stamp
+            FROM Post p
+            WHERE p.held
+            AND NOT EXISTS (SELECT 1 FROM ModerationAction ma WHERE ma.post_id = p.id)
+            ORDER BY p.id;")
+            .fetch_all(&self.conn_pool)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        let comments = sqlx::query_as::<_, HeldContent>(
+            "SELECT c.id, c.commenter_id AS author_id, NULL AS title, c.body, c.moderation_score, c.time_stamp
+            FROM Comment c
+            WHERE c.held
+            AND NOT EXISTS (SELECT 1 FROM ModerationAction ma WHERE ma.comment_id = c.id)
+            ORDER BY c.id;")
+            .fetch_all(&self.conn_pool)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        Ok(ModerationQueue { posts, comments })
+    }
+
+    /// Makes a post held for review visible. Results in `DBError::UnexpectedRowsAffected`
+    /// if the post is not in the review queue.
+    pub async fn approve_held_post(&self, post_id: u64) -> DBResult<()> {
+        self.approve_held(Moderated::Post, post_id).await
+    }
+
+    /// Makes a comment held for review visible. Results in `DBError::UnexpectedRowsAffected`
+    /// if the comment is not in the review queue.
+    pub async fn approve_held_comment(&self, comment_id: u64) -> DBResult<()> {
+        self.approve_held(Moderated::Comment, comment_id).await
+    }
+
+    /// Removes a post for `reason`. Results in `DBError::NoResult` if the post does
+    /// not exist, or `DBError::UnexpectedRowsAffected` if it is already removed.
+    pub async fn remove_post(&self, post_id: u64, moderator_id: u64, reason: &str) -> DBResult<()> {
+        self.remove(Moderated::Post, post_id, moderator_id, reason).await
+    }
+
+    /// Removes a comment for `reason`. Results in `DBError::NoResult` if the comment
+    /// does not exist, or `DBError::UnexpectedRowsAffected` if it is already removed.
+    pub async fn remove_comment(&self, comment_id: u64, moderator_id: u64, reason: &str) -> DBResult<()> {
+        self.remove(Moderated::Comment, comment_id, moderator_id, reason).await
+    }
+
+    async fn approve_held(&self, target: Moderated, target_id: u64) -> DBResult<()> {
+        let result = sqlx::query(&format!(
+            "UPDATE {} t SET t.held = FALSE
+            WHERE t.id = ?
+            AND t.held
+            AND NOT EXISTS (SELECT 1 FROM ModerationAction ma WHERE ma.{} = t.id);",
+            target.table(), target.action_column()))
+            .bind(target_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => expected_rows_affected(res, 1),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    async fn remove(&self, target: Moderated, target_id: u64, moderator_id: u64, reason: &str) -> DBResult<()> {
+        let mut tx = self.conn_pool.begin().await.map_err(|e| log_error(DBError::from(e)))?;
+
+        let author_id = sqlx::query_scalar::<_, u64>(&format!(
+            "SELECT {} FROM {} WHERE id = ? FOR UPDATE;", target.author_column(), target.table()))
+            .bind(target_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?
+            .ok_or(DBError::NoResult)?;
+
+        // Removed, and not restored by an overturned appeal since
+        let in_force = sqlx::query_scalar::<_, u64>(&format!(
+            "SELECT CAST(count(*) AS UNSIGNED) FROM ModerationAction ma
+            LEFT JOIN Appeal ap ON ap.action_id = ma.id
+            WHERE ma.{} = ?
+            AND (ap.status IS NULL OR ap.status <> 'overturned');", target.action_column()))
+            .bind(target_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        if in_force > 0 {
+            return Err(DBError::UnexpectedRowsAffected { expected: 1, actual: 0 })
+        }
+
+        set_held(&mut tx, target, target_id, true).await.map_err(|e| log_error(DBError::from(e)))?;
+        sqlx::query(&format!(
+            "INSERT INTO ModerationAction (account_id, moderator_id, {}, reason) VALUES (?, ?, ?, ?);",
+            target.action_column()))
+            .bind(author_id)
+            .bind(moderator_id)
+            .bind(target_id)
+            .bind(reason)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+
+        tx.commit().await.map_err(|e| log_error(DBError::from(e)))
+    }
+
+    /// Reads the removals of the account's content, newest first.
+    pub async fn read_moderation_actions(&self, account_id: u64) -> DBResult<Vec<ModerationAction>> {
+        let result = sqlx::query_as::<_, ModerationAction>(
+            "SELECT ma.id, ma.post_id, ma.comment_id, ma.reason, ma.time_stamp, ap.status AS appeal_status
+            FROM ModerationAction ma
+            LEFT JOIN Appeal ap ON ap.action_id = ma.id
+            WHERE ma.account_id = ?
+            ORDER BY ma.id DESC;")
+            .bind(account_id)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(actions) => Ok(actions),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Appeals an action taken against `account_id`. Results in
+    /// `DBError::UnexpectedRowsAffected` if the action is not against the account,
+    /// or has already been appealed.
+    pub async fn create_appeal(&self, action_id: u64, account_id: u64, message: &str) -> DBResult<()> {
+        let result = sqlx::query(
+            "INSERT IGNORE INTO Appeal (action_id, message)
+            SELECT ma.id, ? FROM ModerationAction ma
+            WHERE ma.id = ? AND ma.account_id = ?;")
+            .bind(message)
+            .bind(action_id)
+            .bind(account_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => expected_rows_affected(res, 1),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads the open appeals, oldest first.
+    pub async fn read_open_appeals(&self) -> DBResult<Vec<Appeal>> {
+        let result = sqlx::query_as::<_, Appeal>(
+            "SELECT ap.id, ap.action_id, ma.account_id, ma.moderator_id, ma.post_id, ma.comment_id,
+                ma.reason, ap.message, ap.time_stamp
+            FROM Appeal ap
+            INNER JOIN ModerationAction ma ON ap.action_id = ma.id
+            WHERE ap.status = 'open'
+            ORDER BY ap.id;")
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(appeals) => Ok(appeals),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Resolves an open appeal, restoring the removed content if `overturn`. Results
+    /// in `DBError::NoResult` if there is no such open appeal.
+    pub async fn resolve_appeal(&self, appeal_id: u64, resolver_id: u64, overturn: bool) -> DBResult<()> {
+        let mut tx = self.conn_pool.begin().await.map_err(|e| log_error(DBError::from(e)))?;
+
+        let (post_id, comment_id) = sqlx::query_as::<_, (Option<u64>, Option<u64>)>(
+            "SELECT ma.post_id, ma.comment_id
+            FROM Appeal ap
+            INNER JOIN ModerationAction ma ON ap.action_id = ma.id
+            WHERE ap.id = ? AND ap.status = 'open'
+            FOR UPDATE;")
+            .bind(appeal_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?
+            .ok_or(DBError::NoResult)?;
+
+        sqlx::query(
+            "UPDATE Appeal SET status = ?, resolver_id = ?, resolved_at = CURRENT_TIMESTAMP()
+            WHERE id = ?;")
+            .bind(if overturn { "overturned" } else { "upheld" })
+            .bind(resolver_id)
+            .bind(appeal_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        if overturn {
+            let restored = match (post_id, comment_id) {
+                (Some(post_id), _) => set_held(&mut tx, Moderated::Post, post_id, false).await,
+                (_, Some(comment_id)) => set_held(&mut tx, Moderated::Comment, comment_id, false).await,
+                (None, None) => Ok(())
+            };
+            restored.map_err(|e| log_error(DBError::from(e)))?;
+        }
+
+        tx.commit().await.map_err(|e| log_error(DBError::from(e)))
+    }
+}
+
+async fn set_held(
+    tx: &mut Transaction<'_, MySql>,
+    target: Moderated,
+    target_id: u64,
+    held: bool
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!("UPDATE {} SET held = ? WHERE id = ?;", target.table()))
+        .bind(held)
+        .bind(target_id)
+        .execute(&mut **tx)
+        .await
+        .map(|_| ())
+}
\ No newline at end of file
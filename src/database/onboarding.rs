@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use sqlx::{MySql, Transaction};
+
+use crate::models::OnboardingStep;
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Reads when `account_id` completed each onboarding step it has.
+    pub async fn read_onboarding_steps(&self, account_id: u64) -> DBResult<Vec<(OnboardingStep, DateTime<Utc>)>> {
+        let result = sqlx::query_as::<_, (OnboardingStep, DateTime<Utc>)>(
+            "SELECT step, completed_at
+            FROM OnboardingStep
+            WHERE account_id = ?;")
+            .bind(account_id)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(steps) => Ok(steps),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
+
+/// Records within `tx` that `account_id` completed `step`, unless it already had.
+pub(super) async fn record_step(tx: &mut Transaction<'_, MySql>, account_id: u64, step: OnboardingStep) -> DBResult<()> {
+    sqlx::query("INSERT IGNORE INTO OnboardingStep (account_id, step) VALUES (?, ?);")
+        .bind(account_id)
+        .bind(step)
+        .execute(&mut **tx)
+        .await
+        .map(|_| ())
+        .map_err(|e| log_error(DBError::from(e)))
+}
\ No newline at end of file
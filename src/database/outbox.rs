@@ -0,0 +1,89 @@
+use sqlx::{MySql, Transaction};
+
+use crate::events::DomainEvent;
+use crate::models::OutboxEvent;
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+/// Characters of a delivery error kept, as limited by the Outbox table.
+const MAX_ERROR_LENGTH: usize = 1000;
+
+impl Database {
+    /// Reads up to `limit` events that have not been dispatched, oldest first.
+    pub async fn read_pending_events(&self, limit: u64) -> DBResult<Vec<OutboxEvent>> {
+        let result = sqlx::query_as::<_, OutboxEvent>(
+            "SELECT id, event_type, payload, time_stamp, attempts
+            FROM Outbox
+            WHERE dispatched_at IS NULL
+            ORDER BY id
+            LIMIT ?;")
+            .bind(limit)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(events) => Ok(events),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    pub async fn mark_event_dispatched(&self, event_id: u64) -> DBResult<()> {
+        let result = sqlx::query("UPDATE Outbox SET dispatched_at = CURRENT_TIMESTAMP(), attempts = attempts + 1 WHERE id = ?;")
+            .bind(event_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Records a failed attempt at dispatching an event, which is left to be retried.
+    pub async fn record_event_failure(&self, event_id: u64, error: &str) -> DBResult<()> {
+        let error: String = error.chars().take(MAX_ERROR_LENGTH).collect();
+        let result = sqlx::query("UPDATE Outbox SET attempts = attempts + 1, last_error = ? WHERE id = ?;")
+            .bind(error)
+            .bind(event_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Deletes events dispatched more than `days` ago, that every projection has applied.
+    /// 
+    /// Returns the number of events deleted.
+    pub async fn prune_dispatched_events(&self, days: u64) -> DBResult<u64> {
+        let result = sqlx::query(
+            "DELETE FROM Outbox
+            WHERE dispatched_at < CURRENT_TIMESTAMP() - INTERVAL ? DAY
+            AND id <= (SELECT COALESCE(MIN(position), 0) FROM ProjectionCursor);")
+            .bind(days)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => Ok(res.rows_affected()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
+
+/// Writes `event` to the outbox within `tx`, so that it is committed if and only
+/// if the change it describes is.
+pub(super) async fn record_event(tx: &mut Transaction<'_, MySql>, event: &DomainEvent) -> DBResult<()> {
+    // Events are plain structs of ids and enums, which always serialise
+    let payload = serde_json::to_string(event).expect("Failed to serialise a domain event");
+    sqlx::query("INSERT INTO Outbox (event_type, payload) VALUES (?, ?);")
+        .bind(event.name())
+        .bind(payload)
+        .execute(&mut **tx)
+        .await
+        .map(|_| ())
+        .map_err(|e| log_error(DBError::from(e)))
+}
\ No newline at end of file
@@ -0,0 +1,69 @@
+use super::database::{expected_rows_affected, log_error, Database, DBResult};
+use super::error::DBError;
+
+// `updated_at` is set to itself, as pinning a comment is not an edit of the post.
+
+impl Database {
+    /// Reads the post a visible comment is on, and the author of that post.
+    pub async fn read_comment_thread(&self, comment_id: u64) -> DBResult<(u64, u64)> {
+        let result = sqlx::query_as::<_, (u64, u64)>(
+            "SELECT p.id, p.poster_id
+            FROM Comment c
+            INNER JOIN Post p ON c.post_id = p.id
+            WHERE c.id = ?
+            AND NOT c.held;")
+            .bind(comment_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(thread) => Ok(thread),
+            Err(e) => Err(DBError::from(e))
+        }
+    }
+
+    /// The comment pinned to the post, if any. A post that does not exist has none.
+    pub async fn read_pinned_comment(&self, post_id: u64) -> DBResult<Option<u64>> {
+        let result = sqlx::query_scalar::<_, Option<u64>>("SELECT pinned_comment_id FROM Post WHERE id = ?;")
+            .bind(post_id)
+            .fetch_optional(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(pinned) => Ok(pinned.flatten()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Pins the comment to the post, replacing any comment already pinned.
+    pub async fn pin_comment(&self, post_id: u64, comment_id: u64) -> DBResult<()> {
+        let result = sqlx::query(
+            "UPDATE Post SET pinned_comment_id = ?, updated_at = updated_at WHERE id = ?;")
+            .bind(comment_id)
+            .bind(post_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Unpins the comment from the post. Results in `DBError::UnexpectedRowsAffected`
+    /// if it is not the pinned comment.
+    pub async fn unpin_comment(&self, post_id: u64, comment_id: u64) -> DBResult<()> {
+        let result = sqlx::query(
+            "UPDATE Post SET pinned_comment_id = NULL, updated_at = updated_at
+            WHERE id = ? AND pinned_comment_id = ?;")
+            .bind(post_id)
+            .bind(comment_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => expected_rows_affected(res, 1),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
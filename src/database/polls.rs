@@ -0,0 +1,87 @@
+use sqlx::{MySql, QueryBuilder, Transaction};
+
+use crate::models::PollOption;
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// The options of a poll in order, with their votes. A post that is not a poll
+    /// has none.
+    pub async fn read_poll_options(&self, post_id: u64) -> DBResult<Vec<PollOption>> {
+        let result = sqlx::query_as::<_, PollOption>(
+            "SELECT o.id, o.label, CAST(count(v.voter_id) AS UNSIGNED) AS votes
+            FROM PollOption o
+            LEFT JOIN PollVote v ON v.option_id = o.id
+            WHERE o.post_id = ?
+            GROUP BY o.id, o.label, o.position
+            ORDER BY o.position;")
+            .bind(post_id)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(options) => Ok(options),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// The option of the poll `voter_id` voted for, if any.
+    pub async fn read_poll_vote(&self, post_id: u64, voter_id: u64) -> DBResult<Option<u64>> {
+        let result = sqlx::query_scalar::<_, u64>("SELECT option_id FROM PollVote WHERE post_id = ? AND voter_id = ?;")
+            .bind(post_id)
+            .bind(voter_id)
+            .fetch_optional(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(option_id) => Ok(option_id),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Records `voter_id` voting for the option of the poll, replacing any earlier
+    /// vote in it. Results in `DBError::NoResult` if the option is not of the poll.
+    pub async fn vote_in_poll(&self, post_id: u64, option_id: u64, voter_id: u64) -> DBResult<()> {
+        let option_post_id = sqlx::query_scalar::<_, u64>("SELECT post_id FROM PollOption WHERE id = ?;")
+            .bind(option_id)
+            .fetch_optional(&self.conn_pool)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        if option_post_id != Some(post_id) {
+            return Err(DBError::NoResult)
+        }
+
+        let result = sqlx::query(
+            "INSERT INTO PollVote (post_id, voter_id, option_id) VALUES (?, ?, ?)
+            ON DUPLICATE KEY UPDATE option_id = VALUES(option_id), time_stamp = CURRENT_TIMESTAMP();")
+            .bind(post_id)
+            .bind(voter_id)
+            .bind(option_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
+
+/// Records within `tx` the `options` of the poll, in order. There are none for
+/// other posts.
+pub(super) async fn record_options(tx: &mut Transaction<'_, MySql>, post_id: u64, options: &[String]) -> DBResult<()> {
+    if options.is_empty() {
+        return Ok(())
+    }
+
+    let mut query = QueryBuilder::<MySql>::new("INSERT INTO PollOption (post_id, position, label) ");
+    query.push_values(options.iter().enumerate(), |mut row, (position, label)| {
+        row.push_bind(post_id).push_bind(position as u8).push_bind(label);
+    });
+    query.build()
+        .execute(&mut **tx)
+        .await
+        .map(|_| ())
+        .map_err(|e| log_error(DBError::from(e)))
+}
\ No newline at end of file
@@ -0,0 +1,187 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use futures_util::future::BoxFuture;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use sqlx::{Describe, Either, Execute, Executor, MySql, Pool, Transaction};
+use sqlx::mysql::{MySqlQueryResult, MySqlRow, MySqlStatement, MySqlTypeInfo};
+use sqlx::pool::PoolConnection;
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+/// Queries that timed out waiting for a connection, since last taken.
+static ACQUIRE_TIMEOUTS: AtomicU64 = AtomicU64::new(0);
+/// Connections acquired, and the microseconds waited on them, since last taken.
+static ACQUIRES: AtomicU64 = AtomicU64::new(0);
+static ACQUIRE_WAIT_MICROS: AtomicU64 = AtomicU64::new(0);
+
+/// The connection pool, recording how long each query and transaction waits for
+/// its connection. The rows of a query are read before they are returned, as
+/// `fetch_all` would.
+#[derive(Debug, Clone)]
+pub struct TimedPool {
+    pool: Pool<MySql>
+}
+
+impl TimedPool {
+    pub fn new(pool: Pool<MySql>) -> TimedPool {
+        TimedPool { pool }
+    }
+
+    pub fn inner(&self) -> &Pool<MySql> {
+        &self.pool
+    }
+
+    pub async fn acquire(&self) -> Result<PoolConnection<MySql>, sqlx::Error> {
+        let started = Instant::now();
+        let conn = self.pool.acquire().await?;
+        ACQUIRES.fetch_add(1, Ordering::Relaxed);
+        ACQUIRE_WAIT_MICROS.fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+        Ok(conn)
+    }
+
+    pub async fn begin(&self) -> Result<Transaction<'static, MySql>, sqlx::Error> {
+        Transaction::begin(self.acquire().await?).await
+    }
+}
+
+impl<'p> Executor<'p> for &'_ TimedPool {
+    type Database = MySql;
+
+    fn fetch_many<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxStream<'e, Result<Either<MySqlQueryResult, MySqlRow>, sqlx::Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, MySql>,
+    {
+        let pool = self.clone();
+        Box::pin(stream::once(async move {
+            let results = match pool.acquire().await {
+                Ok(mut conn) => conn.fetch_many(query).collect::<Vec<_>>().await,
+                Err(e) => vec![Err(e)]
+            };
+            stream::iter(results)
+        }).flatten())
+    }
+
+    fn fetch_optional<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxFuture<'e, Result<Option<MySqlRow>, sqlx::Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, MySql>,
+    {
+        let pool = self.clone();
+        Box::pin(async move { pool.acquire().await?.fetch_optional(query).await })
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [MySqlTypeInfo],
+    ) -> BoxFuture<'e, Result<MySqlStatement<'q>, sqlx::Error>>
+    where
+        'p: 'e,
+    {
+        let pool = self.clone();
+        Box::pin(async move { pool.acquire().await?.prepare_with(sql, parameters).await })
+    }
+
+    fn describe<'e, 'q: 'e>(self, sql: &'q str) -> BoxFuture<'e, Result<Describe<MySql>, sqlx::Error>>
+    where
+        'p: 'e,
+    {
+        let pool = self.clone();
+        Box::pin(async move { pool.acquire().await?.describe(sql).await })
+    }
+}
+
+/// The connections of the pool at an instant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolStats {
+    /// Open connections, idle or in use.
+    pub size: u32,
+    pub idle: u32,
+    pub max: u32
+}
+
+impl PoolStats {
+    pub fn active(&self) -> u32 {
+        self.size.saturating_sub(self.idle)
+    }
+
+    /// Whether every connection the pool may open is in use, so that queries
+    /// wait for one to be released.
+    pub fn saturated(&self) -> bool {
+        self.idle == 0 && self.size >= self.max
+    }
+}
+
+impl Database {
+    pub fn pool_stats(&self) -> PoolStats {
+        let pool = self.pool().inner();
+        PoolStats {
+            size: pool.size(),
+            idle: pool.num_idle() as u32,
+            max: pool.options().get_max_connections()
+        }
+    }
+
+    /// Acquires and releases a connection, so that the pool is known to be reachable
+    /// even when no queries are made.
+    pub async fn probe_acquire(&self) -> DBResult<()> {
+        match self.pool().acquire().await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    #[cfg(not(feature = "chaos"))]
+    fn pool(&self) -> &TimedPool {
+        &self.conn_pool
+    }
+
+    #[cfg(feature = "chaos")]
+    fn pool(&self) -> &TimedPool {
+        self.conn_pool.inner()
+    }
+}
+
+/// Counts `err` if a query timed out waiting for a connection.
+pub(super) fn record_timeout(err: &DBError) {
+    if let DBError::SQLXError(sqlx::Error::PoolTimedOut) = err {
+        ACQUIRE_TIMEOUTS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Takes the number of acquire timeouts since the last call.
+pub fn take_acquire_timeouts() -> u64 {
+    ACQUIRE_TIMEOUTS.swap(0, Ordering::Relaxed)
+}
+
+/// Takes the number of connections acquired, and the microseconds waited on them
+/// in total, since the last call.
+pub fn take_acquire_waits() -> (u64, u64) {
+    (ACQUIRES.swap(0, Ordering::Relaxed), ACQUIRE_WAIT_MICROS.swap(0, Ordering::Relaxed))
+}
+
+#[cfg(test)]
+mod test {
+    use super::PoolStats;
+
+    #[test]
+    fn test_saturated() {
+        let stats = PoolStats { size: 10, idle: 0, max: 10 };
+        assert!(stats.saturated());
+        assert_eq!(10, stats.active());
+
+        // More connections may still be opened
+        assert!(!PoolStats { size: 4, idle: 0, max: 10 }.saturated());
+        assert!(!PoolStats { size: 10, idle: 1, max: 10 }.saturated());
+        assert_eq!(9, PoolStats { size: 10, idle: 1, max: 10 }.active());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use sqlx::{MySql, QueryBuilder};
+
+use crate::models::{ReactionCounts, ReactionState};
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+/// What a reaction is on. A reaction refers to either a post or a comment.
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    Post(u64),
+    Comment(u64)
+}
+
+impl Target {
+    fn table(self) -> &'static str {
+        match self {
+            Target::Post(_) => "Post",
+            Target::Comment(_) => "Comment"
+        }
+    }
+
+    /// The column of the Reaction table referring to the target.
+    fn column(self) -> &'static str {
+        match self {
+            Target::Post(_) => "post_id",
+            Target::Comment(_) => "comment_id"
+        }
+    }
+
+    fn id(self) -> u64 {
+        match self {
+            Target::Post(id) | Target::Comment(id) => id
+        }
+    }
+}
+
+impl Database {
+    /// Reacts to the post with `emoji` for the account if it has not already,
+    /// otherwise removes the reaction. Results in `DBError::NoResult` if the post
+    /// does not exist.
+    pub async fn toggle_post_reaction(&self, post_id: u64, account_id: u64, emoji: &str) -> DBResult<ReactionState> {
+        let reacted = self.toggle_reaction(Target::Post(post_id), account_id, emoji).await?;
+        let mut reactions = self.read_post_reactions(&[post_id]).await?;
+        Ok(ReactionState { reacted, reactions: reactions.remove(&post_id).unwrap_or_default() })
+    }
+
+    /// As `toggle_post_reaction`, for a comment.
+    pub async fn toggle_comment_reaction(&self, comment_id: u64, account_id: u64, emoji: &str) -> DBResult<ReactionState> {
+        let reacted = self.toggle_reaction(Target::Comment(comment_id), account_id, emoji).await?;
+        let mut reactions = self.read_comment_reactions(&[comment_id]).await?;
+        Ok(ReactionState { reacted, reactions: reactions.remove(&comment_id).unwrap_or_default() })
+    }
+
+    /// Reactions to each of `post_ids`, in a single query. Every id is present in
+    /// the result, with no reactions for posts without any.
+    pub async fn read_post_reactions(&self, post_ids: &[u64]) -> DBResult<HashMap<u64, ReactionCounts>> {
+        self.read_reactions("post_id", post_ids).await
+    }
+
+    /// As `read_post_reactions`, for comments.
+    pub async fn read_comment_reactions(&self, comment_ids: &[u64]) -> DBResult<HashMap<u64, ReactionCounts>> {
+        self.read_reactions("comment_id", comment_ids).await
+    }
+
+    /// Toggles the reaction, returning whether the account has now reacted.
+    async fn toggle_reaction(&self, target: Target, account_id: u64, emoji: &str) -> DBResult<bool> {
+        let mut tx = self.conn_pool.begin().await.map_err(|e| log_error(DBError::from(e)))?;
+
+        // Lock the target, so that toggles of the same reaction happen one at a time
+        let exists = sqlx::query_scalar::<_, u64>(&format!("SELECT id FROM {} WHERE id = ? FOR UPDATE;", target.table()))
+            .bind(target.id())
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        if exists.is_none() {
+            return Err(DBError::NoResult)
+        }
+
+        let deleted = sqlx::query(&format!("DELETE FROM Reaction WHERE {} = ? AND account_id = ? AND emoji = ?;", target.column()))
+            .bind(target.id())
+            .bind(account_id)
+            .bind(emoji)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        let reacted = deleted.rows_affected() == 0;
+        if reacted {
+            sqlx::query(&format!("INSERT INTO Reaction ({}, account_id, emoji) VALUES (?, ?, ?);", target.column()))
+                .bind(target.id())
+                .bind(account_id)
+                .bind(emoji)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| log_error(DBError::from(e)))?;
+        }
+        tx.commit().await.map_err(|e| log_error(DBError::from(e)))?;
+        Ok(reacted)
+    }
+
+    /// Reactions to each of `ids`, of the targets that `column` refers to.
+    async fn read_reactions(&self, column: &str, ids: &[u64]) -> DBResult<HashMap<u64, ReactionCounts>> {
+        let mut reactions = ids.iter()
+            .map(|id| (*id, ReactionCounts::new()))
+            .collect::<HashMap<u64, ReactionCounts>>();
+        if ids.is_empty() {
+            return Ok(reactions)
+        }
+
+        let mut query = QueryBuilder::<MySql>::new(format!(
+            "SELECT r.{0}, r.emoji, CAST(count(*) AS UNSIGNED) FROM Reaction r WHERE r.{0} IN (", column));
+        let mut separated = query.separated(", ");
+        for id in ids {
+            separated.push_bind(*id);
+        }
+        query.push(format!(") GROUP BY r.{}, r.emoji;", column));
+
+        let result = query.build_query_as::<(u64, String, u64)>()
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(counts) => {
+                for (id, emoji, count) in counts {
+                    reactions.entry(id).or_default().insert(emoji, count);
+                }
+                Ok(reactions)
+            },
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
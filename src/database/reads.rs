@@ -0,0 +1,48 @@
+use std::collections::HashSet;
+
+use sqlx::{MySql, QueryBuilder};
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Marks the post as read by the account. Marking a post that has already
+    /// been read keeps the time it was first read.
+    pub async fn mark_post_read(&self, post_id: u64, account_id: u64) -> DBResult<()> {
+        let result = sqlx::query("INSERT IGNORE INTO PostRead (account_id, post_id) VALUES (?, ?);")
+            .bind(account_id)
+            .bind(post_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Which of `post_ids` the account has read, in a single query.
+    pub async fn read_posts_read(&self, account_id: u64, post_ids: &[u64]) -> DBResult<HashSet<u64>> {
+        if post_ids.is_empty() {
+            return Ok(HashSet::new())
+        }
+
+        let mut query = QueryBuilder::<MySql>::new("SELECT pr.post_id FROM PostRead pr WHERE pr.account_id = ");
+        query.push_bind(account_id);
+        query.push(" AND pr.post_id IN (");
+        let mut ids = query.separated(", ");
+        for post_id in post_ids {
+            ids.push_bind(*post_id);
+        }
+        query.push(");");
+
+        let result = query.build_query_scalar::<u64>()
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(read) => Ok(read.into_iter().collect()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,64 @@
+use crate::models::{NewRequestTrace, RequestTrace};
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    pub async fn create_request_trace(&self, trace: &NewRequestTrace) -> DBResult<()> {
+        let result = sqlx::query(
+            "INSERT INTO RequestTrace
+                (request_id, method, uri, request_headers, request_body, status, response_body, duration_ms)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?);")
+            .bind(&trace.request_id)
+            .bind(&trace.method)
+            .bind(&trace.uri)
+            .bind(&trace.request_headers)
+            .bind(&trace.request_body)
+            .bind(trace.status)
+            .bind(&trace.response_body)
+            .bind(trace.duration_ms)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads the recorded requests made with `request_id`, newest first. Clients
+    /// may reuse an id, e.g. when retrying, so there can be more than one.
+    pub async fn read_request_traces(&self, request_id: &str, limit: u64) -> DBResult<Vec<RequestTrace>> {
+        let result = sqlx::query_as::<_, RequestTrace>(
+            "SELECT request_id, method, uri, request_headers, request_body, status, response_body,
+                duration_ms, recorded_at
+            FROM RequestTrace
+            WHERE request_id = ?
+            ORDER BY id DESC
+            LIMIT ?;")
+            .bind(request_id)
+            .bind(limit)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(traces) => Ok(traces),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Deletes the requests recorded more than `hours` ago, returning how many.
+    pub async fn delete_request_traces_before(&self, hours: u64) -> DBResult<u64> {
+        let result = sqlx::query(
+            "DELETE FROM RequestTrace
+            WHERE recorded_at < CURRENT_TIMESTAMP() - INTERVAL ? HOUR;")
+            .bind(hours)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => Ok(res.rows_affected()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,181 @@
+use chrono::{DateTime, Utc};
+
+use crate::models::{LegalHold, RetentionPurge};
+
+use super::database::{expected_rows_affected, log_error, Database, DBResult};
+use super::error::DBError;
+
+/// Whether a hold in force covers post `p` or any of its comments.
+pub(super) const HELD: &str =
+    "EXISTS (SELECT 1 FROM LegalHold lh
+        WHERE lh.released_at IS NULL
+        AND (lh.post_id = p.id OR lh.comment_id IN (SELECT c.id FROM Comment c WHERE c.post_id = p.id)))";
+
+/// Whether a hold in force covers comment `c` or its post.
+pub(super) const COMMENT_HELD: &str =
+    "EXISTS (SELECT 1 FROM LegalHold lh
+        WHERE lh.released_at IS NULL
+        AND (lh.comment_id = c.id OR lh.post_id = c.post_id))";
+
+impl Database {
+    /// Places a legal hold on a post or comment. Results in `DBError::NoResult` if
+    /// it does not exist.
+    pub async fn create_legal_hold(
+        &self,
+        post_id: Option<u64>,
+        comment_id: Option<u64>,
+        placed_by: u64,
+        reason: &str
+    ) -> DBResult<u64> {
+        let result = sqlx::query(
+            "INSERT INTO LegalHold (post_id, comment_id, reason, placed_by)
+            SELECT ?, ?, ?, ? FROM DUAL
+            WHERE EXISTS (SELECT 1 FROM Post WHERE id = ?)
+            OR EXISTS (SELECT 1 FROM Comment WHERE id = ?);")
+            .bind(post_id)
+            .bind(comment_id)
+            .bind(reason)
+            .bind(placed_by)
+            .bind(post_id)
+            .bind(comment_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) if res.rows_affected() == 0 => Err(DBError::NoResult),
+            Ok(res) => Ok(res.last_insert_id()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Releases a legal hold. Results in `DBError::UnexpectedRowsAffected` if it
+    /// does not exist or is already released.
+    pub async fn release_legal_hold(&self, hold_id: u64, released_by: u64) -> DBResult<()> {
+        let result = sqlx::query(
+            "UPDATE LegalHold SET released_by = ?, released_at = CURRENT_TIMESTAMP()
+            WHERE id = ? AND released_at IS NULL;")
+            .bind(released_by)
+            .bind(hold_id)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => expected_rows_affected(res, 1),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads every legal hold, including released ones, newest first.
+    pub async fn read_legal_holds(&self) -> DBResult<Vec<LegalHold>> {
+        let result = sqlx::query_as::<_, LegalHold>(
+            "SELECT id, post_id, comment_id, reason, placed_by, placed_at, released_by, released_at
+            FROM LegalHold
+            ORDER BY id DESC;")
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(holds) => Ok(holds),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads the posts purged by the retention job, newest first.
+    pub async fn read_retention_purges(&self, limit: u64) -> DBResult<Vec<RetentionPurge>> {
+        let result = sqlx::query_as::<_, RetentionPurge>(
+            "SELECT post_id, poster_id, comment_count, deleted_at, purged_at
+            FROM RetentionPurge
+            ORDER BY id DESC
+            LIMIT ?;")
+            .bind(limit)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(purges) => Ok(purges),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads up to `limit` posts soft-deleted at least `days` ago and not under a
+    /// legal hold.
+    pub async fn read_purgeable_posts(&self, days: u64, limit: u64) -> DBResult<Vec<u64>> {
+        let result = sqlx::query_scalar::<_, u64>(&format!(
+            "SELECT p.id FROM Post p
+            WHERE p.deleted_at <= CURRENT_TIMESTAMP() - INTERVAL ? DAY
+            AND NOT {}
+            ORDER BY p.id
+            LIMIT ?;", HELD))
+            .bind(days)
+            .bind(limit)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(post_ids) => Ok(post_ids),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Permanently deletes a soft-deleted post, with its comments and likes, and
+    /// logs the purge. Returns whether it was purged, which it is not if it has
+    /// been put under a legal hold since being read.
+    pub async fn purge_post(&self, post_id: u64) -> DBResult<bool> {
+        let mut tx = self.conn_pool.begin().await.map_err(|e| log_error(DBError::from(e)))?;
+
+        let post = sqlx::query_as::<_, (u64, DateTime<Utc>, u64)>(&format!(
+            "SELECT p.poster_id, p.deleted_at, CAST({} AS UNSIGNED) FROM Post p
+            WHERE p.id = ? AND p.deleted_at IS NOT NULL
+            FOR UPDATE;", HELD))
+            .bind(post_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        let Some((poster_id, deleted_at, 0)) = post else {
+            return Ok(false)
+        };
+
+        sqlx::query(
+            "DELETE cl FROM CommentLike cl
+            INNER JOIN Comment c ON cl.comment_id = c.id
+            WHERE c.post_id = ?;")
+            .bind(post_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        // Replies refer to the comments being deleted alongside them
+        sqlx::query("UPDATE Comment SET comment_reply_id = NULL WHERE post_id = ?;")
+            .bind(post_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        let comments = sqlx::query("DELETE FROM Comment WHERE post_id = ?;")
+            .bind(post_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        sqlx::query("DELETE FROM PostLike WHERE post_id = ?;")
+            .bind(post_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+        sqlx::query("DELETE FROM Post WHERE id = ?;")
+            .bind(post_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+
+        sqlx::query(
+            "INSERT INTO RetentionPurge (post_id, poster_id, comment_count, deleted_at) VALUES (?, ?, ?, ?);")
+            .bind(post_id)
+            .bind(poster_id)
+            .bind(comments.rows_affected())
+            .bind(deleted_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| log_error(DBError::from(e)))?;
+
+        tx.commit().await.map_err(|e| log_error(DBError::from(e)))?;
+        Ok(true)
+    }
+}
\ No newline at end of file
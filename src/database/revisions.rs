@@ -0,0 +1,22 @@
+use crate::models::PostRevision;
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Reads the previous versions of a post, oldest first.
+    pub async fn read_post_revisions(&self, post_id: u64) -> DBResult<Vec<PostRevision>> {
+        let result = sqlx::query_as!(PostRevision,
+            "SELECT id, post_id, title, body, time_stamp
+            FROM PostRevision
+            WHERE post_id = ?
+            ORDER BY id;", post_id)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(revisions) => Ok(revisions),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
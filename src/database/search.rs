@@ -0,0 +1,130 @@
+use sqlx::{MySql, QueryBuilder};
+
+use crate::models::UserComment;
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+/// Words shorter than this are not in InnoDB's FULLTEXT indexes
+/// (`innodb_ft_min_token_size`), so cannot be matched through them.
+const MIN_INDEXED_WORD: usize = 3;
+/// Characters of a query matched by LIKE, which scans every searched comment.
+const MAX_LIKE_LENGTH: usize = 16;
+
+/// How a search query is matched against comment bodies.
+#[derive(Debug, PartialEq)]
+pub enum Terms {
+    /// A boolean mode FULLTEXT expression requiring every word long enough to be
+    /// indexed, by prefix.
+    FullText(String),
+    /// A LIKE pattern of the whole query, for short queries with no word long
+    /// enough to be indexed.
+    Like(String)
+}
+
+impl Terms {
+    /// The terms of `query`. `None` if it can't be matched through the index and
+    /// is too long to be matched by LIKE.
+    pub fn of(query: &str) -> Option<Terms> {
+        let words: Vec<String> = query.split_whitespace()
+            .map(|word| word.chars().filter(|c| c.is_alphanumeric()).collect::<String>())
+            .filter(|word| word.chars().count() >= MIN_INDEXED_WORD)
+            .collect();
+        if !words.is_empty() {
+            return Some(Terms::FullText(words.iter().map(|word| format!("+{}*", word)).collect::<Vec<String>>().join(" ")))
+        }
+        let query = query.trim();
+        if query.is_empty() || query.chars().count() > MAX_LIKE_LENGTH {
+            return None
+        }
+        let escaped = query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        Some(Terms::Like(format!("%{}%", escaped)))
+    }
+}
+
+impl Database {
+    /// Reads up to `limit` comments whose body matches `terms`, newest first,
+    /// optionally only those on `post_id` or by `user_id`. Pages continue from the
+    /// `before` id. Only comments on public posts are searched.
+    pub async fn search_comments(
+        &self,
+        terms: &Terms,
+        post_id: Option<u64>,
+        user_id: Option<u64>,
+        limit: u64,
+        before: Option<u64>
+    ) -> DBResult<Vec<UserComment>> {
+        let mut search = QueryBuilder::<MySql>::new(
+            "SELECT c.id, c.post_id, p.title AS post_title, c.commenter_id, c.body, c.body_format,
+                c.comment_reply_id, c.time_stamp, c.updated_at, c.edited, c.like_count AS likes
+            FROM Comment c
+            INNER JOIN Post p ON c.post_id = p.id");
+        push_filters(&mut search, terms, post_id, user_id);
+        search.push(" AND c.id < ").push_bind(before.unwrap_or(u64::MAX));
+        search.push(" ORDER BY c.id DESC LIMIT ").push_bind(limit);
+
+        let result = search.build_query_as::<UserComment>()
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(comments) => Ok(comments),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Comments that `search_comments` would find across all pages.
+    pub async fn count_search_comments(&self, terms: &Terms, post_id: Option<u64>, user_id: Option<u64>) -> DBResult<u64> {
+        let mut count = QueryBuilder::<MySql>::new(
+            "SELECT CAST(count(*) AS UNSIGNED)
+            FROM Comment c
+            INNER JOIN Post p ON c.post_id = p.id");
+        push_filters(&mut count, terms, post_id, user_id);
+
+        let result = count.build_query_scalar()
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(total) => Ok(total),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
+
+fn push_filters(query: &mut QueryBuilder<'_, MySql>, terms: &Terms, post_id: Option<u64>, user_id: Option<u64>) {
+    query.push(" WHERE NOT c.held AND NOT p.held AND p.deleted_at IS NULL AND p.visibility = 'public'");
+    match terms {
+        Terms::FullText(expression) => {
+            query.push(" AND MATCH (c.body) AGAINST (").push_bind(expression.clone()).push(" IN BOOLEAN MODE)")
+        },
+        Terms::Like(pattern) => query.push(" AND c.body LIKE ").push_bind(pattern.clone())
+    };
+    if let Some(post_id) = post_id {
+        query.push(" AND c.post_id = ").push_bind(post_id);
+    }
+    if let Some(user_id) = user_id {
+        query.push(" AND c.commenter_id = ").push_bind(user_id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Terms;
+
+    #[test]
+    fn test_terms() {
+        assert_eq!(Some(Terms::FullText("+rust* +async*".to_string())), Terms::of("  rust  async "));
+        // Boolean mode operators are dropped
+        assert_eq!(Some(Terms::FullText("+rust* +lang*".to_string())), Terms::of("+rust -lang*"));
+        // Words too short to be indexed are dropped while any other is left
+        assert_eq!(Some(Terms::FullText("+fun*".to_string())), Terms::of("go is fun"));
+
+        // Only short queries without an indexed word are matched by LIKE
+        assert_eq!(Some(Terms::Like("%go is ok%".to_string())), Terms::of("go is ok"));
+        assert_eq!(Some(Terms::Like("%5\\% \\_ok%".to_string())), Terms::of("5% _ok"));
+        assert_eq!(Some(Terms::Like("%!!%".to_string())), Terms::of("!!"));
+        assert_eq!(None, Terms::of("a b c d e f g h i"));
+        assert_eq!(None, Terms::of("   "));
+    }
+}
\ No newline at end of file
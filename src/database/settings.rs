@@ -0,0 +1,53 @@
+use crate::models::{AccountSettings, AccountSettingsUpdate};
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Reads the privacy settings of `account_id`. Accounts that have never
+    /// changed their settings have no row, and receive the default settings.
+    pub async fn read_account_settings(&self, account_id: u64) -> DBResult<AccountSettings> {
+        let result = sqlx::query_as!(AccountSettings,
+            "SELECT show_likes as `show_likes: _`, show_posts as `show_posts: _`,
+                allow_mentions as `allow_mentions: _`,
+                allow_direct_messages as `allow_direct_messages: _`
+            FROM AccountSettings
+            WHERE account_id = ?;", account_id)
+            .fetch_optional(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(Some(settings)) => Ok(settings),
+            Ok(None) => Ok(AccountSettings::default()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Creates or overwrites the privacy settings of `account_id`.
+    /// 
+    /// Note: MySQL reports 0 rows affected when the settings are unchanged, so
+    ///       the affected row count is not checked.
+    pub async fn update_account_settings(&self, account_id: u64, settings: &AccountSettingsUpdate) -> DBResult<()> {
+        let result = sqlx::query(
+            "INSERT INTO AccountSettings
+                (account_id, show_likes, show_posts, allow_mentions, allow_direct_messages)
+            VALUES (?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                show_likes = VALUES(show_likes),
+                show_posts = VALUES(show_posts),
+                allow_mentions = VALUES(allow_mentions),
+                allow_direct_messages = VALUES(allow_direct_messages);")
+            .bind(account_id)
+            .bind(settings.show_likes)
+            .bind(settings.show_posts)
+            .bind(settings.allow_mentions)
+            .bind(settings.allow_direct_messages)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,65 @@
+use crate::models::{SharedSignal, SignalKind};
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Records that `account_id` logged in with the signal, or when it last did.
+    pub async fn record_login_signal(&self, account_id: u64, kind: SignalKind, signal_hash: &str) -> DBResult<()> {
+        let result = sqlx::query(
+            "INSERT INTO LoginSignal (account_id, kind, signal_hash)
+            VALUES (?, ?, ?)
+            ON DUPLICATE KEY UPDATE last_seen = CURRENT_TIMESTAMP();")
+            .bind(account_id)
+            .bind(kind)
+            .bind(signal_hash)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads the signals that between 2 and `max_accounts` accounts have logged in
+    /// with, by signal.
+    pub async fn read_shared_login_signals(&self, max_accounts: u64) -> DBResult<Vec<SharedSignal>> {
+        let result = sqlx::query_as::<_, SharedSignal>(
+            "SELECT s.account_id, a.username,
+                CAST((SELECT count(*) FROM ModerationAction m WHERE m.account_id = s.account_id) AS UNSIGNED) AS 'removals',
+                s.kind, s.signal_hash
+            FROM LoginSignal s
+            INNER JOIN (
+                SELECT kind, signal_hash
+                FROM LoginSignal
+                GROUP BY kind, signal_hash
+                HAVING count(*) BETWEEN 2 AND ?
+            ) shared ON s.kind = shared.kind AND s.signal_hash = shared.signal_hash
+            INNER JOIN Account a ON a.id = s.account_id
+            ORDER BY s.kind, s.signal_hash, s.account_id;")
+            .bind(max_accounts)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(signals) => Ok(signals),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Deletes the signals last seen more than `days` ago, returning how many.
+    pub async fn delete_login_signals_before(&self, days: u64) -> DBResult<u64> {
+        let result = sqlx::query(
+            "DELETE FROM LoginSignal
+            WHERE last_seen < CURRENT_TIMESTAMP() - INTERVAL ? DAY;")
+            .bind(days)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(res) => Ok(res.rows_affected()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use sqlx::{MySql, QueryBuilder};
+
+use crate::models::{AccountActivity, PostAudience, UserStats};
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Aggregates the activity of an account in a single query. Both like counts
+    /// are always read, they are only `Option`s to be hidden from viewers.
+    pub async fn read_user_stats(&self, user_id: u64) -> DBResult<UserStats> {
+        let result = sqlx::query_as!(UserStats,
+            "SELECT CAST(a.id AS UNSIGNED) AS 'account_id',
+                CAST((SELECT count(*) FROM Post p WHERE p.poster_id = a.id) AS UNSIGNED) AS 'post_count',
+                CAST((SELECT count(*) FROM Comment c WHERE c.commenter_id = a.id) AS UNSIGNED) AS 'comment_count',
+                CAST(
+                    (SELECT count(*) FROM PostLike pl
+                        INNER JOIN Post p ON pl.post_id = p.id
+                        WHERE p.poster_id = a.id)
+                    + (SELECT count(*) FROM CommentLike cl
+                        INNER JOIN Comment c ON cl.comment_id = c.id
+                        WHERE c.commenter_id = a.id)
+                AS UNSIGNED) AS 'likes_received?',
+                CAST(
+                    (SELECT count(*) FROM PostLike pl WHERE pl.account_id = a.id)
+                    + (SELECT count(*) FROM CommentLike cl WHERE cl.account_id = a.id)
+                AS UNSIGNED) AS 'likes_given?',
+                a.created_at,
+                CAST(TIMESTAMPDIFF(SECOND, a.created_at, CURRENT_TIMESTAMP()) AS SIGNED) AS 'account_age_sec'
+            FROM Account a
+            WHERE a.id = ?;", user_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(stats) => Ok(stats),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    pub async fn read_account_activity(&self, account_id: u64) -> DBResult<AccountActivity> {
+        let result = sqlx::query_as!(AccountActivity,
+            "SELECT a.created_at,
+                CAST((SELECT count(*) FROM Post p WHERE p.poster_id = a.id) AS UNSIGNED) AS 'post_count',
+                CAST((SELECT count(*) FROM Post p
+                    WHERE p.poster_id = a.id
+                    AND p.time_stamp > CURRENT_TIMESTAMP() - INTERVAL 1 DAY) AS UNSIGNED) AS 'posts_today',
+                CAST((SELECT count(*) FROM Comment c
+                    WHERE c.commenter_id = a.id
+                    AND c.time_stamp > CURRENT_TIMESTAMP() - INTERVAL 1 DAY) AS UNSIGNED) AS 'comments_today'
+            FROM Account a
+            WHERE a.id = ?;", account_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(activity) => Ok(activity),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Counts the listed posts of a user that are public or in `audience`, as read
+    /// by `read_posts_by_user`.
+    pub async fn count_posts_by_user(&self, user_id: u64, audience: PostAudience) -> DBResult<u64> {
+        let result = sqlx::query_scalar::<_, u64>(
+            "SELECT CAST(count(*) AS UNSIGNED) FROM Post
+            WHERE poster_id = ?
+            AND deleted_at IS NULL
+            AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP())
+            AND NOT held
+            AND (visibility = 'public'
+                OR (visibility = 'unlisted' AND ?)
+                OR (visibility = 'followers' AND ?));")
+            .bind(user_id)
+            .bind(audience.unlisted)
+            .bind(audience.followers)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(count) => Ok(count),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Number of comments of a user on listed public posts, the same for every viewer
+    /// so that it can be cached once.
+    pub async fn count_comments_by_user(&self, user_id: u64) -> DBResult<u64> {
+        let result = sqlx::query_scalar::<_, u64>(
+            "SELECT CAST(count(*) AS UNSIGNED) FROM Comment c
+            INNER JOIN Post p ON c.post_id = p.id
+            WHERE c.commenter_id = ?
+            AND NOT c.held
+            AND p.deleted_at IS NULL
+            AND (p.expires_at IS NULL OR p.expires_at > CURRENT_TIMESTAMP())
+            AND NOT p.held
+            AND p.visibility = 'public';")
+            .bind(user_id)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(count) => Ok(count),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+    /// Number of comments on each of `post_ids`, in a single query. Every id is
+    /// present in the result, with 0 for posts without comments.
+    pub async fn read_comment_counts(&self, post_ids: &[u64]) -> DBResult<HashMap<u64, u64>> {
+        if post_ids.is_empty() {
+            return Ok(HashMap::new())
+        }
+
+        let mut query = QueryBuilder::<MySql>::new(
+            "SELECT c.post_id, CAST(count(*) AS UNSIGNED) FROM Comment c WHERE NOT c.held AND c.post_id IN (");
+        let mut ids = query.separated(", ");
+        for post_id in post_ids {
+            ids.push_bind(*post_id);
+        }
+        query.push(") GROUP BY c.post_id;");
+
+        let result = query.build_query_as::<(u64, u64)>()
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(counts) => Ok(post_ids.iter().map(|post_id| (*post_id, 0)).chain(counts).collect()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
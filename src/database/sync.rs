@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+
+use crate::models::{Comment, Post};
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// The current time by the database's clock, which sets the `time_stamp` and
+    /// `updated_at` of posts and comments. Compare against those with this rather
+    /// than the server's clock, which may be skewed from it.
+    pub async fn read_current_time(&self) -> DBResult<DateTime<Utc>> {
+        let result = sqlx::query_scalar::<_, DateTime<Utc>>("SELECT UTC_TIMESTAMP();")
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(now) => Ok(now),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads the public posts that were created or edited at or after `since`.
+    pub async fn read_posts_updated_since(&self, since: DateTime<Utc>) -> DBResult<Vec<Post>> {
+        let result = sqlx::query_as!(Post,
+            "SELECT p.id, p.poster_id, p.kind as `kind: _`, p.title, p.url, p.alt_text, p.body, p.tldr, p.body_format as `body_format: _`,
+                p.comment_mode as `comment_mode: _`, p.visibility as `visibility: _`, p.time_stamp, p.updated_at, p.expires_at,
+                p.body_edited as `body_edited: _`, p.title_edited as `title_edited: _`,
+                p.like_count AS 'likes'
+            FROM Post p
+            WHERE p.updated_at >= ?
+            AND NOT p.held
+            AND p.deleted_at IS NULL
+            AND p.visibility = 'public'
+            ORDER BY p.updated_at;", since)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(posts) => Ok(posts),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads the comments on public posts that were created or edited at or after
+    /// `since`.
+    pub async fn read_comments_updated_since(&self, since: DateTime<Utc>) -> DBResult<Vec<Comment>> {
+        let result = sqlx::query_as!(Comment,
+            "SELECT c.id, c.post_id, c.commenter_id, c.body, c.body_format as `body_format: _`, c.comment_reply_id,
+                c.time_stamp, c.updated_at, c.edited as `edited: _`,
+                c.like_count AS 'likes'
+            FROM Comment c
+            JOIN Post p ON p.id = c.post_id
+            WHERE c.updated_at >= ?
+            AND NOT c.held
+            AND NOT p.held
+            AND p.deleted_at IS NULL
+            AND p.visibility = 'public'
+            ORDER BY c.updated_at;", since)
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(comments) => Ok(comments),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,33 @@
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+impl Database {
+    /// Records that the account accepted `version` of the terms. Accepting a
+    /// version again keeps the time it was first accepted.
+    pub async fn accept_terms(&self, account_id: u64, version: &str) -> DBResult<()> {
+        let result = sqlx::query("INSERT IGNORE INTO TermsAcceptance (account_id, version) VALUES (?, ?);")
+            .bind(account_id)
+            .bind(version)
+            .execute(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    pub async fn has_accepted_terms(&self, account_id: u64, version: &str) -> DBResult<bool> {
+        let result = sqlx::query_scalar::<_, u64>(
+            "SELECT CAST(count(*) AS UNSIGNED) FROM TermsAcceptance WHERE account_id = ? AND version = ?;")
+            .bind(account_id)
+            .bind(version)
+            .fetch_one(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(count) => Ok(count > 0),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use sqlx::{MySql, QueryBuilder};
+
+use crate::models::Comment;
+
+use super::database::{log_error, Database, DBResult};
+use super::error::DBError;
+
+const COMMENT_COLUMNS: &str = "c.id, c.post_id, c.commenter_id, c.body, c.body_format, c.comment_reply_id,
+    c.time_stamp, c.updated_at, c.edited, c.like_count AS likes";
+
+impl Database {
+    /// Reads up to `limit` comments of a post, oldest first, continuing from the
+    /// `after` id and leaving out the `excluded` one. With `top_level`, only the
+    /// comments that are not replies, or that reply to a held comment, are read.
+    pub async fn read_thread_comments(
+        &self,
+        post_id: u64,
+        top_level: bool,
+        limit: u64,
+        after: Option<u64>,
+        excluded: Option<u64>
+    ) -> DBResult<Vec<Comment>> {
+        let mut query = QueryBuilder::<MySql>::new(format!("SELECT {} FROM Comment c", COMMENT_COLUMNS));
+        push_filters(&mut query, post_id, top_level);
+        query.push(" AND c.id > ").push_bind(after.unwrap_or(0));
+        if let Some(excluded) = excluded {
+            query.push(" AND c.id <> ").push_bind(excluded);
+        }
+        query.push(" ORDER BY c.id LIMIT ").push_bind(limit);
+
+        let result = query.build_query_as::<Comment>()
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(comments) => Ok(comments),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads a comment of a post, if `read_thread_comments` would read it.
+    pub async fn read_thread_comment(&self, post_id: u64, comment_id: u64, top_level: bool) -> DBResult<Option<Comment>> {
+        let mut query = QueryBuilder::<MySql>::new(format!("SELECT {} FROM Comment c", COMMENT_COLUMNS));
+        push_filters(&mut query, post_id, top_level);
+        query.push(" AND c.id = ").push_bind(comment_id);
+
+        let result = query.build_query_as::<Comment>()
+            .fetch_optional(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(comment) => Ok(comment),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// Reads the first `limit` replies to each of `comment_ids`, oldest first.
+    pub async fn read_replies(&self, comment_ids: &[u64], limit: u64) -> DBResult<Vec<Comment>> {
+        if comment_ids.is_empty() {
+            return Ok(Vec::new())
+        }
+
+        let mut query = QueryBuilder::<MySql>::new(format!(
+            "SELECT id, post_id, commenter_id, body, body_format, comment_reply_id, time_stamp, updated_at, edited, likes
+            FROM (
+                SELECT {}, ROW_NUMBER() OVER (PARTITION BY c.comment_reply_id ORDER BY c.id) AS reply_rank
+                FROM Comment c
+                WHERE NOT c.held AND c.comment_reply_id IN (", COMMENT_COLUMNS));
+        let mut ids = query.separated(", ");
+        for comment_id in comment_ids {
+            ids.push_bind(*comment_id);
+        }
+        query.push(")) replies WHERE reply_rank <= ").push_bind(limit);
+        query.push(" ORDER BY id;");
+
+        let result = query.build_query_as::<Comment>()
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(replies) => Ok(replies),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+
+    /// The number of replies to each of `comment_ids`, held ones aside.
+    pub async fn read_reply_counts(&self, comment_ids: &[u64]) -> DBResult<HashMap<u64, u64>> {
+        if comment_ids.is_empty() {
+            return Ok(HashMap::new())
+        }
+
+        let mut query = QueryBuilder::<MySql>::new(
+            "SELECT c.comment_reply_id, CAST(count(*) AS UNSIGNED) FROM Comment c WHERE NOT c.held AND c.comment_reply_id IN (");
+        let mut ids = query.separated(", ");
+        for comment_id in comment_ids {
+            ids.push_bind(*comment_id);
+        }
+        query.push(") GROUP BY c.comment_reply_id;");
+
+        let result = query.build_query_as::<(u64, u64)>()
+            .fetch_all(&self.conn_pool)
+            .await;
+
+        match result {
+            Ok(counts) => Ok(comment_ids.iter().map(|comment_id| (*comment_id, 0)).chain(counts).collect()),
+            Err(e) => Err(log_error(DBError::from(e)))
+        }
+    }
+}
+
+fn push_filters(query: &mut QueryBuilder<'_, MySql>, post_id: u64, top_level: bool) {
+    if top_level {
+        query.push(" LEFT JOIN Comment parent ON c.comment_reply_id = parent.id");
+    }
+    query.push(" WHERE c.post_id = ").push_bind(post_id).push(" AND NOT c.held");
+    if top_level {
+        query.push(" AND (parent.id IS NULL OR parent.held)");
+    }
+}
\ No newline at end of file
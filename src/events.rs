@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::PostVisibility;
+
+/// A change to the data that other systems may want to know about. Events are
+/// written to the Outbox table in the same transaction as the change, and relayed
+/// by `jobs::outbox`, so an event is never lost, but may be delivered more than once.
+/// Projections, such as `jobs::feed`, read them back from the outbox.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DomainEvent {
+    /// A held post is not visible until a moderator approves it.
+    PostCreated {
+        #[serde(with = "crate::ids::public")]
+        post_id: u64,
+        #[serde(with = "crate::ids::public")]
+        poster_id: u64,
+        visibility: PostVisibility,
+        held: bool
+    },
+    PostDeleted {
+        #[serde(with = "crate::ids::public")]
+        post_id: u64
+    },
+    /// A held comment is not visible until a moderator approves it.
+    CommentCreated {
+        #[serde(with = "crate::ids::public")]
+        comment_id: u64,
+        #[serde(with = "crate::ids::public")]
+        post_id: u64,
+        #[serde(with = "crate::ids::public")]
+        commenter_id: u64,
+        held: bool
+    },
+    /// A like of a post or comment was added or removed. Exactly one of `post_id`
+    /// and `comment_id` is present.
+    VoteChanged {
+        #[serde(default, with = "crate::ids::public_opt", skip_serializing_if = "Option::is_none")]
+        post_id: Option<u64>,
+        #[serde(default, with = "crate::ids::public_opt", skip_serializing_if = "Option::is_none")]
+        comment_id: Option<u64>,
+        #[serde(with = "crate::ids::public")]
+        account_id: u64,
+        liked: bool,
+        likes: u64
+    },
+    AccountFollowed {
+        #[serde(with = "crate::ids::public")]
+        follower_id: u64,
+        #[serde(with = "crate::ids::public")]
+        followee_id: u64
+    },
+    AccountUnfollowed {
+        #[serde(with = "crate::ids::public")]
+        follower_id: u64,
+        #[serde(with = "crate::ids::public")]
+        followee_id: u64
+    }
+}
+
+impl DomainEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DomainEvent::PostCreated { .. } => "post_created",
+            DomainEvent::PostDeleted { .. } => "post_deleted",
+            DomainEvent::CommentCreated { .. } => "comment_created",
+            DomainEvent::VoteChanged { .. } => "vote_changed",
+            DomainEvent::AccountFollowed { .. } => "account_followed",
+            DomainEvent::AccountUnfollowed { .. } => "account_unfollowed"
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::models::PostVisibility;
+    use super::DomainEvent;
+
+    #[test]
+    fn test_serialise() {
+        let event = DomainEvent::PostCreated { post_id: 7, poster_id: 1, visibility: PostVisibility::Public, held: false };
+        assert_eq!(
+            json!({"type": "post_created", "post_id": 7, "poster_id": 1, "visibility": "public", "held": false}),
+            serde_json::to_value(&event).unwrap()
+        );
+        assert_eq!("post_deleted", DomainEvent::PostDeleted { post_id: 7 }.name());
+        assert_eq!(Some("post_deleted"), serde_json::to_value(DomainEvent::PostDeleted { post_id: 7 }).unwrap()["type"].as_str());
+
+        let vote = DomainEvent::VoteChanged { post_id: None, comment_id: Some(4), account_id: 2, liked: true, likes: 9 };
+        assert_eq!(
+            json!({"type": "vote_changed", "comment_id": 4, "account_id": 2, "liked": true, "likes": 9}),
+            serde_json::to_value(&vote).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let events = [
+            DomainEvent::PostCreated { post_id: 7, poster_id: 1, visibility: PostVisibility::Followers, held: true },
+            DomainEvent::VoteChanged { post_id: Some(7), comment_id: None, account_id: 2, liked: false, likes: 0 },
+            DomainEvent::AccountFollowed { follower_id: 1, followee_id: 2 }
+        ];
+        for event in events {
+            let json = serde_json::to_string(&event).unwrap();
+            assert_eq!(event, serde_json::from_str::<DomainEvent>(&json).unwrap());
+        }
+    }
+}
\ No newline at end of file
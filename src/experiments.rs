@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+use sha2::{Digest, Sha256};
+
+use crate::config::config::ExperimentConfig;
+
+/// Longest anonymous id accepted, as limited by the ExperimentExposure table.
+pub const MAX_ANON_ID_LENGTH: usize = 64;
+
+/// Who variants are assigned to: an account, or an anonymous client by an id it
+/// generated itself. The two never share an assignment.
+pub fn subject(account_id: Option<u64>, anon_id: Option<&str>) -> Option<String> {
+    match (account_id, anon_id) {
+        (Some(account_id), _) => Some(format!("account:{}", account_id)),
+        (None, Some(anon_id)) if valid_anon_id(anon_id) => Some(format!("anon:{}", anon_id)),
+        (None, _) => None
+    }
+}
+
+pub fn valid_anon_id(anon_id: &str) -> bool {
+    !anon_id.is_empty()
+        && anon_id.len() <= MAX_ANON_ID_LENGTH
+        && anon_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The variant of `experiment` assigned to `subject`. The hash of the two picks
+/// a bucket in the total weight of the variants, so the assignment only changes
+/// if the variants or their weights do. `None` if no variant has any weight.
+pub fn assign<'a>(experiment: &str, config: &'a ExperimentConfig, subject: &str) -> Option<&'a str> {
+    let total: u64 = config.variants.values().map(|weight| *weight as u64).sum();
+    if total == 0 {
+        return None
+    }
+    let digest = Sha256::new()
+        .chain_update(experiment)
+        .chain_update([0])
+        .chain_update(subject)
+        .finalize();
+    let mut bucket = u64::from_be_bytes(digest[..8].try_into().unwrap()) % total;
+    for (variant, weight) in &config.variants {
+        if bucket < *weight as u64 {
+            return Some(variant)
+        }
+        bucket -= *weight as u64;
+    }
+    None
+}
+
+/// The variant of each enabled experiment assigned to `subject`.
+pub fn assignments<'a>(
+    experiments: &'a BTreeMap<String, ExperimentConfig>,
+    subject: &str
+) -> BTreeMap<&'a str, &'a str> {
+    experiments.iter()
+        .filter(|(_, config)| config.enabled)
+        .filter_map(|(name, config)| Some((name.as_str(), assign(name, config, subject)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use crate::config::config::ExperimentConfig;
+
+    use super::{assign, assignments, subject};
+
+    fn experiment(variants: &[(&str, u32)]) -> ExperimentConfig {
+        ExperimentConfig {
+            enabled: true,
+            variants: variants.iter().map(|(name, weight)| (name.to_string(), *weight)).collect()
+        }
+    }
+
+    #[test]
+    fn test_subject() {
+        assert_eq!(Some("account:7".to_string()), subject(Some(7), Some("abc")));
+        assert_eq!(Some("anon:abc-1_2".to_string()), subject(None, Some("abc-1_2")));
+        assert_eq!(None, subject(None, Some("")));
+        assert_eq!(None, subject(None, Some("a b")));
+        assert_eq!(None, subject(None, Some(&"a".repeat(65))));
+        assert_eq!(None, subject(None, None));
+    }
+
+    #[test]
+    fn test_assign() {
+        let split = experiment(&[("control", 1), ("treatment", 1)]);
+        // The same subject always gets the same variant
+        for n in 0..20 {
+            let subject = format!("account:{}", n);
+            assert_eq!(assign("composer", &split, &subject), assign("composer", &split, &subject));
+        }
+
+        // Roughly by weight
+        let treated = (0..1000)
+            .filter(|n| assign("composer", &split, &format!("account:{}", n)) == Some("treatment"))
+            .count();
+        assert!((400..600).contains(&treated), "{}", treated);
+
+        let only = experiment(&[("control", 0), ("treatment", 3)]);
+        assert_eq!(Some("treatment"), assign("composer", &only, "anon:x"));
+        assert_eq!(None, assign("composer", &experiment(&[("control", 0)]), "anon:x"));
+        assert_eq!(None, assign("composer", &experiment(&[]), "anon:x"));
+    }
+
+    #[test]
+    fn test_assignments() {
+        let mut experiments = BTreeMap::new();
+        experiments.insert("on".to_string(), experiment(&[("a", 1)]));
+        experiments.insert("off".to_string(), ExperimentConfig { enabled: false, ..experiment(&[("a", 1)]) });
+        experiments.insert("empty".to_string(), experiment(&[]));
+
+        let assigned = assignments(&experiments, "account:1");
+        assert_eq!(vec![("on", "a")], assigned.into_iter().collect::<Vec<_>>());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,112 @@
+use pulldown_cmark::{html, Event, Parser, TagEnd};
+
+use crate::models::TextFormat;
+
+/// Removes anything from an HTML `body` that could run script or break out of
+/// the element it is rendered in, keeping ordinary formatting tags.
+pub fn sanitise_html(body: &str) -> String {
+    ammonia::clean(body)
+}
+
+/// The body to store for a post or comment written in `format`.
+pub fn prepare(body: &str, format: TextFormat) -> String {
+    match format {
+        TextFormat::Html => sanitise_html(body),
+        TextFormat::Markdown | TextFormat::Plain => body.to_string()
+    }
+}
+
+/// Renders a stored `body` as HTML. Raw HTML within markdown is sanitised.
+pub fn to_html(body: &str, format: TextFormat) -> String {
+    match format {
+        TextFormat::Markdown => {
+            let mut rendered = String::new();
+            html::push_html(&mut rendered, Parser::new(body));
+            sanitise_html(&rendered)
+        },
+        TextFormat::Plain => escape_html(body),
+        TextFormat::Html => body.to_string()
+    }
+}
+
+/// The text of a stored `body`, without any markup.
+pub fn to_plain(body: &str, format: TextFormat) -> String {
+    match format {
+        TextFormat::Markdown => markdown_text(body),
+        TextFormat::Plain => body.to_string(),
+        TextFormat::Html => html_text(body)
+    }
+}
+
+fn markdown_text(body: &str) -> String {
+    let mut text = String::new();
+    for event in Parser::new(body) {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item | TagEnd::CodeBlock) => {
+                text.push('\n')
+            },
+            _ => {}
+        }
+    }
+    text.trim_end().to_string()
+}
+
+/// Strips the tags of sanitised HTML and decodes the entities it may contain.
+fn html_text(body: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in body.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::TextFormat;
+
+    use super::{prepare, to_html, to_plain};
+
+    #[test]
+    fn test_prepare() {
+        let unsafe_html = "<p onclick=\"steal()\">Hi<script>steal()</script></p>";
+        assert_eq!("<p>Hi</p>", prepare(unsafe_html, TextFormat::Html));
+        assert_eq!(unsafe_html, prepare(unsafe_html, TextFormat::Markdown));
+        assert_eq!(unsafe_html, prepare(unsafe_html, TextFormat::Plain));
+    }
+
+    #[test]
+    fn test_to_html() {
+        assert_eq!("<p><strong>bold</strong></p>\n", to_html("**bold**", TextFormat::Markdown));
+        assert_eq!("<p>x </p>\n", to_html("x <script>alert(1)</script>", TextFormat::Markdown));
+        assert_eq!("a &lt;b&gt; &amp; c", to_html("a <b> & c", TextFormat::Plain));
+        assert_eq!("<em>hi</em>", to_html("<em>hi</em>", TextFormat::Html));
+    }
+
+    #[test]
+    fn test_to_plain() {
+        assert_eq!("Title\nSome bold and code", to_plain("# Title\nSome **bold** and `code`", TextFormat::Markdown));
+        assert_eq!("a <b> & c", to_plain("<p>a &lt;b&gt; &amp; c</p>", TextFormat::Html));
+        assert_eq!("**as is**", to_plain("**as is**", TextFormat::Plain));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,82 @@
+use std::future::Future;
+use std::net::IpAddr;
+
+use actix_web::{Error, HttpMessage};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use actix_web::web::Data;
+use log::warn;
+
+/// The ISO 3166-1 code of the country a request was made from, e.g. `NZ`. Added to
+/// the extensions of each request by `middleware` when it can be looked up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Country(pub String);
+
+/// Looks up the country of client addresses in a MaxMind GeoIP2/GeoLite2 Country
+/// database. Only available when built with the `geoip` feature, and enabled by
+/// setting `GEOIP_DB_PATH`.
+pub struct GeoIp {
+    #[cfg(feature = "geoip")]
+    reader: Option<maxminddb::Reader<Vec<u8>>>
+}
+
+impl GeoIp {
+    /// Opens the database at `GEOIP_DB_PATH`. Without one, nothing is looked up.
+    pub fn from_env() -> GeoIp {
+        let path = std::env::var("GEOIP_DB_PATH").ok();
+        GeoIp::open(path.as_deref())
+    }
+
+    #[cfg(feature = "geoip")]
+    fn open(path: Option<&str>) -> GeoIp {
+        let reader = path.and_then(|path| match maxminddb::Reader::open_readfile(path) {
+            Ok(reader) => Some(reader),
+            Err(e) => {
+                warn!("geoip: failed to open the database at '{}': {}", path, e);
+                None
+            }
+        });
+        GeoIp { reader }
+    }
+
+    #[cfg(not(feature = "geoip"))]
+    fn open(path: Option<&str>) -> GeoIp {
+        if path.is_some() {
+            warn!("geoip: GEOIP_DB_PATH is set, but the server was built without the `geoip` feature");
+        }
+        GeoIp {}
+    }
+
+    #[cfg(feature = "geoip")]
+    pub fn lookup(&self, client: IpAddr) -> Option<Country> {
+        let country: maxminddb::geoip2::Country = self.reader.as_ref()?.lookup(client).ok()?;
+        Some(Country(country.country?.iso_code?.to_string()))
+    }
+
+    #[cfg(not(feature = "geoip"))]
+    pub fn lookup(&self, _client: IpAddr) -> Option<Country> {
+        None
+    }
+}
+
+/// `wrap_fn` middleware annotating each request with the `Country` of the client
+/// address, for compliance rules and the access log.
+pub fn middleware<S, B>(
+    req: ServiceRequest,
+    srv: &S
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+{
+    let country = req.app_data::<Data<GeoIp>>()
+        .zip(req.peer_addr())
+        .and_then(|(geoip, client)| geoip.lookup(client.ip()));
+    if let Some(country) = country {
+        req.extensions_mut().insert(country);
+    }
+    srv.call(req)
+}
+
+/// The country a request was made from, if it could be looked up.
+pub fn country(req: &impl HttpMessage) -> Option<Country> {
+    req.extensions().get::<Country>().cloned()
+}
\ No newline at end of file
@@ -0,0 +1 @@
+pub mod geoip;
\ No newline at end of file
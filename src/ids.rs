@@ -0,0 +1,225 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+
+const ALPHABET: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const ROUNDS: u8 = 4;
+
+static CODEC: OnceLock<Option<Codec>> = OnceLock::new();
+
+/// Keyed, reversible mapping between row ids and their opaque form.
+pub struct Codec {
+    key: [u8; 32]
+}
+
+impl Codec {
+    pub fn new(secret: &str) -> Self {
+        Codec { key: Sha256::digest(secret.as_bytes()).into() }
+    }
+
+    pub fn encode(&self, id: u64) -> String {
+        to_base62(self.permute(id))
+    }
+
+    pub fn decode(&self, public: &str) -> Option<u64> {
+        from_base62(public).map(|permuted| self.unpermute(permuted))
+    }
+
+    // A small Feistel network over the two 32 bit halves of the id
+
+    fn permute(&self, id: u64) -> u64 {
+        let (mut left, mut right) = ((id >> 32) as u32, id as u32);
+        for round in 0..ROUNDS {
+            (left, right) = (right, left ^ self.round(round, right));
+        }
+        ((left as u64) << 32) | right as u64
+    }
+
+    fn unpermute(&self, permuted: u64) -> u64 {
+        let (mut left, mut right) = ((permuted >> 32) as u32, permuted as u32);
+        for round in (0..ROUNDS).rev() {
+            (left, right) = (right ^ self.round(round, left), left);
+        }
+        ((left as u64) << 32) | right as u64
+    }
+
+    fn round(&self, round: u8, half: u32) -> u32 {
+        let digest = Sha256::new()
+            .chain_update(self.key)
+            .chain_update([round])
+            .chain_update(half.to_be_bytes())
+            .finalize();
+        u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]])
+    }
+}
+
+/// Sets how ids are exposed, for the lifetime of the process. Opaque when a
+/// `secret` is given. Ids are numeric if this is never called.
+pub fn init(secret: Option<&str>) {
+    let _ = CODEC.set(secret.map(Codec::new));
+}
+
+fn codec() -> Option<&'static Codec> {
+    CODEC.get().and_then(Option::as_ref)
+}
+
+/// Parses an id given by a client, e.g. from a path segment.
+pub fn parse(public: &str) -> Option<u64> {
+    match codec() {
+        Some(codec) => codec.decode(public),
+        None => public.parse::<u64>().ok()
+    }
+}
+
+fn to_base62(mut value: u64) -> String {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(ALPHABET[(value % 62) as usize]);
+        value /= 62;
+        if value == 0 {
+            break
+        }
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+fn from_base62(encoded: &str) -> Option<u64> {
+    if encoded.is_empty() {
+        return None
+    }
+    encoded.bytes().try_fold(0u64, |value, byte| {
+        let digit = ALPHABET.iter().position(|&c| c == byte)? as u64;
+        value.checked_mul(62)?.checked_add(digit)
+    })
+}
+
+/// A row id as seen by API clients. Rows are keyed by autoincrement u64s, which
+/// let anyone walk every post and account by counting. With a key set by `init`,
+/// ids leave and enter the API as opaque base62 strings instead: a keyed
+/// permutation of the u64, so no mapping needs to be stored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PublicId(pub u64);
+
+/// The id as a path segment, e.g. in a `Location` header. The inverse of `parse`.
+impl fmt::Display for PublicId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match codec() {
+            Some(codec) => f.write_str(&codec.encode(self.0)),
+            None => write!(f, "{}", self.0)
+        }
+    }
+}
+
+impl Serialize for PublicId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match codec() {
+            Some(codec) => serializer.serialize_str(&codec.encode(self.0)),
+            None => serializer.serialize_u64(self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(PublicIdVisitor)
+    }
+}
+
+struct PublicIdVisitor;
+
+impl<'de> Visitor<'de> for PublicIdVisitor {
+    type Value = PublicId;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match codec() {
+            Some(_) => f.write_str("an opaque id string"),
+            None => f.write_str("a numeric id")
+        }
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<PublicId, E> {
+        match codec() {
+            // Numeric ids are exactly what opaque ids hide
+            Some(_) => Err(E::invalid_type(de::Unexpected::Unsigned(value), &self)),
+            None => Ok(PublicId(value))
+        }
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<PublicId, E> {
+        match u64::try_from(value) {
+            Ok(value) => self.visit_u64(value),
+            Err(_) => Err(E::invalid_value(de::Unexpected::Signed(value), &self))
+        }
+    }
+
+    // Query strings and path segments are always strings
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<PublicId, E> {
+        parse(value)
+            .map(PublicId)
+            .ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+}
+
+/// `#[serde(with = "crate::ids::public")]` for u64 id fields.
+pub mod public {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::PublicId;
+
+    pub fn serialize<S: Serializer>(id: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        PublicId(*id).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        PublicId::deserialize(deserializer).map(|id| id.0)
+    }
+}
+
+/// `#[serde(default, with = "crate::ids::public_opt")]` for Option<u64> id fields.
+pub mod public_opt {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::PublicId;
+
+    pub fn serialize<S: Serializer>(id: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+        id.map(PublicId).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+        Option::<PublicId>::deserialize(deserializer).map(|id| id.map(|id| id.0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_base62, to_base62, Codec};
+
+    #[test]
+    fn test_base62() {
+        assert_eq!("0", to_base62(0));
+        assert_eq!("10", to_base62(62));
+        for value in [0, 1, 61, 62, 3843, 1 << 40, u64::MAX] {
+            assert_eq!(Some(value), from_base62(&to_base62(value)));
+        }
+        assert_eq!(None, from_base62(""));
+        assert_eq!(None, from_base62("not-base62"));
+        assert_eq!(None, from_base62("zzzzzzzzzzzzzzzzzzzz"));
+    }
+
+    #[test]
+    fn test_codec_round_trip() {
+        let codec = Codec::new("secret");
+        for id in [0, 1, 2, 3, 1000, u32::MAX as u64, u64::MAX] {
+            assert_eq!(Some(id), codec.decode(&codec.encode(id)));
+        }
+        // Consecutive ids do not look consecutive
+        assert_ne!(to_base62(2), codec.encode(2));
+        assert_ne!(codec.encode(1), codec.encode(2));
+        // Another key gives other ids
+        assert_ne!(Codec::new("other").encode(1), codec.encode(1));
+    }
+}
\ No newline at end of file
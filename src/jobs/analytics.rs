@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use actix_web::rt;
+use actix_web::web::Data;
+use chrono::{Days, NaiveDate, Utc};
+use log::{info, warn};
+
+use crate::database::{database::Database, error::DBError};
+use crate::metrics::metrics::Metrics;
+
+/// Days before yesterday aggregated when there are no aggregates yet.
+const BACKFILL_DAYS: u64 = 30;
+
+/// The UTC days to aggregate on `today`: each day that has ended since the `last`
+/// aggregated day.
+pub fn days_to_aggregate(last: Option<NaiveDate>, today: NaiveDate) -> Vec<NaiveDate> {
+    let Some(yesterday) = today.pred_opt() else {
+        return Vec::new()
+    };
+    let first = match last {
+        Some(last) => last.succ_opt().unwrap_or(today),
+        None => yesterday.checked_sub_days(Days::new(BACKFILL_DAYS)).unwrap_or(yesterday)
+    };
+    first.iter_days().take_while(|day| *day <= yesterday).collect()
+}
+
+/// Aggregates each day that has ended since the last run, returning how many were.
+pub async fn run(db: &Database, metrics: &Metrics) -> Result<u64, DBError> {
+    let days = days_to_aggregate(db.read_last_stats_day().await?, Utc::now().date_naive());
+    for day in &days {
+        db.aggregate_daily_stats(*day).await?;
+        info!("analytics: aggregated {}", day);
+    }
+    metrics.increment("daily_stats_aggregated_total", days.len() as u64);
+    Ok(days.len() as u64)
+}
+
+/// Spawns the analytics job onto the current runtime, running every `interval`.
+/// A day is aggregated on the first run after it ends.
+pub fn spawn(db: Data<Database>, metrics: Data<Metrics>, interval: Duration) {
+    rt::spawn(async move {
+        let mut ticker = rt::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = run(&db, &metrics).await {
+                warn!("analytics: job failed: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::days_to_aggregate;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, d).unwrap()
+    }
+
+    #[test]
+    fn test_days_to_aggregate() {
+        assert_eq!(vec![day(9)], days_to_aggregate(Some(day(8)), day(10)));
+        assert_eq!(vec![day(7), day(8), day(9)], days_to_aggregate(Some(day(6)), day(10)));
+        assert!(days_to_aggregate(Some(day(9)), day(10)).is_empty());
+        assert!(days_to_aggregate(Some(day(12)), day(10)).is_empty());
+
+        let backfill = days_to_aggregate(None, day(10));
+        assert_eq!(31, backfill.len());
+        assert_eq!(Some(&day(9)), backfill.last());
+    }
+}
\ No newline at end of file
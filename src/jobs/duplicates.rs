@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use actix_web::rt;
+use actix_web::web::Data;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::config::config::{LoginSignalConfig, SharedConfig};
+use crate::database::{database::Database, error::DBError};
+use crate::ids::PublicId;
+use crate::metrics::metrics::Metrics;
+use crate::models::{SharedSignal, SignalKind};
+
+/// Most clusters kept in a report.
+const MAX_CLUSTERS: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClusterAccount {
+    pub id: PublicId,
+    pub username: String,
+    /// Posts and comments of the account removed by moderators
+    pub removals: u64
+}
+
+/// Accounts linked by the addresses they logged in from, which may be one person
+/// with several accounts, e.g. evading the moderation of the first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DuplicateCluster {
+    pub accounts: Vec<ClusterAccount>,
+    pub shared_addresses: u64,
+    /// User agents shared by accounts of the cluster, which only add weight to
+    /// the addresses: a user agent alone is too common to link accounts.
+    pub shared_devices: u64,
+    pub removals: u64
+}
+
+/// Clusters of the most recent duplicates job run, those with the most removals
+/// first.
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateReport {
+    pub clusters: Vec<DuplicateCluster>,
+    /// Signals deleted by the run for being older than `login_signals.keep_days`
+    pub signals_deleted: u64,
+    pub checked_at: DateTime<Utc>
+}
+
+/// The report of the most recent duplicates job run, if any.
+pub type LastDuplicateReport = Mutex<Option<DuplicateReport>>;
+
+/// Deletes the login signals past their retention, then clusters the accounts
+/// sharing the remaining ones. Run even while recording signals is disabled, so
+/// that those recorded before are still deleted.
+pub async fn run(
+    db: &Database,
+    config: &LoginSignalConfig,
+    metrics: &Metrics,
+    last_report: &LastDuplicateReport
+) -> Result<DuplicateReport, DBError> {
+    let signals_deleted = db.delete_login_signals_before(config.keep_days).await?;
+    let signals = db.read_shared_login_signals(config.max_accounts_per_signal).await?;
+    let report = DuplicateReport { clusters: clusters(&signals), signals_deleted, checked_at: Utc::now() };
+
+    metrics.increment("login_signals_deleted_total", signals_deleted);
+    metrics.set_gauge("duplicate_account_clusters", report.clusters.len() as u64);
+    if !report.clusters.is_empty() {
+        info!("duplicates: {} clusters of accounts", report.clusters.len());
+    }
+    *last_report.lock().unwrap() = Some(report.clone());
+    Ok(report)
+}
+
+/// Groups the accounts that share an address, directly or through other accounts.
+fn clusters(signals: &[SharedSignal]) -> Vec<DuplicateCluster> {
+    let mut groups: BTreeMap<(SignalKind, &str), Vec<u64>> = BTreeMap::new();
+    let mut accounts = BTreeMap::new();
+    for signal in signals {
+        groups.entry((signal.kind, &signal.signal_hash)).or_default().push(signal.account_id);
+        accounts.entry(signal.account_id).or_insert_with(|| ClusterAccount {
+            id: PublicId(signal.account_id),
+            username: signal.username.clone(),
+            removals: signal.removals
+        });
+    }
+
+    let mut parents = BTreeMap::new();
+    for ((kind, _), members) in &groups {
+        if *kind == SignalKind::Address {
+            for member in &members[1..] {
+                let (a, b) = (root(&mut parents, members[0]), root(&mut parents, *member));
+                parents.insert(b, a);
+            }
+        }
+    }
+
+    let mut by_root: BTreeMap<u64, DuplicateCluster> = BTreeMap::new();
+    for (account_id, account) in accounts {
+        let cluster = by_root.entry(root(&mut parents, account_id)).or_default();
+        cluster.removals += account.removals;
+        cluster.accounts.push(account);
+    }
+    for ((kind, _), members) in &groups {
+        let mut per_root: BTreeMap<u64, usize> = BTreeMap::new();
+        for member in members {
+            *per_root.entry(root(&mut parents, *member)).or_default() += 1;
+        }
+        for (cluster_root, _) in per_root.into_iter().filter(|(_, count)| *count > 1) {
+            let cluster = by_root.get_mut(&cluster_root).unwrap();
+            match kind {
+                SignalKind::Address => cluster.shared_addresses += 1,
+                SignalKind::Device  => cluster.shared_devices += 1
+            }
+        }
+    }
+
+    let mut clusters: Vec<_> = by_root.into_values().filter(|cluster| cluster.accounts.len() > 1).collect();
+    clusters.sort_by(|a, b| b.removals.cmp(&a.removals).then(b.accounts.len().cmp(&a.accounts.len())));
+    clusters.truncate(MAX_CLUSTERS);
+    clusters
+}
+
+fn root(parents: &mut BTreeMap<u64, u64>, account_id: u64) -> u64 {
+    let parent = *parents.entry(account_id).or_insert(account_id);
+    if parent == account_id {
+        return account_id
+    }
+    let root = root(parents, parent);
+    parents.insert(account_id, root);
+    root
+}
+
+/// Spawns the duplicates job onto the current runtime, running every `interval`.
+pub fn spawn(
+    db: Data<Database>,
+    config: Data<SharedConfig>,
+    metrics: Data<Metrics>,
+    last_report: Data<LastDuplicateReport>,
+    interval: Duration
+) {
+    rt::spawn(async move {
+        let mut ticker = rt::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let login_signals = config.load().login_signals.clone();
+            if let Err(e) = run(&db, &login_signals, &metrics, &last_report).await {
+                warn!("duplicates: job failed: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::{SharedSignal, SignalKind};
+    use super::clusters;
+
+    fn signal(account_id: u64, removals: u64, kind: SignalKind, signal_hash: &str) -> SharedSignal {
+        SharedSignal { account_id, username: format!("user{}", account_id), removals, kind, signal_hash: signal_hash.to_string() }
+    }
+
+    #[test]
+    fn test_clusters() {
+        let signals = [
+            // 1 and 2 share an address, 2 and 3 another
+            signal(1, 0, SignalKind::Address, "a"),
+            signal(2, 1, SignalKind::Address, "a"),
+            signal(2, 1, SignalKind::Address, "b"),
+            signal(3, 0, SignalKind::Address, "b"),
+            signal(1, 0, SignalKind::Device, "d"),
+            signal(3, 0, SignalKind::Device, "d"),
+            // 4 and 5 share an address, and were both moderated
+            signal(4, 2, SignalKind::Address, "c"),
+            signal(5, 1, SignalKind::Address, "c"),
+            // 6 and 7 only share a user agent
+            signal(6, 5, SignalKind::Device, "e"),
+            signal(7, 5, SignalKind::Device, "e")
+        ];
+        let clusters = clusters(&signals);
+        assert_eq!(2, clusters.len());
+
+        assert_eq!(vec![4, 5], clusters[0].accounts.iter().map(|account| account.id.0).collect::<Vec<_>>());
+        assert_eq!(3, clusters[0].removals);
+        assert_eq!((1, 0), (clusters[0].shared_addresses, clusters[0].shared_devices));
+
+        assert_eq!(vec![1, 2, 3], clusters[1].accounts.iter().map(|account| account.id.0).collect::<Vec<_>>());
+        assert_eq!(1, clusters[1].removals);
+        assert_eq!((2, 1), (clusters[1].shared_addresses, clusters[1].shared_devices));
+
+        assert!(super::clusters(&[]).is_empty());
+    }
+}
\ No newline at end of file
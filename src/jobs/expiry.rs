@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+use actix_web::rt;
+use actix_web::web::Data;
+use log::{info, warn};
+
+use crate::database::{database::Database, error::DBError};
+use crate::metrics::metrics::Metrics;
+
+/// Soft-deletes expired posts, returning how many were deleted.
+pub async fn run(db: &Database, metrics: &Metrics) -> Result<u64, DBError> {
+    let expired = db.soft_delete_expired_posts().await?;
+    metrics.increment("expired_posts_total", expired);
+    if expired > 0 {
+        info!("expiry: soft-deleted {} posts", expired);
+    }
+    Ok(expired)
+}
+
+/// Spawns the expiry job onto the current runtime, running every `interval`.
+pub fn spawn(db: Data<Database>, metrics: Data<Metrics>, interval: Duration) {
+    rt::spawn(async move {
+        let mut ticker = rt::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = run(&db, &metrics).await {
+                warn!("expiry: job failed: {}", e);
+            }
+        }
+    });
+}
\ No newline at end of file
@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use actix_web::rt;
+use actix_web::web::Data;
+use log::warn;
+
+use crate::database::{database::Database, error::DBError};
+use crate::metrics::metrics::Metrics;
+
+/// Outbox events applied per batch.
+const BATCH_SIZE: u64 = 500;
+
+/// Brings the Feed table up to date with the outbox, returning how many events
+/// were applied.
+pub async fn run(db: &Database, metrics: &Metrics) -> Result<u64, DBError> {
+    let mut applied = 0;
+    loop {
+        let batch = db.project_feed(BATCH_SIZE).await?;
+        applied += batch;
+        if batch < BATCH_SIZE {
+            break
+        }
+    }
+    metrics.increment("feed_events_projected_total", applied);
+    Ok(applied)
+}
+
+/// Spawns the feed projection job onto the current runtime, running every `interval`.
+pub fn spawn(db: Data<Database>, metrics: Data<Metrics>, interval: Duration) {
+    rt::spawn(async move {
+        let mut ticker = rt::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = run(&db, &metrics).await {
+                warn!("feed: job failed: {}", e);
+            }
+        }
+    });
+}
\ No newline at end of file
@@ -0,0 +1,68 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use actix_web::rt;
+use actix_web::web::Data;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::database::{database::Database, error::DBError};
+use crate::metrics::metrics::Metrics;
+
+/// Counts of the rows repaired by a single run of the integrity job.
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityReport {
+    pub orphaned_post_likes: u64,
+    pub orphaned_comment_likes: u64,
+    pub dangling_comment_replies: u64,
+    pub drifted_like_counts: u64,
+    pub checked_at: DateTime<Utc>
+}
+
+/// The report of the most recent integrity job run, if any.
+pub type LastIntegrityReport = Mutex<Option<IntegrityReport>>;
+
+/// Detects and repairs rows left behind by deletes that foreign keys should
+/// have prevented (e.g. a misconfigured schema or disabled FK checks).
+pub async fn run(
+    db: &Database,
+    metrics: &Metrics,
+    last_report: &LastIntegrityReport
+) -> Result<IntegrityReport, DBError> {
+    let report = IntegrityReport {
+        orphaned_post_likes: db.delete_orphaned_post_likes().await?,
+        orphaned_comment_likes: db.delete_orphaned_comment_likes().await?,
+        dangling_comment_replies: db.clear_dangling_comment_replies().await?,
+        drifted_like_counts: db.recount_drifted_likes().await?,
+        checked_at: Utc::now()
+    };
+
+    metrics.increment("integrity_orphaned_post_likes_total", report.orphaned_post_likes);
+    metrics.increment("integrity_orphaned_comment_likes_total", report.orphaned_comment_likes);
+    metrics.increment("integrity_dangling_comment_replies_total", report.dangling_comment_replies);
+    metrics.increment("integrity_drifted_like_counts_total", report.drifted_like_counts);
+    metrics.increment("integrity_runs_total", 1);
+
+    info!("integrity: {:?}", report);
+    *last_report.lock().unwrap() = Some(report.clone());
+    Ok(report)
+}
+
+/// Spawns the integrity job onto the current runtime, running every `interval`.
+pub fn spawn(
+    db: Data<Database>,
+    metrics: Data<Metrics>,
+    last_report: Data<LastIntegrityReport>,
+    interval: Duration
+) {
+    rt::spawn(async move {
+        let mut ticker = rt::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = run(&db, &metrics, &last_report).await {
+                warn!("integrity: job failed: {}", e);
+            }
+        }
+    });
+}
\ No newline at end of file
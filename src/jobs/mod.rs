@@ -0,0 +1,9 @@
+pub mod analytics;
+pub mod duplicates;
+pub mod expiry;
+pub mod feed;
+pub mod integrity;
+pub mod outbox;
+pub mod pool;
+pub mod publish;
+pub mod retention;
\ No newline at end of file
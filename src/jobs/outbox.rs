@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use actix_web::rt;
+use actix_web::web::{self, Data};
+use log::{info, warn};
+use serde_json::{json, Value};
+
+use crate::config::config::{EventConfig, SharedConfig};
+use crate::database::{database::Database, error::DBError};
+use crate::metrics::metrics::Metrics;
+use crate::models::OutboxEvent;
+use super::publish::Publisher;
+
+/// Events relayed per run. Any more are left for the next run.
+const BATCH_SIZE: u64 = 100;
+/// Days dispatched events are kept for, e.g. to replay them by hand.
+const RETENTION_DAYS: u64 = 7;
+
+/// Somewhere domain events are relayed to. Sinks may block, e.g. on a request.
+pub trait EventSink {
+    fn name(&self) -> String;
+    fn deliver(&self, event: &Value) -> Result<(), String>;
+}
+
+/// POSTs each event to a url as JSON, expecting a 2xx response.
+pub struct WebhookSink<'a> {
+    pub url: &'a str,
+    pub timeout: Duration
+}
+
+impl EventSink for WebhookSink<'_> {
+    fn name(&self) -> String {
+        format!("webhook {}", self.url)
+    }
+
+    fn deliver(&self, event: &Value) -> Result<(), String> {
+        ureq::post(self.url)
+            .timeout(self.timeout)
+            .send_json(event)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// The sinks configured by `config`.
+pub fn sinks(config: &EventConfig) -> Vec<Box<dyn EventSink + '_>> {
+    let timeout = Duration::from_millis(config.webhook_timeout_ms);
+    config.webhook_urls.iter()
+        .map(|url| Box::new(WebhookSink { url, timeout }) as Box<dyn EventSink>)
+        .collect()
+}
+
+/// The event as sent to sinks: its payload, with the outbox id to deduplicate
+/// redeliveries by, and when it happened.
+fn envelope(event: &OutboxEvent) -> Result<Value, String> {
+    let mut body: Value = serde_json::from_str(&event.payload).map_err(|e| e.to_string())?;
+    match body.as_object_mut() {
+        Some(fields) => {
+            fields.insert("id".to_string(), json!(event.id));
+            fields.insert("time_stamp".to_string(), json!(event.time_stamp));
+            Ok(body)
+        },
+        None => Err(format!("payload of event {} is not an object", event.id))
+    }
+}
+
+/// Delivers `event` to every sink, failing if any of them does.
+pub fn deliver(sinks: &[Box<dyn EventSink + '_>], event: &Value) -> Result<(), String> {
+    for sink in sinks {
+        sink.deliver(event).map_err(|e| format!("{}: {}", sink.name(), e))?;
+    }
+    Ok(())
+}
+
+/// Delivers `body` to the configured sinks, then publishes it if there is a `publisher`.
+async fn relay(body: Value, event_type: &str, config: &EventConfig, publisher: Option<&Publisher>) -> Result<(), String> {
+    let sink_config = config.clone();
+    let sink_body = body.clone();
+    web::block(move || deliver(&sinks(&sink_config), &sink_body)).await
+        .unwrap_or_else(|e| Err(e.to_string()))?;
+    match publisher {
+        Some(publisher) => publisher.publish(event_type, &body).await,
+        None => Ok(())
+    }
+}
+
+/// Relays pending events in order, stopping at the first that cannot be delivered
+/// so that it is retried before any later event is sent. Returns how many were relayed.
+pub async fn run(
+    db: &Database,
+    config: &EventConfig,
+    publisher: Option<&Publisher>,
+    metrics: &Metrics
+) -> Result<u64, DBError> {
+    let mut dispatched = 0;
+    for event in db.read_pending_events(BATCH_SIZE).await? {
+        let delivered = match envelope(&event) {
+            Ok(body) => relay(body, &event.event_type, config, publisher).await,
+            Err(e) => Err(e)
+        };
+        if let Err(e) = delivered {
+            warn!("outbox: failed to relay event {} ({}), attempt {}: {}", event.id, event.event_type, event.attempts + 1, e);
+            metrics.increment("outbox_dispatch_failures_total", 1);
+            db.record_event_failure(event.id, &e).await?;
+            break
+        }
+        db.mark_event_dispatched(event.id).await?;
+        dispatched += 1;
+    }
+    metrics.increment("outbox_events_dispatched_total", dispatched);
+
+    let pruned = db.prune_dispatched_events(RETENTION_DAYS).await?;
+    if pruned > 0 {
+        info!("outbox: pruned {} dispatched events", pruned);
+    }
+    Ok(dispatched)
+}
+
+/// Spawns the outbox job onto the current runtime, running every `interval`.
+pub fn spawn(
+    db: Data<Database>,
+    config: Data<SharedConfig>,
+    publisher: Option<Publisher>,
+    metrics: Data<Metrics>,
+    interval: Duration
+) {
+    rt::spawn(async move {
+        let mut ticker = rt::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let events = config.load().events.clone();
+            if let Err(e) = run(&db, &events, publisher.as_ref(), &metrics).await {
+                warn!("outbox: job failed: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use chrono::DateTime;
+    use serde_json::{json, Value};
+
+    use crate::models::OutboxEvent;
+    use super::{deliver, envelope, EventSink};
+
+    struct Recording {
+        delivered: RefCell<Vec<Value>>,
+        fail: bool
+    }
+
+    impl EventSink for Recording {
+        fn name(&self) -> String { "recording".to_string() }
+        fn deliver(&self, event: &Value) -> Result<(), String> {
+            if self.fail {
+                return Err("unavailable".to_string())
+            }
+            self.delivered.borrow_mut().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_envelope() {
+        let event = OutboxEvent {
+            id: 3,
+            event_type: "post_deleted".to_string(),
+            payload: r#"{"type":"post_deleted","post_id":7}"#.to_string(),
+            time_stamp: DateTime::from_timestamp(0, 0).unwrap(),
+            attempts: 0
+        };
+        assert_eq!(
+            Ok(json!({"type": "post_deleted", "post_id": 7, "id": 3, "time_stamp": "1970-01-01T00:00:00Z"})),
+            envelope(&event)
+        );
+        let not_object = OutboxEvent { payload: "[]".to_string(), ..event };
+        assert!(envelope(&not_object).is_err());
+    }
+
+    #[test]
+    fn test_deliver() {
+        let event = json!({"type": "post_deleted", "post_id": 7});
+        let ok = Box::new(Recording { delivered: RefCell::new(Vec::new()), fail: false });
+        assert_eq!(Ok(()), deliver(&[ok as Box<dyn EventSink>], &event));
+
+        let sinks: Vec<Box<dyn EventSink>> = vec![
+            Box::new(Recording { delivered: RefCell::new(Vec::new()), fail: true }),
+            Box::new(Recording { delivered: RefCell::new(Vec::new()), fail: false })
+        ];
+        assert_eq!(Err("recording: unavailable".to_string()), deliver(&sinks, &event));
+        assert_eq!(Ok(()), deliver(&[], &event));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use actix_web::rt;
+use actix_web::web::Data;
+use log::warn;
+
+use crate::database::{database::Database, error::DBError, pool::{self, PoolStats}};
+use crate::metrics::metrics::Metrics;
+
+/// Samples the connection pool into `metrics`, warning when it is saturated, and
+/// returns the sampled connections.
+pub async fn run(db: &Database, metrics: &Metrics) -> Result<PoolStats, DBError> {
+    let stats = db.pool_stats();
+    metrics.set_gauge_labelled("db_pool_connections", &[("state", "idle")], stats.idle as u64);
+    metrics.set_gauge_labelled("db_pool_connections", &[("state", "active")], stats.active() as u64);
+    metrics.set_gauge("db_pool_max_connections", stats.max as u64);
+    metrics.increment("db_pool_acquire_timeouts_total", pool::take_acquire_timeouts());
+    if stats.saturated() {
+        warn!("pool: all {} connections are in use, consider raising DB_MAX_CONNECTIONS", stats.max);
+    }
+
+    db.probe_acquire().await?;
+    let (acquires, waited) = pool::take_acquire_waits();
+    metrics.increment("db_pool_acquires_total", acquires);
+    metrics.increment("db_pool_acquire_wait_microseconds_total", waited);
+    Ok(stats)
+}
+
+/// Spawns the pool sampling job onto the current runtime, running every `interval`.
+pub fn spawn(db: Data<Database>, metrics: Data<Metrics>, interval: Duration) {
+    rt::spawn(async move {
+        let mut ticker = rt::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = run(&db, &metrics).await {
+                warn!("pool: job failed: {}", e);
+            }
+        }
+    });
+}
\ No newline at end of file
@@ -0,0 +1,80 @@
+use log::warn;
+use serde_json::Value;
+
+/// Default prefix of the subjects events are published to, e.g. `posted.post.created`.
+pub const DEFAULT_SUBJECT_PREFIX: &str = "posted";
+
+/// Publishes the events relayed by the outbox job to NATS, for downstream
+/// consumers such as analytics pipelines. Only available when built with the
+/// `nats` feature, and enabled by setting `NATS_URL`.
+pub struct Publisher {
+    #[cfg(feature = "nats")]
+    client: async_nats::Client,
+    subject_prefix: String
+}
+
+impl Publisher {
+    /// Connects to the NATS server at `NATS_URL`, publishing under `NATS_SUBJECT_PREFIX`.
+    /// `None` when `NATS_URL` is not set, or the server cannot be connected to.
+    pub async fn from_env() -> Option<Publisher> {
+        let url = std::env::var("NATS_URL").ok()?;
+        let subject_prefix = std::env::var("NATS_SUBJECT_PREFIX").unwrap_or(DEFAULT_SUBJECT_PREFIX.to_string());
+        Publisher::connect(&url, subject_prefix).await
+    }
+
+    #[cfg(feature = "nats")]
+    async fn connect(url: &str, subject_prefix: String) -> Option<Publisher> {
+        match async_nats::connect(url).await {
+            Ok(client) => {
+                log::info!("publish: connected to NATS at '{}'", url);
+                Some(Publisher { client, subject_prefix })
+            },
+            Err(e) => {
+                warn!("publish: failed to connect to NATS at '{}': {}", url, e);
+                None
+            }
+        }
+    }
+
+    #[cfg(not(feature = "nats"))]
+    async fn connect(_url: &str, _subject_prefix: String) -> Option<Publisher> {
+        warn!("publish: NATS_URL is set, but the server was built without the `nats` feature");
+        None
+    }
+
+    /// Publishes `event` to the subject of `event_type`, waiting until the server
+    /// has it, so that the outbox only marks it dispatched once published.
+    #[cfg(feature = "nats")]
+    pub async fn publish(&self, event_type: &str, event: &Value) -> Result<(), String> {
+        let payload = serde_json::to_vec(event).map_err(|e| e.to_string())?;
+        self.client.publish(subject(&self.subject_prefix, event_type), payload.into()).await
+            .map_err(|e| e.to_string())?;
+        self.client.flush().await.map_err(|e| e.to_string())
+    }
+
+    #[cfg(not(feature = "nats"))]
+    pub async fn publish(&self, event_type: &str, _event: &Value) -> Result<(), String> {
+        Err(format!("cannot publish {}: built without the `nats` feature", subject(&self.subject_prefix, event_type)))
+    }
+}
+
+/// The subject an event is published to: `post_created` becomes `<prefix>.post.created`.
+pub fn subject(prefix: &str, event_type: &str) -> String {
+    let event_subject = event_type.replace('_', ".");
+    match prefix.is_empty() {
+        true  => event_subject,
+        false => format!("{}.{}", prefix, event_subject)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::subject;
+
+    #[test]
+    fn test_subject() {
+        assert_eq!("posted.post.created", subject("posted", "post_created"));
+        assert_eq!("analytics.vote.changed", subject("analytics", "vote_changed"));
+        assert_eq!("comment.created", subject("", "comment_created"));
+    }
+}
\ No newline at end of file
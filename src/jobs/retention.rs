@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use actix_web::rt;
+use actix_web::web::Data;
+use log::{info, warn};
+
+use crate::config::config::{RequestLogConfig, RetentionConfig, SharedConfig};
+use crate::database::{database::Database, error::DBError};
+use crate::metrics::metrics::Metrics;
+
+/// Posts purged per run, to keep each run short.
+const BATCH_SIZE: u64 = 100;
+
+/// Purges the posts soft-deleted more than `purge_deleted_after_days` ago that are
+/// not under a legal hold, returning how many were purged.
+pub async fn run(db: &Database, config: &RetentionConfig, metrics: &Metrics) -> Result<u64, DBError> {
+    if !config.enabled {
+        return Ok(0)
+    }
+    let mut purged = 0;
+    for post_id in db.read_purgeable_posts(config.purge_deleted_after_days, BATCH_SIZE).await? {
+        if db.purge_post(post_id).await? {
+            purged += 1;
+        }
+    }
+    metrics.increment("retention_purged_posts_total", purged);
+    if purged > 0 {
+        info!("retention: purged {} posts", purged);
+    }
+    Ok(purged)
+}
+
+/// Deletes the requests recorded by `api::recorder` more than `keep_hours` ago,
+/// returning how many were deleted. Done even while recording is disabled.
+pub async fn purge_request_traces(db: &Database, config: &RequestLogConfig, metrics: &Metrics) -> Result<u64, DBError> {
+    let deleted = db.delete_request_traces_before(config.keep_hours).await?;
+    metrics.increment("request_traces_deleted_total", deleted);
+    Ok(deleted)
+}
+
+/// Spawns the retention job onto the current runtime, running every `interval`.
+pub fn spawn(db: Data<Database>, config: Data<SharedConfig>, metrics: Data<Metrics>, interval: Duration) {
+    rt::spawn(async move {
+        let mut ticker = rt::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let retention = config.load().retention.clone();
+            if let Err(e) = run(&db, &retention, &metrics).await {
+                warn!("retention: job failed: {}", e);
+            }
+            let request_log = config.load().request_log.clone();
+            if let Err(e) = purge_request_traces(&db, &request_log, &metrics).await {
+                warn!("retention: failed to delete recorded requests: {}", e);
+            }
+        }
+    });
+}
\ No newline at end of file
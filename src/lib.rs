@@ -0,0 +1,27 @@
+pub mod accessibility;
+pub mod api;
+pub mod auth;
+pub mod cache;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod check;
+pub mod clock;
+pub mod config;
+pub mod database;
+pub mod events;
+pub mod experiments;
+pub mod format;
+pub mod geoip;
+pub mod ids;
+pub mod jobs;
+pub mod links;
+pub mod metrics;
+pub mod models;
+pub mod policy;
+pub mod polls;
+pub mod ranking;
+pub mod ratelimit;
+pub mod signals;
+pub mod text;
+pub mod threads;
+pub mod translate;
\ No newline at end of file
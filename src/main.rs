@@ -1,30 +1,127 @@
-mod api;
-mod auth;
-mod cache;
-mod database;
-mod models;
-
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use actix_web::{App, HttpServer, web, middleware::Logger};
+use arc_swap::ArcSwap;
 use argon2::Argon2;
 use dotenv::dotenv;
 
-use crate::auth::auth::AuthService;
-use crate::database::database::Database;
+use posted_server::{api, check, ids};
+use posted_server::api::{deprecation, recorder};
+use posted_server::api::api::{PostListingCache, TotalCountCache, UserStatsCache, TOTAL_COUNT_TTL, USER_STATS_TTL};
+use posted_server::auth::auth as auth_service;
+use posted_server::auth::challenge::ChallengeStore;
+use posted_server::auth::jwt::JwtKeys;
+use posted_server::auth::shards::{AuthShards, DEFAULT_SHARD_COUNT};
+use posted_server::cache::profile::ProfileCache;
+use posted_server::cache::submissions::RecentSubmissions;
+use posted_server::cache::translations::TranslationCache;
+use posted_server::config::config::{self as server_config, ServerConfig, SharedConfig};
+use posted_server::config::logging;
+use posted_server::database::database::{Database, DEFAULT_MAX_CONNECTIONS};
+use posted_server::geoip::geoip::{self, GeoIp};
+use posted_server::jobs::{analytics, expiry, feed, outbox, pool, retention};
+use posted_server::jobs::duplicates::{self, LastDuplicateReport};
+use posted_server::jobs::publish::Publisher;
+use posted_server::jobs::integrity::{self, LastIntegrityReport};
+use posted_server::metrics::metrics::Metrics;
+use posted_server::ratelimit::quota::{EnforceQuota, Quotas};
+use posted_server::ratelimit::ratelimit::{self as rate_limit, RateLimiter, TranslationLimiter};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    std::env::set_var("RUST_LOG", "info");
-
     dotenv().ok();
+
+    // Pre-flight mode: report on the configuration and dependencies, then exit
+    if std::env::args().any(|arg| arg == "--check") {
+        let report = check::run().await;
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        std::process::exit(if report.ok { 0 } else { 1 });
+    }
+
+    let server_config = ServerConfig::load().expect("Failed to load the config file");
+    logging::init(&server_config);
+    let config_data: web::Data<SharedConfig> = web::Data::new(ArcSwap::from_pointee(server_config));
+    #[cfg(unix)]
+    server_config::spawn_reload_on_sighup(config_data.clone());
+
+    // Opaque public ids when a key is set, otherwise numeric ids
+    ids::init(std::env::var("PUBLIC_ID_KEY").ok().as_deref());
+
     let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL is not set");
-    let database = Database::new(&db_url).await;
+    let db_max_connections = std::env::var("DB_MAX_CONNECTIONS")
+        .map(|s| s.parse::<u32>().expect("DB_MAX_CONNECTIONS is not a valid u32"))
+        .unwrap_or(DEFAULT_MAX_CONNECTIONS);
+    let database = Database::new(&db_url, db_max_connections).await;
     let db_data = web::Data::new(database);
 
+    let metrics_data = web::Data::new(Metrics::new());
+
     let redis_url = std::env::var("REDIS_DATABASE_URL").expect("REDIS_DATABASE_URL is not set");
-    let auth_service = AuthService::new(&redis_url);
-    let auth_service_data = web::Data::new(Mutex::new(auth_service));
+    let auth_shard_count = std::env::var("AUTH_SHARDS")
+        .map(|s| s.parse::<usize>().expect("AUTH_SHARDS is not a valid usize"))
+        .unwrap_or(DEFAULT_SHARD_COUNT);
+    let jwt_keys = JwtKeys::from_env().expect("Failed to load the JWT keys");
+    let auth_service = AuthShards::new(
+        &redis_url,
+        config_data.clone().into_inner(),
+        Arc::new(jwt_keys),
+        auth_shard_count,
+        metrics_data.clone().into_inner()
+    );
+    let auth_service_data = web::Data::new(auth_service);
+    let profile_cache_data = web::Data::new(ProfileCache::new(&redis_url));
+    let challenge_store_data = web::Data::new(ChallengeStore::new(auth_service::try_connect(&redis_url).ok()));
+    let recent_submissions_data = web::Data::new(RecentSubmissions::new(auth_service::try_connect(&redis_url).ok()));
+    let translation_cache_data = web::Data::new(TranslationCache::new(auth_service::try_connect(&redis_url).ok()));
+    let quotas_data = web::Data::new(Quotas::new(auth_service::try_connect(&redis_url).ok()));
+
+    let integrity_report_data: web::Data<LastIntegrityReport> = web::Data::new(Mutex::new(None));
+    let duplicate_report_data: web::Data<LastDuplicateReport> = web::Data::new(Mutex::new(None));
+    let user_stats_data = web::Data::new(UserStatsCache::new(USER_STATS_TTL));
+    let total_count_data = web::Data::new(TotalCountCache::new(TOTAL_COUNT_TTL));
+    let post_listing_data = web::Data::new(PostListingCache::default());
+    let rate_limiter_data = web::Data::new(RateLimiter::new());
+    let translation_limiter_data = web::Data::new(TranslationLimiter::default());
+    let geoip_data = web::Data::new(GeoIp::from_env());
+
+    integrity::spawn(
+        db_data.clone(),
+        metrics_data.clone(),
+        integrity_report_data.clone(),
+        job_interval("INTEGRITY_JOB_INTERVAL_SEC", 60 * 60)
+    );
+
+    expiry::spawn(db_data.clone(), metrics_data.clone(), job_interval("EXPIRY_JOB_INTERVAL_SEC", 60));
+
+    outbox::spawn(
+        db_data.clone(),
+        config_data.clone(),
+        Publisher::from_env().await,
+        metrics_data.clone(),
+        job_interval("OUTBOX_JOB_INTERVAL_SEC", 5)
+    );
+
+    feed::spawn(db_data.clone(), metrics_data.clone(), job_interval("FEED_JOB_INTERVAL_SEC", 5));
+
+    analytics::spawn(db_data.clone(), metrics_data.clone(), job_interval("ANALYTICS_JOB_INTERVAL_SEC", 60 * 60));
+
+    retention::spawn(
+        db_data.clone(),
+        config_data.clone(),
+        metrics_data.clone(),
+        job_interval("RETENTION_JOB_INTERVAL_SEC", 60 * 60)
+    );
+
+    duplicates::spawn(
+        db_data.clone(),
+        config_data.clone(),
+        metrics_data.clone(),
+        duplicate_report_data.clone(),
+        job_interval("DUPLICATES_JOB_INTERVAL_SEC", 60 * 60)
+    );
+
+    pool::spawn(db_data.clone(), metrics_data.clone(), job_interval("POOL_JOB_INTERVAL_SEC", 15));
 
     let server_addr = "0.0.0.0";
     let server_port = 8080;
@@ -34,17 +131,53 @@ async fn main() -> std::io::Result<()> {
 
     let app = HttpServer::new(move ||
         App::new()
-            .wrap(Logger::new("%a \"%r\" %s %bb %Tsec"))
+            .wrap_fn(deprecation::middleware)
+            .wrap_fn(rate_limit::middleware)
+            .wrap(EnforceQuota)
+            .wrap(Logger::new("%a %{country}xi \"%r\" %s %bb %Tsec %{x-request-id}o")
+                .custom_request_replace("country", |req| {
+                    geoip::country(req).map(|country| country.0).unwrap_or("-".to_string())
+                }))
+            // Outside of the logger, so that the country and request id are known when it logs
+            .wrap_fn(recorder::middleware)
+            .wrap_fn(geoip::middleware)
             .app_data(db_data.clone())
             .app_data(auth_service_data.clone())
+            .app_data(profile_cache_data.clone())
+            .app_data(challenge_store_data.clone())
+            .app_data(recent_submissions_data.clone())
+            .app_data(translation_cache_data.clone())
             .app_data(encrypt_data.clone())
+            .app_data(config_data.clone())
+            .app_data(metrics_data.clone())
+            .app_data(integrity_report_data.clone())
+            .app_data(duplicate_report_data.clone())
+            .app_data(user_stats_data.clone())
+            .app_data(total_count_data.clone())
+            .app_data(post_listing_data.clone())
+            .app_data(rate_limiter_data.clone())
+            .app_data(translation_limiter_data.clone())
+            .app_data(quotas_data.clone())
+            .app_data(geoip_data.clone())
             .configure(api::api::config)
     )
     .workers(1)
     .bind((server_addr, server_port))?;
 
     println!("Server running at http://{}:{}/", server_addr, server_port);
-    env_logger::init();
 
     app.run().await
 }
+
+/// The interval of a background job, from the `var` environment variable or else
+/// `default_sec` seconds. An interval of 0 would panic the job's timer, so it is
+/// refused at startup like an invalid one.
+fn job_interval(var: &str, default_sec: u64) -> Duration {
+    let interval_sec = std::env::var(var)
+        .map(|s| s.parse::<u64>().unwrap_or_else(|_| panic!("{} is not a valid u64", var)))
+        .unwrap_or(default_sec);
+    if interval_sec == 0 {
+        panic!("{} must be at least 1", var)
+    }
+    Duration::from_secs(interval_sec)
+}
\ No newline at end of file
@@ -0,0 +1,112 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+
+/// A minimal registry of named counters and gauges, rendered in the Prometheus
+/// text exposition format.
+#[derive(Default)]
+pub struct Metrics {
+    counters: Mutex<BTreeMap<String, u64>>,
+    gauges: Mutex<BTreeMap<String, u64>>
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics::default()
+    }
+
+    /// Adds `by` to the counter `name`, creating it if it does not exist.
+    pub fn increment(&self, name: &str, by: u64) {
+        let mut counters = self.counters.lock().unwrap();
+        *counters.entry(name.to_string()).or_insert(0) += by;
+    }
+
+    /// Adds `by` to the series of the counter `name` with `labels`, e.g. `route`.
+    pub fn increment_labelled(&self, name: &str, labels: &[(&str, &str)], by: u64) {
+        self.increment(&labelled(name, labels), by);
+    }
+
+    /// Sets the gauge `name` to `value`, creating it if it does not exist.
+    pub fn set_gauge(&self, name: &str, value: u64) {
+        self.gauges.lock().unwrap().insert(name.to_string(), value);
+    }
+
+    /// Sets the series of the gauge `name` with `labels` to `value`.
+    pub fn set_gauge_labelled(&self, name: &str, labels: &[(&str, &str)], value: u64) {
+        self.set_gauge(&labelled(name, labels), value);
+    }
+
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        render_family(&mut output, "counter", &self.counters.lock().unwrap());
+        render_family(&mut output, "gauge", &self.gauges.lock().unwrap());
+        output
+    }
+}
+
+fn labelled(name: &str, labels: &[(&str, &str)]) -> String {
+    let labels: Vec<String> = labels.iter()
+        .map(|(label, value)| format!("{}=\"{}\"", label, value.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    format!("{}{{{}}}", name, labels.join(","))
+}
+
+fn render_family(output: &mut String, kind: &str, series: &BTreeMap<String, u64>) {
+    let mut typed = BTreeSet::new();
+    for (series, value) in series.iter() {
+        let name = series.split('{').next().unwrap_or(series);
+        if typed.insert(name) {
+            output.push_str(&format!("# TYPE {} {}\n", name, kind));
+        }
+        output.push_str(&format!("{} {}\n", series, value));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Metrics;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::new();
+        assert_eq!("", metrics.render());
+
+        metrics.increment("test_b_total", 2);
+        metrics.increment("test_a_total", 1);
+        metrics.increment("test_b_total", 3);
+
+        assert_eq!(
+            "# TYPE test_a_total counter\ntest_a_total 1\n# TYPE test_b_total counter\ntest_b_total 5\n",
+            metrics.render()
+        );
+    }
+
+    #[test]
+    fn test_render_labelled() {
+        let metrics = Metrics::new();
+        metrics.increment_labelled("test_total", &[("route", "GET /a")], 1);
+        metrics.increment_labelled("test_total", &[("route", "GET /\"b\"")], 2);
+        metrics.increment("test_total_other", 3);
+
+        assert_eq!(
+            "# TYPE test_total_other counter\ntest_total_other 3\n\
+            # TYPE test_total counter\ntest_total{route=\"GET /\\\"b\\\"\"} 2\ntest_total{route=\"GET /a\"} 1\n",
+            metrics.render()
+        );
+    }
+
+    #[test]
+    fn test_render_gauges() {
+        let metrics = Metrics::new();
+        metrics.increment("test_total", 1);
+        metrics.set_gauge_labelled("test_connections", &[("state", "idle")], 4);
+        metrics.set_gauge_labelled("test_connections", &[("state", "idle")], 2);
+        metrics.set_gauge("test_max", 10);
+
+        assert_eq!(
+            "# TYPE test_total counter\ntest_total 1\n\
+            # TYPE test_connections gauge\ntest_connections{state=\"idle\"} 2\n\
+            # TYPE test_max gauge\ntest_max 10\n",
+            metrics.render()
+        );
+    }
+}
\ No newline at end of file
@@ -0,0 +1,2 @@
+pub mod policy;
+pub mod scoring;
\ No newline at end of file
@@ -0,0 +1,102 @@
+use crate::config::config::OnboardingConfig;
+use crate::models::AccountActivity;
+
+/// A restriction on new accounts that a create request would break.
+#[derive(Debug, PartialEq)]
+pub enum Violation {
+    DailyPostLimit,
+    DailyCommentLimit,
+    LinkInEarlyPost
+}
+
+impl Violation {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Violation::DailyPostLimit => "New accounts have reached their daily post limit",
+            Violation::DailyCommentLimit => "New accounts have reached their daily comment limit",
+            Violation::LinkInEarlyPost => "The first posts of new accounts may not contain links"
+        }
+    }
+}
+
+/// Whether the onboarding restrictions apply to the account at unix time `now`.
+pub fn is_new_account(config: &OnboardingConfig, activity: &AccountActivity, now: i64) -> bool {
+    let age_sec = now.saturating_sub(activity.created_at.timestamp());
+    config.enabled && age_sec < (config.new_account_days as i64).saturating_mul(60 * 60 * 24)
+}
+
+/// Checks a new post, made up of `texts` (title, body), against the restrictions.
+pub fn check_post(
+    config: &OnboardingConfig,
+    activity: &AccountActivity,
+    texts: &[&str],
+    now: i64
+) -> Result<(), Violation> {
+    if !is_new_account(config, activity, now) {
+        return Ok(())
+    }
+    if activity.posts_today >= config.max_posts_per_day {
+        return Err(Violation::DailyPostLimit)
+    }
+    if activity.post_count < config.posts_before_links && texts.iter().any(|text| contains_link(text)) {
+        return Err(Violation::LinkInEarlyPost)
+    }
+    Ok(())
+}
+
+pub fn check_comment(config: &OnboardingConfig, activity: &AccountActivity, now: i64) -> Result<(), Violation> {
+    if is_new_account(config, activity, now) && activity.comments_today >= config.max_comments_per_day {
+        return Err(Violation::DailyCommentLimit)
+    }
+    Ok(())
+}
+
+fn contains_link(text: &str) -> bool {
+    let text = text.to_lowercase();
+    ["http://", "https://", "www."].iter().any(|prefix| text.contains(prefix))
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::DateTime;
+
+    use crate::config::config::OnboardingConfig;
+    use crate::models::AccountActivity;
+    use super::{check_comment, check_post, Violation};
+
+    const DAY: i64 = 60 * 60 * 24;
+
+    fn activity(post_count: u64, posts_today: u64, comments_today: u64) -> AccountActivity {
+        AccountActivity {
+            created_at: DateTime::from_timestamp(0, 0).unwrap(),
+            post_count,
+            posts_today,
+            comments_today
+        }
+    }
+
+    #[test]
+    fn test_checks() {
+        let config = OnboardingConfig {
+            enabled: true,
+            new_account_days: 2,
+            max_posts_per_day: 2,
+            max_comments_per_day: 5,
+            posts_before_links: 1
+        };
+
+        assert_eq!(Ok(()), check_post(&config, &activity(0, 0, 0), &["title", "body"], DAY));
+        assert_eq!(Err(Violation::DailyPostLimit), check_post(&config, &activity(2, 2, 0), &["title", "body"], DAY));
+        assert_eq!(
+            Err(Violation::LinkInEarlyPost),
+            check_post(&config, &activity(0, 0, 0), &["title", "see HTTPS://example.com"], DAY)
+        );
+        assert_eq!(Ok(()), check_post(&config, &activity(1, 1, 0), &["title", "www.example.com"], DAY));
+        assert_eq!(Err(Violation::DailyCommentLimit), check_comment(&config, &activity(0, 0, 5), DAY));
+
+        // Restrictions end once the account is old enough, or when disabled
+        assert_eq!(Ok(()), check_post(&config, &activity(2, 2, 0), &["title", "body"], 2 * DAY));
+        let disabled = OnboardingConfig { enabled: false, ..config };
+        assert_eq!(Ok(()), check_comment(&disabled, &activity(0, 0, 5), DAY));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use log::warn;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::config::config::ModerationConfig;
+
+#[derive(Debug)]
+pub enum ScoreError {
+    Request(String),
+    InvalidResponse(String)
+}
+
+impl std::fmt::Display for ScoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScoreError::Request(e) => write!(f, "Scoring request failed: {}", e),
+            ScoreError::InvalidResponse(e) => write!(f, "Invalid scoring response: {}", e)
+        }
+    }
+}
+
+/// Rates how likely text is to be spam or toxic, from 0 (fine) to 1 (certainly).
+/// Scorers may block, e.g. on a request to an external API.
+pub trait Scorer {
+    fn name(&self) -> &'static str;
+    fn score(&self, text: &str) -> Result<f64, ScoreError>;
+}
+
+/// Adds up the scores of the configured keywords that the text contains.
+pub struct KeywordScorer<'a> {
+    pub keywords: &'a HashMap<String, f64>
+}
+
+impl Scorer for KeywordScorer<'_> {
+    fn name(&self) -> &'static str {
+        "keywords"
+    }
+
+    fn score(&self, text: &str) -> Result<f64, ScoreError> {
+        let text = text.to_lowercase();
+        Ok(self.keywords.iter()
+            .filter(|(keyword, _)| text.contains(&keyword.to_lowercase()))
+            .map(|(_, score)| score)
+            .sum())
+    }
+}
+
+/// Asks an external API for a score.
+pub struct ApiScorer<'a> {
+    pub url: &'a str,
+    pub timeout: Duration
+}
+
+#[derive(Deserialize)]
+struct ApiScore {
+    score: f64
+}
+
+impl Scorer for ApiScorer<'_> {
+    fn name(&self) -> &'static str {
+        "api"
+    }
+
+    fn score(&self, text: &str) -> Result<f64, ScoreError> {
+        let response = ureq::post(self.url)
+            .timeout(self.timeout)
+            .send_json(json!({ "text": text }))
+            .map_err(|e| ScoreError::Request(e.to_string()))?;
+        let reply: ApiScore = response.into_json()
+            .map_err(|e| ScoreError::InvalidResponse(e.to_string()))?;
+        match reply.score.is_finite() {
+            true  => Ok(reply.score),
+            false => Err(ScoreError::InvalidResponse(reply.score.to_string()))
+        }
+    }
+}
+
+/// The scorers enabled by `config`.
+pub fn scorers(config: &ModerationConfig) -> Vec<Box<dyn Scorer + '_>> {
+    let mut scorers: Vec<Box<dyn Scorer + '_>> = Vec::new();
+    if !config.keywords.is_empty() {
+        scorers.push(Box::new(KeywordScorer { keywords: &config.keywords }));
+    }
+    if let Some(url) = config.scoring_api_url.as_deref() {
+        let timeout = Duration::from_millis(config.scoring_api_timeout_ms);
+        scorers.push(Box::new(ApiScorer { url, timeout }));
+    }
+    scorers
+}
+
+/// The highest score any of `scorers` gives `text`, clamped to 0..=1. Scorers
+/// that fail are logged and skipped, so an unavailable API holds nothing.
+pub fn score(scorers: &[Box<dyn Scorer + '_>], text: &str) -> f64 {
+    scorers.iter()
+        .filter_map(|scorer| match scorer.score(text) {
+            Ok(score) => Some(score),
+            Err(e) => {
+                warn!("scoring: {} scorer failed: {}", scorer.name(), e);
+                None
+            }
+        })
+        .fold(0.0, f64::max)
+        .clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::{score, KeywordScorer, ScoreError, Scorer};
+
+    struct Fixed(f64);
+
+    impl Scorer for Fixed {
+        fn name(&self) -> &'static str { "fixed" }
+        fn score(&self, _: &str) -> Result<f64, ScoreError> { Ok(self.0) }
+    }
+
+    struct Failing;
+
+    impl Scorer for Failing {
+        fn name(&self) -> &'static str { "failing" }
+        fn score(&self, _: &str) -> Result<f64, ScoreError> { Err(ScoreError::Request("down".to_string())) }
+    }
+
+    #[test]
+    fn test_keyword_scorer() {
+        let keywords = HashMap::from([("free money".to_string(), 0.5), ("Click".to_string(), 0.25)]);
+        let scorer = KeywordScorer { keywords: &keywords };
+
+        assert_eq!(0.0, scorer.score("A normal post").unwrap());
+        assert_eq!(0.25, scorer.score("click here").unwrap());
+        assert_eq!(0.75, scorer.score("CLICK for FREE MONEY").unwrap());
+    }
+
+    #[test]
+    fn test_score() {
+        assert_eq!(0.0, score(&[], "anything"));
+        assert_eq!(0.6, score(&[Box::new(Fixed(0.2)), Box::new(Fixed(0.6))], "anything"));
+        assert_eq!(1.0, score(&[Box::new(Fixed(3.0))], "anything"));
+        assert_eq!(0.2, score(&[Box::new(Failing), Box::new(Fixed(0.2))], "anything"));
+    }
+}
\ No newline at end of file
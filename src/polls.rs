@@ -0,0 +1,89 @@
+use crate::models::PostKind;
+use crate::text;
+
+/// Fewest options a poll can have.
+pub const MIN_POLL_OPTIONS: usize = 2;
+/// Most options a poll can have.
+pub const MAX_POLL_OPTIONS: usize = 10;
+/// Characters of a poll option, as limited by the PollOption table.
+pub const MAX_OPTION_LENGTH: usize = 100;
+
+#[derive(Debug, PartialEq)]
+pub enum PollError {
+    TooFewOptions,
+    TooManyOptions,
+    OptionTooLong,
+    /// Two options that only differ in case.
+    DuplicateOption,
+    /// Options on a post that is not a poll.
+    Unexpected
+}
+
+impl PollError {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            PollError::TooFewOptions => "Polls require at least 2 options",
+            PollError::TooManyOptions => "Polls can have at most 10 options",
+            PollError::OptionTooLong => "Poll option is too long",
+            PollError::DuplicateOption => "Poll options must differ",
+            PollError::Unexpected => "Only polls can have options"
+        }
+    }
+}
+
+/// The options of a post of `kind` as stored, in order: each on one line like a
+/// title, leaving out empty ones. Only polls have options.
+pub fn options(kind: PostKind, options: &[String]) -> Result<Vec<String>, PollError> {
+    let options: Vec<String> = options.iter()
+        .map(|option| text::normalise_title(option))
+        .filter(|option| !option.is_empty())
+        .collect();
+    if kind != PostKind::Poll {
+        return match options.is_empty() {
+            true  => Ok(options),
+            false => Err(PollError::Unexpected)
+        }
+    }
+
+    if options.len() < MIN_POLL_OPTIONS {
+        return Err(PollError::TooFewOptions)
+    }
+    if options.len() > MAX_POLL_OPTIONS {
+        return Err(PollError::TooManyOptions)
+    }
+    if options.iter().any(|option| option.chars().count() > MAX_OPTION_LENGTH) {
+        return Err(PollError::OptionTooLong)
+    }
+    let mut lowercase: Vec<String> = options.iter().map(|option| option.to_lowercase()).collect();
+    lowercase.sort();
+    lowercase.dedup();
+    if lowercase.len() < options.len() {
+        return Err(PollError::DuplicateOption)
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::PostKind;
+    use super::{options, PollError, MAX_OPTION_LENGTH, MAX_POLL_OPTIONS};
+
+    fn strings(options: &[&str]) -> Vec<String> {
+        options.iter().map(|option| option.to_string()).collect()
+    }
+
+    #[test]
+    fn test_options() {
+        assert_eq!(Ok(strings(&["Tabs", "Spaces"])), options(PostKind::Poll, &strings(&[" Tabs\n", "", "Spaces"])));
+        assert_eq!(Ok(Vec::new()), options(PostKind::Text, &strings(&[" "])));
+        assert_eq!(Ok(Vec::new()), options(PostKind::Link, &[]));
+
+        assert_eq!(Err(PollError::Unexpected), options(PostKind::Text, &strings(&["Tabs", "Spaces"])));
+        assert_eq!(Err(PollError::TooFewOptions), options(PostKind::Poll, &strings(&["Tabs", " "])));
+        let many: Vec<String> = (0..=MAX_POLL_OPTIONS).map(|i| i.to_string()).collect();
+        assert_eq!(Err(PollError::TooManyOptions), options(PostKind::Poll, &many));
+        let long = "é".repeat(MAX_OPTION_LENGTH + 1);
+        assert_eq!(Err(PollError::OptionTooLong), options(PostKind::Poll, &[long, "Spaces".to_string()]));
+        assert_eq!(Err(PollError::DuplicateOption), options(PostKind::Poll, &strings(&["Tabs", "Spaces", "TABS"])));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,146 @@
+use chrono::{DateTime, Utc};
+
+use crate::models::{HotCandidate, Post};
+
+/// How quickly the hot score of a post decays with its age. Higher values favour
+/// newer posts over more liked ones.
+const GRAVITY: f64 = 1.8;
+/// Hours added to the age of every post, so that brand new posts do not rank
+/// far above everything else.
+const AGE_OFFSET_HOURS: f64 = 2.0;
+/// Newest posts ranked for the hot listing. Older posts have decayed too far to
+/// make it in.
+pub const HOT_CANDIDATES: u64 = 500;
+/// Newest unseen posts that `GET /api/discover` samples from.
+pub const DISCOVER_CANDIDATES: u64 = 500;
+
+/// The time-decayed score of a post with `likes` that was posted `age_hours` ago.
+pub fn hot_score(likes: u64, age_hours: f64) -> f64 {
+    (likes as f64 + 1.0) / (age_hours.max(0.0) + AGE_OFFSET_HOURS).powf(GRAVITY)
+}
+
+/// Orders `candidates` by descending hot score at `now`, newest first on a tie.
+pub fn rank_hot(candidates: &mut [HotCandidate], now: DateTime<Utc>) {
+    let score = |candidate: &HotCandidate| {
+        let age_hours = (now - candidate.time_stamp).num_seconds() as f64 / 3600.0;
+        hot_score(candidate.likes, age_hours)
+    };
+    candidates.sort_by(|a, b| score(b).total_cmp(&score(a)).then(b.id.cmp(&a.id)));
+}
+
+/// A random sample of up to `count` of `posts`, each drawn with a chance weighted by
+/// its hot score at `now`, most likely first. `random` gives numbers in 0..1.
+pub fn sample_weighted(posts: Vec<Post>, now: DateTime<Utc>, count: usize, mut random: impl FnMut() -> f64) -> Vec<Post> {
+    // Each post is keyed by random^(1 / weight), and the highest keys are a weighted
+    // sample without replacement (Efraimidis and Spirakis). The keys are compared by
+    // their logarithm, as the weights of older posts are small enough for the keys
+    // themselves to underflow to 0
+    let mut keyed = posts.into_iter()
+        .map(|post| {
+            let age_hours = (now - post.time_stamp).num_seconds() as f64 / 3600.0;
+            let key = random().ln() / hot_score(post.likes, age_hours);
+            (key, post)
+        })
+        .collect::<Vec<_>>();
+    keyed.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    keyed.into_iter().take(count).map(|(_, post)| post).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::models::{CommentMode, HotCandidate, MySqlBool, Post, PostKind, PostVisibility, TextFormat};
+    use super::{hot_score, rank_hot, sample_weighted};
+
+    fn post(id: u64, likes: u64, age_hours: i64) -> Post {
+        Post {
+            id,
+            poster_id: 1,
+            kind: PostKind::Text,
+            title: String::new(),
+            url: None,
+            alt_text: None,
+            body: String::new(),
+            tldr: None,
+            body_format: TextFormat::Plain,
+            comment_mode: CommentMode::Open,
+            visibility: PostVisibility::Public,
+            likes,
+            time_stamp: Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap() - Duration::hours(age_hours),
+            updated_at: Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+            expires_at: None,
+            body_edited: MySqlBool(false),
+            title_edited: MySqlBool(false)
+        }
+    }
+
+    #[test]
+    fn test_hot_score() {
+        // More likes rank higher at the same age
+        assert!(hot_score(10, 5.0) > hot_score(2, 5.0));
+        // Decays with age
+        assert!(hot_score(10, 1.0) > hot_score(10, 24.0));
+        // A newer post overtakes an older, more liked one
+        assert!(hot_score(5, 1.0) > hot_score(50, 48.0));
+        // Future timestamps are treated as brand new
+        assert_eq!(hot_score(3, 0.0), hot_score(3, -2.0));
+    }
+
+    #[test]
+    fn test_rank_hot() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+        let candidate = |id: u64, likes: u64, age_hours: i64| HotCandidate {
+            id,
+            poster_id: 1,
+            visibility: PostVisibility::Public,
+            likes,
+            time_stamp: now - Duration::hours(age_hours)
+        };
+        let mut candidates = vec![candidate(1, 50, 48), candidate(2, 5, 1), candidate(3, 0, 0), candidate(4, 0, 0)];
+        rank_hot(&mut candidates, now);
+
+        // Ties are broken by the newer id
+        assert_eq!(vec![2, 4, 3, 1], candidates.iter().map(|candidate| candidate.id).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_sample_weighted() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+        let posts = || vec![post(1, 0, 48), post(2, 50, 1), post(3, 5, 2)];
+
+        let ids = |sample: Vec<Post>| sample.iter().map(|post| post.id).collect::<Vec<_>>();
+        // With the same draw for each, the heaviest post is the likeliest
+        assert_eq!(vec![2, 3, 1], ids(sample_weighted(posts(), now, 3, || 0.5)));
+        assert_eq!(vec![2], ids(sample_weighted(posts(), now, 1, || 0.5)));
+        assert!(sample_weighted(Vec::new(), now, 3, || 0.5).is_empty());
+
+        // A lucky draw still surfaces a lighter post
+        let mut draws = [0.999999, 0.01, 0.01].into_iter();
+        assert_eq!(1, sample_weighted(posts(), now, 1, || draws.next().unwrap())[0].id);
+    }
+
+    #[test]
+    fn test_sample_weighted_old_posts() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+        // A week or more old, with weights around 1e-4 and below
+        let posts = || (0..500).map(|id| post(id, id % 7, 24 * 7 + id as i64)).collect::<Vec<_>>();
+        let sample = |seed: u64| {
+            // xorshift, enough to give each post a different draw
+            let mut state = seed;
+            let random = move || {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 11) as f64 / (1u64 << 53) as f64
+            };
+            sample_weighted(posts(), now, 10, random).iter().map(|post| post.id).collect::<Vec<_>>()
+        };
+
+        // Not the order the posts were given in, and not the same for every draw
+        let first = sample(1);
+        assert_ne!((0..10).collect::<Vec<_>>(), first);
+        assert_ne!(first, sample(2));
+        assert_eq!(first, sample(1));
+    }
+}
\ No newline at end of file
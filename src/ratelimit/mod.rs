@@ -0,0 +1,2 @@
+pub mod quota;
+pub mod ratelimit;
\ No newline at end of file
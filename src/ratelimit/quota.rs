@@ -0,0 +1,316 @@
+use std::rc::Rc;
+use std::sync::Arc;
+
+use actix_web::{Error, HttpMessage, HttpResponse};
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::HeaderName;
+use actix_web::web::Data;
+use chrono::{DateTime, Days, NaiveTime, Utc};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use uuid::Uuid;
+
+use crate::auth::token::TokenHash;
+use crate::cache::{cache::Cache, error::CacheErr};
+use crate::cache::keys::{ApiKeyKey, QuotaKey};
+use crate::clock::{self, Clock};
+use crate::config::config::SharedConfig;
+use crate::database::database::Database;
+use super::ratelimit::{client_status, insert_headers, RateLimitStatus};
+
+/// Header carrying the API key of a request.
+pub const API_KEY_HEADER: HeaderName = HeaderName::from_static("x-api-key");
+
+/// The id of the valid API key of a request, added to its extensions by `EnforceQuota`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ApiKeyId(pub u64);
+
+const INVALID_KEY_REASON: &str = "Invalid API key";
+const QUOTA_REASON: &str = "Daily quota of the API key exceeded";
+
+/// Seconds the id and quota of a key are cached for, and so at most how long a
+/// deleted key or a changed quota takes to apply.
+const KEY_CACHE_SEC: u64 = 60;
+/// Cached in place of the id and quota of a key that does not exist.
+const UNKNOWN_KEY: &str = "-";
+/// Seconds the counter of a day is kept, at least until the day is over.
+const COUNTER_TTL_SEC: u64 = 60 * 60 * 48;
+
+/// Daily request counters of API keys in Redis, shared by every server. Without a
+/// Redis connection requests with an API key are refused, as they can't be counted.
+pub struct Quotas {
+    cache: Option<Cache>,
+    clock: Arc<dyn Clock>
+}
+
+impl Quotas {
+    pub fn new(cache: Option<Cache>) -> Self {
+        Quotas::with_clock(cache, clock::system())
+    }
+
+    /// Quotas whose days are those of `clock`.
+    pub fn with_clock(cache: Option<Cache>, clock: Arc<dyn Clock>) -> Self {
+        Quotas { cache, clock }
+    }
+
+    /// Counts a request made with the API key of `key_hash` against its quota, or
+    /// `default_quota` if it has none of its own. `None` if there is no such key.
+    pub async fn count(&self, db: &Database, key_hash: &TokenHash, default_quota: u64) -> Result<Option<(ApiKeyId, RateLimitStatus)>, ()> {
+        let cache = self.cache.as_ref().ok_or(())?;
+        let Some((api_key_id, quota)) = lookup(cache, db, key_hash, default_quota).await? else {
+            return Ok(None)
+        };
+        let now = self.clock.now();
+        let used = cache.incr(&QuotaKey(api_key_id, now.date_naive()).to_string(), COUNTER_TTL_SEC).await?;
+        Ok(Some((ApiKeyId(api_key_id), status(used, quota, now))))
+    }
+
+    /// The requests made with an API key today, and when the count resets.
+    pub async fn usage(&self, api_key_id: u64) -> Result<(u64, DateTime<Utc>), ()> {
+        let cache = self.cache.as_ref().ok_or(())?;
+        let now = self.clock.now();
+        match cache.get(&QuotaKey(api_key_id, now.date_naive()).to_string()).await {
+            Ok(value) => value.parse::<u64>().map(|used| (used, reset_at(now))).map_err(|_| ()),
+            Err(CacheErr::NilResponse) => Ok((0, reset_at(now))),
+            Err(_) => Err(())
+        }
+    }
+}
+
+/// The id and quota of the API key of `key_hash`, cached so that most requests
+/// don't reach the database, including those with unknown keys.
+async fn lookup(cache: &Cache, db: &Database, key_hash: &TokenHash, default_quota: u64) -> Result<Option<(u64, u64)>, ()> {
+    let key = ApiKeyKey(key_hash).to_string();
+    match cache.get(&key).await {
+        Ok(value) => return Ok(parse_key_value(&value)),
+        Err(CacheErr::NilResponse) => (),
+        Err(_) => return Err(())
+    }
+
+    let found = db.read_api_key_by_hash(key_hash).await
+        .map_err(|_| ())?
+        .map(|api_key| (api_key.id, api_key.daily_quota.unwrap_or(default_quota)));
+    let value = match found {
+        Some((api_key_id, quota)) => format!("{}:{}", api_key_id, quota),
+        None => UNKNOWN_KEY.to_string()
+    };
+    let _ = cache.set_key(&key, &value, KEY_CACHE_SEC).await;
+    Ok(found)
+}
+
+/// The `api_key_id:quota` cached for a key, `None` for an unknown key.
+fn parse_key_value(value: &str) -> Option<(u64, u64)> {
+    let (api_key_id, quota) = value.split_once(':')?;
+    Some((api_key_id.parse().ok()?, quota.parse().ok()?))
+}
+
+/// The quota of a key after its `used` requests of the day, at `now`.
+pub fn status(used: u64, quota: u64, now: DateTime<Utc>) -> RateLimitStatus {
+    RateLimitStatus {
+        allowed: used <= quota,
+        limit: quota,
+        remaining: quota.saturating_sub(used),
+        reset_sec: (reset_at(now) - now).num_seconds().max(1) as u64
+    }
+}
+
+/// When the quotas of the day of `now` reset, at the following midnight UTC.
+pub fn reset_at(now: DateTime<Utc>) -> DateTime<Utc> {
+    (now.date_naive() + Days::new(1)).and_time(NaiveTime::MIN).and_utc()
+}
+
+/// Middleware counting the requests to `/api` made with an `X-Api-Key` against the
+/// daily quota of the key. Requests with an unknown key are refused with 401, and
+/// those over the quota with 429 until it resets. Responses carry the `RateLimit-*`
+/// headers of the quota, in place of those of the limit per client address.
+///
+/// Refused keys count against the limit of the client address instead, and once
+/// it is reached keys are no longer looked up, so that guessing keys can't reach
+/// the database more often than any other request.
+pub struct EnforceQuota;
+
+impl<S, B> Transform<S, ServiceRequest> for EnforceQuota
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = EnforceQuotaMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(EnforceQuotaMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct EnforceQuotaMiddleware<S> {
+    service: Rc<S>
+}
+
+impl<S, B> Service<ServiceRequest> for EnforceQuotaMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        Box::pin(async move {
+            let status = match check_request(&req).await {
+                Ok(status) => status,
+                Err(response) => return Ok(req.into_response(response).map_into_right_body())
+            };
+            let mut res = match &status {
+                Some(status) if !status.allowed => {
+                    req.into_response(HttpResponse::TooManyRequests().reason(QUOTA_REASON).finish()).map_into_right_body()
+                },
+                _ => service.call(req).await?.map_into_left_body()
+            };
+            if let Some(status) = status {
+                insert_headers(res.headers_mut(), &status);
+            }
+            Ok(res)
+        })
+    }
+}
+
+async fn check_request(req: &ServiceRequest) -> Result<Option<RateLimitStatus>, HttpResponse> {
+    let Some(key) = req.headers().get(API_KEY_HEADER).filter(|_| req.path().starts_with("/api")) else {
+        return Ok(None)
+    };
+    let Some(key) = key.to_str().ok().and_then(|key| Uuid::parse_str(key).ok()) else {
+        return Err(refuse_key(req))
+    };
+    if let Some(status) = client_status(req, false).filter(|status| !status.allowed) {
+        return Err(limited(&status))
+    }
+    let (Some(quotas), Some(db), Some(config)) = (
+        req.app_data::<Data<Quotas>>(),
+        req.app_data::<Data<Database>>(),
+        req.app_data::<Data<SharedConfig>>()
+    ) else {
+        return Err(HttpResponse::InternalServerError().finish())
+    };
+
+    match quotas.count(db, &TokenHash::of(&key), config.load().api_keys.daily_quota).await {
+        Ok(Some((api_key_id, status))) => {
+            req.extensions_mut().insert(api_key_id);
+            Ok(Some(status))
+        },
+        Ok(None) => Err(refuse_key(req)),
+        Err(()) => Err(HttpResponse::ServiceUnavailable().finish())
+    }
+}
+
+/// Refuses the API key of `req`, counting it against the limit of the client address.
+fn refuse_key(req: &ServiceRequest) -> HttpResponse {
+    match client_status(req, true) {
+        Some(status) if !status.allowed => limited(&status),
+        Some(status) => {
+            let mut res = HttpResponse::Unauthorized().reason(INVALID_KEY_REASON).finish();
+            insert_headers(res.headers_mut(), &status);
+            res
+        },
+        None => HttpResponse::Unauthorized().reason(INVALID_KEY_REASON).finish()
+    }
+}
+
+/// Refuses a request over the limit of its client address.
+fn limited(status: &RateLimitStatus) -> HttpResponse {
+    let mut res = HttpResponse::TooManyRequests().finish();
+    insert_headers(res.headers_mut(), status);
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+
+    use actix_web::{web, App, HttpResponse};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::http::StatusCode;
+    use arc_swap::ArcSwap;
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    use crate::config::config::{RateLimitConfig, ServerConfig, SharedConfig};
+    use crate::ratelimit::ratelimit::{self as rate_limit, RateLimitStatus, RateLimiter};
+    use super::{parse_key_value, reset_at, status, EnforceQuota, Quotas, API_KEY_HEADER};
+
+    #[test]
+    fn test_status() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 23, 59, 0).unwrap();
+        assert_eq!(Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap(), reset_at(now));
+
+        assert_eq!(RateLimitStatus { allowed: true, limit: 10, remaining: 0, reset_sec: 60 }, status(10, 10, now));
+        assert_eq!(RateLimitStatus { allowed: false, limit: 10, remaining: 0, reset_sec: 60 }, status(11, 10, now));
+        let midnight = Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap();
+        assert_eq!(60 * 60 * 24, status(1, 10, midnight).reset_sec);
+    }
+
+    #[test]
+    fn test_parse_key_value() {
+        assert_eq!(Some((7, 10000)), parse_key_value("7:10000"));
+        assert_eq!(None, parse_key_value("-"));
+        assert_eq!(None, parse_key_value("7:"));
+    }
+
+    #[actix_web::test]
+    async fn test_middleware() {
+        let app = init_service(
+            App::new()
+                .wrap(EnforceQuota)
+                .app_data(web::Data::new(ArcSwap::from_pointee(ServerConfig::default()) as SharedConfig))
+                .app_data(web::Data::new(Quotas::new(None)))
+                .route("/api/ping", web::get().to(HttpResponse::Ok))
+        ).await;
+        let request = |key: Option<&str>| {
+            let request = TestRequest::get().uri("/api/ping");
+            match key {
+                Some(key) => request.insert_header((API_KEY_HEADER, key)).to_request(),
+                None => request.to_request()
+            }
+        };
+
+        assert_eq!(StatusCode::OK, call_service(&app, request(None)).await.status());
+        // Refused before a key is looked up
+        assert_eq!(StatusCode::UNAUTHORIZED, call_service(&app, request(Some("not-a-key"))).await.status());
+    }
+
+    #[actix_web::test]
+    async fn test_refused_keys_are_limited() {
+        let config = ServerConfig {
+            rate_limit: RateLimitConfig { enabled: true, requests: 2, window_sec: 60 },
+            ..ServerConfig::default()
+        };
+        let app = init_service(
+            App::new()
+                .wrap_fn(rate_limit::middleware)
+                .wrap(EnforceQuota)
+                .app_data(web::Data::new(ArcSwap::from_pointee(config) as SharedConfig))
+                .app_data(web::Data::new(Quotas::new(None)))
+                .app_data(web::Data::new(RateLimiter::new()))
+                .route("/api/ping", web::get().to(HttpResponse::Ok))
+        ).await;
+        let request = |key: &str| {
+            TestRequest::get().uri("/api/ping")
+                .peer_addr(SocketAddr::from(([127, 0, 0, 1], 1234)))
+                .insert_header((API_KEY_HEADER, key))
+                .to_request()
+        };
+
+        assert_eq!(StatusCode::UNAUTHORIZED, call_service(&app, request("not-a-key")).await.status());
+        assert_eq!(StatusCode::UNAUTHORIZED, call_service(&app, request("still-not-a-key")).await.status());
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, call_service(&app, request("not-a-key")).await.status());
+        // Without the quotas in Redis a well formed key would be a 503, were it looked up
+        let guess = Uuid::new_v4().to_string();
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, call_service(&app, request(&guess)).await.status());
+    }
+}
\ No newline at end of file
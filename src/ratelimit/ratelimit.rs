@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::{Error, HttpMessage, HttpResponse};
+use actix_web::body::EitherBody;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER};
+use actix_web::web::Data;
+
+use crate::clock::{self, Clock};
+use crate::config::config::{RateLimitConfig, SharedConfig};
+use super::quota::ApiKeyId;
+
+/// Number of tracked clients above which expired windows are cleared out.
+const PRUNE_THRESHOLD: usize = 1024;
+
+const LIMIT_HEADER: HeaderName = HeaderName::from_static("ratelimit-limit");
+const REMAINING_HEADER: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RESET_HEADER: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+/// The state of a client's window after a request has been counted.
+#[derive(Debug, PartialEq)]
+pub struct RateLimitStatus {
+    pub allowed: bool,
+    pub limit: u64,
+    pub remaining: u64,
+    /// Seconds until the window resets
+    pub reset_sec: u64
+}
+
+/// Fixed window request counter per client address.
+pub struct RateLimiter {
+    windows: Mutex<HashMap<IpAddr, (Instant, u64)>>,
+    clock: Arc<dyn Clock>
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        RateLimiter::new()
+    }
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        RateLimiter::with_clock(clock::system())
+    }
+
+    /// A RateLimiter whose windows `middleware` times by `clock`.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        RateLimiter { windows: Mutex::new(HashMap::new()), clock }
+    }
+
+    /// Counts a request from `client` at `now`, starting a new window if the
+    /// previous one has ended.
+    pub fn check(&self, client: IpAddr, config: &RateLimitConfig, now: Instant) -> RateLimitStatus {
+        self.window(client, config, now, true)
+    }
+
+    /// Counts a request from `client` now, by the clock of the limiter.
+    pub fn check_client(&self, client: IpAddr, config: &RateLimitConfig) -> RateLimitStatus {
+        self.check(client, config, self.clock.instant())
+    }
+
+    /// The status a request from `client` at `now` would have, without counting it.
+    pub fn peek(&self, client: IpAddr, config: &RateLimitConfig, now: Instant) -> RateLimitStatus {
+        self.window(client, config, now, false)
+    }
+
+    fn window(&self, client: IpAddr, config: &RateLimitConfig, now: Instant, count_request: bool) -> RateLimitStatus {
+        let window = Duration::from_secs(config.window_sec);
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() >= PRUNE_THRESHOLD {
+            windows.retain(|_, (start, _)| now.saturating_duration_since(*start) < window);
+        }
+
+        let (start, count) = windows.entry(client).or_insert((now, 0));
+        if now.saturating_duration_since(*start) >= window {
+            *start = now;
+            *count = 0;
+        }
+        if count_request {
+            *count += 1;
+        }
+        let counted = match count_request {
+            true  => *count,
+            false => *count + 1
+        };
+
+        let elapsed = now.saturating_duration_since(*start);
+        RateLimitStatus {
+            allowed: counted <= config.requests,
+            limit: config.requests,
+            remaining: config.requests.saturating_sub(counted),
+            reset_sec: window.saturating_sub(elapsed).as_secs_f64().ceil() as u64
+        }
+    }
+}
+
+/// Counts the translations requested for each client address, apart from their
+/// requests to `/api`.
+#[derive(Default)]
+pub struct TranslationLimiter(pub RateLimiter);
+
+/// `wrap_fn` middleware limiting the requests made to `/api`, per client address.
+/// Responses carry the `RateLimit-*` headers so clients can throttle themselves,
+/// and requests over the limit are rejected with 429.
+pub fn middleware<S, B>(
+    req: ServiceRequest,
+    srv: &S
+) -> impl Future<Output = Result<ServiceResponse<EitherBody<B>>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+{
+    let status = check_request(&req);
+    let call = match &status {
+        Some(status) if !status.allowed => Err(req),
+        _ => Ok(srv.call(req))
+    };
+
+    async move {
+        let mut res = match call {
+            Ok(fut) => fut.await?.map_into_left_body(),
+            Err(req) => req.into_response(HttpResponse::TooManyRequests().finish()).map_into_right_body()
+        };
+        if let Some(status) = status {
+            insert_headers(res.headers_mut(), &status);
+        }
+        Ok(res)
+    }
+}
+
+/// Sets the `RateLimit-*` headers of `status`, and `Retry-After` if it was not allowed.
+pub fn insert_headers(headers: &mut HeaderMap, status: &RateLimitStatus) {
+    headers.insert(LIMIT_HEADER, HeaderValue::from(status.limit));
+    headers.insert(REMAINING_HEADER, HeaderValue::from(status.remaining));
+    headers.insert(RESET_HEADER, HeaderValue::from(status.reset_sec));
+    if !status.allowed {
+        headers.insert(RETRY_AFTER, HeaderValue::from(status.reset_sec));
+    }
+}
+
+fn check_request(req: &ServiceRequest) -> Option<RateLimitStatus> {
+    // Requests with a valid API key count against its daily quota instead, see `quota`
+    if !req.path().starts_with("/api") || req.extensions().contains::<ApiKeyId>() {
+        return None
+    }
+    client_status(req, true)
+}
+
+/// The status of the window of the client of `req`, if the limit is enabled.
+/// The request is counted if `count_request`.
+pub(super) fn client_status(req: &ServiceRequest, count_request: bool) -> Option<RateLimitStatus> {
+    let config = req.app_data::<Data<SharedConfig>>()?.load();
+    if !config.rate_limit.enabled {
+        return None
+    }
+    let limiter = req.app_data::<Data<RateLimiter>>()?;
+    let client = req.peer_addr()?.ip();
+    let now = limiter.clock.instant();
+    Some(limiter.window(client, &config.rate_limit, now, count_request))
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use actix_web::{web, App, HttpResponse};
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::http::StatusCode;
+    use arc_swap::ArcSwap;
+    use chrono::Utc;
+
+    use crate::clock::ManualClock;
+    use crate::config::config::{RateLimitConfig, ServerConfig, SharedConfig};
+    use super::{RateLimitStatus, RateLimiter};
+
+    #[test]
+    fn test_check() {
+        let config = RateLimitConfig { enabled: true, requests: 2, window_sec: 10 };
+        let limiter = RateLimiter::new();
+        let client = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let other = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let start = Instant::now();
+
+        assert_eq!(
+            RateLimitStatus { allowed: true, limit: 2, remaining: 1, reset_sec: 10 },
+            limiter.check(client, &config, start)
+        );
+        assert!(limiter.check(client, &config, start + Duration::from_secs(1)).allowed);
+        let limited = limiter.check(client, &config, start + Duration::from_secs(4));
+        assert_eq!(RateLimitStatus { allowed: false, limit: 2, remaining: 0, reset_sec: 6 }, limited);
+
+        // Other clients have their own window, and peeking does not count
+        assert!(limiter.peek(other, &config, start + Duration::from_secs(4)).allowed);
+        assert!(limiter.check(other, &config, start + Duration::from_secs(4)).allowed);
+        assert!(limiter.check(other, &config, start + Duration::from_secs(4)).allowed);
+        assert!(!limiter.peek(other, &config, start + Duration::from_secs(4)).allowed);
+
+        // A new window starts once the previous one has ended
+        assert_eq!(1, limiter.check(client, &config, start + Duration::from_secs(10)).remaining);
+    }
+
+    #[actix_web::test]
+    async fn test_middleware() {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let config = ServerConfig {
+            rate_limit: RateLimitConfig { enabled: true, requests: 1, window_sec: 60 },
+            ..ServerConfig::default()
+        };
+        let app = init_service(
+            App::new()
+                .wrap_fn(super::middleware)
+                .app_data(web::Data::new(ArcSwap::from_pointee(config) as SharedConfig))
+                .app_data(web::Data::new(RateLimiter::with_clock(clock.clone())))
+                .route("/api/ping", web::get().to(HttpResponse::Ok))
+        ).await;
+        let request = || {
+            TestRequest::get().uri("/api/ping").peer_addr(SocketAddr::from(([127, 0, 0, 1], 1234))).to_request()
+        };
+
+        assert_eq!(StatusCode::OK, call_service(&app, request()).await.status());
+        clock.advance(Duration::from_secs(59));
+        let limited = call_service(&app, request()).await;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, limited.status());
+        assert_eq!("1", limited.headers().get("retry-after").unwrap());
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(StatusCode::OK, call_service(&app, request()).await.status());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,85 @@
+use std::net::IpAddr;
+
+use actix_web::HttpRequest;
+use actix_web::http::header::USER_AGENT;
+use sha2::{Digest, Sha256};
+
+use crate::models::SignalKind;
+
+/// The signals of the client making a login request, as salted hashes in hex.
+/// The address and user agent themselves are never stored.
+pub fn of_login(req: &HttpRequest, salt: &str) -> Vec<(SignalKind, String)> {
+    let mut signals = Vec::new();
+    if let Some(client) = req.peer_addr() {
+        signals.push((SignalKind::Address, hash(salt, SignalKind::Address, &network(client.ip()))));
+    }
+    let user_agent = req.headers().get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.trim().is_empty());
+    if let Some(user_agent) = user_agent {
+        signals.push((SignalKind::Device, hash(salt, SignalKind::Device, user_agent)));
+    }
+    signals
+}
+
+/// An IPv4 address, or the /64 network of an IPv6 address. IPv6 clients are
+/// usually given a whole network, and move between its addresses.
+fn network(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => v4.to_string(),
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => v4.to_string(),
+            None => {
+                let segments = v6.segments();
+                format!("{:x}:{:x}:{:x}:{:x}::/64", segments[0], segments[1], segments[2], segments[3])
+            }
+        }
+    }
+}
+
+fn hash(salt: &str, kind: SignalKind, value: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(salt)
+        .chain_update([0, kind as u8])
+        .chain_update(value)
+        .finalize();
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+
+    use actix_web::http::header::USER_AGENT;
+    use actix_web::test::TestRequest;
+
+    use crate::models::SignalKind;
+    use super::{network, of_login};
+
+    #[test]
+    fn test_network() {
+        assert_eq!("192.0.2.1", network("192.0.2.1".parse().unwrap()));
+        assert_eq!("192.0.2.1", network("::ffff:192.0.2.1".parse().unwrap()));
+        assert_eq!("2001:db8:0:1::/64", network("2001:db8:0:1::1".parse().unwrap()));
+        assert_eq!(network("2001:db8:0:1::1".parse().unwrap()), network("2001:db8:0:1:ab::2".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_of_login() {
+        let req = TestRequest::default()
+            .peer_addr(SocketAddr::from(([192, 0, 2, 1], 1234)))
+            .insert_header((USER_AGENT, "posted/1.0"))
+            .to_http_request();
+        let signals = of_login(&req, "salt");
+        assert_eq!(vec![SignalKind::Address, SignalKind::Device], signals.iter().map(|(kind, _)| *kind).collect::<Vec<_>>());
+        assert!(signals.iter().all(|(_, hash)| hash.len() == 64 && !hash.contains("192")));
+
+        // The same client under another salt has other hashes
+        assert!(of_login(&req, "pepper").iter().zip(&signals).all(|(a, b)| a.1 != b.1));
+        // The port is not part of the address
+        let other_port = TestRequest::default().peer_addr(SocketAddr::from(([192, 0, 2, 1], 4321))).to_http_request();
+        assert_eq!(signals[0], of_login(&other_port, "salt")[0]);
+
+        assert!(of_login(&TestRequest::default().to_http_request(), "salt").is_empty());
+    }
+}
\ No newline at end of file
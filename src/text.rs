@@ -0,0 +1,244 @@
+const ELLIPSIS: &str = "…";
+const FENCES: [&str; 2] = ["```", "~~~"];
+/// Markers that come in pairs around inline text. `||` is a spoiler.
+const INLINE_MARKERS: [&str; 3] = ["`", "**", "||"];
+const SPOILER_OPEN: &str = ">!";
+const SPOILER_CLOSE: &str = "!<";
+/// Characters that take up no space. The zero width joiner is not among them,
+/// as it joins the parts of emoji, see `normalise_body`.
+const ZERO_WIDTH: [char; 5] = ['\u{200B}', '\u{200C}', '\u{2060}', '\u{FEFF}', '\u{180E}'];
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// A title as stored: on one line, with runs of whitespace collapsed to a
+/// single space, and without zero width or control characters. A title of only
+/// such characters becomes empty.
+pub fn normalise_title(title: &str) -> String {
+    visible_chars(title)
+        .split(char::is_whitespace)
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// A post or comment body as stored: without zero width or control characters
+/// other than newlines and tabs, with `\r\n` line endings as `\n`, and more
+/// than one blank line in a row collapsed to one. Leading and trailing blank
+/// lines are dropped, so a body of only such characters becomes empty.
+/// 
+/// Indentation and trailing spaces within the body are kept, as markdown gives
+/// them meaning.
+pub fn normalise_body(body: &str) -> String {
+    let body = visible_chars(&body.replace("\r\n", "\n"));
+    let mut normalised = String::with_capacity(body.len());
+    let mut blank_lines = 0;
+    for line in body.trim_end().split('\n') {
+        if line.trim().is_empty() {
+            blank_lines += 1;
+            if normalised.is_empty() || blank_lines > 1 {
+                continue
+            }
+            normalised.push('\n');
+            continue
+        }
+        blank_lines = 0;
+        if !normalised.is_empty() {
+            normalised.push('\n');
+        }
+        normalised.push_str(line);
+    }
+    normalised
+}
+
+/// `text` without zero width characters, or control characters other than
+/// whitespace. A zero width joiner is only kept between two visible characters.
+fn visible_chars(text: &str) -> String {
+    let chars = text.chars().collect::<Vec<char>>();
+    let visible = |c: &char| !c.is_whitespace() && !c.is_control() && !ZERO_WIDTH.contains(c) && *c != ZERO_WIDTH_JOINER;
+    chars.iter().enumerate()
+        .filter(|(i, c)| match **c {
+            ZERO_WIDTH_JOINER => {
+                i.checked_sub(1).and_then(|prev| chars.get(prev)).is_some_and(visible)
+                    && chars.get(i + 1).is_some_and(visible)
+            },
+            '\r' => false,
+            c => !ZERO_WIDTH.contains(&c) && (!c.is_control() || c.is_whitespace())
+        })
+        .map(|(_, c)| *c)
+        .collect()
+}
+
+/// Shortens a markdown `body` to at most `max_chars` characters (plus an
+/// ellipsis), ending on a word boundary where possible. The excerpt never ends
+/// inside a code block, inline code, bold text, a spoiler, or a link, which
+/// would render the rest of a listing as part of it (or reveal the spoiler).
+pub fn summarise(body: &str, max_chars: usize) -> String {
+    if body.chars().count() <= max_chars {
+        return body.to_string()
+    }
+
+    let cut = body.char_indices().nth(max_chars).map(|(i, _)| i).unwrap_or(body.len());
+    let mut excerpt = &body[..cut];
+    if let Some(space) = excerpt.rfind(char::is_whitespace) {
+        excerpt = &excerpt[..space];
+    }
+    excerpt = close_markdown(excerpt);
+
+    format!("{}{}", excerpt.trim_end(), ELLIPSIS)
+}
+
+/// Cuts `excerpt` back to before any unterminated code block, link, spoiler or
+/// marker. Markers within closed code blocks are not markup, so are ignored.
+pub fn close_markdown(mut excerpt: &str) -> &str {
+    if let Some(open) = open_fence(excerpt) {
+        excerpt = &excerpt[..open];
+    }
+
+    // A link is open while its `[text]` or `(url)` is not closed
+    if let Some(open) = prose_positions(excerpt, "[").last().copied() {
+        let rest = &excerpt[open..];
+        let closed = match rest.find("](") {
+            Some(url_start) => rest[url_start..].contains(')'),
+            None => rest.contains(']')
+        };
+        if !closed {
+            excerpt = &excerpt[..open];
+        }
+    }
+
+    if let Some(open) = open_spoiler(excerpt) {
+        excerpt = &excerpt[..open];
+    }
+
+    for marker in INLINE_MARKERS {
+        let positions = prose_positions(excerpt, marker);
+        if positions.len() % 2 == 1 {
+            excerpt = &excerpt[..positions[positions.len() - 1]];
+        }
+    }
+    excerpt
+}
+
+/// Byte ranges of `text` outside fenced code blocks, and the start of the last
+/// fence if it is never closed.
+fn prose_ranges(text: &str) -> (Vec<(usize, usize)>, Option<usize>) {
+    let mut ranges = Vec::new();
+    let mut prose_start = 0;
+    let mut open_fence: Option<(usize, &str)> = None;
+    let mut line_start = 0;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        match open_fence {
+            None => if let Some(fence) = FENCES.iter().find(|fence| trimmed.starts_with(**fence)) {
+                ranges.push((prose_start, line_start));
+                open_fence = Some((line_start, fence));
+            },
+            Some((_, fence)) => if trimmed.starts_with(fence) {
+                prose_start = line_start + line.len();
+                open_fence = None;
+            }
+        }
+        line_start += line.len();
+    }
+
+    match open_fence {
+        Some((start, _)) => (ranges, Some(start)),
+        None => {
+            ranges.push((prose_start, text.len()));
+            (ranges, None)
+        }
+    }
+}
+
+/// The start of a code block that `text` ends within.
+pub fn open_fence(text: &str) -> Option<usize> {
+    prose_ranges(text).1
+}
+
+/// Byte positions of `marker` outside of fenced code blocks.
+fn prose_positions(text: &str, marker: &str) -> Vec<usize> {
+    prose_ranges(text).0.into_iter()
+        .flat_map(|(start, end)| text[start..end].match_indices(marker).map(move |(i, _)| start + i))
+        .collect()
+}
+
+/// The start of a `>!spoiler!<` that `text` ends within.
+fn open_spoiler(text: &str) -> Option<usize> {
+    let mut markers = prose_positions(text, SPOILER_OPEN).into_iter().map(|i| (i, true))
+        .chain(prose_positions(text, SPOILER_CLOSE).into_iter().map(|i| (i, false)))
+        .collect::<Vec<(usize, bool)>>();
+    markers.sort();
+
+    let mut open = None;
+    for (position, opens) in markers {
+        match (open, opens) {
+            (None, true) => open = Some(position),
+            (Some(_), false) => open = None,
+            _ => {}
+        }
+    }
+    open
+}
+
+#[cfg(test)]
+mod test {
+    use super::{close_markdown, normalise_body, normalise_title, open_fence, summarise};
+
+    #[test]
+    fn test_summarise() {
+        assert_eq!("short", summarise("short", 10));
+        assert_eq!("the quick…", summarise("the quick brown fox", 12));
+        // Multi-byte characters are counted as one
+        assert_eq!("ééé…", summarise("ééé éééé", 6));
+        // Unterminated markdown is dropped rather than left open
+        assert_eq!("see…", summarise("see [the docs](https://example.com/docs) here", 20));
+        assert_eq!("run…", summarise("run `cargo build --release` first", 16));
+        assert_eq!("very…", summarise("very **important news** today", 15));
+        assert_eq!("a [link](x) and…", summarise("a [link](x) and more text", 17));
+    }
+
+    #[test]
+    fn test_summarise_code_blocks() {
+        let body = "Try this:\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```\nand then more text";
+        assert_eq!("Try this:…", summarise(body, 30));
+        // A closed block is kept, and the backticks within it are not inline code
+        let body = "Ok:\n~~~\nlet s = `a;\n~~~\nthen `code` and more";
+        assert_eq!("Ok:\n~~~\nlet s = `a;\n~~~\nthen `code` and…", summarise(body, 40));
+    }
+
+    #[test]
+    fn test_summarise_spoilers() {
+        assert_eq!("The ending…", summarise("The ending >!everyone survives!< was great", 25));
+        assert_eq!("The ending…", summarise("The ending ||everyone survives|| was great", 25));
+        assert_eq!("A >!twist!< and a…", summarise("A >!twist!< and a >!second twist!<", 30));
+    }
+
+    #[test]
+    fn test_open_fence() {
+        assert_eq!(None, open_fence("no code"));
+        assert_eq!(None, open_fence("```\ncode\n```\n"));
+        assert_eq!(Some(5), open_fence("text\n```\ncode"));
+        // Fences of another kind do not close a block
+        assert_eq!(Some(0), open_fence("~~~\n```\n"));
+        assert_eq!("`x` **y**", close_markdown("`x` **y**"));
+    }
+
+    #[test]
+    fn test_normalise_title() {
+        assert_eq!("A title", normalise_title("  A \t title\n"));
+        assert_eq!("", normalise_title(" \u{200B}\u{FEFF} \u{2060}"));
+        assert_eq!("", normalise_title("\u{200D}\u{0007}"));
+        assert_eq!("Split word", normalise_title("Sp\u{200B}lit\u{0000} word"));
+        // Joined emoji stay joined
+        assert_eq!("Family 👩\u{200D}👧", normalise_title("Family 👩\u{200D}👧"));
+    }
+
+    #[test]
+    fn test_normalise_body() {
+        assert_eq!("", normalise_body("\n \u{200B}\t\r\n\u{FEFF}"));
+        assert_eq!("First\n\nSecond", normalise_body("\r\nFirst\r\n\r\n\r\n \r\nSecond\u{0008}\n"));
+        // Indentation and hard line breaks are markdown
+        assert_eq!("Code:\n\n    let x = 1;\nline  \nbreak", normalise_body("Code:\n\n    let x = 1;\nline  \nbreak"));
+        assert_eq!("    indented", normalise_body(" \n    indented\n\n"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,199 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::models::{CommentContext, CommentNode, MoreReplies, ThreadComment};
+
+/// Levels of replies nested below a top-level comment in a tree. Replies any
+/// deeper are left to be continued.
+pub const MAX_DEPTH: usize = 8;
+/// Replies nested under each comment in a tree. Any more are left to be continued.
+pub const MAX_REPLIES: usize = 50;
+
+/// How much of a thread is nested into a tree.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub depth: usize,
+    pub replies: usize
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits { depth: MAX_DEPTH, replies: MAX_REPLIES }
+    }
+}
+
+/// Nests the comments of a thread under the comments they reply to, keeping the
+/// order of the top-level comments. Replies to comments absent from the thread,
+/// e.g. held ones, are made top-level. `reply_counts` has the number of replies
+/// to the comments whose replies were not all read into the thread.
+pub fn tree(thread: Vec<ThreadComment>, reply_counts: &HashMap<u64, u64>, limits: Limits) -> Vec<CommentNode> {
+    let (top_level, mut replies) = index(thread);
+    top_level.into_iter()
+        .map(|comment| node(comment, &mut replies, reply_counts, 0, None, limits))
+        .collect()
+}
+
+/// The subtree of `comment_id` in a thread, with the comments it is a reply to.
+/// Only the replies after the `after` id are nested, to continue a `MoreReplies`.
+pub fn context(thread: Vec<ThreadComment>, comment_id: u64, after: Option<u64>, limits: Limits) -> Option<CommentContext> {
+    let parents = thread.iter()
+        .map(|listed| (listed.comment.id, listed.comment.comment_reply_id))
+        .collect::<HashMap<u64, Option<u64>>>();
+    let mut ancestor_ids = Vec::new();
+    let mut visited = HashSet::from([comment_id]);
+    let mut parent_id = *parents.get(&comment_id)?;
+    while let Some(id) = parent_id.filter(|id| parents.contains_key(id) && visited.insert(*id)) {
+        ancestor_ids.push(id);
+        parent_id = parents[&id];
+    }
+
+    let (mut top_level, mut replies) = index(thread);
+    let ancestors = ancestor_ids.into_iter()
+        .rev()
+        .filter_map(|id| take(&mut top_level, &mut replies, id))
+        .collect();
+    let root = take(&mut top_level, &mut replies, comment_id)?;
+    Some(CommentContext { ancestors, comment: node(root, &mut replies, &HashMap::new(), 0, after, limits) })
+}
+
+/// Splits a thread into its top-level comments and the replies to each comment,
+/// oldest first.
+fn index(thread: Vec<ThreadComment>) -> (Vec<ThreadComment>, HashMap<u64, Vec<ThreadComment>>) {
+    let ids = thread.iter().map(|listed| listed.comment.id).collect::<HashSet<u64>>();
+    let mut top_level = Vec::new();
+    let mut replies: HashMap<u64, Vec<ThreadComment>> = HashMap::new();
+    for listed in thread {
+        match listed.comment.comment_reply_id.filter(|parent_id| ids.contains(parent_id)) {
+            Some(parent_id) => replies.entry(parent_id).or_default().push(listed),
+            None => top_level.push(listed)
+        }
+    }
+    replies.values_mut().for_each(|replies| replies.sort_by_key(|listed| listed.comment.id));
+    (top_level, replies)
+}
+
+/// Removes the comment `id` from wherever it is in an indexed thread.
+fn take(top_level: &mut Vec<ThreadComment>, replies: &mut HashMap<u64, Vec<ThreadComment>>, id: u64) -> Option<ThreadComment> {
+    if let Some(index) = top_level.iter().position(|listed| listed.comment.id == id) {
+        return Some(top_level.remove(index))
+    }
+    replies.values_mut().find_map(|siblings| {
+        let index = siblings.iter().position(|listed| listed.comment.id == id)?;
+        Some(siblings.remove(index))
+    })
+}
+
+fn node(
+    listed: ThreadComment,
+    replies: &mut HashMap<u64, Vec<ThreadComment>>,
+    reply_counts: &HashMap<u64, u64>,
+    depth: usize,
+    after: Option<u64>,
+    limits: Limits
+) -> CommentNode {
+    let id = listed.comment.id;
+    let mut children = replies.remove(&id).unwrap_or_default();
+    let unread = reply_counts.get(&id).map_or(0, |count| count.saturating_sub(children.len() as u64));
+    children.retain(|child| after.is_none_or(|after| child.comment.id > after));
+    let count = children.len() as u64 + unread;
+    if count == 0 {
+        return CommentNode { comment: listed, replies: Vec::new(), more: None }
+    }
+    if depth >= limits.depth {
+        let more = MoreReplies { continue_from: id, after: None, count };
+        return CommentNode { comment: listed, replies: Vec::new(), more: Some(more) }
+    }
+
+    children.truncate(limits.replies);
+    let omitted = count - children.len() as u64;
+    let more = (omitted > 0).then(|| MoreReplies {
+        continue_from: id,
+        after: children.last().map(|child| child.comment.id),
+        count: omitted
+    });
+    let children = children.into_iter()
+        .map(|child| node(child, replies, reply_counts, depth + 1, None, limits))
+        .collect();
+    CommentNode { comment: listed, replies: children, more }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use chrono::Utc;
+
+    use crate::models::{AwardCounts, Comment, CommentNode, MoreReplies, MySqlBool, ReactionCounts, TextFormat, ThreadComment};
+    use super::{context, tree, Limits};
+
+    fn listed(id: u64, comment_reply_id: Option<u64>) -> ThreadComment {
+        let comment = Comment {
+            id,
+            post_id: 1,
+            commenter_id: 1,
+            body: format!("Comment {}", id),
+            body_format: TextFormat::Plain,
+            comment_reply_id,
+            likes: 0,
+            time_stamp: Utc::now(),
+            updated_at: Utc::now(),
+            edited: MySqlBool(false)
+        };
+        ThreadComment { comment, pinned: false, reactions: ReactionCounts::new(), awards: AwardCounts::new() }
+    }
+
+    /// (id, reply ids, more) of each node, depth first.
+    fn shape(nodes: &[CommentNode]) -> Vec<(u64, Vec<u64>, Option<MoreReplies>)> {
+        nodes.iter()
+            .flat_map(|node| {
+                let replies = node.replies.iter().map(|reply| reply.comment.comment.id).collect();
+                std::iter::once((node.comment.comment.id, replies, node.more.clone())).chain(shape(&node.replies))
+            })
+            .collect()
+    }
+
+    /// 1 <- 2 <- 3 <- 4, 1 <- 5, 6, and 8 replying to a held 7
+    fn thread() -> Vec<ThreadComment> {
+        vec![listed(1, None), listed(2, Some(1)), listed(3, Some(2)), listed(4, Some(3)),
+            listed(5, Some(1)), listed(6, None), listed(8, Some(7))]
+    }
+
+    #[test]
+    fn test_tree() {
+        let nodes = tree(thread(), &HashMap::new(), Limits { depth: 2, replies: 1 });
+
+        assert_eq!(vec![
+            (1, vec![2], Some(MoreReplies { continue_from: 1, after: Some(2), count: 1 })),
+            (2, vec![3], None),
+            (3, vec![], Some(MoreReplies { continue_from: 3, after: None, count: 1 })),
+            (6, vec![], None),
+            (8, vec![], None)
+        ], shape(&nodes));
+    }
+
+    #[test]
+    fn test_tree_of_page() {
+        // Read as far as the limits allow: 1 has two replies and 3 has one unread
+        let page = vec![listed(1, None), listed(2, Some(1)), listed(3, Some(2))];
+        let reply_counts = HashMap::from([(1, 2), (2, 1), (3, 1)]);
+        let nodes = tree(page, &reply_counts, Limits { depth: 2, replies: 1 });
+
+        assert_eq!(vec![
+            (1, vec![2], Some(MoreReplies { continue_from: 1, after: Some(2), count: 1 })),
+            (2, vec![3], None),
+            (3, vec![], Some(MoreReplies { continue_from: 3, after: None, count: 1 }))
+        ], shape(&nodes));
+    }
+
+    #[test]
+    fn test_context() {
+        let continued = context(thread(), 1, Some(2), Limits::default()).unwrap();
+        assert!(continued.ancestors.is_empty());
+        assert_eq!(vec![(1, vec![5], None), (5, vec![], None)], shape(&[continued.comment]));
+
+        let deep = context(thread(), 3, None, Limits::default()).unwrap();
+        assert_eq!(vec![1, 2], deep.ancestors.iter().map(|listed| listed.comment.id).collect::<Vec<u64>>());
+        assert_eq!(vec![(3, vec![4], None), (4, vec![], None)], shape(&[deep.comment]));
+
+        assert!(context(thread(), 7, None, Limits::default()).is_none());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::config::config::TranslationConfig;
+
+/// Longest language tag accepted, e.g. `zh-Hant-TW` is 10.
+const MAX_LANGUAGE_LENGTH: usize = 35;
+
+#[derive(Debug)]
+pub enum TranslateError {
+    Request(String),
+    InvalidResponse(String)
+}
+
+impl std::fmt::Display for TranslateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TranslateError::Request(e) => write!(f, "Translation request failed: {}", e),
+            TranslateError::InvalidResponse(e) => write!(f, "Invalid translation response: {}", e)
+        }
+    }
+}
+
+/// Translates text into a language, named by its BCP 47 tag (e.g. `de`, `pt-BR`).
+/// Translators may block, e.g. on a request to an external API.
+pub trait Translator {
+    fn name(&self) -> &'static str;
+    fn translate(&self, text: &str, language: &str) -> Result<String, TranslateError>;
+}
+
+/// Asks an external API for a translation.
+pub struct ApiTranslator<'a> {
+    pub url: &'a str,
+    pub timeout: Duration
+}
+
+#[derive(Deserialize)]
+struct ApiTranslation {
+    text: String
+}
+
+impl Translator for ApiTranslator<'_> {
+    fn name(&self) -> &'static str {
+        "api"
+    }
+
+    fn translate(&self, text: &str, language: &str) -> Result<String, TranslateError> {
+        let response = ureq::post(self.url)
+            .timeout(self.timeout)
+            .send_json(json!({ "text": text, "target": language }))
+            .map_err(|e| TranslateError::Request(e.to_string()))?;
+        let reply: ApiTranslation = response.into_json()
+            .map_err(|e| TranslateError::InvalidResponse(e.to_string()))?;
+        Ok(reply.text)
+    }
+}
+
+/// The translator configured by `config`, if any.
+pub fn translator(config: &TranslationConfig) -> Option<Box<dyn Translator + '_>> {
+    let url = config.api_url.as_deref()?;
+    let timeout = Duration::from_millis(config.api_timeout_ms);
+    Some(Box::new(ApiTranslator { url, timeout }))
+}
+
+/// The configured spelling of `language`, if posts may be translated into it.
+/// Tags are matched case-insensitively, so `pt-br` and `PT-BR` share a translation.
+pub fn configured_language<'a>(config: &'a TranslationConfig, language: &str) -> Option<&'a str> {
+    config.languages.iter()
+        .find(|configured| configured.eq_ignore_ascii_case(language))
+        .map(String::as_str)
+}
+
+/// Whether `language` is shaped like a BCP 47 tag: a 2 or 3 letter language,
+/// then subtags of 1 to 8 letters or digits, separated by hyphens.
+pub fn is_language_tag(language: &str) -> bool {
+    if language.len() > MAX_LANGUAGE_LENGTH {
+        return false
+    }
+    let mut subtags = language.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::config::TranslationConfig;
+    use super::{configured_language, is_language_tag, translator};
+
+    #[test]
+    fn test_is_language_tag() {
+        assert!(is_language_tag("de"));
+        assert!(is_language_tag("pt-BR"));
+        assert!(is_language_tag("zh-Hant-TW"));
+        assert!(is_language_tag("yue"));
+
+        assert!(!is_language_tag(""));
+        assert!(!is_language_tag("d"));
+        assert!(!is_language_tag("deutsch"));
+        assert!(!is_language_tag("de-"));
+        assert!(!is_language_tag("de_DE"));
+        assert!(!is_language_tag("de:1"));
+        assert!(!is_language_tag("en-toolongsubtag"));
+    }
+
+    #[test]
+    fn test_configured_language() {
+        let config = TranslationConfig { languages: vec!["de".to_string(), "pt-BR".to_string()], ..TranslationConfig::default() };
+        assert_eq!(Some("de"), configured_language(&config, "de"));
+        assert_eq!(Some("de"), configured_language(&config, "DE"));
+        assert_eq!(Some("pt-BR"), configured_language(&config, "pt-br"));
+
+        assert_eq!(None, configured_language(&config, "pt"));
+        assert_eq!(None, configured_language(&config, "fr"));
+        assert_eq!(None, configured_language(&TranslationConfig::default(), "de"));
+    }
+
+    #[test]
+    fn test_translator() {
+        assert!(translator(&TranslationConfig::default()).is_none());
+
+        let config = TranslationConfig { api_url: Some("http://localhost:9000/translate".to_string()), ..TranslationConfig::default() };
+        assert_eq!(Some("api"), translator(&config).map(|translator| translator.name()));
+    }
+}
\ No newline at end of file